@@ -10,6 +10,9 @@ use anyhow::{Context, anyhow};
 use camino::{Utf8Path, Utf8PathBuf};
 use tracing::{Span, debug, instrument, trace, warn};
 
+/// Number of commits fetched at once when deepening a shallow clone.
+const DEEPEN_STEP: u32 = 100;
+
 /// Repository
 #[derive(Debug)]
 pub struct Repo {
@@ -162,6 +165,80 @@ impl Repo {
         Ok(())
     }
 
+    /// Whether the repository is a shallow clone, i.e. its history is truncated.
+    pub fn is_shallow(&self) -> anyhow::Result<bool> {
+        is_shallow(&self.directory)
+    }
+
+    /// Whether the commit is at the boundary of the history of a shallow clone.
+    /// Git doesn't know the parents of these commits, so they look like they add
+    /// all the files of the repository.
+    pub fn is_shallow_boundary(&self, commit: &str) -> anyhow::Result<bool> {
+        let shallow_file = self.git(&["rev-parse", "--git-path", "shallow"])?;
+        let shallow_file = self.directory.join(shallow_file);
+        if !shallow_file.exists() {
+            return Ok(false);
+        }
+        let commit = self.git(&["rev-parse", &format!("{commit}^{{commit}}")])?;
+        let boundary = fs_err::read_to_string(shallow_file)?;
+        Ok(boundary.lines().any(|line| line == commit))
+    }
+
+    /// Fetch `commits` more commits of the history of a shallow clone,
+    /// together with the tags of the remote.
+    pub fn deepen(&self, commits: u32) -> anyhow::Result<()> {
+        debug!("fetching {commits} more commits of the shallow clone");
+        self.git(&[
+            "fetch",
+            "--deepen",
+            &commits.to_string(),
+            "--tags",
+            &self.original_remote,
+        ])
+        .with_context(|| format!("failed to fetch {commits} more commits"))?;
+        Ok(())
+    }
+
+    /// Fetch more history of a shallow clone until `is_found` returns `true`.
+    /// At most `max_commits` commits of the history of `tip` are fetched.
+    /// `is_found` can check out other commits: `tip` is the commit where the search started.
+    ///
+    /// Returns `false` if the whole history is available, but `is_found` still returns `false`.
+    /// Returns an error if `is_found` returns `false` after fetching `max_commits` commits.
+    pub fn deepen_until(
+        &self,
+        tip: &str,
+        max_commits: u32,
+        mut is_found: impl FnMut() -> anyhow::Result<bool>,
+    ) -> anyhow::Result<bool> {
+        let mut previous_commits = 0;
+        loop {
+            if is_found()? {
+                return Ok(true);
+            }
+            if !self.is_shallow()? {
+                return Ok(false);
+            }
+            let commits: u32 = self
+                .git(&["rev-list", "--count", tip])?
+                .parse()
+                .context("invalid number of commits")?;
+            if commits == previous_commits {
+                // The shallow boundary isn't in the history of `tip`.
+                return Ok(false);
+            }
+            anyhow::ensure!(
+                commits < max_commits,
+                "the repository is a shallow clone and the last {commits} commits of `{tip}` \
+                don't contain what release-plz is looking for. \
+                Fetch the whole history with `git fetch --unshallow` or \
+                increase `max_analyze_commits`"
+            );
+            self.deepen(DEEPEN_STEP.min(max_commits - commits))?;
+            previous_commits = commits;
+        }
+    }
+
     pub fn force_push(&self, obj: &str) -> anyhow::Result<()> {
         // `--force-with-lease` is safer than `--force` because it will not overwrite
         // changes on the remote that you do not have locally.
@@ -269,10 +346,51 @@ impl Repo {
         Ok(last_commit.to_string())
     }
 
+    /// Get the commits reachable from `to` but not from `from` that touch `paths`,
+    /// from the newest to the oldest.
+    ///
+    /// If `from` is `None`, all the ancestors of `to` are considered.
+    /// Commits that only touch `excluded_paths` are ignored.
+    pub fn commits_at_paths(
+        &self,
+        from: Option<&str>,
+        to: &str,
+        paths: &[&Path],
+        excluded_paths: &[&Path],
+    ) -> anyhow::Result<Vec<String>> {
+        let range = match from {
+            Some(from) => format!("{from}..{to}"),
+            None => to.to_string(),
+        };
+        let excluded: Vec<String> = excluded_paths
+            .iter()
+            .map(|p| format!(":(exclude){}", p.to_str().expect("invalid path")))
+            .collect();
+        let mut git_args = vec!["log", "--format=%H", &range, "--"];
+        for p in paths {
+            git_args.push(p.to_str().expect("invalid path"));
+        }
+        git_args.extend(excluded.iter().map(|e| e.as_str()));
+
+        let output = self
+            .git(&git_args)
+            .with_context(|| format!("failed to list commits in range {range}"))?;
+        Ok(output.lines().map(|l| l.to_string()).collect())
+    }
+
     pub fn current_commit_message(&self) -> anyhow::Result<String> {
         self.git(&["log", "-1", "--pretty=format:%B"])
     }
 
+    pub fn get_commit_message(&self, commit_hash: &str) -> anyhow::Result<String> {
+        self.get_commit_info("%B", commit_hash)
+    }
+
+    /// Get the committer date of the commit in the `YYYY-MM-DD` format.
+    pub fn get_commit_date(&self, commit_hash: &str) -> anyhow::Result<String> {
+        self.get_commit_info("%cs", commit_hash)
+    }
+
     pub fn get_author_name(&self, commit_hash: &str) -> anyhow::Result<String> {
         self.get_commit_info("%an", commit_hash)
     }
@@ -348,6 +466,12 @@ impl Repo {
         .is_ok()
     }
 
+    /// Whether the two commits have a common ancestor in the local history.
+    /// In a shallow clone, this is `false` until the history that connects them is fetched.
+    pub fn have_common_ancestor(&self, commit1: &str, commit2: &str) -> bool {
+        self.git(&["merge-base", commit1, commit2]).is_ok()
+    }
+
     /// Name of the remote when the [`Repo`] was created.
     pub fn original_remote(&self) -> &str {
         &self.original_remote
@@ -388,6 +512,17 @@ pub fn is_file_committed(repo_path: &Utf8Path, file: &Utf8Path) -> bool {
     git_in_dir(repo_path, &["ls-files", "--error-unmatch", file]).is_ok()
 }
 
+/// Whether the repository is a shallow clone, i.e. its history is truncated.
+pub fn is_shallow(repo_path: &Utf8Path) -> anyhow::Result<bool> {
+    let output = git_in_dir(repo_path, &["rev-parse", "--is-shallow-repository"])?;
+    Ok(output == "true")
+}
+
+/// Whether `HEAD` points to a commit rather than to a branch.
+pub fn is_detached_head(repo_path: &Utf8Path) -> bool {
+    git_in_dir(repo_path, &["symbolic-ref", "--quiet", "HEAD"]).is_err()
+}
+
 fn changed_files(output: &str, filter: impl FnMut(&&str) -> bool) -> Vec<String> {
     output
         .lines()
@@ -508,6 +643,117 @@ mod tests {
         repo.is_clean().unwrap();
     }
 
+    #[test]
+    fn shallow_clone_is_recognized() {
+        test_logs::init();
+        let repository_dir = tempdir().unwrap();
+        let repo = Repo::init(&repository_dir);
+        for i in 0..2 {
+            fs_err::write(repository_dir.as_ref().join("file1.txt"), i.to_string()).unwrap();
+            repo.add_all_and_commit(&format!("commit {i}")).unwrap();
+        }
+        let clone_dir = tempdir().unwrap();
+        let clone_dir = Utf8Path::from_path(clone_dir.path()).unwrap();
+        let source_url = format!("file://{}", repo.directory());
+        repo.git(&["clone", "--depth", "1", &source_url, clone_dir.as_str()])
+            .unwrap();
+        assert!(!is_shallow(repo.directory()).unwrap());
+        assert!(is_shallow(clone_dir).unwrap());
+    }
+
+    /// Clone a repository with `commits` commits, keeping only the last one.
+    /// Returns the clone, the temporary directories and the hashes of the commits.
+    fn shallow_clone(commits: usize) -> (Repo, [tempfile::TempDir; 2], Vec<String>) {
+        let repository_dir = tempdir().unwrap();
+        let repo = Repo::init(&repository_dir);
+        let mut hashes = vec![repo.current_commit_hash().unwrap()];
+        for i in 1..commits {
+            fs_err::write(repository_dir.as_ref().join("file1.txt"), i.to_string()).unwrap();
+            repo.add_all_and_commit(&format!("commit {i}")).unwrap();
+            hashes.push(repo.current_commit_hash().unwrap());
+        }
+        let clone_dir = tempdir().unwrap();
+        let source_url = format!("file://{}", repo.directory());
+        let clone_path = clone_dir.path().to_str().unwrap();
+        repo.git(&["clone", "--depth", "1", &source_url, clone_path])
+            .unwrap();
+        let clone = Repo::new(clone_path).unwrap();
+        (clone, [repository_dir, clone_dir], hashes)
+    }
+
+    #[test]
+    fn shallow_clone_is_deepened_until_commit_is_found() {
+        test_logs::init();
+        let (clone, _dirs, hashes) = shallow_clone(5);
+        let head = clone.current_commit_hash().unwrap();
+        assert!(clone.is_shallow().unwrap());
+        assert!(clone.is_shallow_boundary(&head).unwrap());
+        let first_commit = &hashes[0];
+        let is_found = clone
+            .deepen_until("HEAD", 10, || {
+                Ok(clone.get_commit_message(first_commit).is_ok())
+            })
+            .unwrap();
+        assert!(is_found);
+        assert!(!clone.is_shallow().unwrap());
+        assert!(!clone.is_shallow_boundary(&head).unwrap());
+    }
+
+    #[test]
+    fn shallow_clone_is_deepened_up_to_max_commits() {
+        test_logs::init();
+        let (clone, _dirs, _) = shallow_clone(5);
+        let error = clone.deepen_until("HEAD", 3, || Ok(false)).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("the repository is a shallow clone and the last 3 commits"),
+            "{error}"
+        );
+        let is_found = clone.deepen_until("HEAD", 10, || Ok(false)).unwrap();
+        assert!(!is_found);
+    }
+
+    #[test]
+    fn shallow_clone_is_deepened_until_branches_have_common_ancestor() {
+        test_logs::init();
+        let (clone, dirs, hashes) = shallow_clone(4);
+        // A maintenance branch that forks from the second commit.
+        let source = Repo::new(Utf8Path::from_path(dirs[0].path()).unwrap()).unwrap();
+        source
+            .git(&["checkout", "-b", "release/0.1", &hashes[1]])
+            .unwrap();
+        fs_err::write(dirs[0].path().join("file2.txt"), "fix").unwrap();
+        source.add_all_and_commit("fix").unwrap();
+        source.git(&["tag", "v0.1.1"]).unwrap();
+        clone
+            .git(&["fetch", "--depth", "1", "origin", "tag", "v0.1.1"])
+            .unwrap();
+
+        assert!(!clone.is_ancestor("v0.1.1", "HEAD"));
+        assert!(!clone.have_common_ancestor("v0.1.1", "HEAD"));
+        let is_found = clone
+            .deepen_until("HEAD", 10, || {
+                Ok(clone.have_common_ancestor("v0.1.1", "HEAD"))
+            })
+            .unwrap();
+        assert!(is_found);
+        assert!(!clone.is_ancestor("v0.1.1", "HEAD"));
+        let merge_base = clone.git(&["merge-base", "v0.1.1", "HEAD"]).unwrap();
+        assert_eq!(merge_base, hashes[1]);
+    }
+
+    #[test]
+    fn detached_head_is_recognized() {
+        test_logs::init();
+        let repository_dir = tempdir().unwrap();
+        let repo = Repo::init(&repository_dir);
+        assert!(!is_detached_head(repo.directory()));
+        let commit = repo.current_commit_hash().unwrap();
+        repo.checkout(&commit).unwrap();
+        assert!(is_detached_head(repo.directory()));
+    }
+
     #[test]
     fn dirty_project_is_recognized() {
         test_logs::init();
@@ -560,6 +806,34 @@ D  crates/git_cmd/CHANGELOG.md
         assert!(!repo.tag_exists("v2.0.0").unwrap());
     }
 
+    #[test]
+    fn commits_at_paths_are_retrieved() {
+        test_logs::init();
+        let repository_dir = tempdir().unwrap();
+        let repo = Repo::init(&repository_dir);
+        let pkg_dir = repository_dir.as_ref().join("pkg");
+        let nested_dir = pkg_dir.join("nested");
+        fs_err::create_dir_all(&nested_dir).unwrap();
+        {
+            fs_err::write(pkg_dir.join("file1.txt"), b"Hello, file1!").unwrap();
+            repo.add_all_and_commit("pkg-1").unwrap();
+            repo.tag("v1.0.0", "test").unwrap();
+            fs_err::write(nested_dir.join("file2.txt"), b"Hello, file2!").unwrap();
+            repo.add_all_and_commit("nested-1").unwrap();
+            fs_err::write(pkg_dir.join("file1.txt"), b"Hello, file1!-2").unwrap();
+            repo.add_all_and_commit("pkg-2").unwrap();
+        }
+        let messages = |from: Option<&str>| -> Vec<String> {
+            repo.commits_at_paths(from, "HEAD", &[&pkg_dir], &[&nested_dir])
+                .unwrap()
+                .iter()
+                .map(|c| repo.get_commit_message(c).unwrap())
+                .collect()
+        };
+        assert_eq!(messages(None), vec!["pkg-2", "pkg-1"]);
+        assert_eq!(messages(Some("v1.0.0")), vec!["pkg-2"]);
+    }
+
     #[test]
     fn tags_are_retrieved() {
         test_logs::init();
@@ -0,0 +1,114 @@
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use cargo_metadata::Metadata;
+use clap::builder::{NonEmptyStringValueParser, PathBufValueParser};
+use release_plz_core::{CommitRange, update_request::UpdateRequest};
+
+use crate::config::Config;
+
+use super::{OutputType, update::Update};
+
+#[derive(clap::Parser, Debug)]
+pub struct Changelog {
+    #[command(subcommand)]
+    pub command: ChangelogCommand,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum ChangelogCommand {
+    /// Regenerate the changelog of the packages from the git history.
+    ///
+    /// For each git tag matching the `git_tag_name` template of a package,
+    /// release-plz adds a changelog entry containing the commits that changed the package
+    /// since the previous tag.
+    /// The header of the existing changelog is preserved, while its releases are overwritten.
+    ///
+    /// Use this command to create the changelog of a package that was released before adopting
+    /// release-plz, or to apply changes of the `[changelog]` configuration to past releases.
+    Regenerate(Regenerate),
+    /// Print the changelog entries without editing the changelogs.
+    ///
+    /// By default, release-plz prints the entries that `release-plz update` would add.
+    /// Use this command to iterate on the `[changelog]` configuration.
+    Preview(Preview),
+    /// Print the changelogs of the packages as JSON.
+    ///
+    /// Each changelog is parsed into its releases, with their versions, dates, compare links
+    /// and sections. Each entry of a section contains the linked pull requests and issues.
+    Export(Export),
+    /// Check that the changelogs of the packages are consistent.
+    ///
+    /// The command verifies the changelog header, the order of the releases, their dates,
+    /// and that the versions and links of the releases match the git tags.
+    /// It exits with an error if it finds any problem, so you can run it in CI.
+    Check(Check),
+}
+
+#[derive(clap::Parser, Debug)]
+pub struct Regenerate {
+    #[command(flatten)]
+    pub update: Update,
+}
+
+#[derive(clap::Parser, Debug)]
+pub struct Export {
+    #[command(flatten)]
+    pub update: Update,
+}
+
+#[derive(clap::Parser, Debug)]
+pub struct Check {
+    #[command(flatten)]
+    pub update: Update,
+
+    /// Output format. If specified, prints the problems of each changelog.
+    #[arg(short, long, value_enum)]
+    pub output: Option<OutputType>,
+}
+
+#[derive(clap::Parser, Debug)]
+pub struct Preview {
+    #[command(flatten)]
+    pub update: Update,
+
+    /// Render the commits of this range instead of the ones of the next release.
+    /// Format: `<from>..<to>` or `<to>`, e.g. `v1.0.0..HEAD`.
+    #[arg(long, value_parser = NonEmptyStringValueParser::new())]
+    range: Option<String>,
+
+    /// Path to a file containing the changelog body template to use
+    /// instead of the configured one.
+    #[arg(long, value_parser = PathBufValueParser::new())]
+    template: Option<PathBuf>,
+
+    /// Output format. If unspecified, prints the changelog entries in Markdown.
+    /// If `json`, prints the entries together with the template context used to render them.
+    #[arg(short, long, value_enum)]
+    pub output: Option<OutputType>,
+}
+
+impl Preview {
+    pub fn update_request(
+        &self,
+        config: &Config,
+        cargo_metadata: Metadata,
+    ) -> anyhow::Result<UpdateRequest> {
+        let request = self.update.update_request(config, cargo_metadata)?;
+        let Some(template) = &self.template else {
+            return Ok(request);
+        };
+        let body = fs_err::read_to_string(template).context("cannot read changelog template")?;
+        let mut changelog_req = request.changelog_req().clone();
+        changelog_req
+            .changelog_config
+            .get_or_insert_with(release_plz_core::default_git_cliff_config)
+            .changelog
+            .body = body;
+        Ok(request.with_changelog_req(changelog_req))
+    }
+
+    pub fn range(&self) -> anyhow::Result<Option<CommitRange>> {
+        self.range.as_deref().map(str::parse).transpose()
+    }
+}
@@ -0,0 +1,182 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use clap::{ValueEnum, builder::PathBufValueParser};
+use tracing::warn;
+
+use super::{config_path::ConfigPath, manifest_command::ManifestCommand};
+use crate::{config::Config, config_import, config_validation};
+
+#[derive(clap::Parser, Debug)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub command: ConfigCommand,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Print the release-plz configuration.
+    ///
+    /// By default, release-plz prints the config file.
+    /// Use `--resolved` to print the configuration that release-plz uses.
+    Show(Show),
+    /// Check the release-plz configuration.
+    ///
+    /// Besides the syntax, release-plz renders the templates with sample values,
+    /// compiles the regexes and checks the package names against the cargo workspace.
+    /// All the problems are printed at once.
+    Validate(Validate),
+    /// Translate the configuration of another release tool into a release-plz config file.
+    ///
+    /// The config file is printed to stdout: review it and save it as `release-plz.toml`.
+    /// The options without an equivalent in release-plz are listed at the top of the file.
+    Import(Import),
+}
+
+#[derive(clap::Parser, Debug)]
+pub struct Show {
+    /// Path to the Cargo.toml of the project.
+    /// If not provided, release-plz will use the Cargo.toml of the current directory.
+    #[arg(long, value_parser = PathBufValueParser::new())]
+    manifest_path: Option<PathBuf>,
+    #[command(flatten)]
+    pub config: ConfigPath,
+    /// Resolve the `extends` of the config file and merge the configuration
+    /// in the metadata of the Cargo manifests.
+    /// Each value is followed by a comment with its source.
+    /// Settings that aren't printed have their default value.
+    #[arg(long)]
+    pub resolved: bool,
+}
+
+impl Show {
+    /// The configuration to print.
+    pub fn output(&self) -> anyhow::Result<String> {
+        if self.resolved {
+            let metadata = self.cargo_metadata()?;
+            let config = self.config.load_sourced(&metadata)?;
+            config
+                .to_config()
+                .context("invalid release-plz configuration")?;
+            return config.to_annotated_toml();
+        }
+        match self.config.load_file()? {
+            Some((path, _)) => fs_err::read_to_string(path).map_err(Into::into),
+            None => anyhow::bail!("release-plz config file not found"),
+        }
+    }
+}
+
+impl ManifestCommand for Show {
+    fn optional_manifest(&self) -> Option<&Path> {
+        self.manifest_path.as_deref()
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+pub struct Validate {
+    /// Path to the Cargo.toml of the project.
+    /// If not provided, release-plz will use the Cargo.toml of the current directory.
+    #[arg(long, value_parser = PathBufValueParser::new())]
+    manifest_path: Option<PathBuf>,
+    #[command(flatten)]
+    pub config: ConfigPath,
+}
+
+impl Validate {
+    /// Print the problems of the configuration and fail if there are any.
+    pub fn run(&self) -> anyhow::Result<()> {
+        let config_file = match self.config.load_file()? {
+            Some((path, _)) => {
+                let content = fs_err::read_to_string(&path)?;
+                // Deserialize the file on its own to point at the invalid TOML.
+                if let Err(e) = toml::from_str::<Config>(&content) {
+                    anyhow::bail!("invalid config file {}: {e}", path.display());
+                }
+                Some((path, content))
+            }
+            None => None,
+        };
+        let metadata = self.cargo_metadata()?;
+        let sourced = self.config.load_all_branches(&metadata)?;
+        let config = sourced
+            .to_config()
+            .context("invalid release-plz configuration")?;
+        let workspace_packages: Vec<&str> = metadata
+            .workspace_packages()
+            .into_iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        let problems = config_validation::problems(&config, &workspace_packages);
+        if problems.is_empty() {
+            println!("the release-plz configuration is valid");
+            return Ok(());
+        }
+        let config_file = config_file
+            .as_ref()
+            .map(|(path, content)| (path.as_path(), content.as_str()));
+        print!(
+            "{}",
+            config_validation::report(&problems, &sourced, config_file)
+        );
+        anyhow::bail!(
+            "found {} problem(s) in the release-plz configuration",
+            problems.len()
+        )
+    }
+}
+
+impl ManifestCommand for Validate {
+    fn optional_manifest(&self) -> Option<&Path> {
+        self.manifest_path.as_deref()
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+pub struct Import {
+    /// Path to the Cargo.toml of the project.
+    /// If not provided, release-plz will use the Cargo.toml of the current directory.
+    #[arg(long, value_parser = PathBufValueParser::new())]
+    manifest_path: Option<PathBuf>,
+    /// Tool to import the configuration from.
+    #[arg(long, value_enum)]
+    pub from: ImportSource,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ImportSource {
+    /// Read the `release.toml` files and the `[workspace.metadata.release]` and
+    /// `[package.metadata.release]` tables of the Cargo manifests.
+    CargoRelease,
+}
+
+impl Import {
+    /// The imported release-plz config file.
+    pub fn output(&self) -> anyhow::Result<String> {
+        let metadata = self.cargo_metadata()?;
+        let (tool, import) = match self.from {
+            ImportSource::CargoRelease => (
+                "cargo-release",
+                config_import::from_cargo_release(&metadata)?,
+            ),
+        };
+        import
+            .config
+            .to_config()
+            .context("the imported configuration is invalid")?;
+        if !import.unsupported.is_empty() {
+            warn!(
+                "{} {tool} option(s) don't have an equivalent in release-plz: \
+                 see the comments at the top of the generated configuration",
+                import.unsupported.len()
+            );
+        }
+        import.to_toml(tool)
+    }
+}
+
+impl ManifestCommand for Import {
+    fn optional_manifest(&self) -> Option<&Path> {
+        self.manifest_path.as_deref()
+    }
+}
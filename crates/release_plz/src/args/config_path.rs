@@ -4,13 +4,22 @@ use std::{
 };
 
 use anyhow::{Context as _, bail};
+use cargo_metadata::{Metadata, camino::Utf8Path};
 use clap::Args;
 use fs_err::read_to_string;
-use tracing::info;
+use git_cmd::Repo;
+use toml::{Table, Value};
+use tracing::{info, warn};
 
-use crate::config::Config;
+use crate::{
+    config::Config,
+    config_sources::{SourcedConfig, resolve_extends},
+};
+
+pub const DEFAULT_CONFIG_PATHS: &[&str] = &["release-plz.toml", ".release-plz.toml"];
 
-const DEFAULT_CONFIG_PATHS: &[&str] = &["release-plz.toml", ".release-plz.toml"];
+/// Key of the release-plz configuration in the `metadata` tables of `Cargo.toml`.
+const METADATA_KEY: &str = "release-plz";
 
 /// A clap [`Args`] struct that specifies the path to the release-plz config file.
 #[derive(Debug, Default, Args)]
@@ -20,58 +29,261 @@ pub struct ConfigPath {
     /// If not specified, the following paths are checked in order: `./release-plz.toml`,
     /// `./.release-plz.toml`
     ///
-    /// If a config file is not found, the default configuration is used.
+    /// The config file is merged with the `[workspace.metadata.release-plz]` and
+    /// `[package.metadata.release-plz]` tables of the Cargo manifests.
+    /// If no configuration is found, the default configuration is used.
     #[arg(long = "config", value_name = "PATH")]
     path: Option<PathBuf>,
+    /// Branch used to select the `[[branch]]` sections of the config file.
+    ///
+    /// If not specified, release-plz uses the current git branch.
+    /// When `HEAD` is detached, e.g. in CI jobs that check out a commit, release-plz reads
+    /// the branch from the `CI_COMMIT_BRANCH`, `GITHUB_HEAD_REF` and `GITHUB_REF_NAME`
+    /// environment variables. If none of them is set, no `[[branch]]` section is applied.
+    #[arg(long, value_name = "BRANCH")]
+    config_branch: Option<String>,
+    /// Directory of the default config file paths.
+    /// If not specified, they are relative to the current directory.
+    #[arg(skip)]
+    default_dir: Option<PathBuf>,
 }
 
 impl ConfigPath {
-    /// Load the release-plz configuration from the specified path or default paths.
+    /// Look for the default config file paths in `dir` instead of the current directory.
+    pub fn with_default_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.default_dir = Some(dir.into());
+        self
+    }
+
+    /// Load the release-plz configuration from the config file and from the Cargo manifests
+    /// of the workspace.
     ///
     /// If a path is specified, it will attempt to load the configuration from that file. If the
     /// file does not exist, it will return an error. If no path is specified, it will check the
     /// default paths (`release-plz.toml` and `.release-plz.toml`) and load the first one that
     /// exists.
-    pub fn load(&self) -> anyhow::Result<Config> {
+    ///
+    /// A setting can be specified either in the config file or in the Cargo manifests:
+    /// if it's specified in both, an error is returned.
+    pub fn load(&self, metadata: &Metadata) -> anyhow::Result<Config> {
+        self.load_sourced(metadata)?
+            .to_config()
+            .context("invalid release-plz configuration")
+    }
+
+    /// Like [`ConfigPath::load`], but each value remembers its source.
+    pub fn load_sourced(&self, metadata: &Metadata) -> anyhow::Result<SourcedConfig> {
+        let mut config = self.load_all_branches(metadata)?;
+        config.apply_branch_overrides(|| match &self.config_branch {
+            Some(branch) => Ok(Some(branch.clone())),
+            None => current_branch(&metadata.workspace_root, |name| std::env::var(name).ok()),
+        })?;
+        Ok(config)
+    }
+
+    /// Like [`ConfigPath::load_sourced`], but the `[[branch]]` sections are kept
+    /// instead of being applied to the current branch.
+    pub fn load_all_branches(&self, metadata: &Metadata) -> anyhow::Result<SourcedConfig> {
+        let manifest_config = manifest_config(metadata)?;
+        let mut config = self.load_with(manifest_config)?;
+        config.interpolate_env(|name| std::env::var(name).ok())?;
+        Ok(config)
+    }
+
+    fn load_with(&self, manifest_config: SourcedConfig) -> anyhow::Result<SourcedConfig> {
+        let mut config = match self.load_file()? {
+            Some((path, table)) => {
+                let base_dir = path.parent().unwrap_or(Path::new(""));
+                resolve_extends(table, &path.display().to_string(), base_dir)?
+            }
+            None => {
+                if manifest_config.is_empty() {
+                    info!("release-plz config file not found, using default configuration");
+                }
+                SourcedConfig::default()
+            }
+        };
+        if !manifest_config.is_empty() {
+            info!("using release-plz config from the metadata of Cargo.toml");
+            config
+                .merge_disjoint(manifest_config)
+                .context("conflicting release-plz configuration")?;
+        }
+        Ok(config)
+    }
+
+    /// Where the release-plz configuration of the workspace is, if the workspace has one.
+    /// It looks for the config file and for the Cargo manifests metadata like
+    /// [`ConfigPath::load`].
+    pub fn existing_config(&self, metadata: &Metadata) -> anyhow::Result<Option<String>> {
+        self.existing_config_with(&manifest_config(metadata)?)
+    }
+
+    fn existing_config_with(
+        &self,
+        manifest_config: &SourcedConfig,
+    ) -> anyhow::Result<Option<String>> {
+        if let Some((path, _)) = self.load_file()? {
+            return Ok(Some(path.display().to_string()));
+        }
+        if !manifest_config.is_empty() {
+            return Ok(Some("the release-plz metadata of Cargo.toml".to_string()));
+        }
+        Ok(None)
+    }
+
+    /// Find and parse the config file.
+    pub fn load_file(&self) -> anyhow::Result<Option<(PathBuf, Table)>> {
         if let Some(path) = self.path.as_deref() {
             match load_config(path) {
-                Ok(Some(config)) => return Ok(config),
+                Ok(Some(table)) => return Ok(Some((path.to_path_buf(), table))),
                 Ok(None) => bail!("specified config file {} does not exist", path.display()),
                 Err(err) => return Err(err.context("failed to read config file")),
             }
         }
 
         for path in DEFAULT_CONFIG_PATHS {
-            let path = Path::new(path);
-            match load_config(path) {
-                Ok(Some(config)) => return Ok(config),
+            let path = match &self.default_dir {
+                Some(dir) => dir.join(path),
+                None => PathBuf::from(path),
+            };
+            match load_config(&path) {
+                Ok(Some(table)) => return Ok(Some((path, table))),
                 Ok(None) => (),
                 Err(err) => return Err(err.context("invalid config file")),
             }
         }
+        Ok(None)
+    }
+}
+
+/// Environment variables containing the branch of the CI job, in order of priority.
+/// - `CI_COMMIT_BRANCH`: GitLab CI. Not set in merge request pipelines.
+/// - `GITHUB_HEAD_REF`: source branch of the pull request in GitHub Actions.
+///   Empty for other events.
+/// - `GITHUB_REF_NAME`: branch or tag that triggered the GitHub Actions workflow.
+const CI_BRANCH_VARS: &[&str] = &["CI_COMMIT_BRANCH", "GITHUB_HEAD_REF", "GITHUB_REF_NAME"];
 
-        info!("release-plz config file not found, using default configuration");
-        Ok(Config::default())
+/// Current git branch. When `HEAD` is detached, the branch is read from the environment
+/// variables of the CI. Returns `None` if the branch can't be determined.
+fn current_branch(
+    workspace_root: &Utf8Path,
+    env: impl Fn(&str) -> Option<String>,
+) -> anyhow::Result<Option<String>> {
+    // `git rev-parse --abbrev-ref HEAD` returns `HEAD` when `HEAD` is detached.
+    let head = git_cmd::git_in_dir(workspace_root, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+    if head != "HEAD" {
+        let repo = Repo::new(workspace_root).context("can't open the git repository")?;
+        return Ok(Some(repo.original_branch().to_string()));
     }
+    let is_github_tag = env("GITHUB_REF_TYPE").as_deref() == Some("tag");
+    let ci_branch = CI_BRANCH_VARS
+        .iter()
+        .filter(|var| !(is_github_tag && **var == "GITHUB_REF_NAME"))
+        .find_map(|var| env(var).filter(|branch| !branch.is_empty()));
+    if ci_branch.is_none() {
+        warn!(
+            "`HEAD` is detached, so release-plz can't determine the current branch and \
+            doesn't apply the `[[branch]]` sections. Specify the branch with `--config-branch`"
+        );
+    }
+    Ok(ci_branch)
 }
 
 /// Try to load the configuration from the specified path.
 ///
-/// Returns `Ok(Some(config))` if the file is found and valid, `Ok(None)` if the file does not exist,
+/// Returns `Ok(Some(table))` if the file is found and is valid TOML, `Ok(None)` if the file does not exist,
 /// and an error if the file exists but is invalid.
-fn load_config(path: &Path) -> anyhow::Result<Option<Config>> {
+fn load_config(path: &Path) -> anyhow::Result<Option<Table>> {
     match read_to_string(path) {
         Ok(contents) => {
-            let config = toml::from_str(&contents)
+            let table = toml::from_str(&contents)
                 .with_context(|| format!("invalid config file {}", path.display()))?;
             info!("using release-plz config file {}", path.display());
-            Ok(Some(config))
+            Ok(Some(table))
         }
         Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
         Err(err) => Err(err.into()),
     }
 }
 
+/// release-plz configuration found in the Cargo manifests of the workspace.
+/// `[workspace.metadata.release-plz]` goes to `[workspace]`, except for its `changelog`
+/// table, which goes to `[changelog]`.
+/// `[package.metadata.release-plz]` goes to the `[[package]]` with the same name.
+fn manifest_config(metadata: &Metadata) -> anyhow::Result<SourcedConfig> {
+    let workspace = metadata_table(&metadata.workspace_metadata)
+        .context("invalid [workspace.metadata.release-plz]")?;
+    let mut config = workspace_metadata_config(workspace)?;
+    for package in metadata.workspace_packages() {
+        let table = metadata_table(&package.metadata).with_context(|| {
+            format!(
+                "invalid [package.metadata.release-plz] of package {}",
+                package.name
+            )
+        })?;
+        if table.is_empty() {
+            continue;
+        }
+        let manifest = package
+            .manifest_path
+            .strip_prefix(&metadata.workspace_root)
+            .unwrap_or(&package.manifest_path);
+        config.merge_disjoint(package_metadata_config(&package.name, table, manifest)?)?;
+    }
+    Ok(config)
+}
+
+fn workspace_metadata_config(mut workspace: Table) -> anyhow::Result<SourcedConfig> {
+    let mut table = Table::new();
+    if let Some(changelog) = workspace.remove("changelog") {
+        anyhow::ensure!(
+            changelog.is_table(),
+            "`changelog` of [workspace.metadata.release-plz] must be a table"
+        );
+        table.insert("changelog".to_string(), changelog);
+    }
+    if !workspace.is_empty() {
+        table.insert("workspace".to_string(), Value::Table(workspace));
+    }
+    Ok(SourcedConfig::new(
+        table,
+        "Cargo.toml [workspace.metadata.release-plz]",
+    ))
+}
+
+fn package_metadata_config(
+    name: &str,
+    mut package: Table,
+    manifest: &Utf8Path,
+) -> anyhow::Result<SourcedConfig> {
+    anyhow::ensure!(
+        !package.contains_key("name"),
+        "[package.metadata.release-plz] of package {name} can't contain `name`"
+    );
+    package.insert("name".to_string(), Value::String(name.to_string()));
+    let mut table = Table::new();
+    table.insert(
+        "package".to_string(),
+        Value::Array(vec![Value::Table(package)]),
+    );
+    Ok(SourcedConfig::new(
+        table,
+        &format!("{manifest} [package.metadata.release-plz]"),
+    ))
+}
+
+/// The `release-plz` table of the given `metadata` of a Cargo manifest.
+fn metadata_table(metadata: &serde_json::Value) -> anyhow::Result<Table> {
+    match metadata.get(METADATA_KEY) {
+        None | Some(serde_json::Value::Null) => Ok(Table::new()),
+        Some(value) => match Value::try_from(value)? {
+            Value::Table(table) => Ok(table),
+            _ => bail!("expected a table"),
+        },
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::Write;
@@ -87,9 +299,18 @@ mod tests {
 
         let config_path = ConfigPath {
             path: Some(temp_file.path().to_path_buf()),
+            config_branch: None,
+            default_dir: None,
         };
 
-        assert_eq!(config_path.load().unwrap(), Config::default());
+        assert_eq!(
+            config_path
+                .load_with(SourcedConfig::default())
+                .unwrap()
+                .to_config()
+                .unwrap(),
+            Config::default()
+        );
     }
 
     #[test]
@@ -99,9 +320,11 @@ mod tests {
 
         let config_path = ConfigPath {
             path: Some(non_existent_path),
+            config_branch: None,
+            default_dir: None,
         };
 
-        let result = config_path.load().unwrap_err();
+        let result = config_path.load_with(SourcedConfig::default()).unwrap_err();
         assert!(result.to_string().contains("specified config file"));
     }
 
@@ -112,9 +335,14 @@ mod tests {
 
         let config_path = ConfigPath {
             path: Some(temp_file.path().to_path_buf()),
+            config_branch: None,
+            default_dir: None,
         };
 
-        let result = format!("{:?}", config_path.load().unwrap_err());
+        let result = format!(
+            "{:?}",
+            config_path.load_with(SourcedConfig::default()).unwrap_err()
+        );
         assert!(result.contains("invalid config file"));
     }
 
@@ -125,21 +353,208 @@ mod tests {
         let default_config = toml::to_string(&Config::default()).unwrap();
         fs_err::write(&default_config_path, default_config).unwrap();
 
-        let config_path = ConfigPath { path: None };
+        let config_path = ConfigPath {
+            path: None,
+            config_branch: None,
+            default_dir: None,
+        };
+
+        assert_eq!(
+            config_path
+                .load_with(SourcedConfig::default())
+                .unwrap()
+                .to_config()
+                .unwrap(),
+            Config::default()
+        );
+    }
+
+    #[test]
+    fn default_paths_are_looked_up_in_default_dir() {
+        let temp_dir = tempdir().unwrap();
+        let config_file = temp_dir.path().join(".release-plz.toml");
+        fs_err::write(&config_file, "[workspace]\npr_draft = true\n").unwrap();
 
-        assert_eq!(config_path.load().unwrap(), Config::default());
+        let config_path = ConfigPath::default().with_default_dir(temp_dir.path());
+        let (path, table) = config_path.load_file().unwrap().unwrap();
+        assert_eq!(path, config_file);
+        assert_eq!(table["workspace"]["pr_draft"].as_bool(), Some(true));
     }
 
     #[test]
     fn load_config_no_config_file_uses_default() {
         let temp_dir = tempdir().unwrap();
-        let config_path = ConfigPath { path: None };
+        let config_path = ConfigPath {
+            path: None,
+            config_branch: None,
+            default_dir: None,
+        };
 
         // Ensure no config file exists
         assert!(!temp_dir.path().join("release-plz.toml").exists());
         assert!(!temp_dir.path().join(".release-plz.toml").exists());
 
         // Load the config, which should return the default
-        assert_eq!(config_path.load().unwrap(), Config::default());
+        assert_eq!(
+            config_path
+                .load_with(SourcedConfig::default())
+                .unwrap()
+                .to_config()
+                .unwrap(),
+            Config::default()
+        );
+    }
+
+    #[test]
+    fn detached_head_branch_is_read_from_ci_env() {
+        let temp_dir = tempdir().unwrap();
+        let repo = Repo::init(temp_dir.path());
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let no_env = |_: &str| None;
+        assert_eq!(
+            current_branch(root, no_env).unwrap().as_deref(),
+            Some(repo.original_branch())
+        );
+
+        repo.git(&["checkout", "--detach"]).unwrap();
+        assert_eq!(current_branch(root, no_env).unwrap(), None);
+
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| (*value).to_string())
+            }
+        };
+        let gitlab = env(&[("CI_COMMIT_BRANCH", "release/1.x")]);
+        assert_eq!(
+            current_branch(root, gitlab).unwrap().as_deref(),
+            Some("release/1.x")
+        );
+        let github_push = env(&[("GITHUB_HEAD_REF", ""), ("GITHUB_REF_NAME", "main")]);
+        assert_eq!(
+            current_branch(root, github_push).unwrap().as_deref(),
+            Some("main")
+        );
+        let github_pr = env(&[("GITHUB_HEAD_REF", "feat"), ("GITHUB_REF_NAME", "1/merge")]);
+        assert_eq!(
+            current_branch(root, github_pr).unwrap().as_deref(),
+            Some("feat")
+        );
+        let github_tag = env(&[("GITHUB_REF_TYPE", "tag"), ("GITHUB_REF_NAME", "v1.0.0")]);
+        assert_eq!(current_branch(root, github_tag).unwrap(), None);
+    }
+
+    fn manifest_config(workspace: &str, packages: &[(&str, &str)]) -> SourcedConfig {
+        let mut config = workspace_metadata_config(toml::from_str(workspace).unwrap()).unwrap();
+        for (name, package) in packages {
+            let manifest = Utf8Path::new(name).join("Cargo.toml");
+            let package_config =
+                package_metadata_config(name, toml::from_str(package).unwrap(), &manifest);
+            config.merge_disjoint(package_config.unwrap()).unwrap();
+        }
+        config
+    }
+
+    fn config_file(contents: &str) -> (NamedTempFile, ConfigPath) {
+        let temp_file = NamedTempFile::new().unwrap();
+        fs_err::write(&temp_file, contents).unwrap();
+        let config_path = ConfigPath {
+            path: Some(temp_file.path().to_path_buf()),
+            config_branch: None,
+            default_dir: None,
+        };
+        (temp_file, config_path)
+    }
+
+    #[test]
+    fn manifest_config_is_merged_with_config_file() {
+        let (_file, config_path) = config_file(
+            r#"
+            [workspace]
+            pr_draft = true
+
+            [[package]]
+            name = "a"
+            semver_check = false
+            "#,
+        );
+        let manifest = manifest_config(
+            r#"
+            release_always = false
+            [changelog]
+            protect_breaking_commits = true
+            "#,
+            &[("a", "publish = false"), ("b", "release = false")],
+        );
+
+        let expected: Config = toml::from_str(
+            r#"
+            [workspace]
+            pr_draft = true
+            release_always = false
+
+            [changelog]
+            protect_breaking_commits = true
+
+            [[package]]
+            name = "a"
+            semver_check = false
+            publish = false
+
+            [[package]]
+            name = "b"
+            release = false
+            "#,
+        )
+        .unwrap();
+        let config = config_path
+            .load_with(manifest)
+            .unwrap()
+            .to_config()
+            .unwrap();
+        assert_eq!(config, expected);
+    }
+
+    #[test]
+    fn setting_in_config_file_and_manifest_is_a_conflict() {
+        let (_file, config_path) = config_file("[workspace]\nrelease_always = true\n");
+        let manifest = manifest_config("release_always = false", &[]);
+
+        let error = format!("{:?}", config_path.load_with(manifest).unwrap_err());
+        assert!(error.contains("`workspace.release_always` is set both in"));
+        assert!(error.contains("and in Cargo.toml [workspace.metadata.release-plz]"));
+    }
+
+    #[test]
+    fn package_setting_in_config_file_and_manifest_is_a_conflict() {
+        let (_file, config_path) = config_file("[[package]]\nname = \"a\"\npublish = true\n");
+        let manifest = manifest_config("", &[("a", "publish = false")]);
+
+        let error = format!("{:?}", config_path.load_with(manifest).unwrap_err());
+        assert!(error.contains("`package.a.publish` is set both in"));
+        assert!(error.contains("and in a/Cargo.toml [package.metadata.release-plz]"));
+    }
+
+    #[test]
+    fn existing_config_includes_manifest_metadata() {
+        let config_path = ConfigPath::default();
+        assert_eq!(
+            config_path
+                .existing_config_with(&SourcedConfig::default())
+                .unwrap(),
+            None
+        );
+        let manifest = manifest_config("release_always = false", &[]);
+        assert_eq!(
+            config_path.existing_config_with(&manifest).unwrap(),
+            Some("the release-plz metadata of Cargo.toml".to_string())
+        );
+
+        let (file, config_path) = config_file("[workspace]\npr_draft = true\n");
+        assert_eq!(
+            config_path.existing_config_with(&manifest).unwrap(),
+            Some(file.path().display().to_string())
+        );
     }
 }
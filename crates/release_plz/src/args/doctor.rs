@@ -0,0 +1,118 @@
+use std::collections::BTreeSet;
+
+use anyhow::Context as _;
+use release_plz_core::GitClient;
+
+use super::{
+    manifest_command::ManifestCommand as _, repo_command::RepoCommand as _, update::Update,
+};
+use crate::{
+    config::Config,
+    config_validation,
+    doctor::{self, Check, Report},
+};
+
+/// Registry name used by cargo for crates.io.
+const CRATES_IO: &str = "crates-io";
+
+#[derive(clap::Parser, Debug)]
+pub struct Doctor {
+    #[command(flatten)]
+    pub update: Update,
+}
+
+impl Doctor {
+    /// Print the report of all the checks and fail if there are blocking problems.
+    pub async fn run(&self) -> anyhow::Result<()> {
+        let manifest_path = self.update.manifest_path();
+        let metadata = self.update.cargo_metadata()?;
+        let repo_path = release_plz_core::root_repo_path(&manifest_path)
+            .context("can't find the git repository of the project")?;
+        let mut report = Report::default();
+
+        let config = match self.update.config.load(&metadata) {
+            Ok(config) => {
+                report.push(configuration(&config, &metadata));
+                config
+            }
+            Err(e) => {
+                report.push(Check::error(
+                    "configuration",
+                    format!("{e:#}"),
+                    "Run `release-plz config validate` for details.",
+                ));
+                Config::default()
+            }
+        };
+
+        report.push(doctor::git_branch(&repo_path));
+        report.push(doctor::git_history(&repo_path));
+        report.push(doctor::working_tree(
+            &repo_path,
+            self.update.allow_dirty(&config),
+        ));
+        report.push(doctor::tag_signing(&repo_path));
+        report.push(doctor::semver_checks(is_semver_check_enabled(&config)));
+        for registry in self.registries(&metadata) {
+            report.push(doctor::registry(&manifest_path, &registry));
+        }
+        report.push(doctor::forge_token(self.git_client(&config)).await);
+
+        print!("{report}");
+        let errors = report.errors();
+        anyhow::ensure!(errors == 0, "found {errors} blocking problem(s)");
+        println!("release-plz is ready to run");
+        Ok(())
+    }
+
+    /// Registries where the packages are published, besides crates.io.
+    fn registries(&self, metadata: &cargo_metadata::Metadata) -> BTreeSet<String> {
+        let mut registries: BTreeSet<String> = metadata
+            .workspace_packages()
+            .iter()
+            .filter_map(|package| package.publish.as_ref()?.first().cloned())
+            .filter(|registry| registry != CRATES_IO)
+            .collect();
+        registries.extend(self.update.registry().map(str::to_string));
+        registries
+    }
+
+    fn git_client(&self, config: &Config) -> anyhow::Result<Option<GitClient>> {
+        if self.update.git_token.is_none() && !self.update.github_app.is_set() {
+            return Ok(None);
+        }
+        let repo_url = self.update.get_repo_url(config)?;
+        self.update
+            .git_forge(repo_url)?
+            .map(GitClient::new)
+            .transpose()
+    }
+}
+
+fn configuration(config: &Config, metadata: &cargo_metadata::Metadata) -> Check {
+    const NAME: &str = "configuration";
+    let workspace_packages: Vec<&str> = metadata
+        .workspace_packages()
+        .into_iter()
+        .map(|p| p.name.as_str())
+        .collect();
+    let problems = config_validation::problems(config, &workspace_packages);
+    if problems.is_empty() {
+        Check::ok(NAME, "valid")
+    } else {
+        Check::error(
+            NAME,
+            format!("found {} problem(s)", problems.len()),
+            "Run `release-plz config validate` for details.",
+        )
+    }
+}
+
+/// Whether at least one package runs cargo-semver-checks.
+fn is_semver_check_enabled(config: &Config) -> bool {
+    config.workspace.packages_defaults.semver_check != Some(false)
+        || config
+            .package
+            .iter()
+            .any(|package| package.config.common().semver_check == Some(true))
+}
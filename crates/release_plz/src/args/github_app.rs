@@ -0,0 +1,82 @@
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use clap::{
+    Args,
+    builder::{NonEmptyStringValueParser, PathBufValueParser},
+};
+use release_plz_core::{GitHub, GitHubApp, RepoUrl};
+use secrecy::SecretString;
+
+/// A clap [`Args`] struct with the credentials of the GitHub App used to authenticate to GitHub.
+#[derive(Debug, Default, Args)]
+pub struct GitHubAppArgs {
+    /// ID of the GitHub App used to authenticate to GitHub.
+    /// Use it instead of `--git-token` to authenticate as the installation of the app in
+    /// the repository. The installation token is refreshed automatically when it expires.
+    #[arg(long, env, value_name = "ID")]
+    github_app_id: Option<u64>,
+
+    /// Private key of the GitHub App, in PEM format.
+    #[arg(
+        long,
+        env,
+        hide_env_values = true,
+        value_parser = NonEmptyStringValueParser::new(),
+        conflicts_with = "github_app_private_key_path",
+        requires = "github_app_id"
+    )]
+    github_app_private_key: Option<String>,
+
+    /// Path to the private key of the GitHub App, in PEM format.
+    #[arg(
+        long,
+        env,
+        value_name = "PATH",
+        value_parser = PathBufValueParser::new(),
+        requires = "github_app_id"
+    )]
+    github_app_private_key_path: Option<PathBuf>,
+}
+
+impl GitHubAppArgs {
+    pub fn is_set(&self) -> bool {
+        self.github_app_id.is_some()
+    }
+
+    fn github_app(&self, app_id: u64) -> anyhow::Result<GitHubApp> {
+        let private_key = match (
+            &self.github_app_private_key,
+            &self.github_app_private_key_path,
+        ) {
+            (Some(private_key), _) => private_key.clone(),
+            (None, Some(path)) => fs_err::read_to_string(path)
+                .context("can't read the private key of the GitHub App")?,
+            (None, None) => anyhow::bail!(
+                "please provide the private key of the GitHub App with the --github-app-private-key or --github-app-private-key-path cli arguments."
+            ),
+        };
+        Ok(GitHubApp::new(app_id, SecretString::from(private_key)))
+    }
+
+    /// GitHub client authenticated as the GitHub App if its ID is set, or with the token otherwise.
+    pub fn github(&self, repo: RepoUrl, token: Option<SecretString>) -> anyhow::Result<GitHub> {
+        let api_url = repo
+            .github_api_url()
+            .parse()
+            .context("invalid GitHub API URL")?;
+        let github = if let Some(app_id) = self.github_app_id {
+            let app = self.github_app(app_id)?;
+            GitHub::from_app(repo.owner, repo.name, app)
+        } else {
+            let token = token.context("please provide the git token with the --git-token cli argument or the GitHub App ID with the --github-app-id cli argument.")?;
+            GitHub::new(repo.owner, repo.name, token)
+        };
+        Ok(github.with_base_url(api_url))
+    }
+}
+
+/// Token of the Gitea and GitLab forges, which don't support GitHub App authentication.
+pub fn forge_token(token: Option<SecretString>) -> anyhow::Result<SecretString> {
+    token.context("please provide the git token with the --git-token cli argument. GitHub App authentication is only supported with the GitHub forge.")
+}
@@ -1,8 +1,12 @@
 use std::path::{Path, PathBuf};
 
-use clap::builder::PathBufValueParser;
+use clap::{
+    ValueEnum,
+    builder::{NonEmptyStringValueParser, PathBufValueParser},
+};
 
 use super::manifest_command::ManifestCommand;
+use crate::init::{Forge, InitOptions};
 
 #[derive(clap::Parser, Debug)]
 pub struct Init {
@@ -13,7 +17,72 @@ pub struct Init {
     manifest_path: Option<PathBuf>,
     /// If set, don't check if the toml files contain `description` and `license` fields, which are mandatory for crates.io.
     #[arg(long)]
-    pub no_toml_check: bool,
+    no_toml_check: bool,
+    /// Git forge where your project is hosted. It determines the CI files to generate.
+    #[arg(long, value_enum, default_value_t = InitForge::Github)]
+    forge: InitForge,
+    /// Don't ask questions: answer yes to all of them and skip the steps that need user input,
+    /// like storing secrets. The skipped steps are printed at the end.
+    #[arg(short, long)]
+    yes: bool,
+    /// Use crates.io trusted publishing instead of a cargo registry token.
+    /// Only supported on GitHub.
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    trusted_publishing: Option<bool>,
+    /// Name of the secret containing the token release-plz uses to open PRs and create releases.
+    /// On GitHub, use `GITHUB_TOKEN` to use the token of GitHub Actions instead of a
+    /// Personal Access Token.
+    /// Default: `RELEASE_PLZ_TOKEN`.
+    #[arg(long, value_name = "NAME", value_parser = NonEmptyStringValueParser::new())]
+    token_secret_name: Option<String>,
+    /// Branch that triggers release-plz.
+    /// If not provided, release-plz uses the default branch of the repository.
+    #[arg(long, value_parser = NonEmptyStringValueParser::new())]
+    branch: Option<String>,
+    /// Print the files instead of writing them, and don't store any secret.
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InitForge {
+    /// Generates `.github/workflows/release-plz.yml`.
+    #[value(name = "github")]
+    Github,
+    /// Generates `.gitlab-ci.yml`.
+    #[value(name = "gitlab")]
+    Gitlab,
+    /// Generates `.gitea/workflows/release-plz.yml`.
+    #[value(name = "gitea")]
+    Gitea,
+    /// Generates `.forgejo/workflows/release-plz.yml`.
+    #[value(name = "forgejo")]
+    Forgejo,
+}
+
+impl From<InitForge> for Forge {
+    fn from(value: InitForge) -> Self {
+        match value {
+            InitForge::Github => Self::Github,
+            InitForge::Gitlab => Self::Gitlab,
+            InitForge::Gitea => Self::Gitea,
+            InitForge::Forgejo => Self::Forgejo,
+        }
+    }
+}
+
+impl Init {
+    pub fn init_options(&self) -> InitOptions {
+        InitOptions {
+            forge: self.forge.into(),
+            toml_check: !self.no_toml_check,
+            yes: self.yes,
+            trusted_publishing: self.trusted_publishing,
+            token_secret_name: self.token_secret_name.clone(),
+            branch: self.branch.clone(),
+            dry_run: self.dry_run,
+        }
+    }
 }
 
 impl ManifestCommand for Init {
@@ -1,5 +1,9 @@
-mod config_path;
+pub(crate) mod changelog;
+pub(crate) mod config;
+pub(crate) mod config_path;
+mod doctor;
 mod generate_completions;
+mod github_app;
 mod init;
 pub(crate) mod manifest_command;
 mod release;
@@ -15,14 +19,15 @@ use clap::{
     ValueEnum,
     builder::{Styles, styling::AnsiColor},
 };
+use doctor::Doctor;
 use init::Init;
 use release_plz_core::fs_utils::current_directory;
 use set_version::SetVersion;
 use tracing::level_filters::LevelFilter;
 
 use self::{
-    generate_completions::GenerateCompletions, release::Release, release_pr::ReleasePr,
-    update::Update,
+    changelog::Changelog, config::ConfigArgs, generate_completions::GenerateCompletions,
+    release::Release, release_pr::ReleasePr, update::Update,
 };
 
 const MAIN_COLOR: AnsiColor = AnsiColor::Red;
@@ -96,10 +101,10 @@ pub enum Command {
     /// Write the JSON schema of the release-plz.toml configuration
     /// to .schema/latest.json
     GenerateSchema,
-    /// Initialize release-plz for the current GitHub repository.
+    /// Initialize release-plz for the current repository.
     ///
-    /// Stores the necessary tokens in the GitHub repository secrets and generates the
-    /// release-plz.yml GitHub Actions workflow file.
+    /// Generates the CI files of the git forge where the repository is hosted.
+    /// On GitHub, it also stores the necessary tokens in the repository secrets.
     Init(Init),
     /// Edit the version of a package in Cargo.toml and changelog.
     ///
@@ -115,6 +120,17 @@ pub enum Command {
     /// Note that this command is meant to edit the versions of the packages of your workspace, not the
     /// version of your dependencies.
     SetVersion(SetVersion),
+    /// Manage the changelogs of the packages.
+    Changelog(Changelog),
+    /// Inspect the release-plz configuration.
+    Config(ConfigArgs),
+    /// Diagnose the environment where release-plz runs.
+    ///
+    /// Checks the git repository, the tools, the cargo registries and the configuration,
+    /// and prints how to fix the problems found.
+    /// If a git token is provided, its permissions on the repository are checked, too.
+    /// Exits with an error if a problem prevents release-plz from working.
+    Doctor(Doctor),
 }
 
 #[derive(ValueEnum, Clone, Copy, Debug, Eq, PartialEq)]
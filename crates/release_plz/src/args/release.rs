@@ -4,13 +4,17 @@ use clap::{
     ValueEnum,
     builder::{NonEmptyStringValueParser, PathBufValueParser},
 };
-use release_plz_core::{GitForge, GitHub, GitLab, Gitea, ReleaseRequest};
+use release_plz_core::{ForgeType, GitForge, GitLab, Gitea, ReleaseGate, ReleaseRequest};
 use secrecy::SecretString;
+use url::Url;
 
 use crate::config::Config;
 
 use super::{
-    OutputType, config_path::ConfigPath, manifest_command::ManifestCommand,
+    OutputType,
+    config_path::ConfigPath,
+    github_app::{GitHubAppArgs, forge_token},
+    manifest_command::ManifestCommand,
     repo_command::RepoCommand,
 };
 
@@ -59,10 +63,18 @@ pub struct Release {
     #[arg(long, value_parser = NonEmptyStringValueParser::new(), env, hide_env_values=true)]
     pub git_token: Option<String>,
 
+    #[command(flatten)]
+    pub github_app: GitHubAppArgs,
+
     /// Kind of git forge
     #[arg(long, visible_alias = "backend", value_enum, default_value_t = ReleaseGitForgeKind::Github)]
     forge: ReleaseGitForgeKind,
 
+    /// Root of the REST API of the git forge, e.g. `https://github.example.com/api/v3`.
+    /// Use it when the API url can't be derived from the repository url.
+    #[arg(long, value_name = "URL")]
+    forge_api_url: Option<Url>,
+
     /// Path to the release-plz config file.
     #[command(flatten)]
     pub config: ConfigPath,
@@ -77,29 +89,42 @@ pub struct Release {
 pub enum ReleaseGitForgeKind {
     #[value(name = "github")]
     Github,
-    #[value(name = "gitea")]
+    /// Gitea or Forgejo (e.g. Codeberg).
+    #[value(name = "gitea", alias = "forgejo")]
     Gitea,
     #[value(name = "gitlab")]
     Gitlab,
 }
 
+impl From<ReleaseGitForgeKind> for ForgeType {
+    fn from(value: ReleaseGitForgeKind) -> Self {
+        match value {
+            ReleaseGitForgeKind::Github => Self::Github,
+            ReleaseGitForgeKind::Gitea => Self::Gitea,
+            ReleaseGitForgeKind::Gitlab => Self::Gitlab,
+        }
+    }
+}
+
 impl Release {
     pub fn release_request(
         self,
         config: &Config,
         metadata: cargo_metadata::Metadata,
     ) -> anyhow::Result<ReleaseRequest> {
-        let git_release = if let Some(git_token) = &self.git_token {
-            let git_token = SecretString::from(git_token.clone());
+        let git_release = if self.git_token.is_some() || self.github_app.is_set() {
+            let git_token = self.git_token.clone().map(SecretString::from);
             let repo_url = self.get_repo_url(config)?;
             let release = release_plz_core::GitRelease {
                 forge: match self.forge {
-                    ReleaseGitForgeKind::Gitea => GitForge::Gitea(Gitea::new(repo_url, git_token)?),
+                    ReleaseGitForgeKind::Gitea => {
+                        GitForge::Gitea(Gitea::new(repo_url, forge_token(git_token)?)?)
+                    }
                     ReleaseGitForgeKind::Github => {
-                        GitForge::Github(GitHub::new(repo_url.owner, repo_url.name, git_token))
+                        GitForge::Github(self.github_app.github(repo_url, git_token)?)
                     }
                     ReleaseGitForgeKind::Gitlab => {
-                        GitForge::Gitlab(GitLab::new(repo_url, git_token)?)
+                        GitForge::Gitlab(GitLab::new(repo_url, forge_token(git_token)?)?)
                     }
                 },
             };
@@ -124,6 +149,24 @@ impl Release {
         if let Some(release_always) = config.workspace.release_always {
             req = req.with_release_always(release_always);
         }
+        if let Some(release_comment) = &config.workspace.release_comment {
+            req = req.with_release_comment(release_comment);
+        }
+        req = req.with_release_gate(
+            ReleaseGate::default()
+                .with_required_approvals(
+                    config
+                        .workspace
+                        .release_required_approvals
+                        .unwrap_or_default(),
+                )
+                .with_require_ci_success(
+                    config
+                        .workspace
+                        .release_require_ci_success
+                        .unwrap_or_default(),
+                ),
+        );
 
         req = req.with_publish_timeout(config.workspace.publish_timeout()?);
 
@@ -141,6 +184,14 @@ impl RepoCommand for Release {
     fn repo_url(&self) -> Option<&str> {
         self.repo_url.as_deref()
     }
+
+    fn forge(&self) -> ForgeType {
+        self.forge.into()
+    }
+
+    fn forge_api_url(&self) -> Option<&Url> {
+        self.forge_api_url.as_ref()
+    }
 }
 
 impl ManifestCommand for Release {
@@ -210,7 +261,9 @@ mod tests {
             dry_run: false,
             repo_url: None,
             git_token: None,
+            github_app: GitHubAppArgs::default(),
             forge: ReleaseGitForgeKind::Github,
+            forge_api_url: None,
             config: ConfigPath::default(),
             output: None,
         }
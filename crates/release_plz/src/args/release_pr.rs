@@ -25,13 +25,27 @@ impl ReleasePr {
         let pr_body = config.workspace.pr_body.clone();
         let pr_labels = config.workspace.pr_labels.clone();
         let pr_draft = config.workspace.pr_draft;
+        let pr_mode = config.workspace.pr_mode.unwrap_or_default();
         let update_request = self.update.update_request(config, cargo_metadata)?;
         let request = ReleasePrRequest::new(update_request)
             .mark_as_draft(pr_draft)
             .with_labels(pr_labels)
+            .with_reviewers(config.workspace.pr_reviewers.clone())
+            .with_team_reviewers(config.workspace.pr_team_reviewers.clone())
+            .with_assignees(config.workspace.pr_assignees.clone())
+            .with_milestone_template(config.workspace.pr_milestone.clone())
+            .with_auto_merge(config.workspace.pr_auto_merge.map(Into::into))
+            .with_update_strategy(
+                config
+                    .workspace
+                    .pr_update_strategy
+                    .unwrap_or_default()
+                    .into(),
+            )
             .with_branch_prefix(pr_branch_prefix)
             .with_pr_name_template(pr_name)
-            .with_pr_body_template(pr_body);
+            .with_pr_body_template(pr_body)
+            .with_pr_mode(pr_mode.into());
         Ok(request)
     }
 }
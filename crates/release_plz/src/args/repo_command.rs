@@ -1,5 +1,6 @@
 use git_cmd::Repo;
-use release_plz_core::RepoUrl;
+use release_plz_core::{ForgeType, RepoUrl};
+use url::Url;
 
 use crate::config::Config;
 
@@ -9,16 +10,35 @@ use super::manifest_command::ManifestCommand;
 pub trait RepoCommand: ManifestCommand {
     fn repo_url(&self) -> Option<&str>;
 
+    /// Kind of git forge where the repo is hosted.
+    fn forge(&self) -> ForgeType;
+
+    /// Root of the REST API of the forge specified by the user in the cli.
+    fn forge_api_url(&self) -> Option<&Url>;
+
     fn get_repo_url(&self, config: &Config) -> anyhow::Result<RepoUrl> {
-        match &self.user_repo_url(config) {
-            Some(url) => RepoUrl::new(url),
+        let repo_url = match &self.user_repo_url(config) {
+            Some(url) => RepoUrl::new(url)?,
             None => {
                 let manifest_path = self.manifest_path();
                 let project_dir = release_plz_core::manifest_dir(&manifest_path)?;
                 let repo = Repo::new(project_dir)?;
-                RepoUrl::from_repo(&repo)
+                RepoUrl::from_repo(&repo)?
             }
-        }
+        };
+        let api_url = self
+            .forge_api_url()
+            .or(config.workspace.forge_api_url.as_ref());
+        let repo_url = match api_url {
+            // The forge is self-hosted, so its host doesn't tell which forge it is.
+            Some(api_url) => repo_url
+                .with_forge(self.forge())
+                .with_api_url(api_url.clone()),
+            // GitHub Enterprise Server hosts are detected from the url.
+            None if self.forge() == ForgeType::Github => repo_url,
+            None => repo_url.with_forge(self.forge()),
+        };
+        Ok(repo_url)
     }
 
     /// Repo url specified by user
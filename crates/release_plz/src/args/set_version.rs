@@ -55,8 +55,11 @@ impl SetVersion {
     }
 
     /// Get [`SetVersionRequest`]
-    pub fn set_version_request(self, config: &Config) -> anyhow::Result<SetVersionRequest> {
-        let cargo_metadata = self.cargo_metadata()?;
+    pub fn set_version_request(
+        self,
+        config: &Config,
+        cargo_metadata: cargo_metadata::Metadata,
+    ) -> anyhow::Result<SetVersionRequest> {
         let version_changes = self.parse_versions()?;
         let mut request = SetVersionRequest::new(version_changes, cargo_metadata)?;
         config.fill_set_version_config(&mut request)?;
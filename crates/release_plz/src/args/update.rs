@@ -9,15 +9,19 @@ use clap::{
 };
 use git_cliff_core::config::Config as GitCliffConfig;
 use release_plz_core::{
-    ChangelogRequest, GitForge, GitHub, GitLab, Gitea, RepoUrl, fs_utils::to_utf8_path,
+    ChangelogRequest, ForgeType, GitForge, GitLab, Gitea, RepoUrl, fs_utils::to_utf8_path,
     update_request::UpdateRequest,
 };
 use secrecy::SecretString;
+use url::Url;
 
 use crate::{changelog_config, config::Config};
 
 use super::{
-    config_path::ConfigPath, manifest_command::ManifestCommand, repo_command::RepoCommand,
+    config_path::ConfigPath,
+    github_app::{GitHubAppArgs, forge_token},
+    manifest_command::ManifestCommand,
+    repo_command::RepoCommand,
 };
 
 /// Update your project locally, without opening a PR.
@@ -108,10 +112,19 @@ pub struct Update {
     #[arg(long, value_parser = NonEmptyStringValueParser::new(), visible_alias = "github-token", env, hide_env_values=true)]
     pub git_token: Option<String>,
 
+    #[command(flatten)]
+    pub github_app: GitHubAppArgs,
+
     /// Kind of git host where your project is hosted.
     #[arg(long, visible_alias = "backend", value_enum, default_value_t = GitForgeKind::Github)]
     forge: GitForgeKind,
+
+    /// Root of the REST API of the git forge, e.g. `https://github.example.com/api/v3`.
+    /// Use it when the API url can't be derived from the repository url.
+    #[arg(long, value_name = "URL")]
+    forge_api_url: Option<Url>,
     /// Maximum number of commits to analyze when the package hasn't been published yet.
+    /// In shallow clones, it also limits the number of commits fetched to find the last release.
     /// Default: 1000.
     #[arg(long)]
     max_analyze_commits: Option<u32>,
@@ -121,16 +134,35 @@ pub struct Update {
 pub enum GitForgeKind {
     #[value(name = "github")]
     Github,
-    #[value(name = "gitea")]
+    /// Gitea or Forgejo (e.g. Codeberg).
+    #[value(name = "gitea", alias = "forgejo")]
     Gitea,
     #[value(name = "gitlab")]
     Gitlab,
 }
 
+impl From<GitForgeKind> for ForgeType {
+    fn from(value: GitForgeKind) -> Self {
+        match value {
+            GitForgeKind::Github => Self::Github,
+            GitForgeKind::Gitea => Self::Gitea,
+            GitForgeKind::Gitlab => Self::Gitlab,
+        }
+    }
+}
+
 impl RepoCommand for Update {
     fn repo_url(&self) -> Option<&str> {
         self.repo_url.as_deref()
     }
+
+    fn forge(&self) -> ForgeType {
+        self.forge.into()
+    }
+
+    fn forge_api_url(&self) -> Option<&Url> {
+        self.forge_api_url.as_ref()
+    }
 }
 
 impl ManifestCommand for Update {
@@ -141,20 +173,22 @@ impl ManifestCommand for Update {
 
 impl Update {
     pub fn git_forge(&self, repo: RepoUrl) -> anyhow::Result<Option<GitForge>> {
-        let Some(token) = self.git_token.clone() else {
+        let token = self.git_token.clone().map(SecretString::from);
+        if token.is_none() && !self.github_app.is_set() {
             return Ok(None);
-        };
-        let token = SecretString::from(token);
+        }
         Ok(Some(match self.forge {
             GitForgeKind::Github => {
                 anyhow::ensure!(
                     repo.is_on_github(),
-                    "Can't create PR: the repository is not hosted in GitHub. Please select a different forge."
+                    "Can't create PR: the repository is not hosted in GitHub. Please select a different forge. \
+                    If the repository is hosted in a GitHub Enterprise Server instance whose host doesn't contain `github`, \
+                    set `forge_api_url`."
                 );
-                GitForge::Github(GitHub::new(repo.owner, repo.name, token))
+                GitForge::Github(self.github_app.github(repo, token)?)
             }
-            GitForgeKind::Gitea => GitForge::Gitea(Gitea::new(repo, token)?),
-            GitForgeKind::Gitlab => GitForge::Gitlab(GitLab::new(repo, token)?),
+            GitForgeKind::Gitea => GitForge::Gitea(Gitea::new(repo, forge_token(token)?)?),
+            GitForgeKind::Gitlab => GitForge::Gitlab(GitLab::new(repo, forge_token(token)?)?),
         }))
     }
 
@@ -162,10 +196,15 @@ impl Update {
         self.update_deps || config.workspace.dependencies_update == Some(true)
     }
 
-    fn allow_dirty(&self, config: &Config) -> bool {
+    pub fn allow_dirty(&self, config: &Config) -> bool {
         self.allow_dirty || config.workspace.allow_dirty == Some(true)
     }
 
+    /// Registry passed with `--registry`.
+    pub fn registry(&self) -> Option<&str> {
+        self.registry.as_deref()
+    }
+
     fn max_analyze_commits(&self, config: &Config) -> Option<u32> {
         self.max_analyze_commits
             .or(config.workspace.max_analyze_commits)
@@ -217,6 +256,11 @@ impl Update {
             let changelog_req = ChangelogRequest {
                 release_date,
                 changelog_config: Some(self.changelog_config(config, pr_link.as_deref())?),
+                unreleased_handling: config
+                    .workspace
+                    .unreleased_handling
+                    .unwrap_or_default()
+                    .into(),
             };
             update = update.with_changelog_req(changelog_req);
         }
@@ -319,10 +363,12 @@ mod tests {
             repo_url: None,
             config: ConfigPath::default(),
             forge: GitForgeKind::Github,
+            forge_api_url: None,
             git_token: None,
+            github_app: GitHubAppArgs::default(),
             max_analyze_commits: None,
         };
-        let config = update_args.config.load().unwrap();
+        let config = update_args.config.load(&fake_metadata()).unwrap();
         let req = update_args
             .update_request(&config, fake_metadata())
             .unwrap();
@@ -1,5 +1,5 @@
 use anyhow::Context as _;
-use cargo_metadata::camino::Utf8Path;
+use cargo_metadata::{camino::Utf8Path, semver::VersionReq};
 use cargo_utils::to_utf8_pathbuf;
 use release_plz_core::{
     GitReleaseConfig, ReleaseRequest,
@@ -20,6 +20,13 @@ use crate::changelog_config::ChangelogCfg;
 #[serde(deny_unknown_fields)]
 #[schemars(extend("$id" = "https://raw.githubusercontent.com/release-plz/release-plz/main/.schema/latest.json"))]
 pub struct Config {
+    /// # Extends
+    /// Configuration files to inherit settings from, e.g. the shared configuration of your
+    /// organization. Later files override earlier ones, and this file overrides all of them.
+    /// Each entry is either a path relative to this file or a file of a git repository
+    /// pinned to a revision: `git+<repo url>?rev=<commit or tag>#<path in the repository>`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extends: Vec<String>,
     /// # Workspace
     /// Global configuration. Applied to all packages by default.
     #[serde(default)]
@@ -30,7 +37,11 @@ pub struct Config {
     /// Package-specific configuration. This overrides `workspace`.
     /// Not all settings of `workspace` can be overridden.
     #[serde(default)]
-    package: Vec<PackageSpecificConfigWithName>,
+    pub package: Vec<PackageSpecificConfigWithName>,
+    /// # Branch
+    /// Configuration overrides applied when release-plz runs on specific branches.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub branch: Vec<BranchConfig>,
 }
 
 impl Config {
@@ -144,7 +155,10 @@ impl Config {
     }
 }
 
-fn validate_git_only_settings(git_only: Option<bool>, publish: Option<bool>) -> anyhow::Result<()> {
+pub fn validate_git_only_settings(
+    git_only: Option<bool>,
+    publish: Option<bool>,
+) -> anyhow::Result<()> {
     if git_only == Some(true) && publish == Some(true) {
         anyhow::bail!(
             "Config options 'git_only' and 'publish' are mutually exclusive. \
@@ -154,6 +168,21 @@ fn validate_git_only_settings(git_only: Option<bool>, publish: Option<bool>) ->
     Ok(())
 }
 
+/// Config overrides applied when the current branch matches `name`.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, JsonSchema)]
+pub struct BranchConfig {
+    /// # Name
+    /// Glob pattern of the branch name, e.g. `release/*`.
+    pub name: String,
+    /// Settings that override the `[workspace]` section.
+    #[serde(flatten)]
+    pub workspace: Workspace,
+    /// # Package
+    /// Settings that override the `[[package]]` sections.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub package: Vec<PackageSpecificConfigWithName>,
+}
+
 /// Config at the `[workspace]` level.
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, JsonSchema)]
 #[serde(deny_unknown_fields)]
@@ -168,6 +197,10 @@ pub struct Workspace {
     /// # Changelog Config
     /// Path to the git cliff configuration file. Defaults to the `keep a changelog` configuration.
     pub changelog_config: Option<PathBuf>,
+    /// # Unreleased Handling
+    /// What to do with the entries of the `## [Unreleased]` section of the changelog
+    /// when release-plz adds a new release. Default: `keep`.
+    pub unreleased_handling: Option<UnreleasedHandling>,
     /// # Dependencies Update
     /// - If `true`, update all the dependencies in the Cargo.lock file by running `cargo update`.
     /// - If `false` or [`Option::None`], only update the workspace packages by running `cargo update --workspace`.
@@ -186,9 +219,36 @@ pub struct Workspace {
     /// Labels to add to the release PR.
     #[serde(default)]
     pub pr_labels: Vec<String>,
+    /// # PR Reviewers
+    /// Usernames of the users to request a review of the release PR from.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pr_reviewers: Vec<String>,
+    /// # PR Team Reviewers
+    /// Slugs of the teams to request a review of the release PR from.
+    /// Not supported on GitLab.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pr_team_reviewers: Vec<String>,
+    /// # PR Assignees
+    /// Usernames of the users to assign the release PR to.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pr_assignees: Vec<String>,
+    /// # PR Milestone
+    /// Tera template of the title of the milestone of the release PR.
+    /// The milestone is created if it doesn't exist.
+    pub pr_milestone: Option<String>,
+    /// # PR Auto Merge
+    /// If set, merge the release PR with this method when its checks pass.
+    pub pr_auto_merge: Option<MergeMethod>,
+    /// # PR Update Strategy
+    /// How to update the release PR when other people pushed commits to it.
+    /// Default: `recreate`.
+    pub pr_update_strategy: Option<PrUpdateStrategy>,
     /// # PR Branch Prefix
     /// Prefix for the PR Branch
     pub pr_branch_prefix: Option<String>,
+    /// # PR Mode
+    /// How to split the package updates across release PRs. Default: `single`.
+    pub pr_mode: Option<PrMode>,
     /// # Publish Timeout
     /// Timeout for the publishing process
     pub publish_timeout: Option<String>,
@@ -197,6 +257,10 @@ pub struct Workspace {
     /// It is used to generate the changelog release link.
     /// It defaults to the url of the default remote.
     pub repo_url: Option<Url>,
+    /// # Forge API URL
+    /// Root of the REST API of the git forge, e.g. `https://github.example.com/api/v3`.
+    /// Use it when the API url can't be derived from the repository url.
+    pub forge_api_url: Option<Url>,
     /// # Release Commits
     /// Prepare release only if at least one commit respects this regex.
     pub release_commits: Option<String>,
@@ -210,11 +274,30 @@ pub struct Workspace {
     ///   `release-plz-`. So if you want to create a PR that should trigger a release
     ///   (e.g. when you fix the CI), use this branch name format (e.g. `release-plz-fix-ci`).
     pub release_always: Option<bool>,
+    /// # Release Comment
+    /// Tera template of the comment that `release-plz release` posts on the released PRs
+    /// and on the issues they close.
+    /// If unspecified, no comment is posted.
+    pub release_comment: Option<String>,
+    /// # Release Required Approvals
+    /// Minimum number of approvals the release PR must have for `release-plz release`
+    /// to publish the packages.
+    pub release_required_approvals: Option<u32>,
+    /// # Release Require CI Success
+    /// If `true`, `release-plz release` publishes the packages only if the CI
+    /// of the last commit of the release PR succeeded.
+    pub release_require_ci_success: Option<bool>,
     /// Maximum number of commits to analyze when the package hasn't been published yet.
+    /// In shallow clones, it also limits the number of commits fetched to find the last release.
     /// Default: 1000.
     #[serde(default = "default_max_analyze_commits")]
     #[schemars(default = "default_max_analyze_commits")]
     pub max_analyze_commits: Option<u32>,
+    /// # Env Allowlist
+    /// Environment variables that the configuration can interpolate with `${VAR}` or
+    /// `${VAR:-default}`. Glob patterns like `CI_*` are supported.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env_allowlist: Vec<String>,
 }
 
 impl Default for Workspace {
@@ -223,17 +306,30 @@ impl Default for Workspace {
             packages_defaults: PackageConfig::default(),
             allow_dirty: None,
             changelog_config: None,
+            unreleased_handling: None,
             dependencies_update: None,
             repo_url: None,
+            forge_api_url: None,
             pr_name: None,
             pr_body: None,
             pr_draft: false,
             pr_labels: Vec::new(),
+            pr_reviewers: Vec::new(),
+            pr_team_reviewers: Vec::new(),
+            pr_assignees: Vec::new(),
+            pr_milestone: None,
+            pr_auto_merge: None,
+            pr_update_strategy: None,
             pr_branch_prefix: None,
+            pr_mode: None,
             publish_timeout: None,
             release_commits: None,
             release_always: None,
+            release_comment: None,
+            release_required_approvals: None,
+            release_require_ci_success: None,
             max_analyze_commits: default_max_analyze_commits(),
+            env_allowlist: vec![],
         }
     }
 }
@@ -317,6 +413,18 @@ impl PackageSpecificConfig {
             version_group: self.version_group,
         }
     }
+
+    pub fn common(&self) -> &PackageConfig {
+        &self.common
+    }
+
+    pub fn changelog_include(&self) -> &[String] {
+        self.changelog_include.as_deref().unwrap_or_default()
+    }
+
+    pub fn version_group(&self) -> Option<&str> {
+        self.version_group.as_deref()
+    }
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, JsonSchema)]
@@ -464,6 +572,17 @@ pub struct PackageConfig {
     /// Custom regex to match commit types that should trigger a major version increment.
     /// Useful when using non-conventional commit prefixes.
     pub custom_major_increment_regex: Option<String>,
+    /// # Version Constraint
+    /// Semver requirement that the new versions of the package must satisfy, e.g. `<2.0.0`.
+    /// If the next version doesn't satisfy it, release-plz fails,
+    /// unless `version_constraint_clamp` is `true`.
+    #[schemars(with = "Option<String>")]
+    pub version_constraint: Option<VersionReq>,
+    /// # Version Constraint Clamp
+    /// - If `true`, when the next version doesn't satisfy the `version_constraint`, release-plz
+    ///   uses a smaller increment (minor, then patch) or fails if no increment satisfies it.
+    /// - If `false` or [`Option::None`], release-plz fails.
+    pub version_constraint_clamp: Option<bool>,
 }
 
 impl From<PackageConfig> for release_plz_core::UpdateConfig {
@@ -479,6 +598,8 @@ impl From<PackageConfig> for release_plz_core::UpdateConfig {
             custom_minor_increment_regex: config.custom_minor_increment_regex,
             custom_major_increment_regex: config.custom_major_increment_regex,
             git_only: config.git_only,
+            version_constraint: config.version_constraint,
+            version_constraint_clamp: config.version_constraint_clamp == Some(true),
         }
     }
 }
@@ -525,6 +646,10 @@ impl PackageConfig {
                 .custom_major_increment_regex
                 .or(default.custom_major_increment_regex),
             git_only: self.git_only.or(default.git_only),
+            version_constraint: self.version_constraint.or(default.version_constraint),
+            version_constraint_clamp: self
+                .version_constraint_clamp
+                .or(default.version_constraint_clamp),
         }
     }
 
@@ -553,6 +678,106 @@ pub enum ReleaseType {
     Auto,
 }
 
+#[derive(Serialize, Deserialize, Default, PartialEq, Eq, Debug, Clone, Copy, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UnreleasedHandling {
+    /// # Keep
+    /// Don't move the entries: the new release is added right below the `Unreleased` heading.
+    #[default]
+    Keep,
+    /// # Merge
+    /// Move the entries into the new release, in the section with the same title
+    /// (e.g. `### Added`). Entries that are already in the new release are skipped.
+    Merge,
+    /// # Replace
+    /// Remove the entries, because the new release replaces them.
+    Replace,
+}
+
+impl From<UnreleasedHandling> for release_plz_core::UnreleasedHandling {
+    fn from(value: UnreleasedHandling) -> Self {
+        match value {
+            UnreleasedHandling::Keep => Self::Keep,
+            UnreleasedHandling::Merge => Self::Merge,
+            UnreleasedHandling::Replace => Self::Replace,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, PartialEq, Eq, Debug, Clone, Copy, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PrMode {
+    /// # Single
+    /// Open one release PR containing the updates of all packages.
+    #[default]
+    Single,
+    /// # Per Package
+    /// Open one release PR per package.
+    /// Packages that depend on each other, or that inherit the workspace version,
+    /// share the same release PR.
+    PerPackage,
+    /// # Per Group
+    /// Open one release PR per `version_group`.
+    /// Packages without a `version_group` have their own release PR.
+    PerGroup,
+}
+
+impl From<PrMode> for release_plz_core::PrMode {
+    fn from(value: PrMode) -> Self {
+        match value {
+            PrMode::Single => Self::Single,
+            PrMode::PerPackage => Self::PerPackage,
+            PrMode::PerGroup => Self::PerGroup,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, PartialEq, Eq, Debug, Clone, Copy, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PrUpdateStrategy {
+    /// # Recreate
+    /// Close the release PR and open a new one, to preserve the commits of the contributors.
+    #[default]
+    Recreate,
+    /// # Rebase
+    /// Re-create the release-plz commit and rebase the commits of the contributors onto it.
+    /// If the rebase fails, close the release PR and open a new one.
+    Rebase,
+}
+
+impl From<PrUpdateStrategy> for release_plz_core::PrUpdateStrategy {
+    fn from(value: PrUpdateStrategy) -> Self {
+        match value {
+            PrUpdateStrategy::Recreate => Self::Recreate,
+            PrUpdateStrategy::Rebase => Self::Rebase,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeMethod {
+    /// # Merge
+    /// Create a merge commit.
+    Merge,
+    /// # Squash
+    /// Squash the commits of the PR into a single commit.
+    Squash,
+    /// # Rebase
+    /// Rebase the commits of the PR onto the base branch. Not supported on GitLab.
+    Rebase,
+}
+
+impl From<MergeMethod> for release_plz_core::MergeMethod {
+    fn from(value: MergeMethod) -> Self {
+        match value {
+            MergeMethod::Merge => Self::Merge,
+            MergeMethod::Squash => Self::Squash,
+            MergeMethod::Rebase => Self::Rebase,
+        }
+    }
+}
+
 impl From<ReleaseType> for release_plz_core::ReleaseType {
     fn from(value: ReleaseType) -> Self {
         match value {
@@ -588,16 +813,19 @@ mod tests {
 
     fn create_base_workspace_config() -> Config {
         Config {
+            extends: vec![],
             changelog: ChangelogCfg::default(),
             workspace: Workspace {
                 dependencies_update: Some(false),
                 changelog_config: Some("../git-cliff.toml".into()),
+                unreleased_handling: None,
                 allow_dirty: Some(false),
                 repo_url: Some(
                     "https://github.com/release-plz/release-plz"
                         .parse()
                         .unwrap(),
                 ),
+                forge_api_url: None,
                 packages_defaults: PackageConfig {
                     semver_check: None,
                     changelog_update: None,
@@ -610,13 +838,25 @@ mod tests {
                 pr_body: None,
                 pr_draft: false,
                 pr_labels: vec![],
+                pr_reviewers: vec![],
+                pr_team_reviewers: vec![],
+                pr_assignees: vec![],
+                pr_milestone: None,
+                pr_auto_merge: None,
+                pr_update_strategy: None,
                 pr_branch_prefix: Some("f-".to_string()),
+                pr_mode: None,
                 publish_timeout: Some("10m".to_string()),
                 release_commits: Some("^feat:".to_string()),
                 release_always: None,
+                release_comment: None,
+                release_required_approvals: None,
+                release_require_ci_success: None,
                 max_analyze_commits: default_max_analyze_commits(),
+                env_allowlist: vec![],
             },
             package: [].into(),
+            branch: vec![],
         }
     }
 
@@ -711,21 +951,31 @@ mod tests {
     #[test]
     fn config_is_serialized() {
         let config = Config {
+            extends: vec!["../shared/release-plz.toml".to_string()],
             changelog: ChangelogCfg::default(),
             workspace: Workspace {
                 dependencies_update: None,
                 changelog_config: Some("../git-cliff.toml".into()),
+                unreleased_handling: Some(UnreleasedHandling::Merge),
                 allow_dirty: None,
                 repo_url: Some(
                     "https://github.com/release-plz/release-plz"
                         .parse()
                         .unwrap(),
                 ),
+                forge_api_url: Some("https://api.github.com/".parse().unwrap()),
                 pr_name: None,
                 pr_body: None,
                 pr_draft: false,
                 pr_labels: vec!["label1".to_string()],
+                pr_reviewers: vec!["user1".to_string()],
+                pr_team_reviewers: vec![],
+                pr_assignees: vec!["user2".to_string()],
+                pr_milestone: Some("v{{ version }}".to_string()),
+                pr_auto_merge: Some(MergeMethod::Squash),
+                pr_update_strategy: Some(PrUpdateStrategy::Rebase),
                 pr_branch_prefix: Some("f-".to_string()),
+                pr_mode: Some(PrMode::PerPackage),
                 packages_defaults: PackageConfig {
                     semver_check: None,
                     changelog_update: true.into(),
@@ -739,7 +989,11 @@ mod tests {
                 publish_timeout: Some("10m".to_string()),
                 release_commits: Some("^feat:".to_string()),
                 release_always: None,
+                release_comment: Some("Released in {{ version }}".to_string()),
+                release_required_approvals: Some(1),
+                release_require_ci_success: Some(true),
                 max_analyze_commits: default_max_analyze_commits(),
+                env_allowlist: vec!["CI_*".to_string()],
             },
             package: [PackageSpecificConfigWithName {
                 name: "crate1".to_string(),
@@ -758,9 +1012,12 @@ mod tests {
                 },
             }]
             .into(),
+            branch: vec![],
         };
 
         expect_test::expect![[r#"
+            extends = ["../shared/release-plz.toml"]
+
             [workspace]
             changelog_path = "./CHANGELOG.md"
             changelog_update = true
@@ -769,13 +1026,25 @@ mod tests {
             git_release_draft = false
             release = true
             changelog_config = "../git-cliff.toml"
+            unreleased_handling = "merge"
             pr_draft = false
             pr_labels = ["label1"]
+            pr_reviewers = ["user1"]
+            pr_assignees = ["user2"]
+            pr_milestone = "v{{ version }}"
+            pr_auto_merge = "squash"
+            pr_update_strategy = "rebase"
             pr_branch_prefix = "f-"
+            pr_mode = "per_package"
             publish_timeout = "10m"
             repo_url = "https://github.com/release-plz/release-plz"
+            forge_api_url = "https://api.github.com/"
             release_commits = "^feat:"
+            release_comment = "Released in {{ version }}"
+            release_required_approvals = 1
+            release_require_ci_success = true
             max_analyze_commits = 1000
+            env_allowlist = ["CI_*"]
 
             [changelog]
 
@@ -792,18 +1061,48 @@ mod tests {
         .assert_eq(&toml::to_string(&config).unwrap());
     }
 
+    #[test]
+    fn branch_config_is_deserialized() {
+        let config = r#"
+            [[branch]]
+            name = "release/1.*"
+            pr_branch_prefix = "release-plz-1.x-"
+            version_constraint = ">=1.0.0, <2.0.0"
+
+            [[branch.package]]
+            name = "crate1"
+            publish = false
+        "#;
+
+        let config: Config = toml::from_str(config).unwrap();
+        let [branch] = config.branch.as_slice() else {
+            panic!("expected one branch, got {:?}", config.branch);
+        };
+        assert_eq!(branch.name, "release/1.*");
+        assert_eq!(
+            branch.workspace.pr_branch_prefix.as_deref(),
+            Some("release-plz-1.x-")
+        );
+        assert_eq!(
+            branch.workspace.packages_defaults.version_constraint,
+            Some(">=1.0.0, <2.0.0".parse().unwrap())
+        );
+        assert_eq!(branch.package[0].name, "crate1");
+        assert_eq!(branch.package[0].config.common.publish, Some(false));
+    }
+
     #[test]
     fn wrong_config_section_is_not_deserialized() {
         let config = "[unknown]";
 
         let error = toml::from_str::<Config>(config).unwrap_err().to_string();
-        expect_test::expect![[r"
+        expect_test::expect![[r#"
             TOML parse error at line 1, column 2
               |
             1 | [unknown]
               |  ^^^^^^^
-            unknown field `unknown`, expected one of `workspace`, `changelog`, `package`
-        "]]
+            unknown field `unknown`, expected one of `extends`, `workspace`, `changelog`, `package`, `branch`
+        "#]]
         .assert_eq(&error);
     }
 
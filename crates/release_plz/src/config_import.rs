@@ -0,0 +1,554 @@
+//! Translate the configuration of other release tools into a release-plz configuration,
+//! to help users migrate to release-plz.
+
+use std::{collections::BTreeSet, fmt::Write as _, io::ErrorKind, sync::LazyLock};
+
+use anyhow::Context as _;
+use cargo_metadata::{
+    Metadata,
+    camino::{Utf8Path, Utf8PathBuf},
+};
+use regex::Regex;
+use toml::{Table, Value};
+
+use crate::config_sources::SourcedConfig;
+
+/// File of the cargo-release configuration, in the workspace root and in the package directories.
+const CARGO_RELEASE_FILE: &str = "release.toml";
+/// Key of the cargo-release configuration in the `metadata` tables of `Cargo.toml`.
+const CARGO_RELEASE_METADATA_KEY: &str = "release";
+/// Version group of the packages with `shared-version = true`.
+const SHARED_VERSION_GROUP: &str = "shared";
+const NO_EQUIVALENT: &str = "release-plz doesn't have an equivalent option";
+
+/// Option of the imported configuration that release-plz doesn't support.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unsupported {
+    /// Where the option is set, e.g. `release.toml`.
+    pub source: String,
+    pub option: String,
+    pub reason: String,
+}
+
+/// Configuration imported from another tool.
+#[derive(Debug, Default)]
+pub struct Import {
+    /// release-plz configuration, where the source of each setting is the imported option.
+    pub config: SourcedConfig,
+    pub unsupported: Vec<Unsupported>,
+}
+
+impl Import {
+    /// release-plz config file.
+    /// Each setting is followed by a comment with the option it comes from,
+    /// and the unsupported options are listed at the top.
+    pub fn to_toml(&self, tool: &str) -> anyhow::Result<String> {
+        let mut toml = format!(
+            "# release-plz configuration imported from {tool}.\n\
+             # Each setting is followed by the {tool} option it comes from.\n\
+             # Docs: https://release-plz.dev/docs/config\n"
+        );
+        if !self.unsupported.is_empty() {
+            writeln!(toml, "#\n# {tool} options that release-plz can't import:")?;
+            for unsupported in &self.unsupported {
+                writeln!(
+                    toml,
+                    "# - {}: `{}`: {}",
+                    unsupported.source, unsupported.option, unsupported.reason
+                )?;
+            }
+        }
+        let config = self.config.to_annotated_toml()?;
+        if !config.is_empty() {
+            toml.push('\n');
+            toml.push_str(&config);
+        }
+        Ok(toml)
+    }
+}
+
+/// Import the cargo-release configuration of the workspace:
+/// the `release.toml` files and the `[workspace.metadata.release]` and
+/// `[package.metadata.release]` tables of the Cargo manifests.
+/// Package settings override workspace settings, like in cargo-release.
+pub fn from_cargo_release(metadata: &Metadata) -> anyhow::Result<Import> {
+    let root = &metadata.workspace_root;
+    let packages = metadata.workspace_packages();
+    let package_names = packages.iter().map(|p| p.name.to_string()).collect();
+    let mut importer = CargoReleaseImporter::new(package_names);
+    let mut found = false;
+
+    let workspace_metadata = metadata_table(&metadata.workspace_metadata)
+        .context("invalid [workspace.metadata.release]")?;
+    let workspace_sources = [
+        release_toml(root)?.map(|t| (CARGO_RELEASE_FILE.to_string(), t)),
+        workspace_metadata.map(|t| ("Cargo.toml [workspace.metadata.release]".to_string(), t)),
+    ];
+    for (source, table) in workspace_sources.into_iter().flatten() {
+        found = true;
+        importer.add(&Scope::Workspace, &source, &table)?;
+    }
+
+    for package in packages {
+        let manifest = package
+            .manifest_path
+            .strip_prefix(root)
+            .unwrap_or(&package.manifest_path);
+        let dir = manifest.parent().unwrap_or(Utf8Path::new(""));
+        let package_dir = package
+            .manifest_path
+            .parent()
+            .context("invalid manifest path")?;
+        let package_metadata = metadata_table(&package.metadata)
+            .with_context(|| format!("invalid [package.metadata.release] of {}", package.name))?;
+        let package_sources = [
+            // The workspace `release.toml` was already read.
+            (package_dir != root)
+                .then(|| release_toml(package_dir))
+                .transpose()?
+                .flatten()
+                .map(|t| (dir.join(CARGO_RELEASE_FILE).to_string(), t)),
+            package_metadata.map(|t| (format!("{manifest} [package.metadata.release]"), t)),
+        ];
+        let scope = Scope::Package {
+            name: package.name.to_string(),
+            dir: dir.to_path_buf(),
+        };
+        for (source, table) in package_sources.into_iter().flatten() {
+            found = true;
+            importer.add(&scope, &source, &table)?;
+        }
+    }
+    anyhow::ensure!(found, "cargo-release configuration not found in {root}");
+    importer.finish()
+}
+
+fn release_toml(dir: &Utf8Path) -> anyhow::Result<Option<Table>> {
+    let path = dir.join(CARGO_RELEASE_FILE);
+    match fs_err::read_to_string(&path) {
+        Ok(content) => toml::from_str(&content)
+            .with_context(|| format!("invalid {path}"))
+            .map(Some),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The cargo-release table of the given `metadata` of a Cargo manifest.
+fn metadata_table(metadata: &serde_json::Value) -> anyhow::Result<Option<Table>> {
+    match metadata.get(CARGO_RELEASE_METADATA_KEY) {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(value) => match Value::try_from(value)? {
+            Value::Table(table) => Ok(Some(table)),
+            _ => anyhow::bail!("expected a table"),
+        },
+    }
+}
+
+/// Where an option applies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Scope {
+    Workspace,
+    Package {
+        name: String,
+        /// Directory of the package, relative to the workspace root.
+        dir: Utf8PathBuf,
+    },
+}
+
+struct CargoReleaseImporter {
+    /// Names of the packages of the workspace.
+    package_names: Vec<String>,
+    /// `tag-prefix` of the workspace, which applies to packages that don't set their own.
+    workspace_tag_prefix: Option<String>,
+    /// Version group of the `shared-version` of the workspace, with its source.
+    /// It applies to the packages that don't set their own `shared-version`.
+    workspace_version_group: Option<(String, String)>,
+    /// Packages that set their own `shared-version`.
+    packages_with_shared_version: BTreeSet<String>,
+    import: Import,
+}
+
+impl CargoReleaseImporter {
+    fn new(package_names: Vec<String>) -> Self {
+        Self {
+            package_names,
+            workspace_tag_prefix: None,
+            workspace_version_group: None,
+            packages_with_shared_version: BTreeSet::new(),
+            import: Import::default(),
+        }
+    }
+
+    fn finish(mut self) -> anyhow::Result<Import> {
+        if let Some((group, source)) = self.workspace_version_group.take() {
+            for name in self.package_names.clone() {
+                if !self.packages_with_shared_version.contains(&name) {
+                    let settings = version_group_setting(&group);
+                    let table = packages_table([(&name, &settings)]);
+                    self.import
+                        .config
+                        .override_with(SourcedConfig::new(table, &source))?;
+                }
+            }
+        }
+        Ok(self.import)
+    }
+
+    /// Import the options of a cargo-release table.
+    fn add(&mut self, scope: &Scope, source: &str, table: &Table) -> anyhow::Result<()> {
+        let tag_prefix = table.get("tag-prefix").and_then(Value::as_str);
+        if *scope == Scope::Workspace && tag_prefix.is_some() {
+            self.workspace_tag_prefix = tag_prefix.map(str::to_string);
+        }
+        let tag_prefix = tag_prefix
+            .map(str::to_string)
+            .or_else(|| self.workspace_tag_prefix.clone())
+            .unwrap_or_else(|| self.default_tag_prefix().to_string());
+        for (option, value) in table {
+            let settings = match option.as_str() {
+                // `tag-name` contains the prefix.
+                "tag-prefix" if table.contains_key("tag-name") => Ok(vec![]),
+                "tag-prefix" => tag_name("{{prefix}}v{{version}}", &tag_prefix)
+                    .map(|tag_name| vec![("git_tag_name", Value::String(tag_name))]),
+                "shared-version" => {
+                    self.shared_version(scope, value, &format!("{source}: {option}"))
+                }
+                "tag-name" => expect_str(value)
+                    .and_then(|template| tag_name(template, &tag_prefix))
+                    .map(|tag_name| vec![("git_tag_name", Value::String(tag_name))]),
+                _ => map_option(scope, option, value),
+            };
+            match settings {
+                Ok(settings) => {
+                    for (key, value) in settings {
+                        self.set(scope, key, value, &format!("{source}: {option}"))?;
+                    }
+                }
+                Err(reason) => self.import.unsupported.push(Unsupported {
+                    source: source.to_string(),
+                    option: option.clone(),
+                    reason,
+                }),
+            }
+        }
+        Ok(())
+    }
+
+    /// The version group is set at the end, because the packages can opt out of the
+    /// `shared-version` of the workspace.
+    fn shared_version(
+        &mut self,
+        scope: &Scope,
+        value: &Value,
+        source: &str,
+    ) -> Result<Vec<(&'static str, Value)>, String> {
+        let group = match value {
+            Value::Boolean(shared) => shared.then(|| SHARED_VERSION_GROUP.to_string()),
+            Value::String(group) => Some(group.clone()),
+            _ => return Err("expected a boolean or a string".to_string()),
+        };
+        match scope {
+            Scope::Workspace => {
+                self.workspace_version_group = group.map(|group| (group, source.to_string()));
+                Ok(vec![])
+            }
+            Scope::Package { name, .. } => {
+                self.packages_with_shared_version.insert(name.clone());
+                Ok(group
+                    .map(|group| vec![("version_group", Value::String(group))])
+                    .unwrap_or_default())
+            }
+        }
+    }
+
+    /// Default `tag-prefix` of cargo-release.
+    fn default_tag_prefix(&self) -> &'static str {
+        if self.package_names.len() > 1 {
+            "{{crate_name}}-"
+        } else {
+            ""
+        }
+    }
+
+    fn set(&mut self, scope: &Scope, key: &str, value: Value, source: &str) -> anyhow::Result<()> {
+        let mut settings = Table::new();
+        settings.insert(key.to_string(), value);
+        let table = match scope {
+            Scope::Workspace => {
+                let mut table = Table::new();
+                table.insert("workspace".to_string(), Value::Table(settings));
+                table
+            }
+            Scope::Package { name, .. } => packages_table([(name, &settings)]),
+        };
+        self.import
+            .config
+            .override_with(SourcedConfig::new(table, source))
+    }
+}
+
+fn version_group_setting(group: &str) -> Table {
+    let mut settings = Table::new();
+    settings.insert(
+        "version_group".to_string(),
+        Value::String(group.to_string()),
+    );
+    settings
+}
+
+fn packages_table<'a>(packages: impl IntoIterator<Item = (&'a String, &'a Table)>) -> Table {
+    let packages = packages
+        .into_iter()
+        .map(|(name, settings)| {
+            let mut package = settings.clone();
+            package.insert("name".to_string(), Value::String(name.clone()));
+            Value::Table(package)
+        })
+        .collect();
+    let mut table = Table::new();
+    table.insert("package".to_string(), Value::Array(packages));
+    table
+}
+
+/// release-plz settings equivalent to a cargo-release option,
+/// or the reason why release-plz doesn't support it.
+fn map_option(
+    scope: &Scope,
+    option: &str,
+    value: &Value,
+) -> Result<Vec<(&'static str, Value)>, String> {
+    let same = |key| Ok(vec![(key, value.clone())]);
+    match option {
+        "release" => expect_bool(value).and_then(|_| same("release")),
+        "publish" => expect_bool(value).and_then(|_| same("publish")),
+        "verify" => {
+            expect_bool(value).map(|verify| vec![("publish_no_verify", Value::Boolean(!verify))])
+        }
+        "tag" => expect_bool(value).and_then(|_| same("git_tag_enable")),
+        "enable-features" | "features" => same("publish_features"),
+        "enable-all-features" | "all-features" => {
+            expect_bool(value).and_then(|_| same("publish_all_features"))
+        }
+        "pre-release-replacements" => changelog_replacements(scope, value),
+        "dependent-version" => match expect_str(value)? {
+            "upgrade" | "fix" => Ok(vec![]),
+            _ => Err(
+                "release-plz always updates the version requirements of the dependent packages"
+                    .to_string(),
+            ),
+        },
+        "push" => {
+            if expect_bool(value)? {
+                Ok(vec![])
+            } else {
+                Err("release-plz always pushes the release commits and tags".to_string())
+            }
+        }
+        "sign-commit" | "sign-tag" => {
+            if expect_bool(value)? {
+                let (git_option, object) = if option == "sign-commit" {
+                    ("commit.gpgSign", "commits")
+                } else {
+                    ("tag.gpgSign", "tags")
+                };
+                Err(format!(
+                    "to sign the {object} that release-plz creates with git, \
+                     set `git config {git_option} true` where release-plz runs"
+                ))
+            } else {
+                Ok(vec![])
+            }
+        }
+        "allow-branch" => Err("release-plz releases from the branch where it runs: \
+             restrict the branches in your CI workflow instead"
+            .to_string()),
+        "pre-release-commit-message"
+        | "tag-message"
+        | "consolidate-commits"
+        | "pre-release-hook"
+        | "owners"
+        | "registry"
+        | "metadata"
+        | "target"
+        | "push-remote"
+        | "push-options"
+        | "certs-source"
+        | "rate-limit" => Err(NO_EQUIVALENT.to_string()),
+        _ => Err("unknown cargo-release option".to_string()),
+    }
+}
+
+/// release-plz updates the changelog by itself, so the replacements that cargo-release
+/// applies to the changelog aren't needed.
+/// If the changelog isn't in the default location, its path is imported.
+fn changelog_replacements(
+    scope: &Scope,
+    value: &Value,
+) -> Result<Vec<(&'static str, Value)>, String> {
+    const NOT_A_CHANGELOG: &str = "release-plz doesn't replace text in files, \
+        but it updates the changelog with git-cliff";
+    let replacements = value.as_array().ok_or("expected an array")?;
+    let mut files: Vec<&str> = replacements
+        .iter()
+        .map(|r| {
+            r.get("file")
+                .and_then(Value::as_str)
+                .ok_or("missing `file`")
+        })
+        .collect::<Result<_, _>>()?;
+    files.dedup();
+    let [file] = files.as_slice() else {
+        return Err(NOT_A_CHANGELOG.to_string());
+    };
+    let file_name = Utf8Path::new(file).file_name().unwrap_or_default();
+    if !file_name.to_lowercase().contains("changelog") {
+        return Err(NOT_A_CHANGELOG.to_string());
+    }
+    if *file == "CHANGELOG.md" {
+        return Ok(vec![]);
+    }
+    match scope {
+        Scope::Package { dir, .. } => Ok(vec![(
+            "changelog_path",
+            Value::String(dir.join(file).to_string()),
+        )]),
+        Scope::Workspace => Err(
+            "release-plz can't set the changelog path for all the packages: \
+             set `changelog_path` in the `[[package]]` sections"
+                .to_string(),
+        ),
+    }
+}
+
+/// Convert a cargo-release tag name template to the Tera syntax of release-plz.
+fn tag_name(template: &str, prefix: &str) -> Result<String, String> {
+    static PLACEHOLDER: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"\{\{\s*(\w+)\s*\}\}").expect("invalid regex"));
+    let template = template.replace("{{prefix}}", prefix);
+    let mut unsupported = vec![];
+    let tag_name =
+        PLACEHOLDER.replace_all(&template, |captures: &regex::Captures| match &captures[1] {
+            "crate_name" => "{{ package }}".to_string(),
+            "version" => "{{ version }}".to_string(),
+            other => {
+                unsupported.push(format!("`{{{{{other}}}}}`"));
+                captures[0].to_string()
+            }
+        });
+    if unsupported.is_empty() {
+        Ok(tag_name.into_owned())
+    } else {
+        Err(format!(
+            "release-plz doesn't support the placeholders {} in tag names",
+            unsupported.join(", ")
+        ))
+    }
+}
+
+fn expect_bool(value: &Value) -> Result<bool, String> {
+    value
+        .as_bool()
+        .ok_or_else(|| "expected a boolean".to_string())
+}
+
+fn expect_str(value: &Value) -> Result<&str, String> {
+    value
+        .as_str()
+        .ok_or_else(|| "expected a string".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import(sources: &[(Scope, &str, &str)]) -> String {
+        let mut importer = CargoReleaseImporter::new(vec!["aaa".to_string(), "bbb".to_string()]);
+        for (scope, source, toml) in sources {
+            importer
+                .add(scope, source, &toml::from_str(toml).unwrap())
+                .unwrap();
+        }
+        let import = importer.finish().unwrap();
+        import.config.to_config().unwrap();
+        import.to_toml("cargo-release").unwrap()
+    }
+
+    fn package(name: &str) -> Scope {
+        Scope::Package {
+            name: name.to_string(),
+            dir: Utf8PathBuf::from("crates").join(name),
+        }
+    }
+
+    #[test]
+    fn tag_name_is_converted() {
+        assert_eq!(
+            tag_name("{{prefix}}v{{version}}", "{{crate_name}}-").unwrap(),
+            "{{ package }}-v{{ version }}"
+        );
+        assert_eq!(
+            tag_name("release-{{ version }}", "").unwrap(),
+            "release-{{ version }}"
+        );
+        assert_eq!(
+            tag_name("v{{version}}-{{date}}", "").unwrap_err(),
+            "release-plz doesn't support the placeholders `{{date}}` in tag names"
+        );
+    }
+
+    #[test]
+    fn cargo_release_config_is_imported() {
+        let toml = import(&[
+            (
+                Scope::Workspace,
+                "release.toml",
+                r#"
+                    allow-branch = ["main"]
+                    sign-tag = true
+                    verify = false
+                    shared-version = true
+                    tag-prefix = ""
+                    dependent-version = "upgrade"
+                    pre-release-replacements = [
+                        { file = "CHANGELOG.md", search = "Unreleased", replace = "{{version}}" },
+                    ]
+                "#,
+            ),
+            (
+                package("bbb"),
+                "crates/bbb/Cargo.toml [package.metadata.release]",
+                r#"
+                    publish = false
+                    shared-version = false
+                    pre-release-replacements = [
+                        { file = "docs/CHANGELOG.md", search = "Unreleased", replace = "{{version}}" },
+                    ]
+                "#,
+            ),
+        ]);
+        expect_test::expect![[r##"
+            # release-plz configuration imported from cargo-release.
+            # Each setting is followed by the cargo-release option it comes from.
+            # Docs: https://release-plz.dev/docs/config
+            #
+            # cargo-release options that release-plz can't import:
+            # - release.toml: `allow-branch`: release-plz releases from the branch where it runs: restrict the branches in your CI workflow instead
+            # - release.toml: `sign-tag`: to sign the tags that release-plz creates with git, set `git config tag.gpgSign true` where release-plz runs
+
+            [workspace]
+            git_tag_name = "v{{ version }}" # release.toml: tag-prefix
+            publish_no_verify = true # release.toml: verify
+
+            [[package]]
+            name = "bbb"
+            changelog_path = "crates/bbb/docs/CHANGELOG.md" # crates/bbb/Cargo.toml [package.metadata.release]: pre-release-replacements
+            publish = false # crates/bbb/Cargo.toml [package.metadata.release]: publish
+
+            [[package]]
+            name = "aaa"
+            version_group = "shared" # release.toml: shared-version
+        "##]]
+        .assert_eq(&toml);
+    }
+}
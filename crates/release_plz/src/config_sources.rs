@@ -0,0 +1,1070 @@
+//! Configuration assembled from several sources: the config file, the files it extends
+//! and the metadata of the Cargo manifests.
+//! Each setting remembers its source, so that users can find where a value comes from.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context as _, bail};
+use cargo_metadata::camino::{Utf8Path, Utf8PathBuf};
+use fs_err::read_to_string;
+use serde::Deserialize as _;
+use toml::{Table, Value};
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::config::{BranchConfig, Config};
+
+const EXTENDS_KEY: &str = "extends";
+const PACKAGE_KEY: &str = "package";
+const BRANCH_KEY: &str = "branch";
+/// Setting of the `[workspace]` section with the environment variables that can be interpolated.
+const ENV_ALLOWLIST_KEY: &str = "env_allowlist";
+const GIT_PREFIX: &str = "git+";
+/// File read from the git repository if the `extends` url doesn't specify a path.
+const DEFAULT_GIT_FILE: &str = "release-plz.toml";
+/// Settings of the `[workspace]` and `[[branch]]` sections with the path of a file
+/// that a preset can ship together with its configuration.
+const PRESET_PATH_KEYS: &[&str] = &["changelog_config"];
+/// Maximum length of a chain of `extends`, to catch cycles.
+const MAX_EXTENDS_DEPTH: usize = 10;
+const SECTIONS_ORDER: &[&str] = &[
+    EXTENDS_KEY,
+    "workspace",
+    "changelog",
+    PACKAGE_KEY,
+    BRANCH_KEY,
+];
+
+/// Path of a setting, e.g. `["workspace", "pr_draft"]`.
+/// Settings of the `[[package]]` array are identified by the package name,
+/// e.g. `["package", "my_crate", "publish"]`.
+type KeyPath = Vec<String>;
+
+/// Configuration table where each value knows where it comes from.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SourcedConfig {
+    table: Table,
+    sources: BTreeMap<KeyPath, String>,
+}
+
+/// How to combine two configurations.
+#[derive(Clone, Copy)]
+enum MergeMode {
+    /// The values of the top configuration take precedence.
+    Override,
+    /// A setting can't be in both configurations.
+    Disjoint,
+}
+
+impl SourcedConfig {
+    /// All the values of `table` come from `source`.
+    pub fn new(table: Table, source: &str) -> Self {
+        let mut sources = BTreeMap::new();
+        record_sources(&table, &mut vec![], source, &mut sources);
+        Self { table, sources }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// Source of the setting at `path`, or of the table that contains it.
+    pub fn source_of(&self, path: &[String]) -> Option<&str> {
+        (1..=path.len()).rev().find_map(|len| {
+            let prefix = &path[..len];
+            self.sources
+                .get(prefix)
+                .or_else(|| {
+                    // Tables like `[[package]]` don't have a source, but their settings do.
+                    self.sources
+                        .iter()
+                        .find(|(key, _)| key.starts_with(prefix))
+                        .map(|(_, source)| source)
+                })
+                .map(String::as_str)
+        })
+    }
+
+    /// Deep-merge `top` over `self`, with the semantics of `PackageConfig::merge`:
+    /// the values of `top` take precedence, while the values that `top` doesn't set
+    /// are inherited from `self`.
+    /// Tables are merged recursively, packages are merged by name
+    /// and the other values (including arrays) are replaced.
+    pub fn override_with(&mut self, top: Self) -> anyhow::Result<()> {
+        self.merge(top, MergeMode::Override)
+    }
+
+    /// Merge configurations that can't set the same values.
+    pub fn merge_disjoint(&mut self, other: Self) -> anyhow::Result<()> {
+        self.merge(other, MergeMode::Disjoint)
+    }
+
+    fn merge(&mut self, top: Self, mode: MergeMode) -> anyhow::Result<()> {
+        let mut merger = Merger {
+            base_sources: &self.sources,
+            top_sources: &top.sources,
+            mode,
+        };
+        merger.merge_tables(&mut self.table, top.table, &mut vec![])?;
+        self.sources.extend(top.sources);
+        Ok(())
+    }
+
+    /// Apply the `[[branch]]` sections whose `name` matches the current branch, in order.
+    /// Their settings take precedence over the rest of the configuration.
+    /// `current_branch` is only called if there are `[[branch]]` sections.
+    /// If it returns `None`, no `[[branch]]` section is applied.
+    pub fn apply_branch_overrides(
+        &mut self,
+        current_branch: impl FnOnce() -> anyhow::Result<Option<String>>,
+    ) -> anyhow::Result<()> {
+        let Some(branches) = self.table.remove(BRANCH_KEY) else {
+            return Ok(());
+        };
+        let source = self
+            .sources
+            .remove(&vec![BRANCH_KEY.to_string()])
+            .unwrap_or_default();
+        // Validate all the sections, including the ones that don't match the current branch.
+        Vec::<BranchConfig>::deserialize(branches.clone())
+            .context("invalid `[[branch]]` section")?;
+        let Value::Array(branches) = branches else {
+            bail!("`branch` must be an array of tables");
+        };
+        let Some(current_branch) = current_branch()
+            .context("can't determine which `[[branch]]` sections apply to the current branch")?
+        else {
+            return Ok(());
+        };
+        for branch in branches {
+            let Value::Table(mut branch) = branch else {
+                bail!("`branch` must be an array of tables");
+            };
+            let Some(Value::String(name)) = branch.remove("name") else {
+                bail!("every `[[branch]]` must have a `name`");
+            };
+            let pattern = glob::Pattern::new(&name)
+                .with_context(|| format!("invalid glob pattern `{name}` in `[[branch]]`"))?;
+            if !pattern.matches(&current_branch) {
+                continue;
+            }
+            info!("applying the configuration of `[[branch]]` {name}");
+            let mut overrides = Table::new();
+            if let Some(packages) = branch.remove(PACKAGE_KEY) {
+                overrides.insert(PACKAGE_KEY.to_string(), packages);
+            }
+            if !branch.is_empty() {
+                overrides.insert("workspace".to_string(), Value::Table(branch));
+            }
+            self.override_with(Self::new(overrides, &format!("{source} [[branch]] {name}")))?;
+        }
+        Ok(())
+    }
+
+    /// Replace `${VAR}` and `${VAR:-default}` in the string values with the environment
+    /// variables allowed by `workspace.env_allowlist`.
+    /// `$${` is a literal `${`.
+    /// Nothing is interpolated if `workspace.env_allowlist` isn't set, and the references to
+    /// other variables or with an invalid syntax (e.g. the `${2}` capture group of a regex
+    /// replacement) are left as they are.
+    /// Names of packages and branches aren't interpolated.
+    pub fn interpolate_env(
+        &mut self,
+        get_var: impl Fn(&str) -> Option<String>,
+    ) -> anyhow::Result<()> {
+        let Some(allowlist) = self.env_allowlist()? else {
+            return Ok(());
+        };
+        let interpolator = Interpolator {
+            allowlist: &allowlist,
+            get_var: &get_var,
+        };
+        let mut errors = vec![];
+        interpolator.interpolate_table(&mut self.table, &mut vec![], &mut errors);
+        if !errors.is_empty() {
+            bail!(
+                "can't interpolate the environment variables of the configuration:\n{}",
+                errors
+                    .iter()
+                    .map(|e| format!("- {e}"))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+        }
+        Ok(())
+    }
+
+    fn env_allowlist(&self) -> anyhow::Result<Option<Vec<glob::Pattern>>> {
+        let Some(allowlist) = self
+            .table
+            .get("workspace")
+            .and_then(|w| w.get(ENV_ALLOWLIST_KEY))
+        else {
+            return Ok(None);
+        };
+        let patterns = allowlist
+            .as_array()
+            .context("`workspace.env_allowlist` must be an array")?;
+        patterns
+            .iter()
+            .map(|pattern| {
+                let pattern = pattern
+                    .as_str()
+                    .context("`workspace.env_allowlist` must contain strings")?;
+                glob::Pattern::new(pattern).with_context(|| {
+                    format!("invalid pattern `{pattern}` in `workspace.env_allowlist`")
+                })
+            })
+            .collect::<anyhow::Result<_>>()
+            .map(Some)
+    }
+
+    pub fn to_config(&self) -> anyhow::Result<Config> {
+        Ok(Value::Table(self.table.clone()).try_into()?)
+    }
+
+    /// The configuration in TOML format, where each value is followed by a comment
+    /// with its source.
+    pub fn to_annotated_toml(&self) -> anyhow::Result<String> {
+        // Print the sections in the same order of the documentation.
+        let mut sections: Vec<&String> = self.table.keys().collect();
+        sections.sort_by_key(|section| {
+            SECTIONS_ORDER
+                .iter()
+                .position(|s| s == section)
+                .unwrap_or(SECTIONS_ORDER.len())
+        });
+        let mut output = vec![];
+        for section in sections {
+            let mut table = Table::new();
+            table.insert(section.clone(), self.table[section].clone());
+            let toml = toml::to_string(&table).context("can't serialize configuration")?;
+            let mut doc: toml_edit::DocumentMut =
+                toml.parse().context("can't parse configuration")?;
+            annotate_table(doc.as_table_mut(), &mut vec![], &self.sources);
+            if let Some(tables) = doc
+                .get_mut(section)
+                .and_then(toml_edit::Item::as_array_of_tables_mut)
+            {
+                // The name identifies the table, so it goes first.
+                for table in tables.iter_mut() {
+                    table.sort_values_by(|k1, _, k2, _| {
+                        (k1.get() != "name").cmp(&(k2.get() != "name"))
+                    });
+                }
+            }
+            output.push(doc.to_string());
+        }
+        Ok(output.join("\n"))
+    }
+}
+
+fn record_sources(
+    table: &Table,
+    path: &mut KeyPath,
+    source: &str,
+    sources: &mut BTreeMap<KeyPath, String>,
+) {
+    for (key, value) in table {
+        path.push(key.clone());
+        match value {
+            Value::Array(packages) if path.len() == 1 && key == PACKAGE_KEY => {
+                for package in packages.iter().filter_map(Value::as_table) {
+                    if let Some(name) = package_name(package) {
+                        // The name identifies the package: it's not a setting.
+                        let mut settings = package.clone();
+                        settings.remove("name");
+                        path.push(name.to_string());
+                        record_sources(&settings, path, source, sources);
+                        path.pop();
+                    }
+                }
+            }
+            Value::Table(table) => record_sources(table, path, source, sources),
+            _ => {
+                sources.insert(path.clone(), source.to_string());
+            }
+        }
+        path.pop();
+    }
+}
+
+struct Interpolator<'a> {
+    allowlist: &'a [glob::Pattern],
+    get_var: &'a dyn Fn(&str) -> Option<String>,
+}
+
+impl Interpolator<'_> {
+    fn interpolate_table(&self, table: &mut Table, path: &mut KeyPath, errors: &mut Vec<String>) {
+        for (key, value) in table.iter_mut() {
+            // Names identify packages and branches, and the allowlist must stay as written.
+            if key == "name" || (path.len() == 1 && key == ENV_ALLOWLIST_KEY) {
+                continue;
+            }
+            path.push(key.clone());
+            self.interpolate_value(value, path, errors);
+            path.pop();
+        }
+    }
+
+    fn interpolate_value(&self, value: &mut Value, path: &mut KeyPath, errors: &mut Vec<String>) {
+        match value {
+            Value::String(s) => match self.interpolate(s) {
+                Ok(interpolated) => *s = interpolated,
+                Err(e) => errors.push(format!("`{}`: {e}", path.join("."))),
+            },
+            Value::Array(values) => {
+                for (i, value) in values.iter_mut().enumerate() {
+                    // Identify tables of arrays like `[[package]]` by name.
+                    let id = value
+                        .as_table()
+                        .and_then(package_name)
+                        .map_or_else(|| i.to_string(), str::to_string);
+                    path.push(id);
+                    self.interpolate_value(value, path, errors);
+                    path.pop();
+                }
+            }
+            Value::Table(table) => self.interpolate_table(table, path, errors),
+            _ => {}
+        }
+    }
+
+    fn interpolate(&self, s: &str) -> anyhow::Result<String> {
+        let mut output = String::new();
+        let mut rest = s;
+        while let Some(start) = rest.find('$') {
+            output.push_str(&rest[..start]);
+            let after_dollar = &rest[start + 1..];
+            if let Some(after_escape) = after_dollar.strip_prefix("${") {
+                output.push_str("${");
+                rest = after_escape;
+            } else if let Some(reference) = after_dollar.strip_prefix('{')
+                && let Some(end) = reference.find('}')
+            {
+                match self.resolve(&reference[..end])? {
+                    Some(value) => output.push_str(&value),
+                    None => output.push_str(&rest[start..start + end + 3]),
+                }
+                rest = &reference[end + 1..];
+            } else {
+                output.push('$');
+                rest = after_dollar;
+            }
+        }
+        output.push_str(rest);
+        Ok(output)
+    }
+
+    /// Value of a `VAR` or `VAR:-default` reference.
+    /// Like in the shell, the default is used if the variable is unset or empty.
+    /// Returns `None` if the reference isn't an allowed environment variable.
+    fn resolve(&self, reference: &str) -> anyhow::Result<Option<String>> {
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+        let is_valid_name = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_valid_name || !self.allowlist.iter().any(|pattern| pattern.matches(name)) {
+            return Ok(None);
+        }
+        let value = match ((self.get_var)(name), default) {
+            (Some(value), Some(default)) if value.is_empty() => default.to_string(),
+            (Some(value), _) => value,
+            (None, Some(default)) => default.to_string(),
+            (None, None) => bail!("environment variable `{name}` isn't set"),
+        };
+        Ok(Some(value))
+    }
+}
+
+fn package_name(package: &Table) -> Option<&str> {
+    package.get("name").and_then(Value::as_str)
+}
+
+struct Merger<'a> {
+    base_sources: &'a BTreeMap<KeyPath, String>,
+    top_sources: &'a BTreeMap<KeyPath, String>,
+    mode: MergeMode,
+}
+
+impl Merger<'_> {
+    fn merge_tables(
+        &mut self,
+        base: &mut Table,
+        top: Table,
+        path: &mut KeyPath,
+    ) -> anyhow::Result<()> {
+        for (key, top_value) in top {
+            path.push(key.clone());
+            let is_packages = path.len() == 1 && key == PACKAGE_KEY;
+            match (base.get_mut(&key), top_value) {
+                (Some(Value::Array(base_packages)), Value::Array(top_packages)) if is_packages => {
+                    self.merge_packages(base_packages, top_packages, path)?;
+                }
+                (Some(Value::Table(base_table)), Value::Table(top_table)) => {
+                    self.merge_tables(base_table, top_table, path)?;
+                }
+                (Some(_), top_value) => {
+                    if let MergeMode::Disjoint = self.mode {
+                        self.conflict(path)?;
+                    }
+                    base.insert(key, top_value);
+                }
+                (None, top_value) => {
+                    base.insert(key, top_value);
+                }
+            }
+            path.pop();
+        }
+        Ok(())
+    }
+
+    fn merge_packages(
+        &mut self,
+        base: &mut Vec<Value>,
+        top: Vec<Value>,
+        path: &mut KeyPath,
+    ) -> anyhow::Result<()> {
+        for top_package in top {
+            let Value::Table(mut top_package) = top_package else {
+                bail!("`package` must be an array of tables");
+            };
+            let name = package_name(&top_package)
+                .context("every `[[package]]` must have a `name`")?
+                .to_string();
+            let existing = base.iter_mut().find_map(|p| {
+                p.as_table_mut()
+                    .filter(|p| package_name(p) == Some(name.as_str()))
+            });
+            match existing {
+                Some(existing) => {
+                    // The name identifies the package: it's not a setting.
+                    top_package.remove("name");
+                    path.push(name);
+                    self.merge_tables(existing, top_package, path)?;
+                    path.pop();
+                }
+                None => base.push(Value::Table(top_package)),
+            }
+        }
+        Ok(())
+    }
+
+    fn conflict(&self, path: &KeyPath) -> anyhow::Result<()> {
+        let source = |sources: &BTreeMap<KeyPath, String>| {
+            sources
+                .get(path)
+                .cloned()
+                .unwrap_or_else(|| "an unknown source".to_string())
+        };
+        bail!(
+            "`{}` is set both in {} and in {}. Remove one of them.",
+            display_key(path),
+            source(self.base_sources),
+            source(self.top_sources)
+        )
+    }
+}
+
+/// E.g. `workspace.pr_draft` or `package.my_crate.publish`.
+fn display_key(path: &KeyPath) -> String {
+    path.join(".")
+}
+
+fn annotate_table(
+    table: &mut toml_edit::Table,
+    path: &mut KeyPath,
+    sources: &BTreeMap<KeyPath, String>,
+) {
+    for (key, item) in table.iter_mut() {
+        path.push(key.get().to_string());
+        match item {
+            toml_edit::Item::Table(table) => annotate_table(table, path, sources),
+            toml_edit::Item::ArrayOfTables(packages) if path.len() == 1 => {
+                for package in packages.iter_mut() {
+                    let name = package
+                        .get("name")
+                        .and_then(|n| n.as_str())
+                        .map(String::from);
+                    if let Some(name) = name {
+                        path.push(name);
+                        annotate_table(package, path, sources);
+                        path.pop();
+                    }
+                }
+            }
+            toml_edit::Item::Value(value) => {
+                if let Some(source) = sources.get(path) {
+                    value.decor_mut().set_suffix(format!(" # {source}"));
+                }
+            }
+            _ => {}
+        }
+        path.pop();
+    }
+}
+
+/// Resolve the `extends` key of the configuration, recursively.
+/// The configuration `table` comes from `source`, and its relative `extends` paths
+/// are relative to `base_dir`.
+pub fn resolve_extends(
+    table: Table,
+    source: &str,
+    base_dir: &Path,
+) -> anyhow::Result<SourcedConfig> {
+    resolve_extends_with_depth(table, source, base_dir, 0)
+}
+
+fn resolve_extends_with_depth(
+    mut table: Table,
+    source: &str,
+    base_dir: &Path,
+    depth: usize,
+) -> anyhow::Result<SourcedConfig> {
+    let extends = extends_list(table.remove(EXTENDS_KEY))
+        .with_context(|| format!("invalid `extends` in {source}"))?;
+    let mut resolved = SourcedConfig::default();
+    if !extends.is_empty() {
+        anyhow::ensure!(
+            depth < MAX_EXTENDS_DEPTH,
+            "too many nested `extends` in {source}. Is there a cycle?"
+        );
+    }
+    // Later presets override the earlier ones.
+    for extended in extends {
+        let preset = Preset::parse(&extended, base_dir)?;
+        let (mut preset_table, preset_dir) = preset
+            .load()
+            .with_context(|| format!("can't load `{extended}`, extended by {source}"))?;
+        rebase_paths(&mut preset_table, &preset_dir);
+        let preset_config =
+            resolve_extends_with_depth(preset_table, &extended, &preset_dir, depth + 1)?;
+        resolved
+            .override_with(preset_config)
+            .with_context(|| format!("can't merge `{extended}`, extended by {source}"))?;
+    }
+    resolved
+        .override_with(SourcedConfig::new(table, source))
+        .with_context(|| format!("can't merge {source} with the files it extends"))?;
+    Ok(resolved)
+}
+
+/// Make the relative paths of the files used by a preset relative to the directory
+/// of the preset, so that the preset can ship them.
+/// Other paths, like `changelog_path`, point to files of the project, so they stay as they are.
+fn rebase_paths(table: &mut Table, preset_dir: &Path) {
+    let rebase = |settings: &mut Table| {
+        for key in PRESET_PATH_KEYS {
+            if let Some(Value::String(path)) = settings.get_mut(*key)
+                && Path::new(path.as_str()).is_relative()
+            {
+                *path = preset_dir.join(&path).to_string_lossy().into_owned();
+            }
+        }
+    };
+    for (key, value) in table.iter_mut() {
+        match (key.as_str(), value) {
+            ("workspace", Value::Table(workspace)) => rebase(workspace),
+            (BRANCH_KEY, Value::Array(branches)) => {
+                branches
+                    .iter_mut()
+                    .filter_map(Value::as_table_mut)
+                    .for_each(rebase);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn extends_list(extends: Option<Value>) -> anyhow::Result<Vec<String>> {
+    let Some(extends) = extends else {
+        return Ok(vec![]);
+    };
+    let Value::Array(extends) = extends else {
+        bail!("`extends` must be an array of strings");
+    };
+    extends
+        .into_iter()
+        .map(|e| match e {
+            Value::String(e) => Ok(e),
+            _ => bail!("`extends` must be an array of strings"),
+        })
+        .collect()
+}
+
+/// Configuration file extended by another configuration.
+#[derive(Debug, PartialEq, Eq)]
+enum Preset {
+    Local(PathBuf),
+    Git(GitPreset),
+}
+
+/// File of a git repository, pinned to a revision.
+/// Format: `git+<repo url>?rev=<commit or tag>#<path in the repository>`.
+#[derive(Debug, PartialEq, Eq)]
+struct GitPreset {
+    url: Url,
+    rev: String,
+    path: Utf8PathBuf,
+}
+
+impl Preset {
+    fn parse(extended: &str, base_dir: &Path) -> anyhow::Result<Self> {
+        match extended.strip_prefix(GIT_PREFIX) {
+            Some(git_url) => GitPreset::parse(git_url)
+                .with_context(|| format!("invalid git url `{extended}` in `extends`"))
+                .map(Self::Git),
+            None => Ok(Self::Local(base_dir.join(extended))),
+        }
+    }
+
+    /// Returns the configuration table and the directory of the file.
+    fn load(&self) -> anyhow::Result<(Table, PathBuf)> {
+        let path = match self {
+            Self::Local(path) => path.clone(),
+            Self::Git(git) => git.checkout()?.join(&git.path).into_std_path_buf(),
+        };
+        let contents = read_to_string(&path)?;
+        let table = toml::from_str(&contents)
+            .with_context(|| format!("invalid config file {}", path.display()))?;
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok((table, dir))
+    }
+}
+
+impl GitPreset {
+    fn parse(git_url: &str) -> anyhow::Result<Self> {
+        let mut url = Url::parse(git_url)?;
+        let path = url.fragment().unwrap_or(DEFAULT_GIT_FILE).to_string();
+        let rev = url
+            .query_pairs()
+            .find(|(key, _)| key == "rev")
+            .map(|(_, rev)| rev.to_string())
+            .context("the revision isn't pinned. Add `?rev=<commit or tag>` to the url")?;
+        url.set_query(None);
+        url.set_fragment(None);
+        let path = Utf8PathBuf::from(path);
+        anyhow::ensure!(
+            path.is_relative() && !path.as_str().contains(".."),
+            "the path of the file must be relative to the root of the repository"
+        );
+        Ok(Self { url, rev, path })
+    }
+
+    /// Directory of the local cache of the repository at the pinned revision.
+    fn cache_dir(&self) -> anyhow::Result<Utf8PathBuf> {
+        let cache_dir = dirs::cache_dir().context("can't determine the cache directory")?;
+        let cache_dir = Utf8PathBuf::try_from(cache_dir)?;
+        Ok(cache_dir
+            .join("release-plz")
+            .join("extends")
+            .join(sanitize(&format!(
+                "{}{}",
+                self.url.host_str().unwrap_or_default(),
+                self.url.path()
+            )))
+            .join(sanitize(&self.rev)))
+    }
+
+    /// Clone the repository at the pinned revision, unless it's already cached.
+    /// Because the revision is pinned, the cache never expires.
+    fn checkout(&self) -> anyhow::Result<Utf8PathBuf> {
+        let dir = self.cache_dir()?;
+        if dir.join(".git").exists() {
+            debug!("using cached {} at {}", self.url, self.rev);
+            return Ok(dir);
+        }
+        info!("fetching {} at {}", self.url, self.rev);
+        // Clone in a temporary directory, so that interrupted clones don't end up in the cache.
+        let tmp_dir = dir.with_extension(format!("tmp-{}", std::process::id()));
+        fs_err::create_dir_all(&tmp_dir)?;
+        let cloned = clone_rev(&tmp_dir, self.url.as_str(), &self.rev);
+        if let Err(e) = cloned {
+            if let Err(remove_err) = fs_err::remove_dir_all(&tmp_dir) {
+                warn!("can't remove {tmp_dir}: {remove_err}");
+            }
+            return Err(e);
+        }
+        fs_err::rename(&tmp_dir, &dir)?;
+        Ok(dir)
+    }
+}
+
+fn clone_rev(dir: &Utf8Path, url: &str, rev: &str) -> anyhow::Result<()> {
+    git_cmd::git_in_dir(dir, &["init", "--quiet"])?;
+    git_cmd::git_in_dir(dir, &["fetch", "--quiet", "--depth", "1", url, rev])?;
+    git_cmd::git_in_dir(dir, &["checkout", "--quiet", "FETCH_HEAD"])?;
+    Ok(())
+}
+
+/// Make the string usable as a directory name.
+fn sanitize(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sourced(toml: &str, source: &str) -> SourcedConfig {
+        SourcedConfig::new(toml::from_str(toml).unwrap(), source)
+    }
+
+    #[test]
+    fn top_config_overrides_base_config() {
+        let mut config = sourced(
+            r#"
+            [workspace]
+            pr_draft = true
+            pr_labels = ["a", "b"]
+
+            [[package]]
+            name = "a"
+            publish = false
+            semver_check = false
+            "#,
+            "base.toml",
+        );
+        config
+            .override_with(sourced(
+                r#"
+            [workspace]
+            pr_labels = ["c"]
+
+            [[package]]
+            name = "a"
+            publish = true
+
+            [[package]]
+            name = "b"
+            release = false
+            "#,
+                "release-plz.toml",
+            ))
+            .unwrap();
+
+        expect_test::expect![[r#"
+            [workspace]
+            pr_draft = true # base.toml
+            pr_labels = ["c"] # release-plz.toml
+
+            [[package]]
+            name = "a"
+            publish = true # release-plz.toml
+            semver_check = false # base.toml
+
+            [[package]]
+            name = "b"
+            release = false # release-plz.toml
+        "#]]
+        .assert_eq(&config.to_annotated_toml().unwrap());
+    }
+
+    #[test]
+    fn disjoint_configs_cant_set_the_same_value() {
+        let mut config = sourced("[workspace]\npr_draft = true", "release-plz.toml");
+        let error = config
+            .merge_disjoint(sourced("[workspace]\npr_draft = false", "Cargo.toml"))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "`workspace.pr_draft` is set both in release-plz.toml and in Cargo.toml. Remove one of them."
+        );
+    }
+
+    #[test]
+    fn local_extends_are_resolved_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let shared = dir.path().join("shared");
+        fs_err::create_dir(&shared).unwrap();
+        fs_err::write(
+            shared.join("org.toml"),
+            "extends = [\"base.toml\"]\n[workspace]\npr_draft = true\npr_labels = [\"org\"]",
+        )
+        .unwrap();
+        fs_err::write(
+            shared.join("base.toml"),
+            "[workspace]\npr_draft = false\nrelease_always = false",
+        )
+        .unwrap();
+        let table = toml::from_str(
+            "extends = [\"shared/org.toml\"]\n[workspace]\npr_labels = [\"release\"]",
+        )
+        .unwrap();
+
+        let config = resolve_extends(table, "release-plz.toml", dir.path()).unwrap();
+
+        expect_test::expect![[r#"
+            [workspace]
+            pr_draft = true # shared/org.toml
+            pr_labels = ["release"] # release-plz.toml
+            release_always = false # base.toml
+        "#]]
+        .assert_eq(&config.to_annotated_toml().unwrap());
+    }
+
+    #[test]
+    fn preset_paths_are_relative_to_the_preset() {
+        let dir = tempfile::tempdir().unwrap();
+        let shared = dir.path().join("shared");
+        fs_err::create_dir(&shared).unwrap();
+        fs_err::write(
+            shared.join("org.toml"),
+            r#"
+            [workspace]
+            changelog_config = "cliff.toml"
+            changelog_path = "docs/CHANGELOG.md"
+
+            [[branch]]
+            name = "release/*"
+            changelog_config = "release-cliff.toml"
+            "#,
+        )
+        .unwrap();
+        let table = toml::from_str("extends = [\"shared/org.toml\"]").unwrap();
+
+        let config = resolve_extends(table, "release-plz.toml", dir.path()).unwrap();
+        let config = config.to_config().unwrap();
+        assert_eq!(
+            config.workspace.changelog_config,
+            Some(shared.join("cliff.toml"))
+        );
+        assert_eq!(
+            config.workspace.packages_defaults.changelog_path,
+            Some(PathBuf::from("docs/CHANGELOG.md"))
+        );
+        assert_eq!(
+            config.branch[0].workspace.changelog_config,
+            Some(shared.join("release-cliff.toml"))
+        );
+    }
+
+    #[test]
+    fn package_without_name_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        fs_err::write(dir.path().join("org.toml"), "[[package]]\nname = \"a\"").unwrap();
+        let table =
+            toml::from_str("extends = [\"org.toml\"]\n[[package]]\npublish = false").unwrap();
+        let error = resolve_extends(table, "release-plz.toml", dir.path()).unwrap_err();
+        assert!(format!("{error:?}").contains("every `[[package]]` must have a `name`"));
+    }
+
+    #[test]
+    fn extends_cycle_is_detected() {
+        let dir = tempfile::tempdir().unwrap();
+        fs_err::write(dir.path().join("a.toml"), "extends = [\"a.toml\"]").unwrap();
+        let table = toml::from_str("extends = [\"a.toml\"]").unwrap();
+        let error = resolve_extends(table, "release-plz.toml", dir.path()).unwrap_err();
+        assert!(format!("{error:?}").contains("Is there a cycle?"));
+    }
+
+    #[test]
+    fn git_preset_is_parsed() {
+        let preset = Preset::parse(
+            "git+https://github.com/org/presets?rev=v1.2.0#rust/release-plz.toml",
+            Path::new("."),
+        )
+        .unwrap();
+        assert_eq!(
+            preset,
+            Preset::Git(GitPreset {
+                url: Url::parse("https://github.com/org/presets").unwrap(),
+                rev: "v1.2.0".to_string(),
+                path: Utf8PathBuf::from("rust/release-plz.toml"),
+            })
+        );
+    }
+
+    #[test]
+    fn git_preset_must_be_pinned() {
+        let error =
+            Preset::parse("git+https://github.com/org/presets", Path::new(".")).unwrap_err();
+        assert!(format!("{error:?}").contains("the revision isn't pinned"));
+    }
+
+    #[test]
+    fn matching_branch_overrides_config() {
+        let mut config = sourced(
+            r#"
+            [workspace]
+            pr_branch_prefix = "release-plz-"
+            pr_labels = ["release"]
+
+            [[package]]
+            name = "a"
+            publish = true
+
+            [[branch]]
+            name = "release/*"
+            pr_branch_prefix = "release-plz-1.x-"
+            version_constraint = "<2.0.0"
+
+            [[branch.package]]
+            name = "a"
+            publish = false
+
+            [[branch]]
+            name = "main"
+            pr_labels = ["main"]
+            "#,
+            "release-plz.toml",
+        );
+        config
+            .apply_branch_overrides(|| Ok(Some("release/1.x".to_string())))
+            .unwrap();
+
+        expect_test::expect![[r#"
+            [workspace]
+            pr_branch_prefix = "release-plz-1.x-" # release-plz.toml [[branch]] release/*
+            pr_labels = ["release"] # release-plz.toml
+            version_constraint = "<2.0.0" # release-plz.toml [[branch]] release/*
+
+            [[package]]
+            name = "a"
+            publish = false # release-plz.toml [[branch]] release/*
+        "#]]
+        .assert_eq(&config.to_annotated_toml().unwrap());
+    }
+
+    #[test]
+    fn invalid_branch_section_is_an_error() {
+        let mut config = sourced(
+            "[[branch]]\nname = \"other\"\npr_labelz = [\"a\"]",
+            "release-plz.toml",
+        );
+        let error = config
+            .apply_branch_overrides(|| Ok(Some("main".to_string())))
+            .unwrap_err();
+        assert!(format!("{error:?}").contains("unknown field `pr_labelz`"));
+    }
+
+    fn interpolate(toml: &str, vars: &[(&str, &str)]) -> anyhow::Result<String> {
+        let mut config = sourced(toml, "release-plz.toml");
+        config.interpolate_env(|name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| (*value).to_string())
+        })?;
+        config.to_annotated_toml()
+    }
+
+    #[test]
+    fn allowed_env_vars_are_interpolated() {
+        let config = interpolate(
+            r#"
+            [workspace]
+            env_allowlist = ["CI_*", "DEPLOY_ENV"]
+            repo_url = "${CI_PROJECT_URL}"
+            pr_labels = ["release", "${DEPLOY_ENV:-staging}", "${CI_EMPTY}"]
+            pr_body = "cost: $5, literal: $${CI_PROJECT_URL}, other: ${GITHUB_TOKEN}"
+
+            [[package]]
+            name = "a"
+            git_tag_name = "${CI_TAG_PREFIX:-v}{{ version }}"
+            "#,
+            &[
+                ("CI_PROJECT_URL", "https://gitlab.com/owner/repo"),
+                ("CI_EMPTY", ""),
+                ("DEPLOY_ENV", ""),
+                ("GITHUB_TOKEN", "secret"),
+            ],
+        )
+        .unwrap();
+        expect_test::expect![[r#"
+            [workspace]
+            env_allowlist = ["CI_*", "DEPLOY_ENV"] # release-plz.toml
+            pr_body = "cost: $5, literal: ${CI_PROJECT_URL}, other: ${GITHUB_TOKEN}" # release-plz.toml
+            pr_labels = ["release", "staging", ""] # release-plz.toml
+            repo_url = "https://gitlab.com/owner/repo" # release-plz.toml
+
+            [[package]]
+            name = "a"
+            git_tag_name = "v{{ version }}" # release-plz.toml
+        "#]]
+        .assert_eq(&config);
+    }
+
+    #[test]
+    fn interpolation_errors_name_the_key() {
+        let error = interpolate(
+            r#"
+            [workspace]
+            env_allowlist = ["CI_*"]
+            repo_url = "${CI_PROJECT_URL}"
+            pr_body = "${CI_COMMIT_SHA}"
+
+            [[package]]
+            name = "a"
+            git_tag_name = "${CI_PREFIX}"
+            "#,
+            &[],
+        )
+        .unwrap_err();
+        expect_test::expect![[r#"
+            can't interpolate the environment variables of the configuration:
+            - `package.a.git_tag_name`: environment variable `CI_PREFIX` isn't set
+            - `workspace.pr_body`: environment variable `CI_COMMIT_SHA` isn't set
+            - `workspace.repo_url`: environment variable `CI_PROJECT_URL` isn't set"#]]
+        .assert_eq(&error.to_string());
+    }
+
+    #[test]
+    fn invalid_references_are_left_as_they_are() {
+        let config = interpolate(
+            r#"
+            [workspace]
+            env_allowlist = ["CI_*"]
+            pr_body = "${CI_PREFIX and ${}"
+
+            [changelog]
+            commit_preprocessors = [
+                { pattern = '\((\w+\s)?#([0-9]+)\)', replace = "${2}" },
+            ]
+            "#,
+            &[],
+        )
+        .unwrap();
+        expect_test::expect![[r#"
+            [workspace]
+            env_allowlist = ["CI_*"] # release-plz.toml
+            pr_body = "${CI_PREFIX and ${}" # release-plz.toml
+
+            [[changelog.commit_preprocessors]]
+            pattern = '\((\w+\s)?#([0-9]+)\)'
+            replace = "${2}"
+        "#]]
+        .assert_eq(&config);
+    }
+
+    #[test]
+    fn nothing_is_interpolated_without_allowlist() {
+        let config = interpolate(
+            r#"
+            [workspace]
+            repo_url = "${CI_PROJECT_URL}"
+            pr_body = "$${CI_PROJECT_URL}"
+            "#,
+            &[("CI_PROJECT_URL", "https://gitlab.com/owner/repo")],
+        )
+        .unwrap();
+        expect_test::expect![[r#"
+            [workspace]
+            pr_body = "$${CI_PROJECT_URL}" # release-plz.toml
+            repo_url = "${CI_PROJECT_URL}" # release-plz.toml
+        "#]]
+        .assert_eq(&config);
+    }
+}
@@ -0,0 +1,584 @@
+//! Checks of the release-plz configuration that go beyond deserialization:
+//! templates, regexes and package names are verified before a command needs them.
+
+use std::{collections::BTreeMap, fmt::Write as _, ops::Range, path::Path};
+
+use git_cliff_core::template::Template;
+use regex::Regex;
+use release_plz_core::{ConfigTemplate, PrMode};
+
+use crate::{
+    changelog_config::{ChangelogCfg, TextProcessor},
+    config::{
+        Config, PackageConfig, PackageSpecificConfigWithName, Workspace, validate_git_only_settings,
+    },
+    config_sources::SourcedConfig,
+};
+
+/// Problem of a setting of the configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    /// Path of the setting, e.g. `["workspace", "pr_name"]`.
+    /// Settings of `[[package]]` and `[[branch]]` are identified by their name,
+    /// e.g. `["package", "my_crate", "git_tag_name"]`.
+    pub path: Vec<String>,
+    pub message: String,
+}
+
+/// Find all the problems of the configuration.
+/// `workspace_packages` are the names of the packages of the cargo workspace.
+pub fn problems(config: &Config, workspace_packages: &[&str]) -> Vec<Problem> {
+    let mut checker = Checker {
+        workspace_packages,
+        pr_mode: config.workspace.pr_mode.unwrap_or_default().into(),
+        problems: vec![],
+    };
+    checker.workspace(&["workspace"], &config.workspace);
+    checker.packages(&["package"], &config.package);
+    checker.version_groups(&["package"], &config.package, &[]);
+    checker.changelog(&config.changelog);
+    for branch in &config.branch {
+        let path = ["branch", branch.name.as_str()];
+        if let Err(e) = glob::Pattern::new(&branch.name) {
+            checker.push(&path, "name", format!("invalid glob pattern: {e}"));
+        }
+        checker.pr_mode = branch
+            .workspace
+            .pr_mode
+            .or(config.workspace.pr_mode)
+            .unwrap_or_default()
+            .into();
+        checker.workspace(&path, &branch.workspace);
+        let packages_path = ["branch", branch.name.as_str(), "package"];
+        checker.packages(&packages_path, &branch.package);
+        checker.version_groups(&packages_path, &branch.package, &config.package);
+    }
+    checker.problems
+}
+
+struct Checker<'a> {
+    workspace_packages: &'a [&'a str],
+    /// PR mode of the section being checked, which determines the variables
+    /// of the release PR templates.
+    pr_mode: PrMode,
+    problems: Vec<Problem>,
+}
+
+impl Checker<'_> {
+    fn push(&mut self, table: &[&str], key: &str, message: String) {
+        let path = table
+            .iter()
+            .chain([&key])
+            .map(|s| (*s).to_string())
+            .collect();
+        let problem = Problem { path, message };
+        if !self.problems.contains(&problem) {
+            self.problems.push(problem);
+        }
+    }
+
+    fn workspace(&mut self, path: &[&str], workspace: &Workspace) {
+        self.template(
+            path,
+            "pr_name",
+            workspace.pr_name.as_deref(),
+            ConfigTemplate::PrName,
+        );
+        self.template(
+            path,
+            "pr_body",
+            workspace.pr_body.as_deref(),
+            ConfigTemplate::PrBody,
+        );
+        self.template(
+            path,
+            "pr_milestone",
+            workspace.pr_milestone.as_deref(),
+            ConfigTemplate::PrMilestone,
+        );
+        self.template(
+            path,
+            "release_comment",
+            workspace.release_comment.as_deref(),
+            ConfigTemplate::ReleaseComment,
+        );
+        self.regex(
+            path,
+            "release_commits",
+            workspace.release_commits.as_deref(),
+        );
+        self.package_config(path, &workspace.packages_defaults);
+    }
+
+    fn packages(&mut self, path: &[&str], packages: &[PackageSpecificConfigWithName]) {
+        for package in packages {
+            let package_path: Vec<&str> = path
+                .iter()
+                .copied()
+                .chain([package.name.as_str()])
+                .collect();
+            if !self.is_workspace_package(&package.name) {
+                self.push(
+                    &package_path,
+                    "name",
+                    format!("package `{}` isn't a member of the workspace", package.name),
+                );
+            }
+            for included in package.config.changelog_include() {
+                if !self.is_workspace_package(included) {
+                    self.push(
+                        &package_path,
+                        "changelog_include",
+                        format!("package `{included}` isn't a member of the workspace"),
+                    );
+                }
+            }
+            self.package_config(&package_path, package.config.common());
+        }
+    }
+
+    /// Version groups with a single package are likely a typo in the group name.
+    /// `base_packages` are the packages that `packages` override, if any.
+    fn version_groups(
+        &mut self,
+        path: &[&str],
+        packages: &[PackageSpecificConfigWithName],
+        base_packages: &[PackageSpecificConfigWithName],
+    ) {
+        let mut groups: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for package in base_packages.iter().chain(packages) {
+            let group = packages
+                .iter()
+                .find(|p| p.name == package.name)
+                .unwrap_or(package)
+                .config
+                .version_group();
+            if let Some(group) = group {
+                let members = groups.entry(group).or_default();
+                if !members.contains(&package.name.as_str()) {
+                    members.push(&package.name);
+                }
+            }
+        }
+        for (group, members) in groups {
+            let [member] = members.as_slice() else {
+                continue;
+            };
+            // Report the problem where the version group is set.
+            if packages
+                .iter()
+                .any(|p| p.name == *member && p.config.version_group().is_some())
+            {
+                let package_path: Vec<&str> = path.iter().copied().chain([*member]).collect();
+                self.push(
+                    &package_path,
+                    "version_group",
+                    format!(
+                        "version group `{group}` only contains package `{member}`. Is the group name misspelled?"
+                    ),
+                );
+            }
+        }
+    }
+
+    fn package_config(&mut self, path: &[&str], config: &PackageConfig) {
+        self.template(
+            path,
+            "git_tag_name",
+            config.git_tag_name.as_deref(),
+            ConfigTemplate::GitTagName,
+        );
+        self.template(
+            path,
+            "git_release_name",
+            config.git_release_name.as_deref(),
+            ConfigTemplate::GitReleaseName,
+        );
+        self.template(
+            path,
+            "git_release_body",
+            config.git_release_body.as_deref(),
+            ConfigTemplate::GitReleaseBody,
+        );
+        self.regex(
+            path,
+            "custom_minor_increment_regex",
+            config.custom_minor_increment_regex.as_deref(),
+        );
+        self.regex(
+            path,
+            "custom_major_increment_regex",
+            config.custom_major_increment_regex.as_deref(),
+        );
+        if let Err(e) = validate_git_only_settings(config.git_only, config.publish) {
+            self.push(path, "git_only", e.to_string());
+        }
+    }
+
+    fn changelog(&mut self, changelog: &ChangelogCfg) {
+        let path = ["changelog"];
+        let trim = changelog.trim.unwrap_or(true);
+        for (key, template) in [("header", &changelog.header), ("body", &changelog.body)] {
+            if let Some(template) = template
+                && let Err(e) = Template::new(key, template.clone(), trim)
+            {
+                self.push(&path, key, format!("invalid template: {e}"));
+            }
+        }
+        self.regex(&path, "tag_pattern", changelog.tag_pattern.as_deref());
+        self.text_processors(
+            "commit_preprocessors",
+            changelog.commit_preprocessors.as_deref(),
+        );
+        self.text_processors("postprocessors", changelog.postprocessors.as_deref());
+        for (i, parser) in changelog.link_parsers.iter().flatten().enumerate() {
+            let index = i.to_string();
+            self.regex(
+                &["changelog", "link_parsers", &index],
+                "pattern",
+                Some(&parser.pattern),
+            );
+        }
+        for (i, parser) in changelog.commit_parsers.iter().flatten().enumerate() {
+            let index = i.to_string();
+            let parser_path = ["changelog", "commit_parsers", &index];
+            self.regex(&parser_path, "message", parser.message.as_deref());
+            self.regex(&parser_path, "body", parser.body.as_deref());
+            self.regex(&parser_path, "pattern", parser.pattern.as_deref());
+        }
+    }
+
+    fn text_processors(&mut self, key: &str, processors: Option<&[TextProcessor]>) {
+        for (i, processor) in processors.into_iter().flatten().enumerate() {
+            let index = i.to_string();
+            self.regex(
+                &["changelog", key, &index],
+                "pattern",
+                Some(&processor.pattern),
+            );
+        }
+    }
+
+    fn template(&mut self, path: &[&str], key: &str, template: Option<&str>, kind: ConfigTemplate) {
+        if let Some(template) = template
+            && let Err(e) =
+                kind.render_sample(template, self.workspace_packages.len() > 1, self.pr_mode)
+        {
+            self.push(path, key, format!("invalid template: {e:#}"));
+        }
+    }
+
+    fn regex(&mut self, path: &[&str], key: &str, regex: Option<&str>) {
+        if let Some(regex) = regex
+            && let Err(e) = Regex::new(regex)
+        {
+            self.push(path, key, format!("invalid regex: {e}"));
+        }
+    }
+
+    fn is_workspace_package(&self, name: &str) -> bool {
+        self.workspace_packages.contains(&name)
+    }
+}
+
+/// Describe the problems, pointing at the TOML of the config file
+/// for the settings that come from it.
+/// `config_file` is the path and the content of the config file, if any.
+pub fn report(
+    problems: &[Problem],
+    config: &SourcedConfig,
+    config_file: Option<(&Path, &str)>,
+) -> String {
+    let document = config_file.and_then(|(path, content)| {
+        toml_edit::Document::parse(content)
+            .ok()
+            .map(|document| (path.display().to_string(), document))
+    });
+    let mut report = String::new();
+    for problem in problems {
+        // Indent multi-line messages, e.g. regex errors, to keep them apart from other problems.
+        let message = problem.message.lines().collect::<Vec<_>>().join("\n    ");
+        writeln!(report, "error: `{}`: {message}", problem.path.join(".")).unwrap();
+        let source = config.source_of(&problem.path);
+        let span = document.as_ref().and_then(|(file, document)| {
+            // Settings without a source, like package names, can be anywhere.
+            let in_file = source.is_none_or(|source| source == file);
+            in_file
+                .then(|| find_span(document.as_item(), &problem.path))
+                .flatten()
+                .map(|span| snippet(file, document.raw(), span))
+        });
+        match (span, source) {
+            (Some(snippet), _) => report.push_str(&snippet),
+            (None, Some(source)) => writeln!(report, "  = note: set in {source}").unwrap(),
+            (None, None) => {}
+        }
+        report.push('\n');
+    }
+    report
+}
+
+/// Span of the setting at `path`, if the file contains it.
+/// Arrays of tables are indexed by the `name` of their tables or by position.
+fn find_span(item: &toml_edit::Item, path: &[String]) -> Option<Range<usize>> {
+    let Some((key, rest)) = path.split_first() else {
+        return item.span();
+    };
+    let child = if let Some(array_len) = array_len(item) {
+        (0..array_len)
+            .filter_map(|i| item.get(i))
+            .find(|element| element.get("name").and_then(|n| n.as_str()) == Some(key.as_str()))
+            .or_else(|| key.parse::<usize>().ok().and_then(|i| item.get(i)))
+    } else {
+        item.get(key.as_str())
+    };
+    find_span(child?, rest)
+}
+
+fn array_len(item: &toml_edit::Item) -> Option<usize> {
+    match item {
+        toml_edit::Item::ArrayOfTables(array) => Some(array.len()),
+        toml_edit::Item::Value(toml_edit::Value::Array(array)) => Some(array.len()),
+        _ => None,
+    }
+}
+
+/// Render the first line of `span`, like `rustc` does.
+fn snippet(file: &str, content: &str, span: Range<usize>) -> String {
+    let line_start = content[..span.start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = content[span.start..]
+        .find('\n')
+        .map_or(content.len(), |i| span.start + i);
+    let line = &content[line_start..line_end];
+    let line_number = content[..span.start].matches('\n').count() + 1;
+    let column = content[line_start..span.start].chars().count();
+    let width = content[span.start..span.end.min(line_end)]
+        .chars()
+        .count()
+        .max(1);
+    let gutter = " ".repeat(line_number.to_string().len());
+    format!(
+        "{gutter}--> {file}:{line_number}:{}\n\
+         {gutter} |\n\
+         {line_number} | {line}\n\
+         {gutter} | {}{}\n",
+        column + 1,
+        " ".repeat(column),
+        "^".repeat(width)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG_FILE: &str = "release-plz.toml";
+
+    fn check(toml: &str) -> String {
+        let table: toml::Table = toml::from_str(toml).unwrap();
+        let sourced = SourcedConfig::new(table, CONFIG_FILE);
+        let config = sourced.to_config().unwrap();
+        let problems = problems(&config, &["aaa", "bbb"]);
+        report(&problems, &sourced, Some((Path::new(CONFIG_FILE), toml)))
+    }
+
+    #[test]
+    fn valid_config_has_no_problems() {
+        let report = check(
+            r#"
+[workspace]
+pr_name = "release{% if package %} {{ package }}{% endif %}"
+release_commits = "^(feat|fix):"
+
+[[package]]
+name = "aaa"
+changelog_include = ["bbb"]
+version_group = "group"
+
+[[package]]
+name = "bbb"
+version_group = "group"
+"#,
+        );
+        assert_eq!(report, "");
+    }
+
+    #[test]
+    fn pr_name_is_checked_for_prs_of_many_packages() {
+        let report = check(
+            r#"
+[workspace]
+pr_name = "release {{ package }}"
+"#,
+        );
+        expect_test::expect![[r#"
+            error: `workspace.pr_name`: invalid template: release PR of packages with the same version: failed to render pr_name: Failed to render 'pr_name': Variable `package` not found in context while rendering 'pr_name'
+             --> release-plz.toml:3:11
+              |
+            3 | pr_name = "release {{ package }}"
+              |           ^^^^^^^^^^^^^^^^^^^^^^^
+
+        "#]]
+        .assert_eq(&report);
+    }
+
+    #[test]
+    fn package_is_available_in_pr_name_of_per_package_prs() {
+        let report = check(
+            r#"
+[workspace]
+pr_mode = "per_package"
+pr_name = "release {{ package }}"
+
+[[branch]]
+name = "main"
+pr_name = "release {{ package }} {{ version }}"
+
+[[branch]]
+name = "release/*"
+pr_mode = "single"
+pr_name = "release {{ package }}"
+"#,
+        );
+        expect_test::expect![[r#"
+            error: `branch.release/*.pr_name`: invalid template: release PR of packages with the same version: failed to render pr_name: Failed to render 'pr_name': Variable `package` not found in context while rendering 'pr_name'
+              --> release-plz.toml:13:11
+               |
+            13 | pr_name = "release {{ package }}"
+               |           ^^^^^^^^^^^^^^^^^^^^^^^
+
+        "#]]
+        .assert_eq(&report);
+    }
+
+    #[test]
+    fn all_problems_are_reported_with_their_span() {
+        let report = check(
+            r#"
+[workspace]
+pr_name = "release {{ pkg }}"
+release_commits = "^(feat"
+
+[[package]]
+name = "aaa"
+changelog_include = ["ccc"]
+version_group = "group"
+
+[[package]]
+name = "bbbb"
+git_tag_name = "{{ package }}-v{{ version"
+
+[[changelog.commit_parsers]]
+message = "^feat"
+
+[[changelog.commit_parsers]]
+message = "^fix("
+"#,
+        );
+        expect_test::expect![[r#"
+            error: `workspace.pr_name`: invalid template: failed to render pr_name: Failed to render 'pr_name': Variable `pkg` not found in context while rendering 'pr_name'
+             --> release-plz.toml:3:11
+              |
+            3 | pr_name = "release {{ pkg }}"
+              |           ^^^^^^^^^^^^^^^^^^^
+
+            error: `workspace.release_commits`: invalid regex: regex parse error:
+                    ^(feat
+                     ^
+                error: unclosed group
+             --> release-plz.toml:4:19
+              |
+            4 | release_commits = "^(feat"
+              |                   ^^^^^^^^
+
+            error: `package.aaa.changelog_include`: package `ccc` isn't a member of the workspace
+             --> release-plz.toml:8:21
+              |
+            8 | changelog_include = ["ccc"]
+              |                     ^^^^^^^
+
+            error: `package.bbbb.name`: package `bbbb` isn't a member of the workspace
+              --> release-plz.toml:12:8
+               |
+            12 | name = "bbbb"
+               |        ^^^^^^
+
+            error: `package.bbbb.git_tag_name`: invalid template: failed to parse tag_name: Failed to parse 'tag_name':  --> 1:26
+                  |
+                1 | {{ package }}-v{{ version
+                  |                          ^---
+                  |
+                  = expected `or`, `and`, `not`, `<=`, `>=`, `<`, `>`, `==`, `!=`, `+`, `-`, `*`, `/`, `%`, a filter, or a variable end (`}}`)
+              --> release-plz.toml:13:16
+               |
+            13 | git_tag_name = "{{ package }}-v{{ version"
+               |                ^^^^^^^^^^^^^^^^^^^^^^^^^^^
+
+            error: `package.aaa.version_group`: version group `group` only contains package `aaa`. Is the group name misspelled?
+             --> release-plz.toml:9:17
+              |
+            9 | version_group = "group"
+              |                 ^^^^^^^
+
+            error: `changelog.commit_parsers.1.message`: invalid regex: regex parse error:
+                    ^fix(
+                        ^
+                error: unclosed group
+              --> release-plz.toml:19:11
+               |
+            19 | message = "^fix("
+               |           ^^^^^^^
+
+        "#]]
+        .assert_eq(&report);
+    }
+
+    #[test]
+    fn branch_problems_are_reported() {
+        let report = check(
+            r#"
+[[package]]
+name = "aaa"
+version_group = "group"
+
+[[package]]
+name = "bbb"
+version_group = "group"
+
+[[branch]]
+name = "release/[1"
+pr_milestone = "{{ version }"
+
+[[branch.package]]
+name = "bbb"
+version_group = "other"
+"#,
+        );
+        expect_test::expect![[r#"
+            error: `branch.release/[1.name`: invalid glob pattern: Pattern syntax error near position 8: invalid range pattern
+              --> release-plz.toml:11:8
+               |
+            11 | name = "release/[1"
+               |        ^^^^^^^^^^^^
+
+            error: `branch.release/[1.pr_milestone`: invalid template: failed to parse pr_milestone: Failed to parse 'pr_milestone':  --> 1:12
+                  |
+                1 | {{ version }
+                  |            ^---
+                  |
+                  = expected `or`, `and`, `not`, `<=`, `>=`, `<`, `>`, `==`, `!=`, `+`, `-`, `*`, `/`, `%`, a filter, or a variable end (`}}`)
+              --> release-plz.toml:12:16
+               |
+            12 | pr_milestone = "{{ version }"
+               |                ^^^^^^^^^^^^^^
+
+            error: `branch.release/[1.package.bbb.version_group`: version group `other` only contains package `bbb`. Is the group name misspelled?
+              --> release-plz.toml:16:17
+               |
+            16 | version_group = "other"
+               |                 ^^^^^^^
+
+        "#]]
+        .assert_eq(&report);
+    }
+}
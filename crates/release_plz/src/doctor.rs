@@ -0,0 +1,343 @@
+//! Checks of `release-plz doctor`.
+//! Each check diagnoses a common cause of failure and suggests how to fix it.
+
+use std::{fmt, path::Path, process::Command};
+
+use cargo_metadata::camino::Utf8Path;
+use git_cmd::{Repo, git_in_dir};
+use release_plz_core::GitClient;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Ok,
+    /// The check didn't run.
+    Skipped,
+    /// release-plz works, but not as well as it could.
+    Warning,
+    /// release-plz can't work.
+    Error,
+}
+
+#[derive(Debug)]
+pub struct Check {
+    name: String,
+    severity: Severity,
+    message: String,
+    fix: Option<String>,
+}
+
+impl Check {
+    pub fn ok(name: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(name, Severity::Ok, message, None)
+    }
+
+    pub fn skipped(name: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(name, Severity::Skipped, message, None)
+    }
+
+    pub fn warning(
+        name: impl Into<String>,
+        message: impl Into<String>,
+        fix: impl Into<String>,
+    ) -> Self {
+        Self::new(name, Severity::Warning, message, Some(fix.into()))
+    }
+
+    pub fn error(
+        name: impl Into<String>,
+        message: impl Into<String>,
+        fix: impl Into<String>,
+    ) -> Self {
+        Self::new(name, Severity::Error, message, Some(fix.into()))
+    }
+
+    fn new(
+        name: impl Into<String>,
+        severity: Severity,
+        message: impl Into<String>,
+        fix: Option<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            severity,
+            message: message.into(),
+            fix,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    checks: Vec<Check>,
+}
+
+impl Report {
+    pub fn push(&mut self, check: Check) {
+        self.checks.push(check);
+    }
+
+    /// Number of problems that prevent release-plz from working.
+    pub fn errors(&self) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.severity == Severity::Error)
+            .count()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            let icon = match check.severity {
+                Severity::Ok => "✅",
+                Severity::Skipped => "➖",
+                Severity::Warning => "⚠️",
+                Severity::Error => "❌",
+            };
+            writeln!(f, "{icon} {}: {}", check.name, check.message)?;
+            if let Some(fix) = &check.fix {
+                writeln!(f, "   💡 {fix}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn git_branch(repo_path: &Utf8Path) -> Check {
+    const NAME: &str = "git branch";
+    if git_cmd::is_detached_head(repo_path) {
+        Check::error(
+            NAME,
+            "HEAD is detached, so release-plz can't determine the branch to release from",
+            "Check out the branch, e.g. `git checkout main`. \
+            In GitLab CI, run `git checkout \"$CI_COMMIT_BRANCH\"` before release-plz.",
+        )
+    } else {
+        match git_in_dir(repo_path, &["branch", "--show-current"]) {
+            Ok(branch) => Check::ok(NAME, format!("on branch `{branch}`")),
+            Err(e) => Check::error(
+                NAME,
+                format!("{e:#}"),
+                "Check that git works in this directory.",
+            ),
+        }
+    }
+}
+
+pub fn git_history(repo_path: &Utf8Path) -> Check {
+    const NAME: &str = "git history";
+    match git_cmd::is_shallow(repo_path) {
+        Ok(false) => Check::ok(NAME, "the repository contains the whole history"),
+        Ok(true) => Check::warning(
+            NAME,
+            "the repository is a shallow clone, so release-plz fetches the missing history \
+            when it needs it, up to `max_analyze_commits` commits",
+            "To avoid the fetches, fetch the whole history with `git fetch --unshallow`. \
+            In GitHub Actions, set `fetch-depth: 0` in the `actions/checkout` step. \
+            In GitLab CI, set the `GIT_DEPTH` variable to `0`.",
+        ),
+        Err(e) => Check::error(
+            NAME,
+            format!("{e:#}"),
+            "Check that git works in this directory.",
+        ),
+    }
+}
+
+pub fn working_tree(repo_path: &Utf8Path, allow_dirty: bool) -> Check {
+    const NAME: &str = "working tree";
+    let is_clean = match Repo::new(repo_path) {
+        Ok(repo) => repo.is_clean(),
+        // E.g. the HEAD is detached.
+        Err(_) => git_in_dir(repo_path, &["status", "--porcelain"]).and_then(|changes| {
+            anyhow::ensure!(
+                changes.is_empty(),
+                "the working directory has uncommitted changes:\n{changes}"
+            );
+            Ok(())
+        }),
+    };
+    match is_clean {
+        Ok(()) => Check::ok(NAME, "no uncommitted changes"),
+        Err(_) if allow_dirty => Check::ok(
+            NAME,
+            "there are uncommitted changes, allowed by `allow_dirty`",
+        ),
+        Err(e) => Check::error(
+            NAME,
+            format!("{e:#}"),
+            "Commit or stash your changes, or use `--allow-dirty`.",
+        ),
+    }
+}
+
+/// Signing tags needs a key that git can use.
+pub fn tag_signing(repo_path: &Utf8Path) -> Check {
+    const NAME: &str = "tag signing";
+    let git_config = |key: &str| git_in_dir(repo_path, &["config", "--get", key]).ok();
+    let is_enabled = git_in_dir(
+        repo_path,
+        &["config", "--type=bool", "--get", "tag.gpgSign"],
+    )
+    .is_ok_and(|value| value == "true");
+    if !is_enabled {
+        return Check::ok(NAME, "`tag.gpgSign` isn't enabled");
+    }
+    let signing_key = git_config("user.signingkey");
+    let format = git_config("gpg.format").unwrap_or_else(|| "openpgp".to_string());
+    let is_key_available = match format.as_str() {
+        "ssh" => signing_key.as_deref().is_some_and(is_ssh_key_available),
+        "openpgp" => {
+            let program = git_config("gpg.program").unwrap_or_else(|| "gpg".to_string());
+            // Without a signing key, gpg uses the key of the committer email.
+            let key = signing_key.or_else(|| git_config("user.email"));
+            key.is_some_and(|key| is_gpg_key_available(&program, &key))
+        }
+        _ => return Check::skipped(NAME, format!("`gpg.format = {format}` isn't checked")),
+    };
+    if is_key_available {
+        Check::ok(
+            NAME,
+            format!("`tag.gpgSign` is enabled and the {format} key is available"),
+        )
+    } else {
+        Check::error(
+            NAME,
+            format!("`tag.gpgSign` is enabled, but git can't find the {format} signing key"),
+            "Import the key and set it with `git config user.signingkey <key>`, \
+            or disable tag signing with `git config tag.gpgSign false`.",
+        )
+    }
+}
+
+/// The ssh signing key is either a public key or the path of a key file.
+fn is_ssh_key_available(signing_key: &str) -> bool {
+    if signing_key.starts_with("key::") || signing_key.starts_with("ssh-") {
+        return true;
+    }
+    match signing_key.strip_prefix("~/") {
+        Some(path) => dirs::home_dir().is_some_and(|home| home.join(path).exists()),
+        None => Path::new(signing_key).exists(),
+    }
+}
+
+fn is_gpg_key_available(program: &str, key: &str) -> bool {
+    Command::new(program)
+        .args(["--batch", "--list-secret-keys", key])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+pub fn semver_checks(is_semver_check_enabled: bool) -> Check {
+    const NAME: &str = "cargo-semver-checks";
+    if !is_semver_check_enabled {
+        Check::skipped(NAME, "`semver_check` is disabled")
+    } else if release_plz_core::semver_check::is_cargo_semver_checks_installed() {
+        Check::ok(NAME, "installed")
+    } else {
+        Check::warning(
+            NAME,
+            "not installed, so release-plz skips the semver check of the packages",
+            "Install it with `cargo install cargo-semver-checks --locked`, \
+            or set `semver_check = false`.",
+        )
+    }
+}
+
+/// The registry must be in the cargo configuration, so that release-plz can
+/// find its index.
+pub fn registry(manifest_path: &Utf8Path, registry: &str) -> Check {
+    let name = format!("registry `{registry}`");
+    match cargo_utils::registry_url(manifest_path.as_std_path(), Some(registry)) {
+        Ok(url) => Check::ok(name, format!("index at {url}")),
+        Err(e) => Check::error(
+            name,
+            format!("{e:#}"),
+            format!(
+                "Add the registry to `.cargo/config.toml`:\n   \
+                [registries.{registry}]\n   \
+                index = \"sparse+https://<registry index url>/\""
+            ),
+        ),
+    }
+}
+
+pub async fn forge_token(client: anyhow::Result<Option<GitClient>>) -> Check {
+    const NAME: &str = "forge token";
+    let client = match client {
+        Ok(Some(client)) => client,
+        Ok(None) => {
+            return Check::skipped(
+                NAME,
+                "pass `--git-token` to check the permissions of the token",
+            );
+        }
+        Err(e) => {
+            return Check::error(
+                NAME,
+                format!("{e:#}"),
+                "Check the `--repo-url` and `--forge` arguments.",
+            );
+        }
+    };
+    match release_plz_core::check_token(&client).await {
+        Ok(check) if check.problems.is_empty() => {
+            if check.pr_permission_verified {
+                Check::ok(NAME, "the token can access the repository")
+            } else {
+                Check::ok(
+                    NAME,
+                    "the token can access the repository and read its pull requests, \
+                    but release-plz can't verify that it can open them",
+                )
+            }
+        }
+        Ok(check) => Check::error(
+            NAME,
+            check.problems.join("; "),
+            "Grant the missing permissions to the token. \
+            Learn more at https://release-plz.dev/docs/github/token",
+        ),
+        // The forge might be unreachable from this machine only.
+        Err(e) => Check::warning(
+            NAME,
+            format!("can't check the token: {e:#}"),
+            "Check that the forge is reachable and that `--forge` is correct.",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_is_displayed() {
+        let mut report = Report::default();
+        report.push(Check::ok("git branch", "on branch `main`"));
+        report.push(Check::skipped("forge token", "no token"));
+        report.push(Check::warning(
+            "cargo-semver-checks",
+            "not installed",
+            "Install it.",
+        ));
+        report.push(Check::error("git history", "shallow clone", "Fetch it."));
+        expect_test::expect![[r#"
+            ✅ git branch: on branch `main`
+            ➖ forge token: no token
+            ⚠️ cargo-semver-checks: not installed
+               💡 Install it.
+            ❌ git history: shallow clone
+               💡 Fetch it.
+        "#]]
+        .assert_eq(&report.to_string());
+        assert_eq!(report.errors(), 1);
+    }
+
+    #[test]
+    fn ssh_public_key_is_available() {
+        assert!(is_ssh_key_available("key::ssh-ed25519 AAAA"));
+        assert!(is_ssh_key_available("ssh-ed25519 AAAA"));
+        assert!(!is_ssh_key_available("/nonexistent/id_ed25519.pub"));
+    }
+}
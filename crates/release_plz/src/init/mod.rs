@@ -1,18 +1,108 @@
 mod gh;
+mod starter_config;
 
 use std::io::Write;
 
 use anyhow::Context;
-use cargo_metadata::camino::{Utf8Path, Utf8PathBuf};
-use release_plz_core::{Project, ReleaseMetadata, ReleaseMetadataBuilder};
-use std::collections::HashSet;
+use cargo_metadata::{
+    Metadata, Package,
+    camino::{Utf8Path, Utf8PathBuf},
+};
+use git_cmd::Repo;
+use release_plz_core::{
+    PackageDownloader, Project, ReleaseMetadata, ReleaseMetadataBuilder, RepoUrl,
+    fs_utils::Utf8TempDir,
+};
+use std::collections::{BTreeMap, HashSet};
+use tracing::{info, warn};
+
+use crate::args::config_path::ConfigPath;
+use starter_config::{CONFIG_FILE, StarterConfig};
 
 const CARGO_REGISTRY_TOKEN: &str = "CARGO_REGISTRY_TOKEN";
 const GITHUB_TOKEN: &str = "GITHUB_TOKEN";
-const CUSTOM_GITHUB_TOKEN: &str = "RELEASE_PLZ_TOKEN";
+const RELEASE_PLZ_TOKEN: &str = "RELEASE_PLZ_TOKEN";
+const GITLAB_BOT_EMAIL: &str = "RELEASE_PLZ_BOT_EMAIL";
+
+/// Git forge hosting the repository. It determines the CI files to generate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Forge {
+    Github,
+    Gitlab,
+    Gitea,
+    Forgejo,
+}
+
+impl Forge {
+    /// Value of the `--forge` argument of release-plz.
+    fn cli_name(self) -> &'static str {
+        match self {
+            Self::Github => "github",
+            Self::Gitlab => "gitlab",
+            Self::Gitea => "gitea",
+            Self::Forgejo => "forgejo",
+        }
+    }
+
+    fn ci_name(self) -> &'static str {
+        match self {
+            Self::Github => "GitHub action",
+            Self::Gitlab => "GitLab CI",
+            Self::Gitea => "Gitea Actions workflow",
+            Self::Forgejo => "Forgejo Actions workflow",
+        }
+    }
+
+    fn secret_kind(self) -> &'static str {
+        match self {
+            Self::Gitlab => "masked CI/CD variable",
+            Self::Github | Self::Gitea | Self::Forgejo => "repository secret",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct InitOptions {
+    pub forge: Forge,
+    /// Check that the manifests contain the fields required by crates.io.
+    pub toml_check: bool,
+    /// Answer yes to every question and skip the steps that need user input.
+    pub yes: bool,
+    /// If `None`, ask the user.
+    pub trusted_publishing: Option<bool>,
+    /// Name of the secret containing the token used to talk to the forge.
+    /// If `None`, ask the user.
+    pub token_secret_name: Option<String>,
+    /// Branch that triggers the workflow.
+    /// If `None`, it's the default branch of the repository.
+    pub branch: Option<String>,
+    /// Print the files instead of writing them, and don't store secrets.
+    pub dry_run: bool,
+}
+
+impl InitOptions {
+    /// Secrets are stored with `gh`, which reads them from stdin.
+    fn stores_secrets(&self) -> bool {
+        self.forge == Forge::Github && !self.yes && !self.dry_run
+    }
+}
 
-pub fn init(manifest_path: &Utf8Path, toml_check: bool) -> anyhow::Result<()> {
-    ensure_gh_is_installed()?;
+/// File generated by `init`.
+struct InitFile {
+    /// What the file is, e.g. "GitHub action file".
+    description: String,
+    path: Utf8PathBuf,
+    content: String,
+}
+
+pub fn init(manifest_path: &Utf8Path, options: &InitOptions) -> anyhow::Result<()> {
+    if options.stores_secrets() {
+        ensure_gh_is_installed()?;
+    }
+    anyhow::ensure!(
+        options.forge == Forge::Github || options.trusted_publishing != Some(true),
+        "trusted publishing is only supported on GitHub"
+    );
 
     // Create a Project instance to check mandatory fields
     let metadata = cargo_utils::get_manifest_metadata(manifest_path)?;
@@ -24,138 +114,448 @@ pub fn init(manifest_path: &Utf8Path, toml_check: bool) -> anyhow::Result<()> {
         &NoopReleaseMetadataBuilder,
     )?;
 
-    if toml_check {
+    if options.toml_check {
         project.check_mandatory_fields()?;
     }
 
     // get the repo url early to verify that the github repository is configured correctly
-    let repo_url = gh::repo_url()?;
+    let github_repo = if options.forge == Forge::Github {
+        Some(github_repo(&metadata.workspace_root)?)
+    } else {
+        None
+    };
 
-    greet();
-    let trusted_publishing = should_use_trusted_publishing()?;
+    let mut setup = Setup::new(options);
+    if !options.yes {
+        greet(options.forge);
+    }
+    let trusted_publishing = setup.trusted_publishing()?;
     if trusted_publishing {
-        print_settings_urls(&project)?;
+        setup.enable_trusted_publishing(&project)?;
     } else {
-        store_cargo_token()?;
+        setup.store_secret(
+            CARGO_REGISTRY_TOKEN,
+            "your cargo registry token",
+            "You can create a crates.io token on https://crates.io/settings/tokens/new, specifying the following scopes: \"publish-new\" and \"publish-update\".",
+        )?;
     }
 
-    let persist_credentials = should_use_persist_credentials()?;
+    let file = match options.forge {
+        Forge::Github => {
+            let repo = github_repo
+                .as_ref()
+                .expect("the GitHub repository is retrieved above");
+            let persist_credentials = setup.persist_credentials()?;
+            setup.manual_step(&format!(
+                "Go to {} and enable the option \"Allow GitHub Actions to create and approve pull requests\".",
+                actions_settings_url(&repo.url)
+            ))?;
+            let github_token = setup.github_token()?;
+            let branch = default_branch(options, &metadata.workspace_root)?;
+            InitFile {
+                description: format!("{} file", options.forge.ci_name()),
+                path: actions_file(),
+                content: action_yaml(
+                    &branch,
+                    &github_token,
+                    &repo.owner,
+                    trusted_publishing,
+                    persist_credentials,
+                ),
+            }
+        }
+        Forge::Gitlab => {
+            let token = setup.forge_token()?;
+            setup.store_secret(
+                GITLAB_BOT_EMAIL,
+                "the email of the bot account of the token",
+                "It's `<username>@noreply.gitlab.com`, where `<username>` is the name of the bot account listed in Manage -> Members.",
+            )?;
+            InitFile {
+                description: format!("{} file", options.forge.ci_name()),
+                path: Utf8PathBuf::from(".gitlab-ci.yml"),
+                content: gitlab_ci_yaml(options.branch.as_deref(), &token),
+            }
+        }
+        Forge::Gitea | Forge::Forgejo => {
+            let token = setup.forge_token()?;
+            let branch = default_branch(options, &metadata.workspace_root)?;
+            InitFile {
+                description: format!("{} file", options.forge.ci_name()),
+                path: Utf8Path::new(&format!(".{}", options.forge.cli_name()))
+                    .join("workflows")
+                    .join("release-plz.yml"),
+                content: gitea_workflow_yaml(options.forge, &branch, &token),
+            }
+        }
+    };
+    let mut files = vec![file];
+    files.extend(starter_config_file(&metadata, &project, &mut setup)?);
+
+    if options.dry_run {
+        print_files(&files);
+        for step in &setup.todo {
+            info!("to do: {step}");
+        }
+    } else {
+        write_files(&files)?;
+        print_recap(&files, github_repo.as_ref(), &setup);
+    }
+    Ok(())
+}
 
-    enable_pr_permissions(&repo_url)?;
-    let github_token = store_github_token()?;
-    write_actions_yaml(github_token, trusted_publishing, persist_credentials)?;
+/// `release-plz.toml` tailored to the workspace,
+/// unless the project already has a configuration file.
+fn starter_config_file(
+    metadata: &Metadata,
+    project: &Project,
+    setup: &mut Setup,
+) -> anyhow::Result<Option<InitFile>> {
+    let config_path = ConfigPath::default().with_default_dir(&metadata.workspace_root);
+    if let Some(existing) = config_path.existing_config(metadata)? {
+        info!("release-plz is already configured in {existing}, so {CONFIG_FILE} isn't generated");
+        return Ok(None);
+    }
+    let tags = match Repo::new(&metadata.workspace_root) {
+        Ok(repo) => repo.get_all_tags(),
+        Err(e) => {
+            warn!("can't read the git tags of the repository: {e:#}");
+            vec![]
+        }
+    };
+    let mut config = StarterConfig::detect(metadata, &tags)?;
+    for package in setup.git_only_packages(project)? {
+        config.set_git_only(&package)?;
+    }
+    Ok(Some(InitFile {
+        description: "release-plz configuration".to_string(),
+        path: metadata.workspace_root.join(CONFIG_FILE),
+        content: config.to_toml()?,
+    }))
+}
 
-    let secrets_stored = !trusted_publishing || github_token != GITHUB_TOKEN;
-    print_recap(&repo_url, secrets_stored);
-    Ok(())
+/// Publishable packages that aren't in their cargo registry.
+fn unpublished_packages(packages: &[&Package]) -> anyhow::Result<Vec<String>> {
+    let mut packages_by_registry: BTreeMap<Option<&str>, Vec<&str>> = BTreeMap::new();
+    for package in packages {
+        // Packages are published to the first registry of the `publish` field.
+        let registry = package
+            .publish
+            .as_ref()
+            .and_then(|registries| registries.first())
+            .map(String::as_str);
+        packages_by_registry
+            .entry(registry)
+            .or_default()
+            .push(package.name.as_str());
+    }
+    let temp_dir = Utf8TempDir::new()?;
+    let mut published = HashSet::new();
+    for (registry, package_names) in packages_by_registry {
+        let mut downloader = PackageDownloader::new(package_names, temp_dir.path().as_str());
+        if let Some(registry) = registry {
+            downloader = downloader.with_registry(registry.to_string());
+        }
+        published.extend(
+            downloader
+                .download()?
+                .into_iter()
+                .map(|p| p.name.to_string()),
+        );
+    }
+    Ok(packages
+        .iter()
+        .map(|p| p.name.to_string())
+        .filter(|name| !published.contains(name))
+        .collect())
 }
 
-fn actions_file_parent() -> Utf8PathBuf {
-    Utf8Path::new(".github").join("workflows")
+/// GitHub repository, as seen by `gh` if installed, or by git otherwise.
+struct GithubRepo {
+    url: String,
+    owner: String,
 }
 
-fn actions_file() -> Utf8PathBuf {
-    actions_file_parent().join("release-plz.yml")
+fn github_repo(workspace_root: &Utf8Path) -> anyhow::Result<GithubRepo> {
+    if gh::is_gh_installed() {
+        Ok(GithubRepo {
+            url: gh::repo_url()?,
+            owner: gh::repo_owner()?,
+        })
+    } else {
+        let repo_url = RepoUrl::from_repo(&Repo::new(workspace_root)?)?;
+        Ok(GithubRepo {
+            url: repo_url.full_host(),
+            owner: repo_url.owner,
+        })
+    }
 }
 
-fn should_use_trusted_publishing() -> anyhow::Result<bool> {
-    ask_confirmation(
-        "👉 Do you want to use trusted publishing? (Recommended). Learn more at https://crates.io/docs/trusted-publishing.",
-    )
+fn default_branch(options: &InitOptions, workspace_root: &Utf8Path) -> anyhow::Result<String> {
+    if let Some(branch) = &options.branch {
+        return Ok(branch.clone());
+    }
+    if options.forge == Forge::Github && gh::is_gh_installed() {
+        return gh::default_branch();
+    }
+    git_default_branch(workspace_root)
 }
 
-fn should_use_persist_credentials() -> anyhow::Result<bool> {
-    ask_confirmation(
-        "👉 Do you want to set `persist-credentials: false` in CI? If yes, you can't use tag signing. (Recommended). Learn more at https://release-plz.dev/docs/github/persist-credentials.",
-    )
+/// Branch pointed by the `HEAD` of the remote, or the current branch if it's unknown.
+fn git_default_branch(workspace_root: &Utf8Path) -> anyhow::Result<String> {
+    let repo = Repo::new(workspace_root)?;
+    let remote = repo.original_remote();
+    let remote_head = repo.git(&[
+        "symbolic-ref",
+        "--short",
+        &format!("refs/remotes/{remote}/HEAD"),
+    ]);
+    let branch = remote_head
+        .ok()
+        .and_then(|head| head.strip_prefix(&format!("{remote}/")).map(str::to_string))
+        .unwrap_or_else(|| repo.original_branch().to_string());
+    Ok(branch)
 }
 
-fn print_settings_urls(project: &Project) -> anyhow::Result<()> {
-    println!(
-        "Enable trusted publishing for your crates. Note:
-- The default workflow name is `release-plz.yml`.
-- If you use an environment, edit the final workflow file.
+/// Questions and instructions for the user.
+/// In non-interactive mode, the steps that need the user are collected
+/// and printed at the end.
+struct Setup<'a> {
+    options: &'a InitOptions,
+    /// Steps the user needs to complete after `init`.
+    todo: Vec<String>,
+    stored_secrets: bool,
+}
 
-Settings URLs:"
-    );
+impl<'a> Setup<'a> {
+    fn new(options: &'a InitOptions) -> Self {
+        Self {
+            options,
+            todo: vec![],
+            stored_secrets: false,
+        }
+    }
 
-    let publishable_packages = &project.publishable_packages();
-    let settings_urls = publishable_packages.iter().map(|package| {
-        let package_name = &package.name;
-        format!("https://crates.io/crates/{package_name}/settings/new-trusted-publisher")
-    });
+    fn confirm(&self, question: &str) -> anyhow::Result<bool> {
+        if self.options.yes {
+            Ok(true)
+        } else {
+            ask_confirmation(question)
+        }
+    }
 
-    for url in settings_urls {
-        println!("* {url}");
+    fn manual_step(&mut self, step: &str) -> anyhow::Result<()> {
+        if self.options.yes {
+            self.todo.push(step.to_string());
+        } else {
+            println!("\n👉 {step} Type Enter when done.");
+            read_stdin()?;
+        }
+        Ok(())
     }
-    println!("\nType Enter when done.");
 
-    read_stdin()?;
+    fn store_secret(&mut self, name: &str, description: &str, hint: &str) -> anyhow::Result<()> {
+        if self.options.stores_secrets() {
+            println!(
+                "👉 Paste {description} to store it in the GitHub actions repository secrets.
+💡 {hint}"
+            );
+            gh::store_secret(name)?;
+            self.stored_secrets = true;
+        } else {
+            self.todo.push(format!(
+                "Store {description} in the `{name}` {}. {hint}",
+                self.options.forge.secret_kind()
+            ));
+        }
+        Ok(())
+    }
 
-    Ok(())
-}
+    /// Packages that the user wants to release with git tags only, among the ones that
+    /// were never published.
+    /// In non-interactive mode, packages are published as usual.
+    fn git_only_packages(&self, project: &Project) -> anyhow::Result<Vec<String>> {
+        if self.options.yes {
+            return Ok(vec![]);
+        }
+        let unpublished = match unpublished_packages(&project.publishable_packages()) {
+            Ok(unpublished) => unpublished,
+            Err(e) => {
+                warn!("can't check if the packages are published: {e:#}");
+                return Ok(vec![]);
+            }
+        };
+        if unpublished.is_empty() {
+            return Ok(vec![]);
+        }
+        let publish = ask_confirmation(&format!(
+            "👉 These packages were never published: {}. Do you want release-plz to publish them? If not, release-plz releases them with git tags only (`git_only`).",
+            unpublished.join(", ")
+        ))?;
+        Ok(if publish { vec![] } else { unpublished })
+    }
 
-fn greet() {
-    println!(
-        "👋 This process will guide you in setting up release-plz in your GitHub repository, using `gh` (the GitHub CLI) to store the necessary tokens in your repository secrets."
-    );
-}
+    fn trusted_publishing(&self) -> anyhow::Result<bool> {
+        if self.options.forge != Forge::Github {
+            return Ok(false);
+        }
+        match self.options.trusted_publishing {
+            Some(trusted_publishing) => Ok(trusted_publishing),
+            None => self.confirm(
+                "👉 Do you want to use trusted publishing? (Recommended). Learn more at https://crates.io/docs/trusted-publishing.",
+            ),
+        }
+    }
 
-fn store_cargo_token() -> anyhow::Result<()> {
-    println!("👉 Paste your cargo registry token to store it in the GitHub actions repository secrets.
-💡 You can create a crates.io token on https://crates.io/settings/tokens/new, specifying the following scopes: \"publish-new\" and \"publish-update\".");
-    gh::store_secret(CARGO_REGISTRY_TOKEN)?;
-    Ok(())
-}
+    /// Whether the checkout step of the GitHub action keeps the git credentials.
+    /// It's not recommended, but it's needed to sign tags.
+    fn persist_credentials(&self) -> anyhow::Result<bool> {
+        let disable_persist_credentials = self.confirm(
+            "👉 Do you want to set `persist-credentials: false` in CI? If yes, you can't use tag signing. (Recommended). Learn more at https://release-plz.dev/docs/github/persist-credentials.",
+        )?;
+        Ok(!disable_persist_credentials)
+    }
 
-fn enable_pr_permissions(repo_url: &str) -> anyhow::Result<()> {
-    println!("
-👉 Go to {} and enable the option \"Allow GitHub Actions to create and approve pull requests\". Type Enter when done.", actions_settings_url(repo_url));
-    read_stdin()?;
-    Ok(())
-}
+    fn enable_trusted_publishing(&mut self, project: &Project) -> anyhow::Result<()> {
+        let settings_urls: String = project
+            .publishable_packages()
+            .iter()
+            .map(|package| {
+                let package_name = &package.name;
+                format!(
+                    "\n* https://crates.io/crates/{package_name}/settings/new-trusted-publisher"
+                )
+            })
+            .collect();
+        self.manual_step(&format!(
+            "Enable trusted publishing for your crates. Note:
+- The default workflow name is `release-plz.yml`.
+- If you use an environment, edit the final workflow file.
 
-fn store_github_token() -> anyhow::Result<&'static str> {
-    let should_create_token = ask_confirmation(
-        "👉 Do you want release-plz to use a GitHub Personal Access Token (PAT)? It's required to run CI on release PRs and to run workflows on tags.",
-    )?;
+Settings URLs:{settings_urls}
+"
+        ))
+    }
 
-    let github_token = if should_create_token {
-        println!("
-👉 Paste your GitHub PAT.
-💡 Create a GitHub PAT following these instructions:
+    fn github_token(&mut self) -> anyhow::Result<String> {
+        let github_token = match &self.options.token_secret_name {
+            Some(name) => name.clone(),
+            None => {
+                let should_create_token = self.confirm(
+                    "👉 Do you want release-plz to use a GitHub Personal Access Token (PAT)? It's required to run CI on release PRs and to run workflows on tags.",
+                )?;
+                if should_create_token {
+                    RELEASE_PLZ_TOKEN.to_string()
+                } else {
+                    GITHUB_TOKEN.to_string()
+                }
+            }
+        };
+        if github_token != GITHUB_TOKEN {
+            self.store_secret(
+                &github_token,
+                "your GitHub PAT",
+                "Create a GitHub PAT following these instructions:
 
    1. Go to https://github.com/settings/personal-access-tokens/new.
    2. Under \"Only selected repositories\", select the repositories where you want to use the PAT, to give release-plz write access.
    3. Under \"Repository permissions\", assign \"Contents\" and \"Pull requests\" read and write permissions.
 
-   If you have doubts, check the documentation: https://release-plz.dev/docs/github/token#use-a-personal-access-token.");
+   If you have doubts, check the documentation: https://release-plz.dev/docs/github/token#use-a-personal-access-token.",
+            )?;
+        }
+        Ok(github_token)
+    }
+
+    /// Token used by release-plz to open PRs and create releases on GitLab, Gitea and Forgejo.
+    fn forge_token(&mut self) -> anyhow::Result<String> {
+        let token = self
+            .options
+            .token_secret_name
+            .clone()
+            .unwrap_or_else(|| RELEASE_PLZ_TOKEN.to_string());
+        let hint = match self.options.forge {
+            Forge::Gitlab => {
+                "Create a project access token with the Maintainer role and the `api`, `read_api`, `read_repository`, and `write_repository` scopes. Learn more at https://release-plz.dev/docs/gitlab."
+            }
+            Forge::Github | Forge::Gitea | Forge::Forgejo => {
+                "Create an access token with read and write permissions on the repository and on issues (pull requests)."
+            }
+        };
+        self.store_secret(&token, "a release-plz access token", hint)?;
+        Ok(token)
+    }
+}
 
-        // GitHub custom token
-        let release_plz_token: &str = CUSTOM_GITHUB_TOKEN;
-        gh::store_secret(release_plz_token)?;
-        release_plz_token
+fn actions_file() -> Utf8PathBuf {
+    Utf8Path::new(".github")
+        .join("workflows")
+        .join("release-plz.yml")
+}
+
+fn greet(forge: Forge) {
+    if forge == Forge::Github {
+        println!(
+            "👋 This process will guide you in setting up release-plz in your GitHub repository, using `gh` (the GitHub CLI) to store the necessary tokens in your repository secrets."
+        );
     } else {
-        // default github token
-        GITHUB_TOKEN
-    };
-    Ok(github_token)
+        println!(
+            "👋 This process will guide you in setting up release-plz in your {} CI.",
+            forge.ci_name()
+        );
+    }
 }
 
-fn print_recap(repo_url: &str, secrets_stored: bool) {
-    println!(
-        "All done 🎉
-- GitHub action file written to {}",
-        actions_file()
-    );
+fn print_files(files: &[InitFile]) {
+    for (i, file) in files.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("==> {} <==", file.path);
+        print!("{}", file.content);
+    }
+}
+
+fn write_files(files: &[InitFile]) -> anyhow::Result<()> {
+    for file in files {
+        // `.gitlab-ci.yml` contains the whole CI of the project, so we don't overwrite it.
+        anyhow::ensure!(
+            file.path != ".gitlab-ci.yml" || !file.path.exists(),
+            "{} already exists. Run `release-plz init --dry-run` to print the release-plz jobs and add them to it",
+            file.path
+        );
+        if let Some(parent) = file.path.parent().filter(|p| !p.as_str().is_empty()) {
+            fs_err::create_dir_all(parent)
+                .with_context(|| format!("failed to create directory {parent}"))?;
+        }
+        fs_err::write(&file.path, &file.content)
+            .with_context(|| format!("error while writing {}", file.path))?;
+    }
+    Ok(())
+}
 
-    if secrets_stored {
+fn print_recap(files: &[InitFile], github_repo: Option<&GithubRepo>, setup: &Setup) {
+    println!("All done 🎉");
+    for file in files {
+        println!("- {} written to {}", file.description, file.path);
+    }
+
+    if let Some(repo) = github_repo
+        && setup.stored_secrets
+    {
         println!(
             "- GitHub action secrets stored. Review them at {}",
-            actions_secret_url(repo_url)
+            actions_secret_url(&repo.url)
         );
     }
 
+    if !setup.todo.is_empty() {
+        println!("Before the first run:");
+        for (i, step) in setup.todo.iter().enumerate() {
+            println!("{}. {step}", i + 1);
+        }
+    }
+
     println!("Enjoy automated releases 🤖");
 }
 
@@ -175,26 +575,6 @@ fn ask_confirmation(question: &str) -> anyhow::Result<bool> {
     Ok(input != "n")
 }
 
-fn write_actions_yaml(
-    github_token: &str,
-    trusted_publishing: bool,
-    persist_credentials: bool,
-) -> anyhow::Result<()> {
-    let branch = gh::default_branch()?;
-    let owner = gh::repo_owner()?;
-    let action_yaml = action_yaml(
-        &branch,
-        github_token,
-        &owner,
-        trusted_publishing,
-        persist_credentials,
-    );
-    fs_err::create_dir_all(actions_file_parent())
-        .context("failed to create GitHub actions workflows directory")?;
-    fs_err::write(actions_file(), action_yaml).context("error while writing GitHub action file")?;
-    Ok(())
-}
-
 fn action_yaml(
     branch: &str,
     github_token: &str,
@@ -307,6 +687,106 @@ jobs:
     )
 }
 
+/// Jobs of the GitLab CI pipeline.
+/// If `branch` is `None`, the jobs run on the default branch.
+fn gitlab_ci_yaml(branch: Option<&str>, token: &str) -> String {
+    let branch = branch.map_or_else(|| "$CI_DEFAULT_BRANCH".to_string(), |b| format!("\"{b}\""));
+    format!(
+        "# Learn more at https://release-plz.dev/docs/gitlab
+.release-plz:
+  stage: deploy
+  image: rust:latest
+  variables:
+    # Clone the entire repository, release-plz needs the git history
+    GIT_STRATEGY: clone
+    GIT_DEPTH: 0
+  rules:
+    - if: $CI_COMMIT_TAG
+      when: never
+    - if: $CI_COMMIT_BRANCH == {branch}
+  before_script:
+    # GitLab checks out a detached HEAD
+    - git checkout \"$CI_COMMIT_BRANCH\"
+    # release-plz creates commits, so git needs an identity
+    - git config --global user.email \"${GITLAB_BOT_EMAIL}\"
+    - git config --global user.name \"release-plz\"
+    - git remote set-url origin \"$CI_SERVER_PROTOCOL://release-plz:${token}@$CI_SERVER_HOST/$CI_PROJECT_PATH.git\"
+    - curl -L --proto '=https' --tlsv1.2 -sSf https://raw.githubusercontent.com/cargo-bins/cargo-binstall/main/install-from-binstall-release.sh | bash
+    - cargo binstall --no-confirm release-plz
+
+release-plz-release:
+  extends: .release-plz
+  script:
+    - release-plz release --forge gitlab --git-token \"${token}\"
+
+release-plz-pr:
+  extends: .release-plz
+  resource_group: release-plz-pr
+  script:
+    - release-plz release-pr --forge gitlab --git-token \"${token}\"
+"
+    )
+}
+
+/// Gitea and Forgejo Actions workflow.
+/// It doesn't use YAML anchors and the release-plz action because
+/// they aren't supported by every version of the runners.
+fn gitea_workflow_yaml(forge: Forge, branch: &str, token: &str) -> String {
+    let forge_name = forge.cli_name();
+    let runs_on = if forge == Forge::Forgejo {
+        "docker"
+    } else {
+        "ubuntu-latest"
+    };
+    let token_secret = format!("${{{{ secrets.{token} }}}}");
+    let steps = format!(
+        "      - name: Checkout repository
+        uses: https://github.com/actions/checkout@11bd71901bbe5b1630ceea73d27597364c9af683 # v4.2.2
+        with:
+          fetch-depth: 0
+          token: {token_secret}
+      - name: Install Rust toolchain
+        uses: https://github.com/dtolnay/rust-toolchain@stable
+      - name: Install release-plz
+        run: |
+          curl -L --proto '=https' --tlsv1.2 -sSf https://raw.githubusercontent.com/cargo-bins/cargo-binstall/main/install-from-binstall-release.sh | bash
+          cargo binstall --no-confirm release-plz"
+    );
+    format!(
+        "name: Release-plz
+
+on:
+  push:
+    branches:
+      - {branch}
+
+jobs:
+  release-plz-release:
+    name: Release-plz release
+    runs-on: {runs_on}
+    steps:
+{steps}
+      - name: Run release-plz
+        run: release-plz release --forge {forge_name} --git-token \"{token_secret}\"
+        env:
+          CARGO_REGISTRY_TOKEN: ${{{{ secrets.{CARGO_REGISTRY_TOKEN} }}}}
+
+  release-plz-pr:
+    name: Release-plz PR
+    runs-on: {runs_on}
+    steps:
+{steps}
+      - name: Run release-plz
+        run: |
+          git config --global user.name \"release-plz\"
+          git config --global user.email \"release-plz@noreply.localhost\"
+          release-plz release-pr --forge {forge_name} --git-token \"{token_secret}\"
+        env:
+          CARGO_REGISTRY_TOKEN: ${{{{ secrets.{CARGO_REGISTRY_TOKEN} }}}}
+"
+    )
+}
+
 fn ensure_gh_is_installed() -> anyhow::Result<()> {
     anyhow::ensure!(
         gh::is_gh_installed(),
@@ -343,6 +823,24 @@ impl ReleaseMetadataBuilder for NoopReleaseMetadataBuilder {
 mod tests {
     use super::*;
 
+    #[test]
+    fn credentials_are_not_persisted_in_non_interactive_mode() {
+        let options = InitOptions {
+            forge: Forge::Github,
+            toml_check: true,
+            yes: true,
+            trusted_publishing: None,
+            token_secret_name: None,
+            branch: None,
+            dry_run: true,
+        };
+        let persist_credentials = Setup::new(&options).persist_credentials().unwrap();
+        assert!(!persist_credentials);
+        let yaml = action_yaml("main", GITHUB_TOKEN, "owner", true, persist_credentials);
+        assert!(yaml.contains("persist-credentials: false"), "{yaml}");
+        assert!(!yaml.contains("persist-credentials: true"), "{yaml}");
+    }
+
     #[test]
     fn actions_yaml_string_is_correct() {
         expect_test::expect![[r"
@@ -460,12 +958,117 @@ mod tests {
         "]]
         .assert_eq(&action_yaml(
             "main",
-            CUSTOM_GITHUB_TOKEN,
+            RELEASE_PLZ_TOKEN,
             "owner",
             false,
             false,
         ));
     }
+
+    #[test]
+    fn gitlab_ci_yaml_string_is_correct() {
+        expect_test::expect![[r##"
+            # Learn more at https://release-plz.dev/docs/gitlab
+            .release-plz:
+              stage: deploy
+              image: rust:latest
+              variables:
+                # Clone the entire repository, release-plz needs the git history
+                GIT_STRATEGY: clone
+                GIT_DEPTH: 0
+              rules:
+                - if: $CI_COMMIT_TAG
+                  when: never
+                - if: $CI_COMMIT_BRANCH == $CI_DEFAULT_BRANCH
+              before_script:
+                # GitLab checks out a detached HEAD
+                - git checkout "$CI_COMMIT_BRANCH"
+                # release-plz creates commits, so git needs an identity
+                - git config --global user.email "$RELEASE_PLZ_BOT_EMAIL"
+                - git config --global user.name "release-plz"
+                - git remote set-url origin "$CI_SERVER_PROTOCOL://release-plz:$RELEASE_PLZ_TOKEN@$CI_SERVER_HOST/$CI_PROJECT_PATH.git"
+                - curl -L --proto '=https' --tlsv1.2 -sSf https://raw.githubusercontent.com/cargo-bins/cargo-binstall/main/install-from-binstall-release.sh | bash
+                - cargo binstall --no-confirm release-plz
+
+            release-plz-release:
+              extends: .release-plz
+              script:
+                - release-plz release --forge gitlab --git-token "$RELEASE_PLZ_TOKEN"
+
+            release-plz-pr:
+              extends: .release-plz
+              resource_group: release-plz-pr
+              script:
+                - release-plz release-pr --forge gitlab --git-token "$RELEASE_PLZ_TOKEN"
+        "##]].assert_eq(&gitlab_ci_yaml(None, RELEASE_PLZ_TOKEN));
+    }
+
+    #[test]
+    fn gitlab_ci_yaml_runs_on_custom_branch() {
+        let yaml = gitlab_ci_yaml(Some("release"), "GITLAB_TOKEN");
+        assert!(yaml.contains(r#"- if: $CI_COMMIT_BRANCH == "release""#));
+        assert!(yaml.contains(r#"--git-token "$GITLAB_TOKEN""#));
+    }
+
+    #[test]
+    fn forgejo_workflow_yaml_string_is_correct() {
+        expect_test::expect![[r#"
+            name: Release-plz
+
+            on:
+              push:
+                branches:
+                  - main
+
+            jobs:
+              release-plz-release:
+                name: Release-plz release
+                runs-on: docker
+                steps:
+                  - name: Checkout repository
+                    uses: https://github.com/actions/checkout@11bd71901bbe5b1630ceea73d27597364c9af683 # v4.2.2
+                    with:
+                      fetch-depth: 0
+                      token: ${{ secrets.RELEASE_PLZ_TOKEN }}
+                  - name: Install Rust toolchain
+                    uses: https://github.com/dtolnay/rust-toolchain@stable
+                  - name: Install release-plz
+                    run: |
+                      curl -L --proto '=https' --tlsv1.2 -sSf https://raw.githubusercontent.com/cargo-bins/cargo-binstall/main/install-from-binstall-release.sh | bash
+                      cargo binstall --no-confirm release-plz
+                  - name: Run release-plz
+                    run: release-plz release --forge forgejo --git-token "${{ secrets.RELEASE_PLZ_TOKEN }}"
+                    env:
+                      CARGO_REGISTRY_TOKEN: ${{ secrets.CARGO_REGISTRY_TOKEN }}
+
+              release-plz-pr:
+                name: Release-plz PR
+                runs-on: docker
+                steps:
+                  - name: Checkout repository
+                    uses: https://github.com/actions/checkout@11bd71901bbe5b1630ceea73d27597364c9af683 # v4.2.2
+                    with:
+                      fetch-depth: 0
+                      token: ${{ secrets.RELEASE_PLZ_TOKEN }}
+                  - name: Install Rust toolchain
+                    uses: https://github.com/dtolnay/rust-toolchain@stable
+                  - name: Install release-plz
+                    run: |
+                      curl -L --proto '=https' --tlsv1.2 -sSf https://raw.githubusercontent.com/cargo-bins/cargo-binstall/main/install-from-binstall-release.sh | bash
+                      cargo binstall --no-confirm release-plz
+                  - name: Run release-plz
+                    run: |
+                      git config --global user.name "release-plz"
+                      git config --global user.email "release-plz@noreply.localhost"
+                      release-plz release-pr --forge forgejo --git-token "${{ secrets.RELEASE_PLZ_TOKEN }}"
+                    env:
+                      CARGO_REGISTRY_TOKEN: ${{ secrets.CARGO_REGISTRY_TOKEN }}
+        "#]].assert_eq(&gitea_workflow_yaml(
+            Forge::Forgejo,
+            "main",
+            RELEASE_PLZ_TOKEN,
+        ));
+    }
 }
 
 #[test]
@@ -525,7 +1128,7 @@ fn actions_yaml_string_with_trusted_publishing_is_correct() {
         "]]
     .assert_eq(&action_yaml(
         "main",
-        CUSTOM_GITHUB_TOKEN,
+        RELEASE_PLZ_TOKEN,
         "owner",
         true,
         false,
@@ -591,7 +1194,7 @@ fn actions_yaml_string_with_persist_credentials_is_correct() {
         "]]
     .assert_eq(&action_yaml(
         "main",
-        CUSTOM_GITHUB_TOKEN,
+        RELEASE_PLZ_TOKEN,
         "owner",
         false,
         true,
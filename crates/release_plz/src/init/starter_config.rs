@@ -0,0 +1,296 @@
+//! Starter `release-plz.toml` tailored to the workspace.
+
+use std::collections::BTreeMap;
+
+use anyhow::Context as _;
+use cargo_metadata::{Metadata, Package, semver::Version};
+use toml::{Table, Value};
+
+use crate::config_sources::SourcedConfig;
+
+pub const CONFIG_FILE: &str = "release-plz.toml";
+const CLIFF_TOML: &str = "cliff.toml";
+/// Version group of the packages that inherit the version of the workspace.
+const WORKSPACE_VERSION_GROUP: &str = "workspace";
+
+/// Settings proposed for the workspace.
+/// The source of each setting is the reason why it's proposed.
+#[derive(Debug, Default)]
+pub struct StarterConfig {
+    config: SourcedConfig,
+}
+
+impl StarterConfig {
+    /// Propose settings based on the Cargo manifests, the files and the git tags
+    /// of the workspace.
+    pub fn detect(metadata: &Metadata, tags: &[String]) -> anyhow::Result<Self> {
+        let mut config = Self::default();
+        if metadata.workspace_root.join(CLIFF_TOML).exists() {
+            config.set_workspace(
+                "changelog_config",
+                Value::String(CLIFF_TOML.to_string()),
+                "`cliff.toml` found in the workspace root",
+            )?;
+        }
+
+        let packages = metadata.workspace_packages();
+        let package_names: Vec<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+        if let Some(tag_naming) = TagNaming::detect(tags, &package_names) {
+            config.set_workspace(
+                "git_tag_name",
+                Value::String(tag_naming.template),
+                &format!(
+                    "matches {} of {} git tags, e.g. `{}`",
+                    tag_naming.matches,
+                    tags.len(),
+                    tag_naming.example
+                ),
+            )?;
+        }
+
+        let mut workspace_version_packages = vec![];
+        for package in &packages {
+            if is_publish_disabled(package) {
+                config.set_package(
+                    &package.name,
+                    "release",
+                    Value::Boolean(false),
+                    "`publish = false` in Cargo.toml",
+                )?;
+            }
+            let manifest = fs_err::read_to_string(&package.manifest_path)?;
+            let inherits_version = inherits_workspace_version(&manifest)
+                .with_context(|| format!("invalid manifest {}", package.manifest_path))?;
+            if inherits_version {
+                workspace_version_packages.push(package.name.as_str());
+            }
+        }
+        // A version group with a single package has no effect.
+        if workspace_version_packages.len() > 1 {
+            for package in workspace_version_packages {
+                config.set_package(
+                    package,
+                    "version_group",
+                    Value::String(WORKSPACE_VERSION_GROUP.to_string()),
+                    "`version.workspace = true` in Cargo.toml",
+                )?;
+            }
+        }
+        Ok(config)
+    }
+
+    /// Release the package with git tags only, without publishing it.
+    pub fn set_git_only(&mut self, package: &str) -> anyhow::Result<()> {
+        self.set_package(
+            package,
+            "git_only",
+            Value::Boolean(true),
+            "never published to a cargo registry",
+        )
+    }
+
+    fn set_workspace(&mut self, key: &str, value: Value, reason: &str) -> anyhow::Result<()> {
+        let mut settings = Table::new();
+        settings.insert(key.to_string(), value);
+        let mut table = Table::new();
+        table.insert("workspace".to_string(), Value::Table(settings));
+        self.config.override_with(SourcedConfig::new(table, reason))
+    }
+
+    fn set_package(
+        &mut self,
+        package: &str,
+        key: &str,
+        value: Value,
+        reason: &str,
+    ) -> anyhow::Result<()> {
+        let mut settings = Table::new();
+        settings.insert("name".to_string(), Value::String(package.to_string()));
+        settings.insert(key.to_string(), value);
+        let mut table = Table::new();
+        table.insert(
+            "package".to_string(),
+            Value::Array(vec![Value::Table(settings)]),
+        );
+        self.config.override_with(SourcedConfig::new(table, reason))
+    }
+
+    pub fn to_toml(&self) -> anyhow::Result<String> {
+        // Catch invalid settings before writing them.
+        self.config.to_config()?;
+        let mut toml = "# release-plz configuration generated by `release-plz init`.\n\
+             # Each setting is followed by the reason why it's proposed.\n\
+             # Docs: https://release-plz.dev/docs/config\n"
+            .to_string();
+        let config = self.config.to_annotated_toml()?;
+        if !config.is_empty() {
+            toml.push('\n');
+            toml.push_str(&config);
+        }
+        Ok(toml)
+    }
+}
+
+fn is_publish_disabled(package: &Package) -> bool {
+    package.publish.as_ref().is_some_and(Vec::is_empty)
+}
+
+/// Whether the manifest contains `version.workspace = true`.
+fn inherits_workspace_version(manifest: &str) -> anyhow::Result<bool> {
+    let manifest: Table = toml::from_str(manifest)?;
+    let workspace_version = manifest
+        .get("package")
+        .and_then(|package| package.get("version"))
+        .and_then(|version| version.get("workspace"))
+        .and_then(Value::as_bool);
+    Ok(workspace_version == Some(true))
+}
+
+/// `git_tag_name` template of the existing git tags.
+#[derive(Debug, PartialEq, Eq)]
+struct TagNaming {
+    template: String,
+    /// Number of tags matching the template.
+    matches: usize,
+    example: String,
+}
+
+impl TagNaming {
+    /// The template matching the most tags.
+    fn detect(tags: &[String], package_names: &[&str]) -> Option<Self> {
+        let mut templates: BTreeMap<String, (usize, &str)> = BTreeMap::new();
+        for tag in tags {
+            if let Some(template) = tag_template(tag, package_names) {
+                let (matches, _) = templates.entry(template).or_insert((0, tag));
+                *matches += 1;
+            }
+        }
+        templates
+            .into_iter()
+            .max_by_key(|(_, (matches, _))| *matches)
+            .map(|(template, (matches, example))| Self {
+                template,
+                matches,
+                example: example.to_string(),
+            })
+    }
+}
+
+/// Template of a tag ending with a semver version, like `my-crate-v1.2.3`.
+/// Returns `None` if the tag doesn't contain a version.
+fn tag_template(tag: &str, package_names: &[&str]) -> Option<String> {
+    let (prefix, v) = tag.char_indices().find_map(|(i, _)| {
+        let rest = &tag[i..];
+        let (v, version) = match rest.strip_prefix('v') {
+            Some(version) => ("v", version),
+            None => ("", rest),
+        };
+        Version::parse(version).is_ok().then_some((&tag[..i], v))
+    })?;
+    // Prefer the longest name, e.g. `foo-bar` over `foo`.
+    let package = package_names
+        .iter()
+        .filter(|name| prefix.starts_with(**name))
+        .max_by_key(|name| name.len());
+    let prefix = match package {
+        Some(name) => format!("{{{{ package }}}}{}", &prefix[name.len()..]),
+        None => prefix.to_string(),
+    };
+    Some(format!("{prefix}{v}{{{{ version }}}}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|t| (*t).to_string()).collect()
+    }
+
+    #[test]
+    fn tag_templates_are_detected() {
+        let packages = ["foo", "foo-bar"];
+        let template = |tag| tag_template(tag, &packages);
+        assert_eq!(template("v1.2.3").unwrap(), "v{{ version }}");
+        assert_eq!(template("1.0.0-rc.1").unwrap(), "{{ version }}");
+        assert_eq!(
+            template("foo-bar-v0.1.0").unwrap(),
+            "{{ package }}-v{{ version }}"
+        );
+        assert_eq!(
+            template("foo@2.0.0").unwrap(),
+            "{{ package }}@{{ version }}"
+        );
+        assert_eq!(template("release-2.0.0").unwrap(), "release-{{ version }}");
+        assert_eq!(template("nightly"), None);
+        assert_eq!(template("v1.2"), None);
+    }
+
+    #[test]
+    fn most_common_tag_naming_wins() {
+        let tags = tags(&[
+            "v0.1.0",
+            "foo-v0.2.0",
+            "foo-v0.3.0",
+            "bar-v0.1.0",
+            "nightly",
+        ]);
+        assert_eq!(
+            TagNaming::detect(&tags, &["foo", "bar"]).unwrap(),
+            TagNaming {
+                template: "{{ package }}-v{{ version }}".to_string(),
+                matches: 3,
+                example: "foo-v0.2.0".to_string(),
+            }
+        );
+        assert_eq!(TagNaming::detect(&[], &["foo"]), None);
+    }
+
+    #[test]
+    fn workspace_version_is_detected() {
+        let inherits = |manifest| inherits_workspace_version(manifest).unwrap();
+        assert!(inherits(
+            "[package]\nname = \"foo\"\nversion.workspace = true"
+        ));
+        assert!(!inherits("[package]\nname = \"foo\"\nversion = \"0.1.0\""));
+        assert!(!inherits("[workspace]\nmembers = [\"foo\"]"));
+    }
+
+    #[test]
+    fn starter_config_is_annotated() {
+        let mut config = StarterConfig::default();
+        config
+            .set_workspace(
+                "git_tag_name",
+                Value::String("v{{ version }}".to_string()),
+                "matches 2 of 2 git tags, e.g. `v0.1.0`",
+            )
+            .unwrap();
+        config
+            .set_package(
+                "xtask",
+                "release",
+                Value::Boolean(false),
+                "`publish = false` in Cargo.toml",
+            )
+            .unwrap();
+        config.set_git_only("internal").unwrap();
+        expect_test::expect![[r##"
+            # release-plz configuration generated by `release-plz init`.
+            # Each setting is followed by the reason why it's proposed.
+            # Docs: https://release-plz.dev/docs/config
+
+            [workspace]
+            git_tag_name = "v{{ version }}" # matches 2 of 2 git tags, e.g. `v0.1.0`
+
+            [[package]]
+            name = "xtask"
+            release = false # `publish = false` in Cargo.toml
+
+            [[package]]
+            name = "internal"
+            git_only = true # never published to a cargo registry
+        "##]]
+        .assert_eq(&config.to_toml().unwrap());
+    }
+}
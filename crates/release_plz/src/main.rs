@@ -1,6 +1,10 @@
 mod args;
 mod changelog_config;
 mod config;
+mod config_import;
+mod config_sources;
+mod config_validation;
+mod doctor;
 mod generate_schema;
 pub mod init;
 mod log;
@@ -12,7 +16,10 @@ use release_plz_core::ReleaseRequest;
 use serde::Serialize;
 use tracing::error;
 
-use crate::args::{CliArgs, Command, manifest_command::ManifestCommand as _};
+use crate::args::{
+    CliArgs, Command, changelog::ChangelogCommand, config::ConfigCommand,
+    manifest_command::ManifestCommand as _,
+};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -30,25 +37,21 @@ async fn run(args: CliArgs) -> anyhow::Result<()> {
     match args.command {
         Command::Update(cmd_args) => {
             let cargo_metadata = cmd_args.cargo_metadata()?;
-            let config = cmd_args.config.load()?;
+            let config = cmd_args.config.load(&cargo_metadata)?;
             let update_request = cmd_args.update_request(&config, cargo_metadata)?;
             let (packages_update, _temp_repo) = release_plz_core::update(&update_request).await?;
             println!("{}", packages_update.summary());
         }
         Command::ReleasePr(cmd_args) => {
             anyhow::ensure!(
-                cmd_args.update.git_token.is_some(),
-                "please provide the git token with the --git-token cli argument."
+                cmd_args.update.git_token.is_some() || cmd_args.update.github_app.is_set(),
+                "please provide the git token with the --git-token cli argument or the GitHub App ID with the --github-app-id cli argument."
             );
             let cargo_metadata = cmd_args.update.cargo_metadata()?;
-            let config = cmd_args.update.config.load()?;
+            let config = cmd_args.update.config.load(&cargo_metadata)?;
             let request = cmd_args.release_pr_req(&config, cargo_metadata)?;
-            let release_pr = release_plz_core::release_pr(&request).await?;
+            let prs = release_plz_core::release_prs(&request).await?;
             if let Some(output_type) = cmd_args.output {
-                let prs = match release_pr {
-                    Some(pr) => vec![pr],
-                    None => vec![],
-                };
                 let prs_json = serde_json::json!({
                     "prs": prs
                 });
@@ -57,7 +60,7 @@ async fn run(args: CliArgs) -> anyhow::Result<()> {
         }
         Command::Release(cmd_args) => {
             let cargo_metadata = cmd_args.cargo_metadata()?;
-            let config = cmd_args.config.load()?;
+            let config = cmd_args.config.load(&cargo_metadata)?;
             let cmd_args_output = cmd_args.output;
             let request: ReleaseRequest = cmd_args.release_request(&config, cargo_metadata)?;
             let output = release_plz_core::release(&request)
@@ -70,12 +73,89 @@ async fn run(args: CliArgs) -> anyhow::Result<()> {
         Command::GenerateCompletions(cmd_args) => cmd_args.print(),
         Command::CheckUpdates => update_checker::check_update().await?,
         Command::GenerateSchema => generate_schema::generate_schema_to_disk()?,
-        Command::Init(cmd_args) => init::init(&cmd_args.manifest_path(), !cmd_args.no_toml_check)?,
+        Command::Init(cmd_args) => init::init(&cmd_args.manifest_path(), &cmd_args.init_options())?,
         Command::SetVersion(cmd_args) => {
-            let config = cmd_args.config.load()?;
-            let request = cmd_args.set_version_request(&config)?;
+            let cargo_metadata = cmd_args.cargo_metadata()?;
+            let config = cmd_args.config.load(&cargo_metadata)?;
+            let request = cmd_args.set_version_request(&config, cargo_metadata)?;
             release_plz_core::set_version::set_version(&request)?;
         }
+        Command::Changelog(cmd_args) => match cmd_args.command {
+            ChangelogCommand::Regenerate(cmd_args) => {
+                let cargo_metadata = cmd_args.update.cargo_metadata()?;
+                let config = cmd_args.update.config.load(&cargo_metadata)?;
+                let request = cmd_args.update.update_request(&config, cargo_metadata)?;
+                release_plz_core::regenerate_changelogs(&request).await?;
+            }
+            ChangelogCommand::Export(cmd_args) => {
+                let cargo_metadata = cmd_args.update.cargo_metadata()?;
+                let config = cmd_args.update.config.load(&cargo_metadata)?;
+                let request = cmd_args.update.update_request(&config, cargo_metadata)?;
+                let changelogs = release_plz_core::export_changelogs(&request)?;
+                let changelogs_json = serde_json::json!({
+                    "changelogs": changelogs
+                });
+                print_output(OutputType::Json, changelogs_json);
+            }
+            ChangelogCommand::Check(cmd_args) => {
+                let cargo_metadata = cmd_args.update.cargo_metadata()?;
+                let config = cmd_args.update.config.load(&cargo_metadata)?;
+                let request = cmd_args.update.update_request(&config, cargo_metadata)?;
+                let checks = release_plz_core::check_changelogs(&request)?;
+                let problems_count: usize = checks.iter().map(|c| c.problems.len()).sum();
+                match cmd_args.output {
+                    Some(output_type) => {
+                        let checks_json = serde_json::json!({
+                            "changelogs": checks
+                        });
+                        print_output(output_type, checks_json);
+                    }
+                    None => {
+                        for check in checks.iter().filter(|c| !c.problems.is_empty()) {
+                            println!("{} ({}):", check.package, check.path);
+                            for problem in &check.problems {
+                                println!("  - {problem}");
+                            }
+                        }
+                    }
+                }
+                anyhow::ensure!(
+                    problems_count == 0,
+                    "found {problems_count} problems in the changelogs"
+                );
+            }
+            ChangelogCommand::Preview(cmd_args) => {
+                let cargo_metadata = cmd_args.update.cargo_metadata()?;
+                let config = cmd_args.update.config.load(&cargo_metadata)?;
+                let request = cmd_args.update_request(&config, cargo_metadata)?;
+                let range = cmd_args.range()?;
+                let previews =
+                    release_plz_core::preview_changelogs(&request, range.as_ref()).await?;
+                match cmd_args.output {
+                    Some(output_type) => {
+                        let previews_json = serde_json::json!({
+                            "changelogs": previews
+                        });
+                        print_output(output_type, previews_json);
+                    }
+                    None => {
+                        let is_multi_package = previews.len() > 1;
+                        for preview in previews {
+                            if is_multi_package {
+                                println!("# {}\n", preview.package);
+                            }
+                            println!("{}\n", preview.entry);
+                        }
+                    }
+                }
+            }
+        },
+        Command::Config(cmd_args) => match cmd_args.command {
+            ConfigCommand::Show(cmd_args) => print!("{}", cmd_args.output()?),
+            ConfigCommand::Validate(cmd_args) => cmd_args.run()?,
+            ConfigCommand::Import(cmd_args) => print!("{}", cmd_args.output()?),
+        },
+        Command::Doctor(cmd_args) => cmd_args.run().await?,
     }
     Ok(())
 }
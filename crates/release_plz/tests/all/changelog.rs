@@ -427,3 +427,94 @@ and this project adheres to [Semantic Versioning](https://semver.org/spec/v2.0.0
         .trim()
     );
 }
+
+#[tokio::test]
+#[cfg_attr(not(feature = "docker-tests"), ignore)]
+async fn changelog_is_regenerated_from_git_tags() {
+    let context = TestContext::new().await;
+    let config = r#"
+    [changelog]
+    body = """
+
+    ## [{{ version }}]
+    {% for group, commits in commits | group_by(attribute="group") %}
+    ### {{ group | upper_first }}
+    {% for commit in commits %}
+    - {{ commit.message }}
+    {%- endfor %}
+    {% endfor -%}"""
+    "#;
+    context.write_release_plz_toml(config);
+    context.repo.tag("v0.1.0", "release v0.1.0").unwrap();
+
+    fs_err::write(context.repo_dir().join("src").join("lib.rs"), "// hi").unwrap();
+    context.push_all_changes("feat: add lib");
+    context.repo.tag("v0.2.0", "release v0.2.0").unwrap();
+
+    context.run_changelog_regenerate().success();
+
+    expect_test::expect![[r"
+        # Changelog
+
+        All notable changes to this project will be documented in this file.
+
+        The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.0.0/),
+        and this project adheres to [Semantic Versioning](https://semver.org/spec/v2.0.0.html).
+
+        ## [Unreleased]
+
+        ## [0.2.0]
+
+        ### Added
+
+        - add lib
+
+        ## [0.1.0]
+
+        ### Other
+
+        - add config file
+        - cargo init
+    "]]
+    .assert_eq(&context.read_changelog());
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "docker-tests"), ignore)]
+async fn changelog_preview_of_commit_range_is_printed() {
+    let context = TestContext::new().await;
+    context.repo.tag("v0.1.0", "release v0.1.0").unwrap();
+
+    fs_err::write(context.repo_dir().join("src").join("lib.rs"), "// hi").unwrap();
+    context.push_all_changes("feat: add lib");
+    let changelog = fs_err::read_to_string(context.repo_dir().join("CHANGELOG.md")).ok();
+
+    let template_dir = Utf8TempDir::new().unwrap();
+    let template = template_dir.path().join("template.tera");
+    fs_err::write(
+        &template,
+        r#"
+## [{{ version }}]
+{% for commit in commits %}
+- {{ commit.message }}
+{%- endfor %}"#,
+    )
+    .unwrap();
+    let outcome = context
+        .run_changelog_preview(&["--range", "v0.1.0..HEAD", "--template", template.as_str()])
+        .success();
+    let stdout = String::from_utf8(outcome.get_output().stdout.clone()).unwrap();
+
+    expect_test::expect![[r"
+        ## [0.1.1]
+
+        - add lib
+
+    "]]
+    .assert_eq(&stdout);
+    // The changelog is not edited.
+    assert_eq!(
+        fs_err::read_to_string(context.repo_dir().join("CHANGELOG.md")).ok(),
+        changelog
+    );
+}
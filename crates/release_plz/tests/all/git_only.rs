@@ -1027,3 +1027,43 @@ git_only = true
     assert_eq!(opened_prs.len(), 1);
     assert_eq!(opened_prs[0].title, "chore: release v0.1.1");
 }
+
+#[tokio::test]
+#[cfg_attr(not(feature = "docker-tests"), ignore)]
+async fn git_only_update_fetches_release_tag_in_shallow_clone() {
+    let context = TestContext::new().await;
+
+    let config = r#"
+[workspace]
+git_only = true
+"#;
+    context.write_release_plz_toml(config);
+
+    // The release tag isn't in the last commit, so the shallow clone doesn't contain it.
+    context.repo.tag("v0.1.0", "Release v0.1.0").unwrap();
+    context.repo.git(&["push", "--tags"]).unwrap();
+    for i in 0..3 {
+        let readme = context.repo_dir().join("README.md");
+        fs_err::write(&readme, format!("# Updated README {i}")).unwrap();
+        context.push_all_changes(&format!("fix: update readme {i}"));
+    }
+
+    let remote_url = context.repo.git(&["remote", "get-url", "origin"]).unwrap();
+    let clone_dir = Utf8TempDir::new().unwrap();
+    let clone_path = clone_dir.path().join("clone");
+    context
+        .repo
+        .git(&["clone", "--depth", "1", &remote_url, clone_path.as_str()])
+        .unwrap();
+    assert!(git_cmd::is_shallow(&clone_path).unwrap());
+
+    crate::helpers::cmd::release_plz_cmd(&context.cargo_target_dir())
+        .current_dir(&clone_path)
+        .arg("update")
+        .arg("--verbose")
+        .assert()
+        .success();
+
+    let manifest = fs_err::read_to_string(clone_path.join("Cargo.toml")).unwrap();
+    assert!(manifest.contains("version = \"0.1.1\""), "{manifest}");
+}
@@ -85,7 +85,10 @@ async fn create_repository(user_token: &str, repo_name: &str, client: &reqwest::
 async fn upload_registry_config(user_token: &str, username: &str, client: &reqwest::Client) {
     use base64::Engine as _;
     let content = {
-        let cargo_url = format!("http://localhost:3000/api/packages/{username}/cargo");
+        let cargo_url = format!(
+            "http://{}/api/packages/{username}/cargo",
+            super::gitea_address()
+        );
         format!("{{\"dl\":\"{cargo_url}/api/v1/crates\",\"api\":\"{cargo_url}\"}}")
     };
 
@@ -109,10 +112,11 @@ async fn upload_registry_config(user_token: &str, username: &str, client: &reqwe
 }
 
 fn run_create_user_command(user: &GiteaUser) {
+    let container = super::forge().container();
     Command::new("docker")
         .arg("exec")
-        .arg("gitea")
-        .arg("gitea")
+        .arg(container)
+        .arg(container)
         .arg("admin")
         .arg("user")
         .arg("create")
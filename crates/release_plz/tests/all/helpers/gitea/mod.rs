@@ -31,5 +31,39 @@ fn gitea_endpoint(endpoint: &str) -> String {
 }
 
 pub fn gitea_address() -> &'static str {
-    "localhost:3000"
+    match forge() {
+        TestForge::Gitea => "localhost:3000",
+        TestForge::Forgejo => "localhost:3001",
+    }
+}
+
+/// Server implementing the Gitea API used in the tests.
+/// Set the `RELEASE_PLZ_TEST_FORGE` environment variable to `forgejo`
+/// to run the tests against Forgejo instead of Gitea.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestForge {
+    Gitea,
+    Forgejo,
+}
+
+impl TestForge {
+    /// Value of the `--forge` cli argument.
+    pub fn cli_name(self) -> &'static str {
+        match self {
+            Self::Gitea => "gitea",
+            Self::Forgejo => "forgejo",
+        }
+    }
+
+    /// Name of the docker container, which is also the name of the server binary.
+    fn container(self) -> &'static str {
+        self.cli_name()
+    }
+}
+
+pub fn forge() -> TestForge {
+    match std::env::var("RELEASE_PLZ_TEST_FORGE").as_deref() {
+        Ok("forgejo") => TestForge::Forgejo,
+        _ => TestForge::Gitea,
+    }
 }
@@ -19,7 +19,7 @@ use tracing::info;
 
 use super::{
     TEST_REGISTRY, fake_utils,
-    gitea::{GiteaContext, gitea_address},
+    gitea::{self, GiteaContext, gitea_address},
     package::TestPackage,
 };
 
@@ -95,6 +95,10 @@ impl TestContext {
             body: "This is my pull request".to_string(),
             draft: false,
             labels: vec![],
+            reviewers: vec![],
+            team_reviewers: vec![],
+            assignees: vec![],
+            milestone: None,
         };
         self.git_client.open_pr(&pr).await.unwrap();
         // go back to main
@@ -194,6 +198,44 @@ impl TestContext {
             .assert()
     }
 
+    /// Run `release-plz update` in a clone of the repository created with `git clone --depth 1`.
+    pub fn run_update_in_shallow_clone(&self, clone_path: &Utf8Path, args: &[&str]) -> Assert {
+        let remote_url = self.repo.git(&["remote", "get-url", "origin"]).unwrap();
+        self.repo
+            .git(&["clone", "--depth", "1", &remote_url, clone_path.as_str()])
+            .unwrap();
+        assert!(git_cmd::is_shallow(clone_path).unwrap());
+        super::cmd::release_plz_cmd(&self.cargo_target_dir())
+            .current_dir(clone_path)
+            .env(RELEASE_PLZ_LOG, log_level())
+            .arg("update")
+            .arg("--verbose")
+            .arg("--registry")
+            .arg(TEST_REGISTRY)
+            .args(args)
+            .assert()
+    }
+
+    pub fn run_changelog_regenerate(&self) -> Assert {
+        super::cmd::release_plz_cmd(&self.cargo_target_dir())
+            .current_dir(self.repo_dir())
+            .env(RELEASE_PLZ_LOG, log_level())
+            .arg("changelog")
+            .arg("regenerate")
+            .arg("--verbose")
+            .assert()
+    }
+
+    pub fn run_changelog_preview(&self, args: &[&str]) -> Assert {
+        super::cmd::release_plz_cmd(&self.cargo_target_dir())
+            .current_dir(self.repo_dir())
+            .env(RELEASE_PLZ_LOG, log_level())
+            .arg("changelog")
+            .arg("preview")
+            .args(args)
+            .assert()
+    }
+
     pub fn run_release_pr(&self) -> Assert {
         super::cmd::release_plz_cmd(&self.cargo_target_dir())
             .current_dir(self.repo_dir())
@@ -203,7 +245,7 @@ impl TestContext {
             .arg("--git-token")
             .arg(&self.gitea.token)
             .arg("--forge")
-            .arg("gitea")
+            .arg(gitea::forge().cli_name())
             .arg("--registry")
             .arg(TEST_REGISTRY)
             .arg("--output")
@@ -223,7 +265,7 @@ impl TestContext {
             .arg("--git-token")
             .arg(&self.gitea.token)
             .arg("--forge")
-            .arg("gitea")
+            .arg(gitea::forge().cli_name())
             .arg("--registry")
             .arg(TEST_REGISTRY)
             .arg("--output")
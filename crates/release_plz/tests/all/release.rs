@@ -267,3 +267,27 @@ async fn release_plz_can_do_backports() {
     .to_string();
     outcome.stdout(format!("{expected_stdout}\n"));
 }
+
+#[tokio::test]
+#[cfg_attr(not(feature = "docker-tests"), ignore)]
+async fn release_gate_is_skipped_when_published_package_has_no_tag() {
+    let context = TestContext::new().await;
+
+    let config = r"
+    [workspace]
+    git_tag_enable = false
+    ";
+    context.write_release_plz_toml(config);
+    context.run_release().success();
+
+    // The package is already published, so there's nothing to release and
+    // the gate doesn't need a release PR, even if the git tag doesn't exist.
+    let config = r"
+    [workspace]
+    git_tag_enable = false
+    release_required_approvals = 1
+    ";
+    context.write_release_plz_toml(config);
+    let outcome = context.run_release().success();
+    outcome.stdout("{\"releases\":[]}\n");
+}
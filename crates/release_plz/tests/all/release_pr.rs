@@ -7,6 +7,7 @@ use crate::helpers::{
 use assert_cmd::Command;
 use cargo_metadata::semver::Version;
 use cargo_utils::{CARGO_TOML, LocalManifest, cargo_registries_token_env_var_name};
+use release_plz_core::{GitPr, fs_utils::Utf8TempDir};
 
 fn assert_cargo_semver_checks_is_installed() {
     assert!(
@@ -483,6 +484,83 @@ This PR was generated with [release-plz](https://github.com/release-plz/release-
     assert_eq!(opened_prs.len(), 0);
 }
 
+#[tokio::test]
+#[cfg_attr(not(feature = "docker-tests"), ignore)]
+async fn release_plz_opens_pr_per_package() {
+    let context = TestContext::new_workspace(&["one", "two"]).await;
+    let config = r#"
+    [workspace]
+    pr_mode = "per_package"
+    "#;
+    context.write_release_plz_toml(config);
+
+    context.run_release_pr().success();
+    let mut opened_prs = context.opened_release_prs().await;
+    opened_prs.sort_by(|a, b| a.branch().cmp(b.branch()));
+    assert_eq!(opened_prs.len(), 2);
+    for (pr, package, other_package) in [
+        (&opened_prs[0], "one", "two"),
+        (&opened_prs[1], "two", "one"),
+    ] {
+        assert!(pr.branch().starts_with(&format!("release-plz-{package}-")));
+        let body = pr.body.as_ref().unwrap();
+        assert!(body.contains(&format!("* `{package}`: 0.1.0")));
+        assert!(!body.contains(&format!("`{other_package}`")));
+    }
+
+    // Running release-plz again updates the PRs instead of opening new ones.
+    context.run_release_pr().success();
+    let mut updated_prs = context.opened_release_prs().await;
+    updated_prs.sort_by(|a, b| a.branch().cmp(b.branch()));
+    let numbers = |prs: &[GitPr]| prs.iter().map(|pr| pr.number).collect::<Vec<_>>();
+    assert_eq!(numbers(&updated_prs), numbers(&opened_prs));
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "docker-tests"), ignore)]
+async fn release_plz_opens_one_pr_per_package_for_dependents() {
+    let binary = "binary";
+    let library = "library";
+    let context = TestContext::new_workspace_with_packages(&[
+        TestPackage::new(binary)
+            .with_type(PackageType::Bin)
+            .with_path_dependencies(vec![format!("../{library}")]),
+        TestPackage::new(library).with_type(PackageType::Lib),
+    ])
+    .await;
+
+    context.run_release_pr().success();
+    context.merge_release_pr().await;
+    context.run_release().success();
+
+    let config = r#"
+    [workspace]
+    pr_mode = "per_package"
+    "#;
+    context.write_release_plz_toml(config);
+    let lib_file = context.package_path(library).join("src").join("aa.rs");
+    fs_err::write(&lib_file, "pub fn foo() {}").unwrap();
+    context.push_all_changes("edit library");
+
+    // The binary is released with the new version of the library, in the same PR.
+    context.run_release_pr().success();
+    let opened_prs = context.opened_release_prs().await;
+    assert_eq!(opened_prs.len(), 1);
+    let open_pr = &opened_prs[0];
+    assert!(open_pr.branch().starts_with("release-plz-binary-"));
+    let body = open_pr.body.as_ref().unwrap();
+    assert!(body.contains(&format!("* `{library}`: 0.1.0 -> 0.1.1")));
+    assert!(body.contains(&format!("* `{binary}`: 0.1.0 -> 0.1.1")));
+
+    context.merge_release_pr().await;
+    let binary_cargo_toml =
+        fs_err::read_to_string(context.package_path(binary).join(CARGO_TOML)).unwrap();
+    assert!(binary_cargo_toml.contains(r#"library = { version = "0.1.1""#));
+    let library_cargo_toml =
+        fs_err::read_to_string(context.package_path(library).join(CARGO_TOML)).unwrap();
+    assert!(library_cargo_toml.contains(r#"version = "0.1.1""#));
+}
+
 #[tokio::test]
 #[cfg_attr(not(feature = "docker-tests"), ignore)]
 async fn release_plz_should_set_custom_pr_details() {
@@ -883,6 +961,37 @@ async fn release_plz_adds_labels_to_release_pr() {
     );
 }
 
+#[tokio::test]
+#[cfg_attr(not(feature = "docker-tests"), ignore)]
+async fn release_plz_rebuilds_release_pr_on_top_of_main() {
+    let context = TestContext::new().await;
+
+    context.run_release_pr().success();
+    let opened_prs = context.opened_release_prs().await;
+    assert_eq!(opened_prs.len(), 1);
+    let pr_number = opened_prs[0].number;
+
+    fs_err::write(context.repo_dir().join("new.rs"), "// hello").unwrap();
+    context.push_all_changes("feat: new file");
+    context.run_release_pr().success();
+
+    let opened_prs = context.opened_release_prs().await;
+    assert_eq!(opened_prs.len(), 1);
+    let pr = &opened_prs[0];
+    assert_eq!(pr.number, pr_number, "the release PR should be updated");
+
+    // The release commit is the only commit of the PR branch and its parent is the tip of main.
+    context.repo.git(&["fetch", "origin", pr.branch()]).unwrap();
+    let main_tip = context.repo.git(&["rev-parse", "origin/main"]).unwrap();
+    let parent = context.repo.git(&["rev-parse", "FETCH_HEAD^"]).unwrap();
+    assert_eq!(parent, main_tip);
+    let commits_ahead = context
+        .repo
+        .git(&["rev-list", "--count", "origin/main..FETCH_HEAD"])
+        .unwrap();
+    assert_eq!(commits_ahead, "1");
+}
+
 #[tokio::test]
 #[cfg_attr(not(feature = "docker-tests"), ignore)]
 async fn release_plz_doesnt_add_invalid_labels_to_release_pr() {
@@ -1655,3 +1764,63 @@ and this project adheres to [Semantic Versioning](https://semver.org/spec/v2.0.0
         )
     );
 }
+
+/// Publish the package and push a few fixes, so that the commit of the published package
+/// isn't in a shallow clone of the repository.
+fn publish_and_push_fixes(context: &TestContext) {
+    context.run_cargo_publish(&context.gitea.repo);
+    for i in 0..3 {
+        let main = context.repo_dir().join("src").join("main.rs");
+        fs_err::write(&main, format!("fn main() {{ println!(\"{i}\"); }}")).unwrap();
+        context.push_all_changes(&format!("fix: print {i}"));
+    }
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "docker-tests"), ignore)]
+async fn release_plz_update_fetches_published_commit_in_shallow_clone() {
+    let context = TestContext::new().await;
+    publish_and_push_fixes(&context);
+
+    let clone_dir = Utf8TempDir::new().unwrap();
+    let clone_path = clone_dir.path().join("clone");
+    context
+        .run_update_in_shallow_clone(&clone_path, &[])
+        .success();
+
+    let manifest = fs_err::read_to_string(clone_path.join(CARGO_TOML)).unwrap();
+    assert!(manifest.contains("version = \"0.1.1\""), "{manifest}");
+    // The diff is computed on the fetched history: it contains all the commits after the
+    // published one, but not the published one.
+    let changelog = fs_err::read_to_string(clone_path.join("CHANGELOG.md")).unwrap();
+    for i in 0..3 {
+        assert!(changelog.contains(&format!("- print {i}")), "{changelog}");
+    }
+    assert!(!changelog.contains("cargo init"), "{changelog}");
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "docker-tests"), ignore)]
+async fn release_plz_update_fails_if_published_commit_is_older_than_max_analyze_commits() {
+    let context = TestContext::new().await;
+    publish_and_push_fixes(&context);
+
+    let clone_dir = Utf8TempDir::new().unwrap();
+    let clone_path = clone_dir.path().join("clone");
+    let outcome = context
+        .run_update_in_shallow_clone(&clone_path, &["--max-analyze-commits", "2"])
+        .failure();
+
+    let stderr = String::from_utf8_lossy(&outcome.get_output().stderr);
+    let package = &context.gitea.repo;
+    assert!(
+        stderr.contains(&format!(
+            "can't find the commit where package `{package}` was published"
+        )),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("increase `max_analyze_commits`"),
+        "{stderr}"
+    );
+}
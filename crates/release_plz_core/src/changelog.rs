@@ -9,6 +9,7 @@ use git_cliff_core::{
 };
 use regex::Regex;
 use serde::Serialize;
+use std::sync::LazyLock;
 use tracing::warn;
 
 use crate::changelog_parser;
@@ -35,6 +36,21 @@ pub struct Changelog<'a> {
     package: String,
     remote: Option<Remote>,
     pr_link: Option<String>,
+    unreleased_handling: UnreleasedHandling,
+}
+
+/// What to do with the entries of the `## [Unreleased]` section of an existing changelog
+/// when adding a new release.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnreleasedHandling {
+    /// Don't move the entries: the new release is added right below the `Unreleased` heading.
+    #[default]
+    Keep,
+    /// Move the entries into the new release, in the section with the same title
+    /// (e.g. `### Added`). Entries that are already in the new release are skipped.
+    Merge,
+    /// Remove the entries, because the new release replaces them.
+    Replace,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -77,7 +93,12 @@ impl Changelog<'_> {
         // If we successfully parsed an old header, compose manually to preserve exact formatting
         // and avoid potential header duplication.
         if let Some(header) = old_header {
-            return compose_changelog(&old_changelog, &changelog, &header);
+            return compose_changelog(
+                &old_changelog,
+                &changelog,
+                &header,
+                self.unreleased_handling,
+            );
         }
 
         // Fallback: let git-cliff handle the prepend.
@@ -88,6 +109,31 @@ impl Changelog<'_> {
         String::from_utf8(out).context("cannot convert bytes to string")
     }
 
+    /// Template context used by git-cliff to render the release.
+    ///
+    /// It contains the release and the variables added by release-plz,
+    /// such as `package` and `remote`.
+    pub fn context(&self) -> anyhow::Result<serde_json::Value> {
+        let config = self.changelog_config(None);
+        let changelog = self.get_changelog(&config)?;
+        let release = changelog
+            .releases
+            .first()
+            .context("changelog doesn't contain any release")?;
+        let mut context = serde_json::to_value(release).context("cannot serialize release")?;
+        if let Some(context) = context.as_object_mut() {
+            context.insert("package".to_string(), self.package.clone().into());
+            if let Some(release_link) = &self.release_link {
+                context.insert(RELEASE_LINK.to_string(), release_link.clone().into());
+            }
+            if let Some(remote) = &self.remote {
+                let remote = serde_json::to_value(remote).context("cannot serialize remote")?;
+                context.insert(REMOTE.to_string(), remote);
+            }
+        }
+        Ok(context)
+    }
+
     fn get_changelog<'a>(
         &'a self,
         config: &'a Config,
@@ -116,6 +162,7 @@ fn compose_changelog(
     old_changelog: &str,
     changelog: &GitCliffChangelog<'_>,
     header: &str,
+    unreleased_handling: UnreleasedHandling,
 ) -> Result<String, anyhow::Error> {
     let generated = {
         let mut new_out = Vec::new();
@@ -136,7 +183,140 @@ fn compose_changelog(
         .strip_prefix(&header_to_strip)
         .unwrap_or(generated.as_str());
     let old_body = old_changelog.strip_prefix(header).unwrap_or(old_changelog);
-    Ok(format!("{header}{generated_body}{old_body}"))
+    match unreleased_handling {
+        UnreleasedHandling::Keep => Ok(format!("{header}{generated_body}{old_body}")),
+        UnreleasedHandling::Merge => {
+            let (unreleased, old_releases) = split_unreleased(header, old_body);
+            let generated_body = merge_unreleased(generated_body, unreleased);
+            Ok(format!("{header}{generated_body}{old_releases}"))
+        }
+        UnreleasedHandling::Replace => {
+            let (_, old_releases) = split_unreleased(header, old_body);
+            Ok(format!("{header}{generated_body}{old_releases}"))
+        }
+    }
+}
+
+/// Split the changelog body in the entries of the `Unreleased` section and the releases.
+/// If the header doesn't end with an `Unreleased` heading, the `Unreleased` section is empty.
+fn split_unreleased<'a>(header: &str, body: &'a str) -> (&'a str, &'a str) {
+    static UNRELEASED_RE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"(?mi)^##\s*\[?unreleased\]?\s*$").unwrap());
+    if !UNRELEASED_RE.is_match(header) {
+        return ("", body);
+    }
+    // The entries of the `Unreleased` section are between the header and the first release.
+    if body.starts_with("## ") {
+        return ("", body);
+    }
+    match body.find("\n## ") {
+        Some(index) => body.split_at(index),
+        None => (body, ""),
+    }
+}
+
+/// Add the entries of the `Unreleased` section to the sections of the generated release.
+fn merge_unreleased(generated_release: &str, unreleased: &str) -> String {
+    let generated_entries: Vec<String> = changelog_parser::parse_sections(generated_release)
+        .iter()
+        .flat_map(|section| &section.entries)
+        .map(|entry| normalize_entry(&entry.text))
+        .collect();
+    let mut lines: Vec<String> = generated_release.lines().map(str::to_string).collect();
+    for section in changelog_parser::parse_sections(unreleased) {
+        let title = section.title.unwrap_or_else(|| "Other".to_string());
+        let entries: Vec<String> = section
+            .entries
+            .iter()
+            .filter(|entry| !generated_entries.contains(&normalize_entry(&entry.text)))
+            .map(|entry| format!("- {}", entry.text.replace('\n', "\n  ")))
+            .collect();
+        if entries.is_empty() {
+            continue;
+        }
+        let section_start = lines.iter().position(|line| {
+            line.strip_prefix("###")
+                .is_some_and(|t| t.trim().eq_ignore_ascii_case(&title))
+        });
+        match section_start {
+            Some(start) => {
+                let index = end_of_block(&lines, start + 1, &["## ", "### "]);
+                lines.splice(index..index, entries);
+            }
+            None => {
+                let release_start = lines
+                    .iter()
+                    .position(|line| line.starts_with("## "))
+                    .unwrap_or_default();
+                let release_end = end_of_block(&lines, release_start + 1, &["## "]);
+                let next_section = section_rank(&title).and_then(|rank| {
+                    (release_start + 1..release_end).find(|&i| {
+                        lines[i].strip_prefix("###").is_some_and(|t| {
+                            section_rank(t.trim()).is_none_or(|other| other > rank)
+                        })
+                    })
+                });
+                match next_section {
+                    Some(index) => {
+                        let mut new_section = vec![format!("### {title}"), String::new()];
+                        new_section.extend(entries);
+                        new_section.push(String::new());
+                        lines.splice(index..index, new_section);
+                    }
+                    None => {
+                        let mut new_section =
+                            vec![String::new(), format!("### {title}"), String::new()];
+                        new_section.extend(entries);
+                        lines.splice(release_end..release_end, new_section);
+                    }
+                }
+            }
+        }
+    }
+    let mut merged = lines.join("\n");
+    if generated_release.ends_with('\n') {
+        merged.push('\n');
+    }
+    merged
+}
+
+/// Position of a section in the order recommended by [Keep a Changelog](https://keepachangelog.com).
+/// Returns `None` for sections that Keep a Changelog doesn't define, e.g. `Other`.
+fn section_rank(title: &str) -> Option<usize> {
+    const SECTIONS: [&str; 6] = [
+        "added",
+        "changed",
+        "deprecated",
+        "removed",
+        "fixed",
+        "security",
+    ];
+    SECTIONS
+        .iter()
+        .position(|section| section.eq_ignore_ascii_case(title))
+}
+
+/// Index of the line after the last non-blank line of the block starting at `start`.
+/// The block ends before the first line starting with one of `next_block_prefixes`.
+fn end_of_block(lines: &[String], start: usize, next_block_prefixes: &[&str]) -> usize {
+    let start = start.min(lines.len());
+    let end = lines[start..]
+        .iter()
+        .position(|line| next_block_prefixes.iter().any(|p| line.starts_with(p)))
+        .map_or(lines.len(), |i| start + i);
+    (start..end)
+        .rev()
+        .find(|&i| !lines[i].trim().is_empty())
+        .map_or(end, |i| i + 1)
+}
+
+/// Normalize a changelog entry to compare it with other entries,
+/// ignoring case and the pull request link added by release-plz (e.g. `([#12](https://...))`).
+fn normalize_entry(entry: &str) -> String {
+    static PR_LINK_RE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"\s*\(\[#\d+\]\([^)]*\)\)\s*$").unwrap());
+    let first_line = entry.lines().next().unwrap_or_default();
+    PR_LINK_RE.replace(first_line, "").trim().to_lowercase()
 }
 
 /// Apply release-plz defaults to git config
@@ -264,7 +444,7 @@ fn is_version_unchanged(release: &Release) -> bool {
     previous_version == new_version
 }
 
-fn default_git_cliff_config() -> Config {
+pub fn default_git_cliff_config() -> Config {
     Config {
         changelog: default_changelog_config(None),
         git: default_git_config(None),
@@ -284,6 +464,7 @@ pub struct ChangelogBuilder<'a> {
     release_link: Option<String>,
     package: String,
     pr_link: Option<String>,
+    unreleased_handling: UnreleasedHandling,
 }
 
 impl<'a> ChangelogBuilder<'a> {
@@ -302,6 +483,7 @@ impl<'a> ChangelogBuilder<'a> {
             release_link: None,
             package: package.into(),
             pr_link: None,
+            unreleased_handling: UnreleasedHandling::default(),
         }
     }
 
@@ -340,6 +522,13 @@ impl<'a> ChangelogBuilder<'a> {
         }
     }
 
+    pub fn with_unreleased_handling(self, unreleased_handling: UnreleasedHandling) -> Self {
+        Self {
+            unreleased_handling,
+            ..self
+        }
+    }
+
     pub fn with_remote(self, remote: Remote) -> Self {
         Self {
             remote: Some(remote),
@@ -405,6 +594,7 @@ impl<'a> ChangelogBuilder<'a> {
             config: self.config.clone(),
             package: self.package.clone(),
             pr_link: self.pr_link.clone(),
+            unreleased_handling: self.unreleased_handling,
         }
     }
 
@@ -680,6 +870,269 @@ mod tests {
         .assert_eq(&new);
     }
 
+    fn changelog_with_unreleased_entries(unreleased_handling: UnreleasedHandling) -> String {
+        let commits = vec![
+            Commit::new(NO_COMMIT_ID.to_string(), "fix: myfix".to_string()),
+            Commit::new(NO_COMMIT_ID.to_string(), "simple update".to_string()),
+        ];
+        let changelog = ChangelogBuilder::new(commits, "1.1.1", "my_pkg")
+            .with_release_date(NaiveDate::from_ymd_opt(2015, 5, 15).unwrap())
+            .with_unreleased_handling(unreleased_handling)
+            .build();
+        let unreleased = r"
+### Fixed
+
+- myfix
+- fix crash on startup
+  when the config is missing
+
+### Added
+
+- add `foo` command
+";
+        let old_body = r"
+## [1.1.0] - 1970-01-01
+
+### other
+
+- complex update
+";
+        let old = format!("{CHANGELOG_HEADER}{unreleased}{old_body}");
+        changelog.prepend(old).unwrap()
+    }
+
+    #[test]
+    fn unreleased_entries_are_merged_into_new_release() {
+        let new = changelog_with_unreleased_entries(UnreleasedHandling::Merge);
+        expect_test::expect![[r"
+            # Changelog
+
+            All notable changes to this project will be documented in this file.
+
+            The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.0.0/),
+            and this project adheres to [Semantic Versioning](https://semver.org/spec/v2.0.0.html).
+
+            ## [Unreleased]
+
+            ## [1.1.1] - 2015-05-15
+
+            ### Added
+
+            - add `foo` command
+
+            ### Fixed
+
+            - myfix
+            - fix crash on startup
+              when the config is missing
+
+            ### Other
+
+            - simple update
+
+            ## [1.1.0] - 1970-01-01
+
+            ### other
+
+            - complex update
+        "]]
+        .assert_eq(&new);
+    }
+
+    #[test]
+    fn unreleased_entries_are_replaced_by_new_release() {
+        let new = changelog_with_unreleased_entries(UnreleasedHandling::Replace);
+        expect_test::expect![[r"
+            # Changelog
+
+            All notable changes to this project will be documented in this file.
+
+            The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.0.0/),
+            and this project adheres to [Semantic Versioning](https://semver.org/spec/v2.0.0.html).
+
+            ## [Unreleased]
+
+            ## [1.1.1] - 2015-05-15
+
+            ### Fixed
+
+            - myfix
+
+            ### Other
+
+            - simple update
+
+            ## [1.1.0] - 1970-01-01
+
+            ### other
+
+            - complex update
+        "]]
+        .assert_eq(&new);
+    }
+
+    #[test]
+    fn unreleased_entries_are_kept_by_default() {
+        let new = changelog_with_unreleased_entries(UnreleasedHandling::Keep);
+        expect_test::expect![[r#"
+            # Changelog
+
+            All notable changes to this project will be documented in this file.
+
+            The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.0.0/),
+            and this project adheres to [Semantic Versioning](https://semver.org/spec/v2.0.0.html).
+
+            ## [Unreleased]
+
+            ## [1.1.1] - 2015-05-15
+
+            ### Fixed
+
+            - myfix
+
+            ### Other
+
+            - simple update
+
+            ### Fixed
+
+            - myfix
+            - fix crash on startup
+              when the config is missing
+
+            ### Added
+
+            - add `foo` command
+
+            ## [1.1.0] - 1970-01-01
+
+            ### other
+
+            - complex update
+        "#]]
+        .assert_eq(&new);
+    }
+
+    #[test]
+    fn unreleased_sections_are_merged_in_keep_a_changelog_order() {
+        let commits = vec![
+            Commit::new(NO_COMMIT_ID.to_string(), "fix: myfix".to_string()),
+            Commit::new(NO_COMMIT_ID.to_string(), "simple update".to_string()),
+        ];
+        let changelog = ChangelogBuilder::new(commits, "1.1.1", "my_pkg")
+            .with_release_date(NaiveDate::from_ymd_opt(2015, 5, 15).unwrap())
+            .with_unreleased_handling(UnreleasedHandling::Merge)
+            .build();
+        let unreleased = r"
+### Security
+
+- update vulnerable dependency
+
+### Removed
+
+- remove `bar` command
+
+### Deprecated
+
+- deprecate `baz` option
+";
+        let old = format!("{CHANGELOG_HEADER}{unreleased}\n## [1.1.0] - 1970-01-01\n");
+        let new = changelog.prepend(old).unwrap();
+        expect_test::expect![[r"
+            # Changelog
+
+            All notable changes to this project will be documented in this file.
+
+            The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.0.0/),
+            and this project adheres to [Semantic Versioning](https://semver.org/spec/v2.0.0.html).
+
+            ## [Unreleased]
+
+            ## [1.1.1] - 2015-05-15
+
+            ### Deprecated
+
+            - deprecate `baz` option
+
+            ### Removed
+
+            - remove `bar` command
+
+            ### Fixed
+
+            - myfix
+
+            ### Security
+
+            - update vulnerable dependency
+
+            ### Other
+
+            - simple update
+
+            ## [1.1.0] - 1970-01-01
+        "]]
+        .assert_eq(&new);
+    }
+
+    fn changelog_with_header(old: &str, unreleased_handling: UnreleasedHandling) -> String {
+        let commits = vec![Commit::new(
+            NO_COMMIT_ID.to_string(),
+            "fix: myfix".to_string(),
+        )];
+        let changelog = ChangelogBuilder::new(commits, "0.1.1", "my_pkg")
+            .with_release_date(NaiveDate::from_ymd_opt(2015, 5, 15).unwrap())
+            .with_unreleased_handling(unreleased_handling)
+            .build();
+        changelog.prepend(old.to_string()).unwrap()
+    }
+
+    #[test]
+    fn last_release_is_kept_when_header_has_no_unreleased_heading() {
+        let old = "# Changelog\n\nMy changes.\n\n## [0.1.0] - 1970-01-01\n\n- first release\n";
+        let new = changelog_with_header(old, UnreleasedHandling::Replace);
+        assert_eq!(changelog_with_header(old, UnreleasedHandling::Merge), new);
+        expect_test::expect![[r#"
+            # Changelog
+
+            My changes.
+
+            ## [0.1.1] - 2015-05-15
+
+            ### Fixed
+
+            - myfix
+
+            ## [0.1.0] - 1970-01-01
+
+            - first release
+        "#]]
+        .assert_eq(&new);
+    }
+
+    #[test]
+    fn last_release_is_kept_when_header_has_no_blank_line() {
+        let old =
+            "# ChangeLog\n## [0.1.0] - 1970-01-01\n\n- first release\n\n## [0.0.1] - 1970-01-01\n";
+        let new = changelog_with_header(old, UnreleasedHandling::Replace);
+        assert_eq!(changelog_with_header(old, UnreleasedHandling::Merge), new);
+        expect_test::expect![[r#"
+            # ChangeLog
+
+            ## [0.1.1] - 2015-05-15
+
+            ### Fixed
+
+            - myfix
+            ## [0.1.0] - 1970-01-01
+
+            - first release
+
+            ## [0.0.1] - 1970-01-01
+        "#]]
+        .assert_eq(&new);
+    }
+
     #[test]
     fn changelog_without_header_is_updated() {
         let commits = vec![
@@ -770,6 +1223,27 @@ mod tests {
         .assert_eq(&changelog.generate().unwrap());
     }
 
+    #[test]
+    fn changelog_context_contains_release_plz_variables() {
+        let commits = vec![Commit::new(
+            NO_COMMIT_ID.to_string(),
+            "fix: myfix".to_string(),
+        )];
+        let changelog = ChangelogBuilder::new(commits, "1.1.1", "my_pkg")
+            .with_release_date(NaiveDate::from_ymd_opt(2015, 5, 15).unwrap())
+            .with_release_link("https://example.com/compare/v1.1.0...v1.1.1")
+            .build();
+        let context = changelog.context().unwrap();
+        assert_eq!(context["version"], "1.1.1");
+        assert_eq!(context["package"], "my_pkg");
+        assert_eq!(
+            context[RELEASE_LINK],
+            "https://example.com/compare/v1.1.0...v1.1.1"
+        );
+        assert_eq!(context["commits"][0]["message"], "myfix");
+        assert_eq!(context["commits"][0]["group"], "fixed");
+    }
+
     #[test]
     fn changelog_sort_newest() {
         let commits = vec![
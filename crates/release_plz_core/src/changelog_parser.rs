@@ -1,8 +1,11 @@
 use anyhow::Context;
 use cargo_metadata::camino::Utf8Path;
 use regex::Regex;
+use serde::Serialize;
 use std::sync::LazyLock;
 
+use crate::pr_parser::{Issue, Pr, issues_from_text, prs_from_text};
+
 /// Parse the header from a changelog.
 /// The changelog header is a string at the begin of the changelog that:
 /// - Starts with `# Changelog`, `# CHANGELOG`, or `# changelog`
@@ -139,6 +142,73 @@ fn release_at<'a>(
     Some(release)
 }
 
+/// Group of changes of a release, e.g. `### Added`.
+#[derive(Debug, Serialize)]
+pub struct ChangelogSection {
+    /// Title of the section, without the leading `###`.
+    /// `None` for the entries that precede the first section.
+    pub title: Option<String>,
+    pub entries: Vec<ChangelogEntry>,
+}
+
+/// Change listed in a changelog, e.g. `- fix crash ([#12](https://...))`.
+#[derive(Debug, Serialize)]
+pub struct ChangelogEntry {
+    /// Text of the entry, without the leading list marker.
+    pub text: String,
+    /// Pull requests linked in the entry.
+    pub prs: Vec<Pr>,
+    /// Issues linked in the entry.
+    pub issues: Vec<Issue>,
+}
+
+/// Parse the sections (e.g. `### Added`) of the notes of a release.
+pub(crate) fn parse_sections(notes: &str) -> Vec<ChangelogSection> {
+    static LINK_REFERENCE_RE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"^\[[^\]]+\]:\s").unwrap());
+    let mut sections: Vec<ChangelogSection> = vec![];
+    for line in notes.lines() {
+        if let Some(title) = line.strip_prefix("###") {
+            sections.push(ChangelogSection {
+                title: Some(title.trim().to_string()),
+                entries: vec![],
+            });
+            continue;
+        }
+        let trimmed = line.trim();
+        if trimmed.is_empty() || LINK_REFERENCE_RE.is_match(trimmed) {
+            continue;
+        }
+        let item = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "));
+        if sections.is_empty() {
+            sections.push(ChangelogSection {
+                title: None,
+                entries: vec![],
+            });
+        }
+        let entries = &mut sections.last_mut().expect("sections are not empty").entries;
+        match (item, entries.last_mut()) {
+            // Continuation of the previous entry, e.g. a nested list or a wrapped line.
+            (None, Some(entry)) => {
+                entry.text.push('\n');
+                entry.text.push_str(trimmed);
+            }
+            (item, _) => entries.push(ChangelogEntry {
+                text: item.unwrap_or(trimmed).to_string(),
+                prs: vec![],
+                issues: vec![],
+            }),
+        }
+    }
+    for entry in sections.iter_mut().flat_map(|s| s.entries.iter_mut()) {
+        entry.prs = prs_from_text(&entry.text);
+        entry.issues = issues_from_text(&entry.text);
+    }
+    sections
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,456 @@
+use std::{fmt, sync::LazyLock};
+
+use anyhow::Context as _;
+use cargo_metadata::{camino::Utf8PathBuf, semver::Version};
+use chrono::NaiveDate;
+use git_cmd::Repo;
+use regex::Regex;
+use serde::Serialize;
+use tracing::instrument;
+
+use crate::{
+    Project, RepoUrl, changelog_parser, registry_packages, tera::default_tag_name_template,
+    update_request::UpdateRequest,
+};
+
+use super::{
+    export::parse_changelog_releases,
+    regenerate::{TaggedRelease, tagged_releases},
+};
+
+/// Problems found in the changelog of a package.
+#[derive(Debug, Serialize)]
+pub struct ChangelogCheck {
+    /// Package name.
+    pub package: String,
+    /// Path of the changelog file.
+    pub path: Utf8PathBuf,
+    pub problems: Vec<ChangelogProblem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ChangelogProblem {
+    /// The changelog doesn't start with a `# Changelog` header.
+    MissingHeader,
+    /// The changelog contains more than one `## [Unreleased]` section.
+    DuplicatedUnreleased { count: usize },
+    /// The changelog can't be parsed.
+    Unparsable { error: String },
+    /// The version of a release isn't valid semver.
+    InvalidVersion { version: String },
+    /// A release isn't older than the release that precedes it in the changelog.
+    OutOfOrder { version: String, previous: String },
+    /// A version tagged in git isn't in the changelog.
+    MissingVersion { version: String, tag: String },
+    /// The latest version published in the registry isn't in the changelog.
+    MissingPublishedVersion { version: String },
+    /// The date of a release isn't in the `YYYY-MM-DD` format.
+    InvalidDate { version: String, date: String },
+    /// The link of a release doesn't match the git tags of the release.
+    WrongLink {
+        version: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+impl fmt::Display for ChangelogProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingHeader => write!(f, "the changelog doesn't start with `# Changelog`"),
+            Self::DuplicatedUnreleased { count } => {
+                write!(
+                    f,
+                    "found {count} `## [Unreleased]` sections, expected at most one"
+                )
+            }
+            Self::Unparsable { error } => write!(f, "can't parse changelog: {error}"),
+            Self::InvalidVersion { version } => {
+                write!(f, "version `{version}` isn't valid semver")
+            }
+            Self::OutOfOrder { version, previous } => write!(
+                f,
+                "version `{version}` should be listed after version `{previous}`"
+            ),
+            Self::MissingVersion { version, tag } => {
+                write!(f, "version `{version}` (git tag `{tag}`) is missing")
+            }
+            Self::MissingPublishedVersion { version } => {
+                write!(
+                    f,
+                    "version `{version}` (published in the registry) is missing"
+                )
+            }
+            Self::InvalidDate { version, date } => write!(
+                f,
+                "date `{date}` of version `{version}` isn't in the YYYY-MM-DD format"
+            ),
+            Self::WrongLink {
+                version,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "link of version `{version}` is `{actual}`, expected `{expected}`"
+            ),
+        }
+    }
+}
+
+/// Check the consistency of the changelogs of the packages with their git tags
+/// and with the latest version published in the registry.
+/// Packages without a changelog file are skipped.
+#[instrument(skip_all)]
+pub fn check_changelogs(input: &UpdateRequest) -> anyhow::Result<Vec<ChangelogCheck>> {
+    let overrides = input.packages_config().overridden_packages();
+    let project = Project::new(
+        input.local_manifest(),
+        input.single_package(),
+        &overrides,
+        input.cargo_metadata(),
+        input,
+    )?;
+    let repository = Repo::new(project.root())?;
+    let is_multi_package = project.publishable_packages().len() > 1;
+    // The versions of `git_only` packages are only tracked by git tags.
+    let registry_packages: Vec<_> = project
+        .publishable_packages()
+        .into_iter()
+        .filter(|p| !input.should_use_git_only(&p.name))
+        .collect();
+    let registry_packages = registry_packages::get_registry_packages(
+        input.registry_manifest(),
+        &registry_packages,
+        input.registry(),
+    )?;
+
+    let mut checks = vec![];
+    for package in project.workspace_packages() {
+        let path = input.changelog_path(package);
+        if !path.exists() {
+            continue;
+        }
+        let changelog = fs_err::read_to_string(&path).context("can't read changelog file")?;
+        let template = input
+            .get_package_tag_name(&package.name)
+            .unwrap_or_else(|| default_tag_name_template(is_multi_package));
+        let releases = tagged_releases(&repository, &template, &package.name)?;
+        let published_version = registry_packages
+            .get_package(&package.name)
+            .map(|p| &p.version);
+        checks.push(ChangelogCheck {
+            package: package.name.to_string(),
+            problems: changelog_problems(
+                &changelog,
+                &releases,
+                published_version,
+                input.repo_url(),
+            ),
+            path,
+        });
+    }
+    Ok(checks)
+}
+
+fn changelog_problems(
+    changelog: &str,
+    tagged_releases: &[TaggedRelease],
+    published_version: Option<&Version>,
+    repo_url: Option<&RepoUrl>,
+) -> Vec<ChangelogProblem> {
+    // The heading can link to the changes since the last release, e.g.
+    // `## [Unreleased](https://github.com/owner/repo/compare/v1.0.0...HEAD)`.
+    static UNRELEASED_RE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"(?mi)^##\s*\[?unreleased\]?(\([^)\n]*\))?\s*$").unwrap());
+
+    let mut problems = vec![];
+    if changelog_parser::parse_header(changelog).is_none() {
+        problems.push(ChangelogProblem::MissingHeader);
+    }
+    let unreleased_count = UNRELEASED_RE.find_iter(changelog).count();
+    if unreleased_count > 1 {
+        problems.push(ChangelogProblem::DuplicatedUnreleased {
+            count: unreleased_count,
+        });
+    }
+    // Parse the changelog without the duplicated sections, to report the other problems too.
+    let mut is_first_unreleased = true;
+    let changelog = UNRELEASED_RE.replace_all(changelog, |captures: &regex::Captures| {
+        if std::mem::take(&mut is_first_unreleased) {
+            captures[0].to_string()
+        } else {
+            String::new()
+        }
+    });
+    let releases = match parse_changelog_releases(&changelog) {
+        Ok(releases) => releases,
+        Err(e) => {
+            problems.push(ChangelogProblem::Unparsable {
+                error: format!("{e:#}"),
+            });
+            return problems;
+        }
+    };
+
+    // Released versions, from the newest to the oldest.
+    let mut versions: Vec<(Version, Option<String>)> = vec![];
+    for release in releases
+        .iter()
+        .filter(|r| !r.version.eq_ignore_ascii_case("unreleased"))
+    {
+        let Ok(version) = Version::parse(release.version.trim_start_matches('v')) else {
+            problems.push(ChangelogProblem::InvalidVersion {
+                version: release.version.clone(),
+            });
+            continue;
+        };
+        if let Some(date) = title_date(&release.title)
+            && NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err()
+        {
+            problems.push(ChangelogProblem::InvalidDate {
+                version: version.to_string(),
+                date: date.to_string(),
+            });
+        }
+        if let Some((previous, _)) = versions.last()
+            && previous <= &version
+        {
+            problems.push(ChangelogProblem::OutOfOrder {
+                version: version.to_string(),
+                previous: previous.to_string(),
+            });
+        }
+        versions.push((version, release.compare_link.clone()));
+    }
+
+    let tag_of = |version: &Version| {
+        tagged_releases
+            .iter()
+            .find(|r| &r.version == version)
+            .map(|r| r.tag.as_str())
+    };
+    if let Some(repo_url) = repo_url {
+        for (i, (version, link)) in versions.iter().enumerate() {
+            let (Some(link), Some(tag)) = (link, tag_of(version)) else {
+                continue;
+            };
+            let previous_tag = match versions.get(i + 1) {
+                Some((previous, _)) => tag_of(previous),
+                None => Some(tag),
+            };
+            let Some(previous_tag) = previous_tag else {
+                continue;
+            };
+            let expected = repo_url.git_release_link(previous_tag, tag);
+            let legacy = repo_url.legacy_git_release_link(previous_tag, tag);
+            if link != &expected && legacy.as_ref() != Some(link) {
+                problems.push(ChangelogProblem::WrongLink {
+                    version: version.to_string(),
+                    expected,
+                    actual: link.clone(),
+                });
+            }
+        }
+    }
+
+    // Tags older than the oldest release of the changelog were created before
+    // the changelog, so we don't expect them to be documented.
+    if let Some((oldest, _)) = versions.iter().min_by(|a, b| a.0.cmp(&b.0)) {
+        for release in tagged_releases.iter().filter(|r| &r.version >= oldest) {
+            if !versions.iter().any(|(v, _)| v == &release.version) {
+                problems.push(ChangelogProblem::MissingVersion {
+                    version: release.version.to_string(),
+                    tag: release.tag.clone(),
+                });
+            }
+        }
+        if let Some(published) = published_version
+            && published >= oldest
+            && !versions.iter().any(|(v, _)| v == published)
+            && !tagged_releases.iter().any(|r| &r.version == published)
+        {
+            problems.push(ChangelogProblem::MissingPublishedVersion {
+                version: published.to_string(),
+            });
+        }
+    }
+    problems
+}
+
+/// Date of a release title, e.g. `2024-01-01` in `[1.0.0](https://...) - 2024-01-01`.
+fn title_date(title: &str) -> Option<&str> {
+    let (_, date) = title.rsplit_once(" - ")?;
+    let date = date.trim();
+    (!date.is_empty()).then_some(date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tagged_release(version: &str) -> TaggedRelease {
+        TaggedRelease {
+            tag: format!("v{version}"),
+            version: Version::parse(version).unwrap(),
+        }
+    }
+
+    fn repo_url() -> RepoUrl {
+        RepoUrl::new("https://github.com/owner/repo").unwrap()
+    }
+
+    #[test]
+    fn valid_changelog_has_no_problems() {
+        let changelog = "\
+# Changelog
+
+## [Unreleased]
+
+## [0.2.0](https://github.com/owner/repo/compare/v0.1.0...v0.2.0) - 2024-03-01
+
+- add foo
+
+## [0.1.0](https://github.com/owner/repo/releases/tag/v0.1.0) - 2024-01-01
+
+- initial release
+";
+        let releases = [tagged_release("0.1.0"), tagged_release("0.2.0")];
+        let problems = changelog_problems(changelog, &releases, None, Some(&repo_url()));
+        assert_eq!(problems, vec![]);
+    }
+
+    #[test]
+    fn legacy_gitlab_compare_links_are_valid() {
+        let changelog = "\
+# Changelog
+
+## [Unreleased]
+
+## [0.3.0](https://gitlab.com/owner/repo/-/compare/v0.2.0...v0.3.0) - 2024-05-01
+
+## [0.2.0](https://gitlab.com/owner/repo/compare/v0.1.0...v0.2.0) - 2024-03-01
+
+## [0.1.0](https://gitlab.com/owner/repo/-/releases/v0.1.0) - 2024-01-01
+";
+        let repo_url = RepoUrl::new("https://gitlab.com/owner/repo")
+            .unwrap()
+            .with_forge(crate::ForgeType::Gitlab);
+        let releases = [
+            tagged_release("0.1.0"),
+            tagged_release("0.2.0"),
+            tagged_release("0.3.0"),
+        ];
+        let problems = changelog_problems(changelog, &releases, None, Some(&repo_url));
+        assert_eq!(problems, vec![]);
+    }
+
+    #[test]
+    fn inconsistent_changelog_problems_are_reported() {
+        let changelog = "\
+# Changelog
+
+## [Unreleased]
+
+## [Unreleased]
+
+## [0.1.0](https://github.com/owner/repo/compare/v0.0.1...v0.1.0) - 2024-1-32
+
+- add foo
+
+## [0.2.0](https://github.com/owner/repo/releases/tag/v0.2.0) - 2024-03-01
+
+- initial release
+";
+        let releases = [
+            tagged_release("0.1.0"),
+            tagged_release("0.2.0"),
+            tagged_release("0.3.0"),
+        ];
+        let problems = changelog_problems(changelog, &releases, None, Some(&repo_url()));
+        assert_eq!(
+            problems,
+            vec![
+                ChangelogProblem::DuplicatedUnreleased { count: 2 },
+                ChangelogProblem::InvalidDate {
+                    version: "0.1.0".to_string(),
+                    date: "2024-1-32".to_string()
+                },
+                ChangelogProblem::OutOfOrder {
+                    version: "0.2.0".to_string(),
+                    previous: "0.1.0".to_string()
+                },
+                ChangelogProblem::WrongLink {
+                    version: "0.1.0".to_string(),
+                    expected: "https://github.com/owner/repo/compare/v0.2.0...v0.1.0".to_string(),
+                    actual: "https://github.com/owner/repo/compare/v0.0.1...v0.1.0".to_string()
+                },
+                ChangelogProblem::MissingVersion {
+                    version: "0.3.0".to_string(),
+                    tag: "v0.3.0".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn changelog_without_header_is_reported() {
+        let changelog = "\
+## [0.1.0] - 2024-01-01
+
+- initial release
+";
+        let problems = changelog_problems(changelog, &[], None, None);
+        assert_eq!(problems, vec![ChangelogProblem::MissingHeader]);
+    }
+
+    #[test]
+    fn linked_unreleased_headings_are_counted() {
+        let changelog = "\
+# Changelog
+
+## [Unreleased](https://github.com/owner/repo/compare/v0.1.0...HEAD)
+
+## [Unreleased]
+
+## [0.1.0](https://github.com/owner/repo/releases/tag/v0.1.0) - 2024-01-01
+";
+        let releases = [tagged_release("0.1.0")];
+        let problems = changelog_problems(changelog, &releases, None, Some(&repo_url()));
+        assert_eq!(
+            problems,
+            vec![ChangelogProblem::DuplicatedUnreleased { count: 2 }]
+        );
+    }
+
+    #[test]
+    fn published_version_missing_from_changelog_is_reported() {
+        let changelog = "\
+# Changelog
+
+## [0.1.0] - 2024-01-01
+
+- initial release
+";
+        let releases = [tagged_release("0.1.0")];
+        let published = Version::new(0, 2, 0);
+        let problems = changelog_problems(changelog, &releases, Some(&published), None);
+        assert_eq!(
+            problems,
+            vec![ChangelogProblem::MissingPublishedVersion {
+                version: "0.2.0".to_string()
+            }]
+        );
+
+        // Versions that are also tagged are reported once, with their tag.
+        let releases = [tagged_release("0.1.0"), tagged_release("0.2.0")];
+        let problems = changelog_problems(changelog, &releases, Some(&published), None);
+        assert_eq!(
+            problems,
+            vec![ChangelogProblem::MissingVersion {
+                version: "0.2.0".to_string(),
+                tag: "v0.2.0".to_string()
+            }]
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use std::sync::LazyLock;
+
+use anyhow::Context as _;
+use cargo_metadata::camino::Utf8PathBuf;
+use regex::Regex;
+use serde::Serialize;
+use tracing::{instrument, warn};
+
+use crate::{Project, changelog_parser::parse_sections, update_request::UpdateRequest};
+
+pub use crate::changelog_parser::{ChangelogEntry, ChangelogSection};
+
+/// Changelog of a package, parsed into its releases.
+#[derive(Debug, Serialize)]
+pub struct ExportedChangelog {
+    /// Package name.
+    pub package: String,
+    /// Path of the changelog file.
+    pub path: Utf8PathBuf,
+    /// Releases of the changelog, from the newest to the oldest.
+    pub releases: Vec<ChangelogReleaseEntry>,
+}
+
+/// Release section of a changelog, e.g. `## [0.2.0](https://...) - 2024-01-01`.
+#[derive(Debug, Serialize)]
+pub struct ChangelogReleaseEntry {
+    /// Version of the release, or `Unreleased`.
+    pub version: String,
+    /// Title of the release, without the leading `##`.
+    pub title: String,
+    /// Release date, in the `YYYY-MM-DD` format.
+    pub date: Option<String>,
+    /// Link comparing the release with the previous one.
+    pub compare_link: Option<String>,
+    /// Sections of the release, e.g. `Added` or `Fixed`.
+    pub sections: Vec<ChangelogSection>,
+}
+
+/// Parse the changelogs of the packages.
+/// Packages without a changelog file are skipped.
+#[instrument(skip_all)]
+pub fn export_changelogs(input: &UpdateRequest) -> anyhow::Result<Vec<ExportedChangelog>> {
+    let overrides = input.packages_config().overridden_packages();
+    let project = Project::new(
+        input.local_manifest(),
+        input.single_package(),
+        &overrides,
+        input.cargo_metadata(),
+        input,
+    )?;
+    let mut changelogs = vec![];
+    for package in project.workspace_packages() {
+        let path = input.changelog_path(package);
+        if !path.exists() {
+            warn!("{}: changelog {path} not found", package.name);
+            continue;
+        }
+        let changelog = fs_err::read_to_string(&path).context("can't read changelog file")?;
+        let releases = parse_changelog_releases(&changelog)
+            .with_context(|| format!("can't parse changelog {path}"))?;
+        changelogs.push(ExportedChangelog {
+            package: package.name.to_string(),
+            path,
+            releases,
+        });
+    }
+    Ok(changelogs)
+}
+
+/// Parse the releases of a changelog in the [keep a changelog](https://keepachangelog.com) format.
+pub fn parse_changelog_releases(changelog: &str) -> anyhow::Result<Vec<ChangelogReleaseEntry>> {
+    let releases = parse_changelog::parse(changelog).context("can't parse changelog")?;
+    let releases = releases
+        .values()
+        .map(|release| ChangelogReleaseEntry {
+            version: release.version.to_string(),
+            title: release.title.to_string(),
+            date: release_date(release.title),
+            compare_link: compare_link(changelog, release.version, release.title),
+            sections: parse_sections(release.notes),
+        })
+        .collect();
+    Ok(releases)
+}
+
+fn release_date(title: &str) -> Option<String> {
+    static DATE_RE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"\b\d{4}-\d{2}-\d{2}\b").unwrap());
+    DATE_RE.find(title).map(|date| date.as_str().to_string())
+}
+
+/// Link of the title (e.g. `## [0.2.0](https://...)`)
+/// or link reference definition at the end of the changelog (e.g. `[0.2.0]: https://...`).
+fn compare_link(changelog: &str, version: &str, title: &str) -> Option<String> {
+    static TITLE_LINK_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\]\((\S+)\)").unwrap());
+    if let Some(link) = TITLE_LINK_RE.captures(title) {
+        return Some(link[1].to_string());
+    }
+    let reference_re =
+        Regex::new(&format!(r"(?mi)^\[v?{}\]:\s*(\S+)", regex::escape(version))).ok()?;
+    reference_re
+        .captures(changelog)
+        .map(|link| link[1].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changelog_is_parsed_into_releases() {
+        let changelog = "\
+# Changelog
+
+## [Unreleased]
+
+## [0.2.0](https://github.com/a/b/compare/v0.1.0...v0.2.0) - 2024-03-01
+
+### Added
+
+- add `foo` ([#12](https://github.com/a/b/pull/12))
+- add `bar`, closes [#10](https://github.com/a/b/issues/10)
+  with a second line
+
+### Fixed
+
+- fix crash
+
+## [0.1.0] - 2024-01-01
+
+- initial release
+
+[0.1.0]: https://github.com/a/b/releases/tag/v0.1.0
+";
+        let releases = parse_changelog_releases(changelog).unwrap();
+        expect_test::expect![[r#"
+            [
+              {
+                "version": "Unreleased",
+                "title": "[Unreleased]",
+                "date": null,
+                "compare_link": null,
+                "sections": []
+              },
+              {
+                "version": "0.2.0",
+                "title": "[0.2.0](https://github.com/a/b/compare/v0.1.0...v0.2.0) - 2024-03-01",
+                "date": "2024-03-01",
+                "compare_link": "https://github.com/a/b/compare/v0.1.0...v0.2.0",
+                "sections": [
+                  {
+                    "title": "Added",
+                    "entries": [
+                      {
+                        "text": "add `foo` ([#12](https://github.com/a/b/pull/12))",
+                        "prs": [
+                          {
+                            "html_url": "https://github.com/a/b/pull/12",
+                            "number": 12
+                          }
+                        ],
+                        "issues": []
+                      },
+                      {
+                        "text": "add `bar`, closes [#10](https://github.com/a/b/issues/10)\nwith a second line",
+                        "prs": [],
+                        "issues": [
+                          {
+                            "html_url": "https://github.com/a/b/issues/10",
+                            "number": 10
+                          }
+                        ]
+                      }
+                    ]
+                  },
+                  {
+                    "title": "Fixed",
+                    "entries": [
+                      {
+                        "text": "fix crash",
+                        "prs": [],
+                        "issues": []
+                      }
+                    ]
+                  }
+                ]
+              },
+              {
+                "version": "0.1.0",
+                "title": "[0.1.0] - 2024-01-01",
+                "date": "2024-01-01",
+                "compare_link": "https://github.com/a/b/releases/tag/v0.1.0",
+                "sections": [
+                  {
+                    "title": null,
+                    "entries": [
+                      {
+                        "text": "initial release",
+                        "prs": [],
+                        "issues": []
+                      }
+                    ]
+                  }
+                ]
+              }
+            ]"#]]
+        .assert_eq(&serde_json::to_string_pretty(&releases).unwrap());
+    }
+}
@@ -0,0 +1,154 @@
+mod check;
+mod export;
+mod preview;
+mod regenerate;
+
+pub use check::*;
+pub use export::*;
+pub use preview::*;
+pub use regenerate::*;
+
+use std::{collections::HashMap, path::Path};
+
+use anyhow::Context as _;
+use cargo_metadata::{Package, camino::Utf8PathBuf, semver::Version};
+use chrono::NaiveDate;
+use git_cmd::Repo;
+
+use crate::{
+    ChangelogBuilder, PackagePath as _, Remote,
+    changelog_filler::{fill_commit, get_required_info},
+    diff::Commit,
+    update_request::UpdateRequest,
+    updater::{first_line_of_unconventional_commits, get_contributors, pathbufs_to_check},
+};
+
+/// Git history of a package.
+/// Used to render changelog entries of arbitrary commit ranges.
+struct PackageHistory<'a> {
+    input: &'a UpdateRequest,
+    repository: &'a Repo,
+    package: &'a Package,
+    paths_to_check: Vec<Utf8PathBuf>,
+    /// Packages contained in a subdirectory of this package have their own history.
+    paths_to_exclude: Vec<Utf8PathBuf>,
+}
+
+impl<'a> PackageHistory<'a> {
+    fn new(
+        input: &'a UpdateRequest,
+        repository: &'a Repo,
+        package: &'a Package,
+        workspace_packages: &[Package],
+    ) -> anyhow::Result<Self> {
+        let package_path = package.package_path()?;
+        let paths_to_check = pathbufs_to_check(package_path, package)?;
+        let mut paths_to_exclude = vec![];
+        for other in workspace_packages.iter().filter(|p| p.name != package.name) {
+            let other_path = other.package_path()?;
+            if other_path != package_path && other_path.starts_with(package_path) {
+                paths_to_exclude.push(other_path.to_path_buf());
+            }
+        }
+        Ok(Self {
+            input,
+            repository,
+            package,
+            paths_to_check,
+            paths_to_exclude,
+        })
+    }
+
+    /// Commits that changed the package, from the newest to the oldest.
+    /// The commits contain the information required by the changelog template.
+    async fn commits(&self, from: Option<&str>, to: &str) -> anyhow::Result<Vec<Commit>> {
+        let paths_to_check: Vec<&Path> = self.paths_to_check.iter().map(|p| p.as_ref()).collect();
+        let paths_to_exclude: Vec<&Path> =
+            self.paths_to_exclude.iter().map(|p| p.as_ref()).collect();
+        let commits = self
+            .repository
+            .commits_at_paths(from, to, &paths_to_check, &paths_to_exclude)?
+            .into_iter()
+            .map(|hash| {
+                let message = self.repository.get_commit_message(&hash)?;
+                Ok(Commit::new(hash, message))
+            })
+            .collect::<anyhow::Result<Vec<Commit>>>()?;
+        let commits = self.fill_commits(commits).await?;
+        Ok(first_line_of_unconventional_commits(commits))
+    }
+
+    /// Fill the commits with the information required by the changelog template.
+    async fn fill_commits(&self, mut commits: Vec<Commit>) -> anyhow::Result<Vec<Commit>> {
+        let Some(changelog_config) = self.input.changelog_req().changelog_config.as_ref() else {
+            return Ok(commits);
+        };
+        let git_client = self.input.git_client()?;
+        let required_info = get_required_info(&changelog_config.changelog);
+        let mut all_commits = HashMap::new();
+        for commit in &mut commits {
+            fill_commit(
+                commit,
+                &required_info,
+                self.repository,
+                &mut all_commits,
+                git_client.as_ref(),
+            )
+            .await
+            .context("Failed to fetch the commit information required by the changelog template")?;
+        }
+        Ok(commits)
+    }
+
+    /// Date of the commit, used as release date.
+    fn commit_date(&self, commit: &str) -> anyhow::Result<NaiveDate> {
+        self.repository
+            .get_commit_date(commit)
+            .and_then(|date| {
+                NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+                    .with_context(|| format!("cannot parse date `{date}`"))
+            })
+            .with_context(|| format!("cannot determine date of {commit}"))
+    }
+
+    fn changelog_builder<'c>(
+        &self,
+        commits: &[git_cliff_core::commit::Commit<'c>],
+        version: &Version,
+        previous_version: Option<&Version>,
+        release_link: Option<String>,
+        release_date: Option<NaiveDate>,
+    ) -> ChangelogBuilder<'c> {
+        let mut changelog_builder = ChangelogBuilder::new(
+            commits.to_vec(),
+            version.to_string(),
+            self.package.name.to_string(),
+        );
+        let changelog_req = self.input.changelog_req();
+        if let Some(release_date) = release_date.or(changelog_req.release_date) {
+            changelog_builder = changelog_builder.with_release_date(release_date);
+        }
+        if let Some(config) = changelog_req.changelog_config.clone() {
+            changelog_builder = changelog_builder.with_config(config);
+        }
+        if let Some(previous_version) = previous_version {
+            changelog_builder =
+                changelog_builder.with_previous_version(previous_version.to_string());
+        }
+        if let Some(release_link) = release_link {
+            changelog_builder = changelog_builder.with_release_link(release_link);
+        }
+        if let Some(repo_url) = self.input.repo_url() {
+            let remote = Remote {
+                owner: repo_url.owner.clone(),
+                repo: repo_url.name.clone(),
+                link: repo_url.full_host(),
+                contributors: get_contributors(commits),
+            };
+            changelog_builder = changelog_builder
+                .with_remote(remote)
+                .with_pr_link(repo_url.git_pr_link());
+        }
+        changelog_builder
+    }
+}
@@ -0,0 +1,224 @@
+use std::str::FromStr;
+
+use anyhow::Context as _;
+use cargo_metadata::semver::Version;
+use git_cmd::Repo;
+use serde::Serialize;
+use tracing::instrument;
+
+use crate::{
+    Project, diff::Diff, update_request::UpdateRequest, updater::new_changelog_entry,
+    version::NextVersionFromDiff as _,
+};
+
+use super::PackageHistory;
+
+/// Changelog entry rendered without writing it to disk.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangelogPreview {
+    /// Package name.
+    pub package: String,
+    /// Version of the changelog entry.
+    pub version: Version,
+    /// Rendered changelog entry, in Markdown.
+    pub entry: String,
+    /// Template context used to render the entry.
+    pub context: serde_json::Value,
+}
+
+/// Git revisions delimiting the commits of a changelog preview.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitRange {
+    /// Excluded start of the range. If `None`, the range starts from the first commit.
+    pub from: Option<String>,
+    /// Included end of the range.
+    pub to: String,
+}
+
+impl FromStr for CommitRange {
+    type Err = anyhow::Error;
+
+    /// Parse a range in the format `<from>..<to>` or `<to>`.
+    fn from_str(range: &str) -> Result<Self, Self::Err> {
+        let (from, to) = match range.split_once("..") {
+            Some((from, to)) => (Some(from), to),
+            None => (None, range),
+        };
+        anyhow::ensure!(
+            !to.is_empty() && from.is_none_or(|from| !from.is_empty()),
+            "invalid commit range `{range}`. Expected format: `<from>..<to>` or `<to>`"
+        );
+        Ok(Self {
+            from: from.map(ToString::to_string),
+            to: to.to_string(),
+        })
+    }
+}
+
+/// Render the changelog entries without updating the changelogs.
+///
+/// If `range` is `None`, the entries are the ones that `release-plz update` would add.
+/// Otherwise, each entry contains the commits of the range that changed the package.
+#[instrument(skip_all)]
+pub async fn preview_changelogs(
+    input: &UpdateRequest,
+    range: Option<&CommitRange>,
+) -> anyhow::Result<Vec<ChangelogPreview>> {
+    match range {
+        Some(range) => range_previews(input, range).await,
+        None => next_release_previews(input).await,
+    }
+}
+
+/// Entries of the next release, as computed by `release-plz update`.
+async fn next_release_previews(input: &UpdateRequest) -> anyhow::Result<Vec<ChangelogPreview>> {
+    let input = input.clone().with_changelog_context(true);
+    let (packages_to_update, _temp_repo) = crate::next_versions(&input).await?;
+    let previews = packages_to_update
+        .updates()
+        .iter()
+        .filter_map(|(package, update)| {
+            let entry = update.new_changelog_entry.clone()?;
+            let context = update.new_changelog_context.clone()?;
+            Some(ChangelogPreview {
+                package: package.name.to_string(),
+                version: update.version.clone(),
+                entry,
+                context,
+            })
+        })
+        .collect();
+    Ok(previews)
+}
+
+async fn range_previews(
+    input: &UpdateRequest,
+    range: &CommitRange,
+) -> anyhow::Result<Vec<ChangelogPreview>> {
+    let overrides = input.packages_config().overridden_packages();
+    let project = Project::new(
+        input.local_manifest(),
+        input.single_package(),
+        &overrides,
+        input.cargo_metadata(),
+        input,
+    )?;
+    let repository = Repo::new(project.root())?;
+    let workspace_packages = crate::workspace_packages(input.cargo_metadata())?;
+
+    let mut previews = vec![];
+    for package in project.workspace_packages() {
+        let config = input.get_package_config(&package.name);
+        if !config.should_update_changelog() {
+            continue;
+        }
+        let history = PackageHistory::new(input, &repository, package, &workspace_packages)?;
+        let commits = history
+            .commits(range.from.as_deref(), &range.to)
+            .await
+            .with_context(|| format!("cannot read git history of `{}`", package.name))?;
+        if commits.is_empty() {
+            continue;
+        }
+        let mut diff = Diff::new(true);
+        diff.add_commits(&commits);
+        let version = package
+            .version
+            .next_from_diff(&diff, config.generic.version_updater()?);
+        let cliff_commits: Vec<git_cliff_core::commit::Commit> =
+            commits.iter().map(|c| c.to_cliff_commit()).collect();
+        let release_link = range.from.as_deref().and_then(|from| {
+            input
+                .repo_url()
+                .map(|r| r.git_release_link(from, &range.to))
+        });
+        let changelog_builder = history.changelog_builder(
+            &cliff_commits,
+            &version,
+            Some(&package.version),
+            release_link,
+            None,
+        );
+        let context = changelog_builder.build().context()?;
+        let entry = new_changelog_entry(changelog_builder)?.unwrap_or_default();
+        previews.push(ChangelogPreview {
+            package: package.name.to_string(),
+            version,
+            entry,
+            context,
+        });
+    }
+    Ok(previews)
+}
+
+#[cfg(test)]
+mod tests {
+    use git_cliff_core::commit::Commit;
+    use git_cliff_core::config::{Bump, ChangelogConfig, Config, RemoteConfig};
+
+    use super::*;
+    use crate::changelog::{default_changelog_config, default_git_config};
+    use crate::{ChangelogBuilder, NO_COMMIT_ID};
+
+    #[test]
+    fn commit_range_is_parsed() {
+        let range: CommitRange = "v1.0.0..HEAD".parse().unwrap();
+        assert_eq!(
+            range,
+            CommitRange {
+                from: Some("v1.0.0".to_string()),
+                to: "HEAD".to_string()
+            }
+        );
+        let range: CommitRange = "main".parse().unwrap();
+        assert_eq!(range.from, None);
+        assert_eq!(range.to, "main");
+        assert!("v1.0.0..".parse::<CommitRange>().is_err());
+        assert!("..HEAD".parse::<CommitRange>().is_err());
+    }
+
+    /// Error of the changelog entry rendered with `body`.
+    fn template_error(body: &str) -> String {
+        let commits = vec![Commit::new(
+            NO_COMMIT_ID.to_string(),
+            "fix: myfix".to_string(),
+        )];
+        let config = Config {
+            changelog: ChangelogConfig {
+                body: body.to_string(),
+                ..default_changelog_config(None)
+            },
+            git: default_git_config(None),
+            remote: RemoteConfig::default(),
+            bump: Bump::default(),
+        };
+        let changelog_builder =
+            ChangelogBuilder::new(commits, "1.1.1", "my_pkg").with_config(config);
+        let error = new_changelog_entry(changelog_builder).unwrap_err();
+        format!("{error:#}")
+    }
+
+    #[test]
+    fn template_syntax_errors_are_reported_with_their_location() {
+        let template =
+            "## {{ version }}\n{% for commit in commits %}\n- {{ commit.message }\n{% endfor %}";
+        expect_test::expect![[r#"
+            error while building changelog: Template parse error:
+             --> 3:21
+              |
+            3 | - {{ commit.message }
+              |                     ^---
+              |
+              = expected `or`, `and`, `not`, `<=`, `>=`, `<`, `>`, `==`, `!=`, `+`, `-`, `*`, `/`, `%`, a filter, or a variable end (`}}`)"#]].assert_eq(&template_error(template));
+    }
+
+    #[test]
+    fn template_render_errors_are_reported_unchanged() {
+        let template =
+            "## {{ version }}\n{% for commit in commits %}\n- {{ commit.foo }}\n{% endfor %}";
+        expect_test::expect![[r#"
+            cannot generate changelog: Template render error:
+            Variable `commit.foo` not found in context while rendering 'body'"#]]
+        .assert_eq(&template_error(template));
+    }
+}
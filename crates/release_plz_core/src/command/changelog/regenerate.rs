@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+
+use anyhow::Context as _;
+use cargo_metadata::{camino::Utf8PathBuf, semver::Version};
+use git_cmd::Repo;
+use serde::Serialize;
+use tracing::{info, instrument, warn};
+
+use crate::{
+    Project, changelog_parser, release_regex, tera::default_tag_name_template,
+    update_request::UpdateRequest,
+};
+
+use super::PackageHistory;
+
+/// Changelog rebuilt from the git history of a package.
+#[derive(Debug, Clone, Serialize)]
+pub struct RegeneratedChangelog {
+    /// Package name.
+    pub package: String,
+    /// Path of the changelog file.
+    pub path: Utf8PathBuf,
+    /// Released versions contained in the changelog, from the oldest to the newest.
+    pub versions: Vec<Version>,
+    /// Content of the changelog.
+    #[serde(skip)]
+    pub changelog: String,
+}
+
+/// A release of a package, identified by its git tag.
+#[derive(Debug, Clone)]
+pub(super) struct TaggedRelease {
+    pub(super) tag: String,
+    pub(super) version: Version,
+}
+
+/// Rebuild the changelogs of the packages from their git history and write them to disk.
+///
+/// Every git tag matching the `git_tag_name` template of a package becomes a changelog entry.
+/// Each entry contains the commits that changed the package between the previous
+/// tag and the tag of the entry.
+/// The header of the existing changelog is preserved.
+#[instrument(skip_all)]
+pub async fn regenerate_changelogs(
+    input: &UpdateRequest,
+) -> anyhow::Result<Vec<RegeneratedChangelog>> {
+    let changelogs = changelogs_from_git_history(input).await?;
+    for changelog in &changelogs {
+        fs_err::write(&changelog.path, &changelog.changelog).context("cannot write changelog")?;
+        info!(
+            "{}: regenerated {} with {} releases",
+            changelog.package,
+            changelog.path,
+            changelog.versions.len()
+        );
+    }
+    Ok(changelogs)
+}
+
+async fn changelogs_from_git_history(
+    input: &UpdateRequest,
+) -> anyhow::Result<Vec<RegeneratedChangelog>> {
+    let overrides = input.packages_config().overridden_packages();
+    let project = Project::new(
+        input.local_manifest(),
+        input.single_package(),
+        &overrides,
+        input.cargo_metadata(),
+        input,
+    )?;
+    // We only read the git history, so we don't need to copy the repository.
+    let repository = Repo::new(project.root())?;
+    let workspace_packages = crate::workspace_packages(input.cargo_metadata())?;
+    let is_multi_package = project.publishable_packages().len() > 1;
+
+    let mut changelog_owners: HashMap<Utf8PathBuf, String> = HashMap::new();
+    let mut changelogs = vec![];
+    for package in project.workspace_packages() {
+        let config = input.get_package_config(&package.name);
+        if !config.should_update_changelog() {
+            continue;
+        }
+        let changelog_path = input.changelog_path(package);
+        if let Some(owner) =
+            changelog_owners.insert(changelog_path.clone(), package.name.to_string())
+        {
+            anyhow::bail!(
+                "packages `{owner}` and `{}` share the changelog {changelog_path}. Regenerate them separately with `--package`.",
+                package.name
+            );
+        }
+        let template = input
+            .get_package_tag_name(&package.name)
+            .unwrap_or_else(|| default_tag_name_template(is_multi_package));
+        let releases = tagged_releases(&repository, &template, &package.name)?;
+        if releases.is_empty() {
+            warn!(
+                "{}: no git tag matches the `git_tag_name` template `{template}`. Skipping changelog regeneration.",
+                package.name
+            );
+            continue;
+        }
+        let old_changelog = fs_err::read_to_string(&changelog_path).ok();
+        let history = PackageHistory::new(input, &repository, package, &workspace_packages)?;
+        let changelog = package_changelog(&history, &releases, old_changelog.as_deref())
+            .await
+            .with_context(|| format!("failed to regenerate changelog of `{}`", package.name))?;
+        changelogs.push(RegeneratedChangelog {
+            package: package.name.to_string(),
+            path: changelog_path,
+            versions: releases.into_iter().map(|r| r.version).collect(),
+            changelog,
+        });
+    }
+    Ok(changelogs)
+}
+
+/// Git tags of the package, sorted from the oldest to the newest version.
+pub(super) fn tagged_releases(
+    repository: &Repo,
+    template: &str,
+    package_name: &str,
+) -> anyhow::Result<Vec<TaggedRelease>> {
+    let release_regex =
+        release_regex::get_release_regex(template, package_name).context("get release regex")?;
+    let mut releases: Vec<TaggedRelease> = repository
+        .get_all_tags()
+        .into_iter()
+        .filter_map(|tag| {
+            let version = release_regex.captures(&tag)?.get(1)?.as_str();
+            let version = Version::parse(version).ok()?;
+            Some(TaggedRelease { tag, version })
+        })
+        .collect();
+    releases.sort_by(|a, b| a.version.cmp(&b.version));
+    Ok(releases)
+}
+
+async fn package_changelog(
+    history: &PackageHistory<'_>,
+    releases: &[TaggedRelease],
+    old_changelog: Option<&str>,
+) -> anyhow::Result<String> {
+    // Start from the header of the old changelog, so that we preserve it.
+    let mut changelog: Option<String> = old_changelog.and_then(changelog_parser::parse_header);
+    let mut previous: Option<&TaggedRelease> = None;
+    for release in releases {
+        let commits = history
+            .commits(previous.map(|p| p.tag.as_str()), &release.tag)
+            .await?;
+        let commits: Vec<git_cliff_core::commit::Commit> =
+            commits.iter().map(|c| c.to_cliff_commit()).collect();
+        let release_link = previous.and_then(|previous| {
+            history
+                .input
+                .repo_url()
+                .map(|r| r.git_release_link(&previous.tag, &release.tag))
+        });
+        let release_changelog = history
+            .changelog_builder(
+                &commits,
+                &release.version,
+                previous.map(|p| &p.version),
+                release_link,
+                Some(history.commit_date(&release.tag)?),
+            )
+            .build();
+        changelog = Some(match changelog {
+            Some(old_changelog) => release_changelog.prepend(old_changelog)?,
+            None => release_changelog.generate()?,
+        });
+        previous = Some(release);
+    }
+    changelog.context("no release found")
+}
@@ -1,9 +1,14 @@
+mod changelog;
 mod release;
+mod release_comment;
+mod release_gate;
 mod release_pr;
 pub mod set_version;
 mod trusted_publishing;
 mod update;
 
+pub use changelog::*;
 pub use release::*;
+pub use release_gate::ReleaseGate;
 pub use release_pr::*;
 pub use update::*;
@@ -3,7 +3,7 @@ use std::{
     time::Duration,
 };
 
-use crate::command::trusted_publishing;
+use crate::command::{ReleaseGate, release_comment, trusted_publishing};
 use anyhow::Context;
 use cargo::util::VersionExt;
 use cargo_metadata::{
@@ -19,10 +19,10 @@ use url::Url;
 
 use crate::{
     CHANGELOG_FILENAME, DEFAULT_BRANCH_PREFIX, GitForge, PackagePath, Project, Publishable as _,
-    ReleaseMetadata, ReleaseMetadataBuilder, Remote,
+    ReleaseMetadata, ReleaseMetadataBuilder, Remote, RepoUrl,
     cargo::{CargoRegistry, CmdOutput, is_published, run_cargo_with_env, wait_until_published},
     changelog_parser,
-    git::forge::GitClient,
+    git::forge::{GitClient, GitPr},
     pr_parser::{Pr, prs_from_text},
 };
 
@@ -54,6 +54,11 @@ pub struct ReleaseRequest {
     publish_timeout: Duration,
     /// PR Branch Prefix
     branch_prefix: String,
+    /// Tera template of the comment posted on the released PRs and on the issues they close.
+    /// If `None`, no comment is posted.
+    release_comment: Option<String>,
+    /// Checks that the release PR must pass before releasing.
+    release_gate: ReleaseGate,
 }
 
 impl ReleaseRequest {
@@ -70,6 +75,8 @@ impl ReleaseRequest {
             publish_timeout: minutes_30,
             release_always: true,
             branch_prefix: DEFAULT_BRANCH_PREFIX.to_string(),
+            release_comment: None,
+            release_gate: ReleaseGate::default(),
         }
     }
 
@@ -125,6 +132,17 @@ impl ReleaseRequest {
         self
     }
 
+    /// Template of the comment posted on the released PRs and on the issues they close.
+    pub fn with_release_comment(mut self, release_comment: impl Into<String>) -> Self {
+        self.release_comment = Some(release_comment.into());
+        self
+    }
+
+    pub fn with_release_gate(mut self, release_gate: ReleaseGate) -> Self {
+        self.release_gate = release_gate;
+        self
+    }
+
     /// Set release config for a specific package.
     pub fn with_package_config(
         mut self,
@@ -518,14 +536,14 @@ pub struct Release {
 
 #[derive(Serialize, Debug)]
 pub struct PackageRelease {
-    package_name: String,
-    prs: Vec<Pr>,
+    pub(super) package_name: String,
+    pub(super) prs: Vec<Pr>,
     /// Git tag name. It's not guaranteed that release-plz created the git tag.
     /// In fact, users can disable git tag creation in the [`ReleaseRequest`].
     /// We return the git tag name anyway, because users might use it to create
     /// the tag by themselves.
-    tag: String,
-    version: Version,
+    pub(super) tag: String,
+    pub(super) version: Version,
 }
 
 /// Release the project as it is.
@@ -549,6 +567,14 @@ pub async fn release(input: &ReleaseRequest) -> anyhow::Result<Option<Release>>
         return Ok(None);
     }
 
+    if input.release_gate.is_enabled() && has_unreleased_packages(input, &project, &repo).await? {
+        let release_pr = associated_release_pr(input, &repo, &git_client).await?;
+        input
+            .release_gate
+            .check(&git_client, release_pr.as_ref())
+            .await?;
+    }
+
     let mut checkout_done = false;
     if let ShouldRelease::YesWithCommit(commit) = &should_release {
         match repo.checkout(commit) {
@@ -610,6 +636,22 @@ async fn release_packages(
     {
         warn!("Failed to revoke trusted publishing token: {e:?}");
     }
+    if let Some(template) = &input.release_comment {
+        let repo_url = match &input.repo_url {
+            Some(url) => RepoUrl::new(url),
+            None => RepoUrl::from_repo(repo),
+        };
+        let repo_url = repo_url
+            .inspect_err(|e| warn!("can't determine the release link: {e:?}"))
+            .ok();
+        release_comment::comment_released_prs(
+            git_client,
+            repo_url.as_ref(),
+            template,
+            &package_releases,
+        )
+        .await;
+    }
     let release = (!package_releases.is_empty()).then_some(Release {
         releases: package_releases,
     });
@@ -651,23 +693,13 @@ async fn release_package_if_needed(
         let registry_indexes = registry_indexes(package, input.registry.clone())
             .context("can't determine registry indexes")?;
 
-        for CargoRegistry { name, index_url } in registry_indexes {
-            let token = input.find_registry_token(name.as_deref())?;
-            let pkg_is_published = is_published(
-                &input.metadata.workspace_root,
-                package,
-                input.publish_timeout,
-                name.as_deref(),
-                index_url.as_ref(),
-                token.as_ref(),
-            )
-            .await
-            .with_context(|| format!("can't determine if package {} is published", package.name))?;
-
-            if pkg_is_published {
+        for registry in registry_indexes {
+            if is_published_in_registry(input, package, &registry).await? {
                 info!("{} {}: already published", package.name, package.version);
                 continue;
             }
+            let CargoRegistry { name, index_url } = registry;
+            let token = input.find_registry_token(name.as_deref())?;
             let package_was_released_at_index = release_package(
                 input,
                 repo,
@@ -721,12 +753,7 @@ async fn should_release(
     git_client: &GitClient,
 ) -> anyhow::Result<ShouldRelease> {
     let last_commit = repo.current_commit_hash()?;
-    let prs = git_client.associated_prs(&last_commit).await?;
-    let associated_release_pr = prs
-        .iter()
-        .find(|pr| pr.branch().starts_with(&input.branch_prefix));
-
-    match associated_release_pr {
+    match associated_release_pr(input, repo, git_client).await? {
         Some(pr) => {
             let pr_commits = git_client.pr_commits(pr.number).await?;
             // Get the last commit of the PR, i.e. the last commit that was pushed before the PR was merged
@@ -757,6 +784,64 @@ async fn should_release(
     }
 }
 
+/// Release PR that introduced the current commit, if any.
+async fn associated_release_pr(
+    input: &ReleaseRequest,
+    repo: &Repo,
+    git_client: &GitClient,
+) -> anyhow::Result<Option<GitPr>> {
+    let last_commit = repo.current_commit_hash()?;
+    let prs = git_client.associated_prs(&last_commit).await?;
+    Ok(prs
+        .into_iter()
+        .find(|pr| pr.branch().starts_with(&input.branch_prefix)))
+}
+
+/// Whether [`release_packages`] would release at least one package.
+/// Like [`release_package_if_needed`], a package is released if its git tag doesn't exist and
+/// it isn't published in one of its registries yet, or if publishing is disabled.
+async fn has_unreleased_packages(
+    input: &ReleaseRequest,
+    project: &Project,
+    repo: &Repo,
+) -> anyhow::Result<bool> {
+    for package in project.publishable_packages() {
+        let git_tag = project.git_tag(&package.name, &package.version.to_string())?;
+        if repo.tag_exists(&git_tag)? {
+            continue;
+        }
+        if !input.is_publish_enabled(&package.name) {
+            return Ok(true);
+        }
+        let registry_indexes = registry_indexes(package, input.registry.clone())
+            .context("can't determine registry indexes")?;
+        for registry in &registry_indexes {
+            if !is_published_in_registry(input, package, registry).await? {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+async fn is_published_in_registry(
+    input: &ReleaseRequest,
+    package: &Package,
+    CargoRegistry { name, index_url }: &CargoRegistry,
+) -> anyhow::Result<bool> {
+    let token = input.find_registry_token(name.as_deref())?;
+    is_published(
+        &input.metadata.workspace_root,
+        package,
+        input.publish_timeout,
+        name.as_deref(),
+        index_url.as_ref(),
+        token.as_ref(),
+    )
+    .await
+    .with_context(|| format!("can't determine if package {} is published", package.name))
+}
+
 fn is_pr_commit_in_original_branch(repo: &Repo, commit: &crate::git::forge::PrCommit) -> bool {
     let branches_of_commit = repo.get_branches_of_commit(&commit.sha);
     if let Ok(branches) = branches_of_commit {
@@ -1001,8 +1086,19 @@ async fn create_git_tag_and_release(
         let should_sign_tags = repo
             .git(&["config", "--default", "false", "--get", "tag.gpgSign"])
             .map(|s| s.trim() == "true")?;
-        // If tag signing is enabled, create the tag locally instead of using the API
-        if should_sign_tags {
+        // If tag signing is enabled or the forge can't create tags,
+        // create the tag locally instead of using the API
+        let supports_tags_api = git_client.supports_tags_api().await;
+        if !should_sign_tags && !supports_tags_api {
+            let server = git_client.gitea_server().await;
+            warn!(
+                "the {} server implements Gitea API {}, which can't create tags (added in Gitea 1.15). Pushing tag {} with git, which requires git credentials",
+                server.name(),
+                server.api_version,
+                release_info.git_tag
+            );
+        }
+        if should_sign_tags || !supports_tags_api {
             repo.tag(release_info.git_tag, &message)?;
             repo.push(release_info.git_tag)?;
         } else {
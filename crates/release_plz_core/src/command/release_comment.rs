@@ -0,0 +1,264 @@
+use std::collections::BTreeSet;
+
+use anyhow::Context as _;
+use tracing::{debug, info, instrument, warn};
+
+use super::PackageRelease;
+use crate::{
+    RepoUrl,
+    git::forge::{CommentTarget, GitClient},
+    tera::{RELEASE_LINK_VAR, render_template, tera_context},
+};
+
+/// Comment on the released PRs and on the issues they close.
+/// PRs and issues that already contain the comment are skipped, so that reruns don't comment twice.
+/// Errors are logged, because the packages are already released.
+#[instrument(skip_all)]
+pub(super) async fn comment_released_prs(
+    git_client: &GitClient,
+    repo_url: Option<&RepoUrl>,
+    template: &str,
+    released_packages: &[PackageRelease],
+) {
+    for package in released_packages {
+        if let Err(e) = comment_package_release(git_client, repo_url, template, package).await {
+            warn!(
+                "{}: failed to comment on released PRs: {e:?}",
+                package.package_name
+            );
+        }
+    }
+}
+
+async fn comment_package_release(
+    git_client: &GitClient,
+    repo_url: Option<&RepoUrl>,
+    template: &str,
+    package: &PackageRelease,
+) -> anyhow::Result<()> {
+    let release_link = repo_url
+        .map(|url| url.git_release_link(&package.tag, &package.tag))
+        .unwrap_or_default();
+    let comment = release_comment(template, package, &release_link)?;
+    let marker = comment_marker(package);
+
+    let mut targets: Vec<CommentTarget> = package
+        .prs
+        .iter()
+        .map(|pr| CommentTarget::Pr(pr.number))
+        .collect();
+    let mut issues = BTreeSet::new();
+    for pr in &package.prs {
+        let closed_issues = git_client
+            .closed_issues(pr.number)
+            .await
+            .with_context(|| format!("can't retrieve the issues closed by PR #{}", pr.number))?;
+        issues.extend(closed_issues);
+    }
+    targets.extend(issues.into_iter().map(CommentTarget::Issue));
+
+    for target in targets {
+        let comments = git_client.comments(target).await?;
+        if comments.iter().any(|comment| comment.contains(&marker)) {
+            debug!("{target:?} already contains the release comment");
+            continue;
+        }
+        git_client.create_comment(target, &comment).await?;
+        info!("{}: commented on {target:?}", package.package_name);
+    }
+    Ok(())
+}
+
+/// Render the comment template, followed by a hidden marker used to detect existing comments.
+fn release_comment(
+    template: &str,
+    package: &PackageRelease,
+    release_link: &str,
+) -> anyhow::Result<String> {
+    let mut context = tera_context(&package.package_name, &package.version.to_string());
+    context.insert(RELEASE_LINK_VAR, release_link);
+    let comment = render_template(template, &context, "release_comment")?;
+    Ok(format!(
+        "{}\n\n{}",
+        comment.trim_end(),
+        comment_marker(package)
+    ))
+}
+
+fn comment_marker(package: &PackageRelease) -> String {
+    format!(
+        "<!-- release-plz-release-comment: {}@{} -->",
+        package.package_name, package.version
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use cargo_metadata::semver::Version;
+    use secrecy::SecretString;
+    use serde_json::json;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::pr_parser::Pr;
+    use crate::{ForgeType, GitForge, GitHub, GitLab, Gitea};
+
+    const TEMPLATE: &str = "Released in {{ package }} {{ version }}";
+
+    fn package(prs: Vec<Pr>) -> PackageRelease {
+        PackageRelease {
+            package_name: "my_package".to_string(),
+            prs,
+            tag: "v1.2.3".to_string(),
+            version: Version::new(1, 2, 3),
+        }
+    }
+
+    /// Package released by PR #1, as linked in the changelog of the repository.
+    fn package_release(repo_url: &RepoUrl) -> PackageRelease {
+        let changelog = format!("- fix bug ([#1]({}/1))", repo_url.git_pr_link());
+        package(crate::pr_parser::prs_from_text(&changelog))
+    }
+
+    /// Mock the comments of a PR or issue.
+    /// If `commented` is true, the release comment was already created by a previous run.
+    async fn mock_comments(server: &MockServer, comments_path: &str, commented: bool) {
+        let mut comments = vec![json!({ "body": "thanks!" })];
+        if commented {
+            let body = format!("Released\n\n{}", comment_marker(&package(vec![])));
+            comments.push(json!({ "body": body }));
+        }
+        Mock::given(method("GET"))
+            .and(path(comments_path))
+            .respond_with(ResponseTemplate::new(200).set_body_json(comments))
+            .mount(server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path(comments_path))
+            .and(body_partial_json(json!({
+                "body": release_comment(TEMPLATE, &package(vec![]), "").unwrap()
+            })))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(u64::from(!commented))
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn github_pr_and_closed_issues_are_commented_once() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .and(body_partial_json(
+                json!({ "variables": { "owner": "owner", "repo": "repo", "number": 1 } }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "repository": { "pullRequest": {
+                    "closingIssuesReferences": { "nodes": [{ "number": 5 }, { "number": 6 }] }
+                } } }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        mock_comments(&server, "/repos/owner/repo/issues/1/comments", false).await;
+        mock_comments(&server, "/repos/owner/repo/issues/5/comments", true).await;
+        mock_comments(&server, "/repos/owner/repo/issues/6/comments", false).await;
+        let github = GitHub::new("owner".to_string(), "repo".to_string(), "token".into())
+            .with_base_url(format!("{}/", server.uri()).parse().unwrap());
+        let client = GitClient::new(GitForge::Github(github)).unwrap();
+
+        let repo_url = RepoUrl::new("https://github.com/owner/repo").unwrap();
+        comment_package_release(&client, None, TEMPLATE, &package_release(&repo_url))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn gitlab_mr_and_closed_issues_are_commented_once() {
+        let server = MockServer::start().await;
+        let project_path = "/api/v4/projects/owner%2Frepo";
+        Mock::given(method("GET"))
+            .and(path(format!(
+                "{project_path}/merge_requests/1/closes_issues"
+            )))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([{ "iid": 5 }])))
+            .expect(1)
+            .mount(&server)
+            .await;
+        mock_comments(
+            &server,
+            &format!("{project_path}/merge_requests/1/notes"),
+            true,
+        )
+        .await;
+        mock_comments(&server, &format!("{project_path}/issues/5/notes"), false).await;
+        let url = RepoUrl::new(&format!("{}/owner/repo", server.uri())).unwrap();
+        let gitlab = GitLab::new(url.clone(), SecretString::from("token")).unwrap();
+        let client = GitClient::new(GitForge::Gitlab(gitlab)).unwrap();
+
+        let repo_url = url.with_forge(ForgeType::Gitlab);
+        comment_package_release(&client, None, TEMPLATE, &package_release(&repo_url))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn gitea_issues_closed_by_pr_description_are_commented_once() {
+        let server = MockServer::start().await;
+        let repo_path = "/api/v1/repos/owner/repo";
+        Mock::given(method("GET"))
+            .and(path(format!("{repo_path}/pulls/1")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "user": { "id": 1, "login": "contributor" },
+                "number": 1,
+                "html_url": "https://localhost/owner/repo/pulls/1",
+                "head": { "ref": "fix-bug", "sha": "abc" },
+                "title": "fix: bug",
+                "body": "Fixes #5, closes #6 and mentions #7",
+                "labels": [],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        mock_comments(&server, &format!("{repo_path}/issues/1/comments"), false).await;
+        mock_comments(&server, &format!("{repo_path}/issues/5/comments"), false).await;
+        mock_comments(&server, &format!("{repo_path}/issues/6/comments"), true).await;
+        Mock::given(method("GET"))
+            .and(path(format!("{repo_path}/issues/7/comments")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let url = RepoUrl::new(&format!("{}/owner/repo", server.uri())).unwrap();
+        let gitea = Gitea::new(url.clone(), SecretString::from("token")).unwrap();
+        let client = GitClient::new(GitForge::Gitea(gitea)).unwrap();
+
+        let repo_url = url.with_forge(ForgeType::Gitea);
+        comment_package_release(&client, None, TEMPLATE, &package_release(&repo_url))
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn release_comment_contains_marker() {
+        let package = PackageRelease {
+            package_name: "my_package".to_string(),
+            prs: vec![],
+            tag: "v1.2.3".to_string(),
+            version: Version::new(1, 2, 3),
+        };
+        let template = "Released in {{ package }} {{ version }}: {{ release_link }}\n";
+        let comment = release_comment(
+            template,
+            &package,
+            "https://github.com/owner/repo/releases/tag/v1.2.3",
+        )
+        .unwrap();
+        expect_test::expect![[r#"
+            Released in my_package 1.2.3: https://github.com/owner/repo/releases/tag/v1.2.3
+
+            <!-- release-plz-release-comment: my_package@1.2.3 -->"#]]
+        .assert_eq(&comment);
+    }
+}
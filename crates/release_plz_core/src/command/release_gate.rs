@@ -0,0 +1,149 @@
+use tracing::{info, instrument};
+
+use crate::git::{
+    forge::{GitClient, GitPr},
+    release_checks::{self, Check, CheckState},
+};
+
+/// Checks that the release PR must pass before `release-plz release` publishes anything.
+/// They protect against release PRs merged without following the branch protection rules,
+/// e.g. with an admin override.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReleaseGate {
+    /// Minimum number of users that must have approved the release PR.
+    required_approvals: u32,
+    /// Whether the CI of the last commit of the release PR must be green.
+    require_ci_success: bool,
+}
+
+impl ReleaseGate {
+    pub fn with_required_approvals(mut self, required_approvals: u32) -> Self {
+        self.required_approvals = required_approvals;
+        self
+    }
+
+    pub fn with_require_ci_success(mut self, require_ci_success: bool) -> Self {
+        self.require_ci_success = require_ci_success;
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.required_approvals > 0 || self.require_ci_success
+    }
+
+    /// Fails with a report of all the unmet requirements.
+    #[instrument(skip_all)]
+    pub(super) async fn check(
+        &self,
+        git_client: &GitClient,
+        release_pr: Option<&GitPr>,
+    ) -> anyhow::Result<()> {
+        let Some(pr) = release_pr else {
+            anyhow::bail!(
+                "release gate failed: the current commit doesn't come from a release PR, \
+                so release-plz can't verify its approvals and CI status"
+            );
+        };
+        let mut problems = vec![];
+        if self.required_approvals > 0 {
+            let approvers = release_checks::pr_approvers(git_client, pr.number).await?;
+            problems.extend(self.approval_problem(&approvers));
+        }
+        if self.require_ci_success {
+            let checks = release_checks::pr_checks(git_client, pr.number, &pr.head.sha).await?;
+            problems.extend(ci_problems(&pr.head.sha, &checks));
+        }
+        anyhow::ensure!(
+            problems.is_empty(),
+            "release gate failed for release PR #{} ({}):\n{}",
+            pr.number,
+            pr.html_url,
+            problems
+                .iter()
+                .map(|p| format!("- {p}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+        info!("release PR #{} passed the release gate", pr.number);
+        Ok(())
+    }
+
+    fn approval_problem(&self, approvers: &[String]) -> Option<String> {
+        let approvals = approvers.len();
+        (approvals < self.required_approvals as usize).then(|| {
+            let approved_by = if approvers.is_empty() {
+                String::new()
+            } else {
+                format!(" (approved by {})", approvers.join(", "))
+            };
+            format!(
+                "{approvals} approval(s){approved_by}, {} required",
+                self.required_approvals
+            )
+        })
+    }
+}
+
+fn ci_problems(sha: &str, checks: &[Check]) -> Vec<String> {
+    if checks.is_empty() {
+        return vec![format!("no CI status found for commit {sha}")];
+    }
+    checks
+        .iter()
+        .filter_map(|check| {
+            let state = match check.state {
+                CheckState::Success => return None,
+                CheckState::Pending => "is still running",
+                CheckState::Failure => "didn't succeed",
+            };
+            Some(format!("CI check `{}` {state} on commit {sha}", check.name))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_approvals_are_reported() {
+        let gate = ReleaseGate::default().with_required_approvals(2);
+        assert_eq!(
+            gate.approval_problem(&["alice".to_string()]).unwrap(),
+            "1 approval(s) (approved by alice), 2 required"
+        );
+        assert!(
+            gate.approval_problem(&["alice".to_string(), "bob".to_string()])
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn unsuccessful_checks_are_reported() {
+        let checks = [
+            Check {
+                name: "test".to_string(),
+                state: CheckState::Success,
+            },
+            Check {
+                name: "lint".to_string(),
+                state: CheckState::Failure,
+            },
+            Check {
+                name: "docs".to_string(),
+                state: CheckState::Pending,
+            },
+        ];
+        assert_eq!(
+            ci_problems("abc", &checks),
+            [
+                "CI check `lint` didn't succeed on commit abc",
+                "CI check `docs` is still running on commit abc"
+            ]
+        );
+        assert_eq!(
+            ci_problems("abc", &[]),
+            ["no CI status found for commit abc"]
+        );
+    }
+}
@@ -1,19 +1,27 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::sync::LazyLock;
+
+use cargo_metadata::DependencyKind;
 use cargo_metadata::camino::Utf8Path;
 use cargo_metadata::semver::Version;
-use cargo_utils::CARGO_TOML;
+use cargo_utils::{CARGO_TOML, LocalManifest};
 use git_cmd::Repo;
 
 use anyhow::Context;
+use regex::Regex;
 use serde::Serialize;
-use tracing::{debug, info, instrument};
+use tracing::{debug, info, instrument, warn};
 use url::Url;
 pub(crate) mod git;
 
+use crate::command::update::apply_updates;
+use crate::fs_utils::strip_prefix;
 use crate::git::forge::{
-    ForgeType, GitClient, GitPr, PrEdit, contributors_from_commits, validate_labels,
+    ForgeType, GitClient, GitPr, MergeMethod, PrCommit, PrEdit, contributors_from_commits,
+    validate_labels,
 };
-use crate::git::github_graphql;
-use crate::pr::{DEFAULT_BRANCH_PREFIX, OLD_BRANCH_PREFIX, Pr};
+use crate::git::{api_commit, github_graphql};
+use crate::pr::{DEFAULT_BRANCH_PREFIX, OLD_BRANCH_PREFIX, Pr, milestone_title};
 use crate::{
     PackagesUpdate, copy_to_temp_dir, new_manifest_dir_path, new_project_root,
     publishable_packages_from_manifest, root_repo_path_from_manifest_dir, update,
@@ -31,11 +39,51 @@ pub struct ReleasePrRequest {
     draft: bool,
     /// Labels to add to the release PR.
     labels: Vec<String>,
+    /// Usernames of the users to request a review from.
+    reviewers: Vec<String>,
+    /// Slugs of the teams to request a review from.
+    team_reviewers: Vec<String>,
+    /// Usernames of the users to assign the release PR to.
+    assignees: Vec<String>,
+    /// Tera template for the title of the milestone of the release PR.
+    milestone_template: Option<String>,
+    /// If set, merge the release PR with this method when its checks pass.
+    auto_merge: Option<MergeMethod>,
+    /// How to update the release PR when other people pushed commits to it.
+    update_strategy: PrUpdateStrategy,
     /// PR Branch Prefix
     branch_prefix: String,
+    /// How to split the package updates across release PRs.
+    pr_mode: PrMode,
     pub update_request: UpdateRequest,
 }
 
+/// How release-plz updates a release PR that contains commits of other people.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PrUpdateStrategy {
+    /// Close the release PR and open a new one, to preserve the commits of the contributors.
+    #[default]
+    Recreate,
+    /// Re-create the release-plz commit and rebase the commits of the contributors onto it.
+    /// If the rebase fails, fall back to [`PrUpdateStrategy::Recreate`].
+    Rebase,
+}
+
+/// How release-plz splits the package updates across release PRs.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PrMode {
+    /// One release PR containing the updates of all packages.
+    #[default]
+    Single,
+    /// One release PR per package.
+    /// Packages that depend on each other, or that inherit the workspace version,
+    /// share the same release PR.
+    PerPackage,
+    /// One release PR per `version_group`.
+    /// Packages without a `version_group` have their own release PR.
+    PerGroup,
+}
+
 impl ReleasePrRequest {
     pub fn new(update_request: UpdateRequest) -> Self {
         Self {
@@ -43,7 +91,14 @@ impl ReleasePrRequest {
             pr_body_template: None,
             draft: false,
             labels: vec![],
+            reviewers: vec![],
+            team_reviewers: vec![],
+            assignees: vec![],
+            milestone_template: None,
+            auto_merge: None,
+            update_strategy: PrUpdateStrategy::default(),
             branch_prefix: DEFAULT_BRANCH_PREFIX.to_string(),
+            pr_mode: PrMode::default(),
             update_request,
         }
     }
@@ -63,6 +118,36 @@ impl ReleasePrRequest {
         self
     }
 
+    pub fn with_reviewers(mut self, reviewers: Vec<String>) -> Self {
+        self.reviewers = reviewers;
+        self
+    }
+
+    pub fn with_team_reviewers(mut self, team_reviewers: Vec<String>) -> Self {
+        self.team_reviewers = team_reviewers;
+        self
+    }
+
+    pub fn with_assignees(mut self, assignees: Vec<String>) -> Self {
+        self.assignees = assignees;
+        self
+    }
+
+    pub fn with_milestone_template(mut self, milestone_template: Option<String>) -> Self {
+        self.milestone_template = milestone_template;
+        self
+    }
+
+    pub fn with_auto_merge(mut self, auto_merge: Option<MergeMethod>) -> Self {
+        self.auto_merge = auto_merge;
+        self
+    }
+
+    pub fn with_update_strategy(mut self, update_strategy: PrUpdateStrategy) -> Self {
+        self.update_strategy = update_strategy;
+        self
+    }
+
     pub fn mark_as_draft(mut self, draft: bool) -> Self {
         self.draft = draft;
         self
@@ -74,6 +159,11 @@ impl ReleasePrRequest {
         }
         self
     }
+
+    pub fn with_pr_mode(mut self, pr_mode: PrMode) -> Self {
+        self.pr_mode = pr_mode;
+        self
+    }
 }
 
 /// Release pull request that release-plz opened/updated.
@@ -118,9 +208,267 @@ pub struct PrPackageRelease {
 /// - [`ReleasePr`] if release-plz opened or updated a PR.
 /// - [`None`] if release-plz didn't open any pr. This happens when all packages
 ///   are up-to-date.
-#[instrument(skip_all)]
+///
+/// Only supports [`PrMode::Single`]. Use [`release_prs`] for the other modes.
 pub async fn release_pr(input: &ReleasePrRequest) -> anyhow::Result<Option<ReleasePr>> {
-    let manifest_dir = input.update_request.local_manifest_dir()?;
+    anyhow::ensure!(
+        input.pr_mode == PrMode::Single,
+        "`release_pr` opens a single release PR: use `release_prs` with `{:?}`",
+        input.pr_mode
+    );
+    let release_prs = release_prs(input).await?;
+    Ok(release_prs.into_iter().next())
+}
+
+/// Open pull requests with the next packages versions of a local rust project.
+/// Returns the [`ReleasePr`]s that release-plz opened or updated.
+/// The list is empty when all packages are up-to-date.
+#[instrument(skip_all)]
+pub async fn release_prs(input: &ReleasePrRequest) -> anyhow::Result<Vec<ReleasePr>> {
+    validate_labels(&input.labels)?;
+    let git_client = input
+        .update_request
+        .git_client()?
+        .context("can't find git client")?;
+    if let Some(merge_method) = input.auto_merge {
+        git_client.check_auto_merge_method(merge_method)?;
+    }
+
+    match input.pr_mode {
+        PrMode::Single => {
+            let opened_release_prs = opened_release_prs(&git_client, &input.branch_prefix).await?;
+            let release_pr = prepare_release_pr(
+                input,
+                None,
+                &git_client,
+                opened_release_prs,
+                &input.branch_prefix,
+            )
+            .await?;
+            Ok(release_pr.into_iter().collect())
+        }
+        PrMode::PerPackage | PrMode::PerGroup => release_pr_per_group(input, &git_client).await,
+    }
+}
+
+/// Open or update a release PR for every package (or version group) that needs a release,
+/// and close the release PRs of the packages that don't need a release anymore.
+async fn release_pr_per_group(
+    input: &ReleasePrRequest,
+    git_client: &GitClient,
+) -> anyhow::Result<Vec<ReleasePr>> {
+    // Analyze the workspace once, and apply the updates of each group to its own copy
+    // of the project.
+    let (packages_to_update, _temp_repository) = crate::next_versions(&input.update_request)
+        .await
+        .context("failed to determine the packages to update")?;
+    let mut opened_release_prs = git_client
+        .opened_prs(&input.branch_prefix)
+        .await
+        .context("cannot get opened release-plz prs")?;
+    let opened_groups: BTreeSet<&str> = opened_release_prs
+        .iter()
+        .filter_map(|pr| pr_group(pr.branch(), &input.branch_prefix))
+        .collect();
+    let groups = release_groups(input, &packages_to_update, &opened_groups)?;
+    debug!("release PR groups: {groups:?}");
+
+    let mut release_prs = vec![];
+    for (group, packages) in &groups {
+        let (group_prs, other_prs) = opened_release_prs
+            .into_iter()
+            .partition(|pr| pr_group(pr.branch(), &input.branch_prefix) == Some(group.as_str()));
+        opened_release_prs = other_prs;
+
+        let group_update = packages_update_of(&packages_to_update, packages)?;
+        let branch_prefix = group_branch_prefix(&input.branch_prefix, group);
+        let release_pr = prepare_release_pr(
+            input,
+            Some(group_update),
+            git_client,
+            group_prs,
+            &branch_prefix,
+        )
+        .await
+        .with_context(|| format!("failed to prepare the release PR of `{group}`"))?;
+        release_prs.extend(release_pr);
+    }
+
+    // The remaining PRs belong to packages that don't need a release anymore.
+    for pr in opened_release_prs {
+        info!("closing stale release pr {}", pr.html_url);
+        git_client
+            .close_pr(pr.number)
+            .await
+            .context("cannot close stale release-plz prs")?;
+    }
+    Ok(release_prs)
+}
+
+/// Packages that need a release, grouped by release PR.
+/// The key of the map is the name of the group, used in the branch name of the PR.
+///
+/// Packages that depend on each other, or that inherit the workspace version, are
+/// in the same group, so that every PR releases the dependents together with the
+/// new version of their dependencies.
+/// Dev-dependencies aren't published, so they don't group packages.
+fn release_groups(
+    input: &ReleasePrRequest,
+    packages_to_update: &PackagesUpdate,
+    opened_groups: &BTreeSet<&str>,
+) -> anyhow::Result<BTreeMap<String, Vec<String>>> {
+    let updated: HashSet<&str> = packages_to_update
+        .updates()
+        .iter()
+        .map(|(p, _)| p.name.as_str())
+        .collect();
+    let mut package_groups = vec![];
+    let mut related_packages = vec![];
+    let mut inherited_version_pkgs = vec![];
+    for (package, _) in packages_to_update.updates() {
+        let version_group = match input.pr_mode {
+            PrMode::PerGroup => {
+                input
+                    .update_request
+                    .get_package_config(&package.name)
+                    .version_group
+            }
+            PrMode::Single | PrMode::PerPackage => None,
+        };
+        let group = version_group.unwrap_or_else(|| package.name.to_string());
+        package_groups.push((package.name.as_str(), group));
+        for dependency in &package.dependencies {
+            if dependency.kind == DependencyKind::Development {
+                continue;
+            }
+            if let Some(dependency) = updated.get(dependency.name.as_str()) {
+                related_packages.push((package.name.as_str(), *dependency));
+            }
+        }
+        let manifest = LocalManifest::try_new(&package.manifest_path)?;
+        if manifest.version_is_inherited() {
+            inherited_version_pkgs.push(package.name.as_str());
+        }
+    }
+    related_packages.extend(
+        inherited_version_pkgs
+            .windows(2)
+            .map(|pkgs| (pkgs[0], pkgs[1])),
+    );
+    Ok(merge_groups(
+        package_groups,
+        &related_packages,
+        opened_groups,
+    ))
+}
+
+/// Group the packages, merging the groups of related packages.
+/// A merged group keeps the name of the group that already has an open release PR,
+/// so that the branch of the PR doesn't change when a package joins or leaves the group.
+/// Otherwise, it takes the name that comes first alphabetically.
+fn merge_groups(
+    package_groups: Vec<(&str, String)>,
+    related_packages: &[(&str, &str)],
+    opened_groups: &BTreeSet<&str>,
+) -> BTreeMap<String, Vec<String>> {
+    let mut group_of: BTreeMap<&str, String> = package_groups.into_iter().collect();
+    for (package, other) in related_packages {
+        let (Some(group), Some(other_group)) = (group_of.get(package), group_of.get(other)) else {
+            continue;
+        };
+        if group == other_group {
+            continue;
+        }
+        let group_key = |group: &String| (!opened_groups.contains(group.as_str()), group.clone());
+        let (kept, merged) = if group_key(group) < group_key(other_group) {
+            (group.clone(), other_group.clone())
+        } else {
+            (other_group.clone(), group.clone())
+        };
+        for group in group_of.values_mut() {
+            if *group == merged {
+                *group = kept.clone();
+            }
+        }
+    }
+    let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (package, group) in group_of {
+        groups.entry(group).or_default().push(package.to_string());
+    }
+    groups
+}
+
+/// Updates of the given packages.
+fn packages_update_of(
+    packages_to_update: &PackagesUpdate,
+    packages: &[String],
+) -> anyhow::Result<PackagesUpdate> {
+    let mut workspace_version_inherited = false;
+    let mut updates = vec![];
+    for (package, update) in packages_to_update.updates() {
+        if packages.contains(&package.name.to_string()) {
+            let manifest = LocalManifest::try_new(&package.manifest_path)?;
+            workspace_version_inherited |= manifest.version_is_inherited();
+            updates.push((package.clone(), update.clone()));
+        }
+    }
+    let mut group_update = PackagesUpdate::new(updates);
+    if workspace_version_inherited && let Some(version) = packages_to_update.workspace_version() {
+        group_update.with_workspace_version(version.clone());
+    }
+    Ok(group_update)
+}
+
+fn group_branch_prefix(branch_prefix: &str, group: &str) -> String {
+    format!("{branch_prefix}{group}-")
+}
+
+/// Group of a release PR branch created with [`group_branch_prefix`],
+/// e.g. `my-crate` for `release-plz-my-crate-2024-01-26T18-30-09Z`.
+/// Returns [`None`] for branches that don't belong to a group.
+fn pr_group<'a>(branch: &'a str, branch_prefix: &str) -> Option<&'a str> {
+    static BRANCH_DATE_RE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"-\d{4}-\d{2}-\d{2}T\d{2}-\d{2}-\d{2}Z$").unwrap());
+    let branch = branch.strip_prefix(branch_prefix)?;
+    let date = BRANCH_DATE_RE.find(branch)?;
+    let group = &branch[..date.start()];
+    (!group.is_empty()).then_some(group)
+}
+
+async fn opened_release_prs(
+    git_client: &GitClient,
+    branch_prefix: &str,
+) -> anyhow::Result<Vec<GitPr>> {
+    let mut opened_release_prs = git_client
+        .opened_prs(branch_prefix)
+        .await
+        .context("cannot get opened release-plz prs")?;
+
+    // Check if there are opened release-plz prs with the old prefix.
+    // This ensures retro-compatibility with the release-plz versions.
+    // TODO: Remove this check on release-plz v0.4.0.
+    if opened_release_prs.is_empty() {
+        opened_release_prs = git_client
+            .opened_prs(OLD_BRANCH_PREFIX)
+            .await
+            .context("cannot get opened release-plz prs")?;
+    }
+    Ok(opened_release_prs)
+}
+
+/// Update the packages in a copy of the project and open or update
+/// the release PR with the changes.
+/// If `packages_update` is [`None`], the updates are determined from the project.
+/// Returns [`None`] if the packages are up-to-date.
+async fn prepare_release_pr(
+    input: &ReleasePrRequest,
+    packages_update: Option<PackagesUpdate>,
+    git_client: &GitClient,
+    opened_release_prs: Vec<GitPr>,
+    branch_prefix: &str,
+) -> anyhow::Result<Option<ReleasePr>> {
+    let update_request = &input.update_request;
+    let manifest_dir = update_request.local_manifest_dir()?;
     let original_project_root = root_repo_path_from_manifest_dir(manifest_dir)?;
     let tmp_project_root_parent = copy_to_temp_dir(&original_project_root)?;
     let tmp_project_manifest_dir = new_manifest_dir_path(
@@ -128,26 +476,31 @@ pub async fn release_pr(input: &ReleasePrRequest) -> anyhow::Result<Option<Relea
         manifest_dir,
         tmp_project_root_parent.path(),
     )?;
-
-    validate_labels(&input.labels)?;
     let tmp_project_root =
         new_project_root(&original_project_root, tmp_project_root_parent.path())?;
 
     // NOTE: I was planning on using worktrees here too, but a bunch of the tests started failing
     // so I went back to using full copies.
     let local_manifest = tmp_project_manifest_dir.join(CARGO_TOML);
-    let new_update_request = input
-        .update_request
+    let new_update_request = update_request
         .clone()
         .set_local_manifest(&local_manifest)
         .context("can't find temporary project")?;
-    let (packages_to_update, _temp_repository) = update(&new_update_request)
-        .await
-        .context("failed to update packages")?;
-    let git_client = input
-        .update_request
-        .git_client()?
-        .context("can't find git client")?;
+    let (packages_to_update, _temp_repository) = match packages_update {
+        Some(packages_update) => {
+            let packages_update =
+                move_to_project(&packages_update, &original_project_root, &tmp_project_root)?;
+            apply_updates(&new_update_request, &packages_update)
+                .context("failed to update packages")?;
+            (packages_update, None)
+        }
+        None => {
+            let (packages_update, temp_repository) = update(&new_update_request)
+                .await
+                .context("failed to update packages")?;
+            (packages_update, Some(temp_repository))
+        }
+    };
 
     if !packages_to_update.updates().is_empty() {
         let unreleased_package_worktree_repo =
@@ -157,14 +510,21 @@ pub async fn release_pr(input: &ReleasePrRequest) -> anyhow::Result<Option<Relea
             let pr = open_or_update_release_pr(
                 &local_manifest,
                 &packages_to_update,
-                &git_client,
+                git_client,
                 &unreleased_package_worktree_repo,
+                opened_release_prs,
                 ReleasePrOptions {
                     draft: input.draft,
                     pr_name: input.pr_name_template.clone(),
                     pr_body: input.pr_body_template.clone(),
                     pr_labels: input.labels.clone(),
-                    pr_branch_prefix: input.branch_prefix.clone(),
+                    pr_reviewers: input.reviewers.clone(),
+                    pr_team_reviewers: input.team_reviewers.clone(),
+                    pr_assignees: input.assignees.clone(),
+                    pr_milestone: input.milestone_template.clone(),
+                    pr_auto_merge: input.auto_merge,
+                    pr_update_strategy: input.update_strategy,
+                    pr_branch_prefix: branch_prefix.to_string(),
                 },
             )
             .await?;
@@ -175,11 +535,37 @@ pub async fn release_pr(input: &ReleasePrRequest) -> anyhow::Result<Option<Relea
     Ok(None)
 }
 
+/// Point the packages to their copy in `new_root`.
+fn move_to_project(
+    packages_update: &PackagesUpdate,
+    original_root: &Utf8Path,
+    new_root: &Utf8Path,
+) -> anyhow::Result<PackagesUpdate> {
+    let workspace_version = packages_update.workspace_version().cloned();
+    let mut updates = packages_update.updates_clone();
+    for (package, _) in &mut updates {
+        let relative_path = strip_prefix(&package.manifest_path, original_root)?;
+        package.manifest_path = new_root.join(relative_path);
+    }
+    let mut packages_update = PackagesUpdate::new(updates);
+    if let Some(version) = workspace_version {
+        packages_update.with_workspace_version(version);
+    }
+    Ok(packages_update)
+}
+
 struct ReleasePrOptions {
     draft: bool,
     pr_name: Option<String>,
     pr_body: Option<String>,
     pr_labels: Vec<String>,
+    pr_reviewers: Vec<String>,
+    pr_team_reviewers: Vec<String>,
+    pr_assignees: Vec<String>,
+    /// Tera template of the milestone title.
+    pr_milestone: Option<String>,
+    pr_auto_merge: Option<MergeMethod>,
+    pr_update_strategy: PrUpdateStrategy,
     pr_branch_prefix: String,
 }
 
@@ -188,23 +574,9 @@ async fn open_or_update_release_pr(
     packages_to_update: &PackagesUpdate,
     git_client: &GitClient,
     repo: &Repo,
+    opened_release_prs: Vec<GitPr>,
     release_pr_options: ReleasePrOptions,
 ) -> anyhow::Result<ReleasePr> {
-    let mut opened_release_prs = git_client
-        .opened_prs(&release_pr_options.pr_branch_prefix)
-        .await
-        .context("cannot get opened release-plz prs")?;
-
-    // Check if there are opened release-plz prs with the old prefix.
-    // This ensures retro-compatibility with the release-plz versions.
-    // TODO: Remove this check on release-plz v0.4.0.
-    if opened_release_prs.is_empty() {
-        opened_release_prs = git_client
-            .opened_prs(OLD_BRANCH_PREFIX)
-            .await
-            .context("cannot get opened release-plz prs")?;
-    }
-
     // Close all release-plz prs, except one.
     let old_release_prs = opened_release_prs.iter().skip(1);
     for pr in old_release_prs {
@@ -227,6 +599,17 @@ async fn open_or_update_release_pr(
         )?
         .mark_as_draft(release_pr_options.draft)
         .with_labels(release_pr_options.pr_labels)
+        .with_reviewers(
+            release_pr_options.pr_reviewers,
+            release_pr_options.pr_team_reviewers,
+        )
+        .with_assignees(release_pr_options.pr_assignees)
+        .with_milestone(
+            release_pr_options
+                .pr_milestone
+                .map(|template| milestone_title(packages_to_update, &template))
+                .transpose()?,
+        )
     };
     let release_pr = match opened_release_prs.first() {
         Some(opened_pr) => {
@@ -236,11 +619,22 @@ async fn open_or_update_release_pr(
                 repo,
                 &new_pr,
                 &release_pr_options.pr_branch_prefix,
+                release_pr_options.pr_update_strategy,
             )
             .await
         }
         None => create_pr(git_client, repo, &new_pr).await,
     }?;
+    if let Some(merge_method) = release_pr_options.pr_auto_merge
+        && let Err(e) = git_client
+            .enable_auto_merge(release_pr.number, repo.original_branch(), merge_method)
+            .await
+    {
+        warn!(
+            "cannot enable auto-merge on release pr {}: {e:?}. Make sure that auto-merge is allowed in the repository settings and that the PR has required checks.",
+            release_pr.html_url
+        );
+    }
     let release_pr = ReleasePr {
         releases: packages_to_update
             .updates()
@@ -261,12 +655,26 @@ async fn handle_opened_pr(
     repo: &Repo,
     new_pr: &Pr,
     branch_prefix: &str,
+    update_strategy: PrUpdateStrategy,
 ) -> Result<ReleasePr, anyhow::Error> {
     let pr_commits = git_client
         .pr_commits(opened_pr.number)
         .await
         .context("cannot get commits of release-plz pr")?;
-    let pr_contributors = contributors_from_commits(&pr_commits, git_client.forge);
+    // Empty commits (e.g. to trigger the CI) don't contain any work.
+    let non_empty_commits: Vec<PrCommit> = match repo.fetch(opened_pr.branch()) {
+        Ok(()) => pr_commits
+            .iter()
+            .filter(|commit| !is_empty_commit(repo, &commit.sha))
+            .cloned()
+            .collect(),
+        Err(e) => {
+            debug!("cannot fetch release pr branch, so empty commits are not ignored: {e:?}");
+            pr_commits.clone()
+        }
+    };
+    let non_empty_commits = without_gitea_update_commits(non_empty_commits, opened_pr, git_client);
+    let pr_contributors = contributors_from_commits(&non_empty_commits, git_client.forge);
     Ok(if pr_contributors.is_empty() {
         // There are no contributors, so we can force-push
         // in this PR, because we don't care about the git history.
@@ -295,10 +703,24 @@ async fn handle_opened_pr(
             }
         }
     } else {
+        if update_strategy == PrUpdateStrategy::Rebase {
+            // The first commit is the one created by release-plz.
+            let contributor_commits: Vec<&str> = non_empty_commits
+                .iter()
+                .skip(1)
+                .filter(|commit| !commit.is_merge())
+                .map(|commit| commit.sha.as_str())
+                .collect();
+            match rebase_pr(git_client, opened_pr, &contributor_commits, repo, new_pr).await {
+                Ok(()) => return Ok(ReleasePr::new(opened_pr, new_pr.base_branch.clone())),
+                Err(e) => warn!(
+                    "cannot rebase the commits of {pr_contributors:?} in release pr {}: {e:?}. I'm closing the old release pr and opening a new one",
+                    opened_pr.html_url
+                ),
+            }
+        }
         // There's a contributor, so we don't want to force-push in this PR.
         // We close it because we want to save the contributor's work.
-        // TODO improvement: check how many lines the commit added, if no lines (for example a merge to update the branch),
-        //      then don't count it as a contributor.
         info!("closing pr {} to preserve git history", opened_pr.html_url);
         git_client
             .close_pr(opened_pr.number)
@@ -308,11 +730,292 @@ async fn handle_opened_pr(
     })
 }
 
+/// On Gitea, release-plz updates the PR by adding commits on top of the PR branch
+/// (see [`gitea_update_pr_branch`]). These commits are authored by the owner of the token,
+/// who is the author of the PR, and don't contain any work of the contributors.
+fn without_gitea_update_commits(
+    commits: Vec<PrCommit>,
+    pr: &GitPr,
+    git_client: &GitClient,
+) -> Vec<PrCommit> {
+    if git_client.forge != ForgeType::Gitea {
+        return commits;
+    }
+    commits
+        .into_iter()
+        .enumerate()
+        // The first commit is the release-plz one.
+        .filter(|(i, commit)| {
+            *i == 0
+                || commit
+                    .author
+                    .as_ref()
+                    .is_none_or(|author| author.login != pr.user.login)
+        })
+        .map(|(_, commit)| commit)
+        .collect()
+}
+
+fn is_empty_commit(repo: &Repo, sha: &str) -> bool {
+    repo.git(&[
+        "diff-tree",
+        "--root",
+        "--no-commit-id",
+        "--name-only",
+        "-r",
+        sha,
+    ])
+    .is_ok_and(|changed_files| changed_files.trim().is_empty())
+}
+
+/// Re-create the release-plz commit of the PR on top of the original branch
+/// and rebase the commits of the contributors onto it.
+/// If the rebase fails, the repository is restored to its previous state.
+async fn rebase_pr(
+    git_client: &GitClient,
+    opened_pr: &GitPr,
+    contributor_commits: &[&str],
+    repository: &Repo,
+    new_pr: &Pr,
+) -> anyhow::Result<()> {
+    let original_commit = repository.current_commit_hash()?;
+    // Save the release-plz changes, to restore them if the rebase fails.
+    // `git stash push` doesn't create a stash if the working tree is clean.
+    let has_changes = !repository.changes_except_typechanges()?.is_empty();
+    if has_changes {
+        repository.git(&["stash", "push", "--include-untracked"])?;
+        repository.git(&["stash", "apply"])?;
+    }
+    let rebase_result = if git_client.forge == ForgeType::Github {
+        github_rebase_pr_branch(git_client, opened_pr, contributor_commits, repository).await
+    } else if git_client.supports_api_commits().await {
+        api_rebase_pr_branch(git_client, opened_pr, contributor_commits, repository).await
+    } else {
+        warn_git_push_fallback(git_client).await;
+        rebase_pr_branch(opened_pr, contributor_commits, repository)
+    };
+    if let Err(e) = rebase_result {
+        // Restore the release-plz changes in the original branch,
+        // so that they can be committed in a new PR.
+        restore_release_changes(repository, &original_commit, has_changes).with_context(|| {
+            format!("cannot restore the repository after the failed rebase: {e:?}")
+        })?;
+        return Err(e);
+    }
+    if has_changes {
+        repository.git(&["stash", "drop"])?;
+    }
+    edit_pr(git_client, opened_pr, new_pr).await?;
+    info!(
+        "rebased the commits of the contributors in pr {}",
+        opened_pr.html_url
+    );
+    Ok(())
+}
+
+/// Discard the changes made by the rebase, including the ones of the contributor commits,
+/// and restore the release-plz changes saved in the stash, if any.
+fn restore_release_changes(
+    repository: &Repo,
+    original_commit: &str,
+    is_stashed: bool,
+) -> anyhow::Result<()> {
+    repository.git(&["checkout", "--force", repository.original_branch()])?;
+    repository.git(&["reset", "--hard", original_commit])?;
+    repository.git(&["clean", "--force", "-d"])?;
+    if is_stashed {
+        repository.stash_pop()?;
+    }
+    Ok(())
+}
+
+fn rebase_pr_branch(
+    opened_pr: &GitPr,
+    contributor_commits: &[&str],
+    repository: &Repo,
+) -> anyhow::Result<()> {
+    repository.git(&["checkout", "-B", opened_pr.branch()])?;
+    add_changes_and_commit(repository, &opened_pr.title)?;
+    cherry_pick(repository, contributor_commits)?;
+    repository.force_push(opened_pr.branch())?;
+    Ok(())
+}
+
+async fn github_rebase_pr_branch(
+    client: &GitClient,
+    opened_pr: &GitPr,
+    contributor_commits: &[&str],
+    repository: &Repo,
+) -> anyhow::Result<()> {
+    // Create the "Verified" release-plz commit with the GitHub API in a temporary branch.
+    // See `github_force_push` to learn why we don't commit in the release PR branch directly.
+    let tmp_release_branch = format!("{}-tmp-{}", opened_pr.branch(), rand::random::<u32>());
+    repository.checkout_new_branch(&tmp_release_branch)?;
+    let result = async {
+        github_create_release_branch(client, repository, &tmp_release_branch, &opened_pr.title)
+            .await?;
+        repository.fetch(&tmp_release_branch)?;
+        repository.git(&["reset", "--hard", "FETCH_HEAD"])?;
+        cherry_pick(repository, contributor_commits)?;
+        repository.push(&tmp_release_branch)?;
+        let sha = repository.current_commit_hash()?;
+        client
+            .patch_github_ref(&format!("heads/{}", opened_pr.branch()), &sha)
+            .await
+            .context("failed to force push PR branch")
+    }
+    .await;
+    // Delete the temporary branch if it was created. Even if the rebase failed.
+    if let Err(e) = client.delete_branch(&tmp_release_branch).await {
+        tracing::error!("cannot delete branch {tmp_release_branch}: {e:?}");
+    }
+    result
+}
+
+/// Rebase the PR branch with the GitLab or Gitea API, so that git credentials aren't needed.
+/// The commits are rebased locally, to detect conflicts before editing the PR branch.
+async fn api_rebase_pr_branch(
+    client: &GitClient,
+    opened_pr: &GitPr,
+    contributor_commits: &[&str],
+    repository: &Repo,
+) -> anyhow::Result<()> {
+    let base = repository.current_commit_hash()?;
+    repository.git(&["checkout", "-B", opened_pr.branch()])?;
+    // The commit is created again by the owner of the token, so it isn't signed off.
+    repository.add(&repository.changes_except_typechanges()?)?;
+    repository.commit(&opened_pr.title)?;
+    cherry_pick(repository, contributor_commits)?;
+    let result = if client.forge == ForgeType::Gitlab {
+        gitlab_replay_commits(client, opened_pr, repository, &base).await
+    } else {
+        gitea_update_pr_branch(client, opened_pr, repository, &opened_pr.title).await
+    };
+    fall_back_to_git_push(result, || repository.force_push(opened_pr.branch()))
+}
+
+/// If the API can't create a commit because of one of its changes
+/// (see [`api_commit::UnsupportedChange`]), push with git instead.
+fn fall_back_to_git_push(
+    api_result: anyhow::Result<()>,
+    git_push: impl FnOnce() -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let Err(e) = api_result else {
+        return Ok(());
+    };
+    match api_commit::unsupported_change(&e) {
+        Some(change) => {
+            warn!(
+                "{change}. Pushing the release PR branch with git, which requires git credentials"
+            );
+            git_push()
+        }
+        None => Err(e),
+    }
+}
+
+/// Recreate the commits from `base` to `HEAD` in the PR branch with the GitLab API.
+/// The first commit overwrites the PR branch, the others are added on top of it.
+/// If a commit can't be created, the PR branch is restored to its previous head.
+async fn gitlab_replay_commits(
+    client: &GitClient,
+    pr: &GitPr,
+    repository: &Repo,
+    base: &str,
+) -> anyhow::Result<()> {
+    let rebased_commits = repository.git(&["rev-list", "--reverse", &format!("{base}..HEAD")])?;
+    let rebased_commits: Vec<&str> = rebased_commits.lines().collect();
+    api_commit::check_commits(client, repository, &rebased_commits)?;
+    let mut sha = base.to_string();
+    for (i, commit) in rebased_commits.into_iter().enumerate() {
+        // The first commit is the release-plz one, so it's created by the owner of the token.
+        // The other commits keep the author of the contributor.
+        let preserve_author = i > 0;
+        match api_commit::replay_commit(
+            client,
+            repository,
+            commit,
+            preserve_author,
+            pr.branch(),
+            &sha,
+        )
+        .await
+        {
+            Ok(new_sha) => sha = new_sha,
+            // The PR branch wasn't edited yet.
+            Err(e) if i == 0 => return Err(e),
+            Err(e) => {
+                restore_gitlab_branch(client, pr).await.with_context(|| {
+                    format!("cannot restore the PR branch after the failed rebase: {e:?}")
+                })?;
+                return Err(e);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Point the PR branch to the commit it had before the rebase.
+/// The GitLab API can't force-update a branch to an existing commit,
+/// so the branch is deleted and created again. GitLab doesn't close the PR in the meantime.
+async fn restore_gitlab_branch(client: &GitClient, pr: &GitPr) -> anyhow::Result<()> {
+    client.delete_branch(pr.branch()).await?;
+    client.create_branch(pr.branch(), &pr.head.sha).await?;
+    Ok(())
+}
+
+/// Update the PR branch with the Gitea API, so that its files are the ones of the working tree.
+/// The Gitea API can't force-update a branch, and deleting the branch closes the PR,
+/// so the new commit goes on top of the current head of the PR branch.
+async fn gitea_update_pr_branch(
+    client: &GitClient,
+    pr: &GitPr,
+    repository: &Repo,
+    commit_message: &str,
+) -> anyhow::Result<()> {
+    repository.fetch(pr.branch())?;
+    let pr_head = repository.git(&["rev-parse", "FETCH_HEAD"])?;
+    api_commit::commit_changes(
+        client,
+        repository,
+        commit_message,
+        pr.branch(),
+        &pr_head,
+        false,
+    )
+    .await
+    .context("failed to update PR branch")?;
+    Ok(())
+}
+
+/// Apply the commits on top of the current branch.
+fn cherry_pick(repository: &Repo, commits: &[&str]) -> anyhow::Result<()> {
+    if commits.is_empty() {
+        return Ok(());
+    }
+    let mut args = vec!["cherry-pick", "--allow-empty"];
+    args.extend(commits);
+    if let Err(e) = repository.git(&args) {
+        // Get back to the state before "git cherry-pick" to clean the conflict.
+        repository.git(&["cherry-pick", "--abort"])?;
+        return Err(e.context("cannot rebase the commits of the release pr"));
+    }
+    Ok(())
+}
+
 async fn create_pr(git_client: &GitClient, repo: &Repo, pr: &Pr) -> anyhow::Result<ReleasePr> {
     repo.checkout_new_branch(&pr.branch)?;
     if git_client.forge == ForgeType::Github {
         github_create_release_branch(git_client, repo, &pr.branch, &pr.title).await?;
+    } else if git_client.supports_api_commits().await {
+        fall_back_to_git_push(
+            api_create_release_branch(git_client, repo, &pr.branch, &pr.title)
+                .await
+                .map(|_sha| ()),
+            || create_release_branch(repo, &pr.branch, &pr.title),
+        )?;
     } else {
+        warn_git_push_fallback(git_client).await;
         create_release_branch(repo, &pr.branch, &pr.title)?;
     }
     debug!("changes committed to release branch {}", pr.branch);
@@ -335,11 +1038,14 @@ async fn update_pr(
             repository.original_branch()
         )
     })?;
-    if git_client.forge == ForgeType::Github {
-        github_force_push(git_client, opened_pr, repository).await?;
-    } else {
-        force_push(opened_pr, repository)?;
-    }
+    push_release_commit(git_client, opened_pr, repository).await?;
+    edit_pr(git_client, opened_pr, new_pr).await?;
+    info!("updated pr {}", opened_pr.html_url);
+    Ok(())
+}
+
+/// Update the title, body, labels and assignments of the opened PR.
+async fn edit_pr(git_client: &GitClient, opened_pr: &GitPr, new_pr: &Pr) -> anyhow::Result<()> {
     let pr_edit = {
         let mut pr_edit = PrEdit::new();
         if opened_pr.title != new_pr.title {
@@ -358,7 +1064,10 @@ async fn update_pr(
             .add_labels(&new_pr.labels, opened_pr.number)
             .await?;
     }
-    info!("updated pr {}", opened_pr.html_url);
+    git_client
+        .assign_pr(new_pr, opened_pr.number)
+        .await
+        .context("failed to add reviewers, assignees and milestone")?;
     Ok(())
 }
 
@@ -417,9 +1126,65 @@ fn reset_branch(
     Ok(())
 }
 
+/// Overwrite the PR branch with a commit containing the release-plz changes.
+/// The parent of the commit is the current commit, i.e. the tip of the original branch.
+/// On Gitea, the commit goes on top of the PR branch instead (see [`gitea_update_pr_branch`]).
+async fn push_release_commit(
+    git_client: &GitClient,
+    pr: &GitPr,
+    repository: &Repo,
+) -> anyhow::Result<()> {
+    match git_client.forge {
+        ForgeType::Github => github_force_push(git_client, pr, repository).await,
+        ForgeType::Gitlab => {
+            fall_back_to_git_push(gitlab_force_push(git_client, pr, repository).await, || {
+                force_push(pr, repository)
+            })
+        }
+        ForgeType::Gitea if git_client.supports_api_commits().await => fall_back_to_git_push(
+            gitea_update_pr_branch(git_client, pr, repository, &pr.title).await,
+            || force_push(pr, repository),
+        ),
+        ForgeType::Gitea => {
+            warn_git_push_fallback(git_client).await;
+            force_push(pr, repository)
+        }
+    }
+}
+
+/// Servers older than Gitea 1.20 can't create commits with the API, so release-plz
+/// pushes with git, which requires git credentials.
+async fn warn_git_push_fallback(git_client: &GitClient) {
+    let server = git_client.gitea_server().await;
+    warn!(
+        "the {} server implements Gitea API {}, which can't create commits (added in Gitea 1.20). Pushing the release PR branch with git, which requires git credentials",
+        server.name(),
+        server.api_version
+    );
+}
+
 fn force_push(pr: &GitPr, repository: &Repo) -> anyhow::Result<()> {
     add_changes_and_commit(repository, &pr.title)?;
-    repository.force_push(pr.branch())?;
+    if let Err(e) = repository.force_push(pr.branch()) {
+        // Undo the commit, so that the changes can be committed in a new PR.
+        repository.git(&["reset", "HEAD~1"])?;
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Replace the release-plz commit of the PR branch using the GitLab API.
+/// The new commit is created on top of the current commit, which is the tip of the
+/// original branch, and the PR branch is overwritten with it.
+async fn gitlab_force_push(
+    client: &GitClient,
+    pr: &GitPr,
+    repository: &Repo,
+) -> anyhow::Result<()> {
+    let parent = repository.current_commit_hash()?;
+    api_commit::commit_changes(client, repository, &pr.title, pr.branch(), &parent, true)
+        .await
+        .context("failed to force push PR branch")?;
     Ok(())
 }
 
@@ -480,6 +1245,31 @@ fn create_release_branch(
     Ok(())
 }
 
+async fn api_create_release_branch(
+    client: &GitClient,
+    repository: &Repo,
+    release_branch: &str,
+    commit_message: &str,
+) -> anyhow::Result<String> {
+    let sha = repository.current_commit_hash()?;
+    // GitLab creates the branch when committing.
+    if client.forge == ForgeType::Gitea {
+        client.create_branch(release_branch, &sha).await?;
+    }
+    let sha = api_commit::commit_changes(
+        client,
+        repository,
+        commit_message,
+        release_branch,
+        &sha,
+        false,
+    )
+    .await
+    .with_context(|| format!("failed to create commit via API on branch `{release_branch}`"))?;
+    tracing::debug!("committed changes on branch `{release_branch}` via API");
+    Ok(sha)
+}
+
 async fn github_create_release_branch(
     client: &GitClient,
     repository: &Repo,
@@ -503,3 +1293,827 @@ fn add_changes_and_commit(repository: &Repo, commit_message: &str) -> anyhow::Re
     repository.commit_signed(commit_message)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use base64::prelude::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    #[test]
+    fn pr_group_is_parsed_from_branch() {
+        let prefix = "release-plz-";
+        let branch = format!(
+            "{}2024-01-26T18-30-09Z",
+            group_branch_prefix(prefix, "my-crate")
+        );
+        assert_eq!(branch, "release-plz-my-crate-2024-01-26T18-30-09Z");
+        assert_eq!(pr_group(&branch, prefix), Some("my-crate"));
+        // Branch of the `single` PR mode.
+        assert_eq!(pr_group("release-plz-2024-01-26T18-30-09Z", prefix), None);
+        assert_eq!(pr_group("release-plz-my-crate", prefix), None);
+        assert_eq!(pr_group("feature-2024-01-26T18-30-09Z", prefix), None);
+    }
+
+    #[test]
+    fn packages_without_relations_have_their_own_group() {
+        let package_groups = vec![
+            ("one", "one".to_string()),
+            ("two", "two".to_string()),
+            ("three", "numbers".to_string()),
+            ("four", "numbers".to_string()),
+        ];
+        let groups = merge_groups(package_groups, &[], &BTreeSet::new());
+        expect_test::expect![[r#"
+            {
+                "numbers": [
+                    "four",
+                    "three",
+                ],
+                "one": [
+                    "one",
+                ],
+                "two": [
+                    "two",
+                ],
+            }
+        "#]]
+        .assert_debug_eq(&groups);
+    }
+
+    #[test]
+    fn dependents_are_in_the_group_of_their_dependencies() {
+        // `bin` depends on `lib2`, which depends on `lib1`.
+        let package_groups = vec![
+            ("bin", "bin".to_string()),
+            ("lib1", "libs".to_string()),
+            ("lib2", "lib2".to_string()),
+            ("other", "other".to_string()),
+        ];
+        let related_packages = [("bin", "lib2"), ("lib2", "lib1")];
+        let groups = merge_groups(package_groups, &related_packages, &BTreeSet::new());
+        expect_test::expect![[r#"
+            {
+                "bin": [
+                    "bin",
+                    "lib1",
+                    "lib2",
+                ],
+                "other": [
+                    "other",
+                ],
+            }
+        "#]]
+        .assert_debug_eq(&groups);
+    }
+
+    #[test]
+    fn merged_group_keeps_the_name_of_its_open_release_pr() {
+        // `app` depends on `lib`, which already has a release PR.
+        let package_groups = vec![("app", "app".to_string()), ("lib", "lib".to_string())];
+        let groups = merge_groups(package_groups, &[("app", "lib")], &BTreeSet::from(["lib"]));
+        expect_test::expect![[r#"
+            {
+                "lib": [
+                    "app",
+                    "lib",
+                ],
+            }
+        "#]]
+        .assert_debug_eq(&groups);
+    }
+
+    const PR_BRANCH: &str = "release-plz-2024-01-26T18-30-09Z";
+
+    struct PrRepo {
+        clone: Repo,
+        origin_dir: tempfile::TempDir,
+        _clone_dir: tempfile::TempDir,
+        contributor_commits: Vec<String>,
+    }
+
+    /// Clone a repository with a release PR branch containing the release-plz commit and
+    /// a contributor commit for each of the given files and contents.
+    /// The clone contains the new release-plz changes, not committed yet.
+    fn repo_with_pr(contributions: &[(&str, &str)]) -> PrRepo {
+        let origin_dir = tempfile::tempdir().unwrap();
+        let origin = Repo::init(origin_dir.path());
+        origin.checkout_new_branch(PR_BRANCH).unwrap();
+        fs_err::write(origin_dir.path().join("version.txt"), "1").unwrap();
+        origin.add_all_and_commit("chore: release").unwrap();
+        let mut contributor_commits = vec![];
+        for (file, content) in contributions {
+            fs_err::write(origin_dir.path().join(file), content).unwrap();
+            origin.add_all_and_commit(&format!("edit {file}")).unwrap();
+            contributor_commits.push(origin.current_commit_hash().unwrap());
+        }
+        origin.checkout_head().unwrap();
+
+        let clone_dir = tempfile::tempdir().unwrap();
+        let clone_path = clone_dir.path().to_str().unwrap();
+        origin
+            .git(&[
+                "clone",
+                &format!("file://{}", origin.directory()),
+                clone_path,
+            ])
+            .unwrap();
+        for config in [
+            ["user.name", "author_name"],
+            ["user.email", "author@example.com"],
+        ] {
+            git_cmd::git_in_dir(Utf8Path::new(clone_path), &["config", config[0], config[1]])
+                .unwrap();
+        }
+        let clone = Repo::new(clone_path).unwrap();
+        clone.disable_gpg_signing().unwrap();
+        fs_err::write(clone_dir.path().join("version.txt"), "2").unwrap();
+        PrRepo {
+            clone,
+            origin_dir,
+            _clone_dir: clone_dir,
+            contributor_commits,
+        }
+    }
+
+    impl PrRepo {
+        fn contributor_commits(&self) -> Vec<&str> {
+            self.contributor_commits
+                .iter()
+                .map(String::as_str)
+                .collect()
+        }
+    }
+
+    fn opened_pr() -> GitPr {
+        serde_json::from_value(serde_json::json!({
+            "user": { "id": 1, "login": "release-plz" },
+            "number": 1,
+            "html_url": "https://localhost/owner/repo/pulls/1",
+            "head": { "ref": PR_BRANCH, "sha": "abc" },
+            "title": "chore: release",
+            "body": "",
+            "labels": [],
+        }))
+        .unwrap()
+    }
+
+    fn new_pr() -> Pr {
+        let pr = opened_pr();
+        Pr {
+            base_branch: "main".to_string(),
+            branch: pr.branch().to_string(),
+            title: pr.title,
+            body: String::new(),
+            draft: false,
+            labels: vec![],
+            reviewers: vec![],
+            team_reviewers: vec![],
+            assignees: vec![],
+            milestone: None,
+        }
+    }
+
+    const GITEA_REPO_PATH: &str = "/api/v1/repos/owner/repo";
+
+    /// Client of a Gitea server with the given version.
+    async fn gitea_client(server: &MockServer, version: &str) -> GitClient {
+        Mock::given(method("GET"))
+            .and(path("/api/v1/version"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "version": version })))
+            .mount(server)
+            .await;
+        let url = crate::RepoUrl::new(&format!("{}/owner/repo", server.uri())).unwrap();
+        let gitea = crate::Gitea::new(url, "token".into()).unwrap();
+        GitClient::new(crate::GitForge::Gitea(gitea)).unwrap()
+    }
+
+    /// Client of a Gitea server that can't create commits with the API,
+    /// so release-plz pushes with git.
+    async fn legacy_gitea_client(server: &MockServer) -> GitClient {
+        gitea_client(server, "1.19.0").await
+    }
+
+    /// The clone is on the original branch, with only the release-plz changes.
+    fn assert_release_changes_are_restored(repo: &PrRepo, original_commit: &str) {
+        let clone = &repo.clone;
+        let changes = clone.git(&["status", "--porcelain"]).unwrap();
+        assert_eq!(changes, "?? version.txt");
+        assert_eq!(
+            git_cmd::git_in_dir(clone.directory(), &["branch", "--show-current"]).unwrap(),
+            clone.original_branch()
+        );
+        assert_eq!(clone.current_commit_hash().unwrap(), original_commit);
+        let version = fs_err::read_to_string(clone.directory().join("version.txt")).unwrap();
+        assert_eq!(version, "2");
+        assert!(clone.git(&["stash", "list"]).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn contributor_commits_are_rebased_on_release_commit() {
+        let server = MockServer::start().await;
+        let repo = repo_with_pr(&[("notes.txt", "my notes")]);
+        let clone = &repo.clone;
+        rebase_pr(
+            &legacy_gitea_client(&server).await,
+            &opened_pr(),
+            &repo.contributor_commits(),
+            clone,
+            &new_pr(),
+        )
+        .await
+        .unwrap();
+
+        clone.fetch(PR_BRANCH).unwrap();
+        let log = clone
+            .git(&["log", "--format=%s", "-3", "FETCH_HEAD"])
+            .unwrap();
+        assert_eq!(log, "edit notes.txt\nchore: release\nadd README");
+        let version = clone.git(&["show", "FETCH_HEAD:version.txt"]).unwrap();
+        assert_eq!(version, "2");
+        let notes = clone.git(&["show", "FETCH_HEAD:notes.txt"]).unwrap();
+        assert_eq!(notes, "my notes");
+    }
+
+    #[tokio::test]
+    async fn release_changes_are_restored_when_rebase_conflicts() {
+        let server = MockServer::start().await;
+        // The PR branch isn't edited, because the conflict is detected locally.
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(0)
+            .mount(&server)
+            .await;
+        // The first commit is applied, then the second one conflicts.
+        let repo = repo_with_pr(&[
+            ("notes.txt", "my notes"),
+            ("version.txt", "contributor version"),
+        ]);
+        let clone = &repo.clone;
+        let original_commit = clone.current_commit_hash().unwrap();
+        let error = rebase_pr(
+            &gitea_client(&server, "1.21.0").await,
+            &opened_pr(),
+            &repo.contributor_commits(),
+            clone,
+            &new_pr(),
+        )
+        .await
+        .unwrap_err();
+        assert!(format!("{error:?}").contains("cannot rebase"), "{error:?}");
+        assert_release_changes_are_restored(&repo, &original_commit);
+    }
+
+    #[tokio::test]
+    async fn release_changes_are_restored_when_push_fails() {
+        let repo = repo_with_pr(&[("notes.txt", "my notes")]);
+        let clone = &repo.clone;
+        let original_commit = clone.current_commit_hash().unwrap();
+        // Somebody pushes to the PR branch, so the force-push is rejected.
+        let origin_dir = &repo.origin_dir;
+        let origin = Repo::new(Utf8Path::from_path(origin_dir.path()).unwrap()).unwrap();
+        origin.checkout(PR_BRANCH).unwrap();
+        fs_err::write(origin_dir.path().join("other.txt"), "other").unwrap();
+        origin.add_all_and_commit("edit other.txt").unwrap();
+        origin.checkout_head().unwrap();
+
+        let server = MockServer::start().await;
+        rebase_pr(
+            &legacy_gitea_client(&server).await,
+            &opened_pr(),
+            &repo.contributor_commits(),
+            clone,
+            &new_pr(),
+        )
+        .await
+        .unwrap_err();
+        assert_release_changes_are_restored(&repo, &original_commit);
+    }
+
+    #[tokio::test]
+    async fn release_changes_are_restored_when_github_rebase_fails() {
+        let server = wiremock::MockServer::start().await;
+        // Creating the temporary branch fails.
+        wiremock::Mock::given(wiremock::matchers::method("POST"))
+            .respond_with(wiremock::ResponseTemplate::new(422))
+            .mount(&server)
+            .await;
+        let github = crate::GitHub::new("owner".to_string(), "repo".to_string(), "token".into())
+            .with_base_url(format!("{}/", server.uri()).parse().unwrap());
+        let client = GitClient::new(crate::GitForge::Github(github)).unwrap();
+
+        let repo = repo_with_pr(&[("notes.txt", "my notes")]);
+        let clone = &repo.clone;
+        let original_commit = clone.current_commit_hash().unwrap();
+        rebase_pr(
+            &client,
+            &opened_pr(),
+            &repo.contributor_commits(),
+            clone,
+            &new_pr(),
+        )
+        .await
+        .unwrap_err();
+        assert_release_changes_are_restored(&repo, &original_commit);
+    }
+
+    #[tokio::test]
+    async fn release_commit_is_rebuilt_on_top_of_original_branch() {
+        let server = MockServer::start().await;
+        let client = legacy_gitea_client(&server).await;
+        let repo = repo_with_pr(&[]);
+        let clone = &repo.clone;
+        let main = clone.original_branch().to_string();
+        for run in 1..=2 {
+            // The original branch moves forward between runs.
+            clone.checkout(&main).unwrap();
+            fs_err::write(clone.directory().join("main.txt"), run.to_string()).unwrap();
+            clone.git(&["add", "main.txt"]).unwrap();
+            clone.git(&["commit", "-m", "edit main.txt"]).unwrap();
+            fs_err::write(clone.directory().join("version.txt"), run.to_string()).unwrap();
+            let main_tip = clone.current_commit_hash().unwrap();
+
+            let pr = opened_pr();
+            update_pr_branch(1, &pr, clone, DEFAULT_BRANCH_PREFIX).unwrap();
+            push_release_commit(&client, &pr, clone).await.unwrap();
+
+            clone.fetch(PR_BRANCH).unwrap();
+            let parent = clone.git(&["rev-parse", "FETCH_HEAD^"]).unwrap();
+            assert_eq!(parent, main_tip, "run {run}");
+            let log = clone
+                .git(&["log", "--format=%s", "-1", "FETCH_HEAD"])
+                .unwrap();
+            assert_eq!(log, "chore: release", "run {run}");
+            let version = clone.git(&["show", "FETCH_HEAD:version.txt"]).unwrap();
+            assert_eq!(version, run.to_string(), "run {run}");
+        }
+    }
+
+    #[tokio::test]
+    async fn contributor_commits_are_rebased_with_gitea_api() {
+        let server = MockServer::start().await;
+        let client = gitea_client(&server, "1.21.0").await;
+        let repo = repo_with_pr(&[("notes.txt", "my notes")]);
+        let clone = &repo.clone;
+        let origin = Repo::new(Utf8Path::from_path(repo.origin_dir.path()).unwrap()).unwrap();
+        let pr_branch_tip = origin.git(&["rev-parse", PR_BRANCH]).unwrap();
+        let old_version_blob = origin
+            .git(&["rev-parse", &format!("{PR_BRANCH}:version.txt")])
+            .unwrap();
+
+        // A single commit on top of the PR branch contains the rebased files,
+        // so the commits of the contributors stay in the PR.
+        Mock::given(method("POST"))
+            .and(path(format!("{GITEA_REPO_PATH}/contents")))
+            .and(body_partial_json(json!({
+                "branch": PR_BRANCH,
+                "message": "chore: release",
+                "files": [{
+                    "operation": "update",
+                    "path": "version.txt",
+                    "content": BASE64_STANDARD.encode("2"),
+                    "sha": old_version_blob,
+                }],
+            })))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .set_body_json(json!({ "commit": { "sha": "release-sha" } })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        // The PR branch isn't deleted and the PR isn't closed.
+        Mock::given(method("DELETE"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(0)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path(format!("{GITEA_REPO_PATH}/branches")))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        rebase_pr(
+            &client,
+            &opened_pr(),
+            &repo.contributor_commits(),
+            clone,
+            &new_pr(),
+        )
+        .await
+        .unwrap();
+        // Nothing is pushed with git.
+        assert_eq!(
+            origin.git(&["rev-parse", PR_BRANCH]).unwrap(),
+            pr_branch_tip
+        );
+    }
+
+    #[test]
+    fn gitea_update_commits_are_not_contributions() {
+        let commit = |sha: &str, login: &str| -> PrCommit {
+            serde_json::from_value(json!({ "sha": sha, "author": { "id": 1, "login": login } }))
+                .unwrap()
+        };
+        let commits = vec![
+            commit("release", "release-plz"),
+            commit("notes", "contributor"),
+            commit("update", "release-plz"),
+        ];
+        let server_url = "https://localhost/owner/repo";
+        let gitea = crate::Gitea::new(crate::RepoUrl::new(server_url).unwrap(), "token".into());
+        let client = GitClient::new(crate::GitForge::Gitea(gitea.unwrap())).unwrap();
+        let commits = without_gitea_update_commits(commits, &opened_pr(), &client);
+        let shas: Vec<&str> = commits.iter().map(|c| c.sha.as_str()).collect();
+        assert_eq!(shas, ["release", "notes"]);
+    }
+
+    const GITLAB_REPO_PATH: &str = "/api/v4/projects/owner%2Frepo";
+
+    fn gitlab_client(server: &MockServer) -> GitClient {
+        let url = crate::RepoUrl::new(&format!("{}/owner/repo", server.uri())).unwrap();
+        let gitlab = crate::GitLab::new(url, "token".into()).unwrap();
+        GitClient::new(crate::GitForge::Gitlab(gitlab)).unwrap()
+    }
+
+    /// Mock the creation of the release-plz commit, which overwrites the PR branch.
+    async fn mock_gitlab_release_commit(server: &MockServer, base: &str) {
+        Mock::given(method("POST"))
+            .and(path(format!("{GITLAB_REPO_PATH}/repository/commits")))
+            .and(body_partial_json(json!({
+                "branch": PR_BRANCH,
+                "commit_message": "chore: release",
+                "start_sha": base,
+                "force": true,
+            })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({ "id": "release-sha" })))
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn contributor_commits_are_rebased_with_gitlab_api() {
+        let server = MockServer::start().await;
+        let repo = repo_with_pr(&[("notes.txt", "my notes")]);
+        let clone = &repo.clone;
+        let base = clone.current_commit_hash().unwrap();
+
+        mock_gitlab_release_commit(&server, &base).await;
+        Mock::given(method("POST"))
+            .and(path(format!("{GITLAB_REPO_PATH}/repository/commits")))
+            .and(body_partial_json(json!({
+                "branch": PR_BRANCH,
+                "commit_message": "edit notes.txt",
+                "start_sha": "release-sha",
+                "author_email": "author@example.com",
+            })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({ "id": "notes-sha" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        // The commits are created directly in the PR branch.
+        Mock::given(method("DELETE"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        gitlab_replay(&server, &repo).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn gitlab_pr_branch_is_restored_when_rebase_fails() {
+        let server = MockServer::start().await;
+        let repo = repo_with_pr(&[("notes.txt", "my notes")]);
+        let clone = &repo.clone;
+        let original_commit = clone.current_commit_hash().unwrap();
+
+        mock_gitlab_release_commit(&server, &original_commit).await;
+        Mock::given(method("POST"))
+            .and(path(format!("{GITLAB_REPO_PATH}/repository/commits")))
+            .and(body_partial_json(
+                json!({ "commit_message": "edit notes.txt" }),
+            ))
+            .respond_with(ResponseTemplate::new(400))
+            .expect(1)
+            .mount(&server)
+            .await;
+        // The PR branch points to its previous head again.
+        let branches_path = format!("{GITLAB_REPO_PATH}/repository/branches");
+        Mock::given(method("DELETE"))
+            .and(path(format!(
+                "{branches_path}/{}",
+                urlencoding::encode(PR_BRANCH)
+            )))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path(&branches_path))
+            .and(body_partial_json(
+                json!({ "branch": PR_BRANCH, "ref": opened_pr().head.sha }),
+            ))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let error = gitlab_replay(&server, &repo).await.unwrap_err();
+        assert!(
+            format!("{error:?}").contains("failed to recreate commit"),
+            "{error:?}"
+        );
+        assert_release_changes_are_restored(&repo, &original_commit);
+    }
+
+    async fn gitlab_replay(server: &MockServer, repo: &PrRepo) -> anyhow::Result<()> {
+        rebase_pr(
+            &gitlab_client(server),
+            &opened_pr(),
+            &repo.contributor_commits(),
+            &repo.clone,
+            &new_pr(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn other_stashes_are_kept_when_rebase_fails_on_clean_tree() {
+        let server = MockServer::start().await;
+        let repo = repo_with_pr(&[("notes.txt", "my notes")]);
+        let clone = &repo.clone;
+        // The working tree is clean, but the stash contains unrelated changes.
+        clone
+            .git(&["stash", "push", "--include-untracked"])
+            .unwrap();
+        let original_commit = clone.current_commit_hash().unwrap();
+
+        // There are no release-plz changes to commit, so the rebase fails.
+        rebase_pr(
+            &legacy_gitea_client(&server).await,
+            &opened_pr(),
+            &repo.contributor_commits(),
+            clone,
+            &new_pr(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(clone.current_commit_hash().unwrap(), original_commit);
+        assert!(clone.git(&["status", "--porcelain"]).unwrap().is_empty());
+        let stashes = clone.git(&["stash", "list"]).unwrap();
+        assert_eq!(stashes.lines().count(), 1, "{stashes}");
+    }
+
+    /// Mock the creation of the branch of a new release PR from `base`
+    /// and of its release commit with the Gitea API.
+    async fn mock_gitea_new_release_pr(server: &MockServer, base: &str, branch: &str) {
+        Mock::given(method("POST"))
+            .and(path(format!("{GITEA_REPO_PATH}/branches")))
+            .and(body_partial_json(
+                json!({ "new_branch_name": branch, "old_ref_name": base }),
+            ))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path(format!("{GITEA_REPO_PATH}/contents")))
+            .and(body_partial_json(
+                json!({ "branch": branch, "message": "chore: release" }),
+            ))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .set_body_json(json!({ "commit": { "sha": "release-sha" } })),
+            )
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    /// Mock the commit that updates the PR branch with the Gitea API,
+    /// which contains the changes of the original branch and the new release-plz changes.
+    async fn mock_gitea_update_commit(server: &MockServer, origin: &Repo, status: u16) {
+        let old_version_blob = origin
+            .git(&["rev-parse", &format!("{PR_BRANCH}:version.txt")])
+            .unwrap();
+        Mock::given(method("POST"))
+            .and(path(format!("{GITEA_REPO_PATH}/contents")))
+            .and(body_partial_json(json!({
+                "branch": PR_BRANCH,
+                "message": "chore: release",
+                "files": [
+                    {
+                        "operation": "create",
+                        "path": "main.txt",
+                        "content": BASE64_STANDARD.encode("main"),
+                    },
+                    {
+                        "operation": "update",
+                        "path": "version.txt",
+                        "content": BASE64_STANDARD.encode("2"),
+                        "sha": old_version_blob,
+                    },
+                ],
+            })))
+            .respond_with(
+                ResponseTemplate::new(status)
+                    .set_body_json(json!({ "commit": { "sha": "update-sha" } })),
+            )
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    /// The original branch moves forward after the release PR was opened.
+    fn commit_in_original_branch(clone: &Repo) -> String {
+        let version = fs_err::read_to_string(clone.directory().join("version.txt")).unwrap();
+        fs_err::remove_file(clone.directory().join("version.txt")).unwrap();
+        fs_err::write(clone.directory().join("main.txt"), "main").unwrap();
+        clone.add_all_and_commit("edit main.txt").unwrap();
+        fs_err::write(clone.directory().join("version.txt"), version).unwrap();
+        clone.current_commit_hash().unwrap()
+    }
+
+    #[tokio::test]
+    async fn release_pr_is_updated_with_gitea_api() {
+        let server = MockServer::start().await;
+        let client = gitea_client(&server, "1.21.0").await;
+        let repo = repo_with_pr(&[]);
+        let clone = &repo.clone;
+        let origin = Repo::new(Utf8Path::from_path(repo.origin_dir.path()).unwrap()).unwrap();
+        commit_in_original_branch(clone);
+        let pr_branch_tip = origin.git(&["rev-parse", PR_BRANCH]).unwrap();
+
+        mock_gitea_update_commit(&server, &origin, 201).await;
+        // The PR branch isn't deleted and the PR isn't closed.
+        Mock::given(method("DELETE"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(0)
+            .mount(&server)
+            .await;
+        Mock::given(method("PATCH"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        update_pr(
+            &client,
+            &opened_pr(),
+            1,
+            clone,
+            &new_pr(),
+            DEFAULT_BRANCH_PREFIX,
+        )
+        .await
+        .unwrap();
+        // Nothing is pushed with git.
+        assert_eq!(
+            origin.git(&["rev-parse", PR_BRANCH]).unwrap(),
+            pr_branch_tip
+        );
+    }
+
+    #[tokio::test]
+    async fn release_pr_is_pushed_with_git_when_gitea_api_cant_commit_changes() {
+        let server = MockServer::start().await;
+        let client = gitea_client(&server, "1.21.0").await;
+        let repo = repo_with_pr(&[]);
+        let clone = &repo.clone;
+        // The original branch makes a script executable,
+        // which can't be done with the Gitea API.
+        let script = clone.directory().join("run.sh");
+        fs_err::write(&script, "echo hi").unwrap();
+        clone.git(&["add", "--chmod=+x", "run.sh"]).unwrap();
+        clone.git(&["commit", "-m", "add run.sh"]).unwrap();
+        fs_err::remove_file(&script).unwrap();
+        clone.git(&["checkout", "--", "run.sh"]).unwrap();
+
+        Mock::given(method("POST"))
+            .and(path(format!("{GITEA_REPO_PATH}/contents")))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        update_pr(
+            &client,
+            &opened_pr(),
+            1,
+            clone,
+            &new_pr(),
+            DEFAULT_BRANCH_PREFIX,
+        )
+        .await
+        .unwrap();
+        let origin = Repo::new(Utf8Path::from_path(repo.origin_dir.path()).unwrap()).unwrap();
+        let script_entry = origin.git(&["ls-tree", PR_BRANCH, "run.sh"]).unwrap();
+        assert!(script_entry.starts_with("100755"), "{script_entry}");
+        let version = origin
+            .git(&["show", &format!("{PR_BRANCH}:version.txt")])
+            .unwrap();
+        assert_eq!(version, "2");
+    }
+
+    #[tokio::test]
+    async fn release_pr_is_replaced_when_gitea_update_fails() {
+        let server = MockServer::start().await;
+        let client = gitea_client(&server, "1.21.0").await;
+        let repo = repo_with_pr(&[]);
+        let clone = &repo.clone;
+        let origin = Repo::new(Utf8Path::from_path(repo.origin_dir.path()).unwrap()).unwrap();
+        let release_commit = origin.git(&["rev-parse", PR_BRANCH]).unwrap();
+        let main_tip = commit_in_original_branch(clone);
+
+        Mock::given(method("GET"))
+            .and(path(format!("{GITEA_REPO_PATH}/pulls/1/commits")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+                "sha": release_commit,
+                "author": { "id": 1, "login": "release-plz" },
+            }])))
+            .mount(&server)
+            .await;
+        // The PR branch can't be updated, e.g. because somebody pushed to it.
+        mock_gitea_update_commit(&server, &origin, 409).await;
+        Mock::given(method("PATCH"))
+            .and(path(format!("{GITEA_REPO_PATH}/pulls/1")))
+            .and(body_partial_json(json!({ "state": "closed" })))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let new_pr = Pr {
+            branch: "release-plz-2024-01-27T18-30-09Z".to_string(),
+            ..new_pr()
+        };
+        // The branch of the new PR is created from the tip of the original branch.
+        mock_gitea_new_release_pr(&server, &main_tip, &new_pr.branch).await;
+        Mock::given(method("POST"))
+            .and(path(format!("{GITEA_REPO_PATH}/pulls")))
+            .and(body_partial_json(json!({ "head": new_pr.branch })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "user": { "id": 1, "login": "release-plz" },
+                "number": 2,
+                "html_url": "https://localhost/owner/repo/pulls/2",
+                "head": { "ref": new_pr.branch, "sha": "release-sha" },
+                "title": "chore: release",
+                "body": "",
+                "labels": [],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let release_pr = handle_opened_pr(
+            &client,
+            &opened_pr(),
+            clone,
+            &new_pr,
+            DEFAULT_BRANCH_PREFIX,
+            PrUpdateStrategy::Recreate,
+        )
+        .await
+        .unwrap();
+        assert_eq!(release_pr.number, 2);
+    }
+
+    #[tokio::test]
+    async fn release_commit_is_undone_when_git_push_fails() {
+        let server = MockServer::start().await;
+        let client = legacy_gitea_client(&server).await;
+        let repo = repo_with_pr(&[]);
+        let clone = &repo.clone;
+        let main_tip = clone.current_commit_hash().unwrap();
+        clone
+            .git(&["remote", "set-url", "--push", "origin", "/nonexistent"])
+            .unwrap();
+
+        let pr = opened_pr();
+        update_pr_branch(1, &pr, clone, DEFAULT_BRANCH_PREFIX).unwrap();
+        push_release_commit(&client, &pr, clone).await.unwrap_err();
+
+        // The changes can be committed in a new PR.
+        assert_eq!(clone.current_commit_hash().unwrap(), main_tip);
+        let changes = clone.git(&["status", "--porcelain"]).unwrap();
+        assert_eq!(changes, "?? version.txt");
+    }
+
+    #[test]
+    fn empty_commits_are_detected() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = Repo::init(tmp.path());
+        let readme_commit = repo.current_commit_hash().unwrap();
+        repo.git(&["commit", "--allow-empty", "-m", "trigger ci"])
+            .unwrap();
+        let empty_commit = repo.current_commit_hash().unwrap();
+        assert!(!is_empty_commit(&repo, &readme_commit));
+        assert!(is_empty_commit(&repo, &empty_commit));
+    }
+}
@@ -36,20 +36,44 @@ pub struct ReleaseInfo {
     semver_check: String,
 }
 
+impl ReleaseInfo {
+    /// Release with sample values, used to check the templates of the configuration.
+    pub(crate) fn sample(package: &str, version: &str) -> Self {
+        Self {
+            package: package.to_string(),
+            title: Some(format!("[{version}] - 2024-01-01")),
+            changelog: Some("### Added\n\n- new feature".to_string()),
+            previous_version: "1.2.2".to_string(),
+            next_version: version.to_string(),
+            breaking_changes: None,
+            semver_check: "compatible".to_string(),
+        }
+    }
+}
+
 /// Update a local Rust project.
 #[instrument(skip_all)]
 pub async fn update(input: &UpdateRequest) -> anyhow::Result<(PackagesUpdate, TempRepo)> {
     let (packages_to_update, repository) = crate::next_versions(input)
         .await
         .context("failed to determine next versions")?;
+    apply_updates(input, &packages_to_update)?;
+    Ok((packages_to_update, repository))
+}
+
+/// Write the new versions and changelogs of the packages to the local project.
+pub(crate) fn apply_updates(
+    input: &UpdateRequest,
+    packages_to_update: &PackagesUpdate,
+) -> anyhow::Result<()> {
     let local_manifest_path = input.local_manifest();
     let local_metadata = cargo_utils::get_manifest_metadata(local_manifest_path)?;
     // Read packages from `local_metadata` to update the manifest of local
     // workspace dependencies.
     let all_packages: Vec<Package> = cargo_utils::workspace_members(&local_metadata)?.collect();
     let all_packages_ref: Vec<&Package> = all_packages.iter().collect();
-    update_manifests(&packages_to_update, local_manifest_path, &all_packages_ref)?;
-    update_changelogs(input, &packages_to_update)?;
+    update_manifests(packages_to_update, local_manifest_path, &all_packages_ref)?;
+    update_changelogs(input, packages_to_update)?;
     if !packages_to_update.updates().is_empty() {
         let local_manifest_dir = input.local_manifest_dir()?;
         update_cargo_lock(local_manifest_dir, input.should_update_dependencies())?;
@@ -60,8 +84,7 @@ pub async fn update(input: &UpdateRequest) -> anyhow::Result<(PackagesUpdate, Te
             info!("the repository is already up-to-date");
         }
     }
-
-    Ok((packages_to_update, repository))
+    Ok(())
 }
 
 fn update_manifests(
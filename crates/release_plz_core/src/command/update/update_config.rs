@@ -1,5 +1,9 @@
-use cargo_metadata::camino::Utf8PathBuf;
-use next_version::VersionUpdater;
+use cargo_metadata::{
+    camino::Utf8PathBuf,
+    semver::{Version, VersionReq},
+};
+use next_version::{NextVersion as _, VersionUpdater};
+use tracing::warn;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct UpdateConfig {
@@ -30,6 +34,13 @@ pub struct UpdateConfig {
     pub custom_major_increment_regex: Option<String>,
     /// Whether to use git tags instead of registry for determining package versions.
     pub git_only: Option<bool>,
+    /// Range of versions the package is allowed to have, e.g. `<2.0.0`
+    /// in a maintenance branch of `1.x`.
+    pub version_constraint: Option<VersionReq>,
+    /// - If `true`, a next version that doesn't satisfy the [`UpdateConfig::version_constraint`]
+    ///   is replaced by a smaller increment that satisfies it.
+    /// - If `false` (default), it's an error.
+    pub version_constraint_clamp: bool,
 }
 
 /// Package-specific config
@@ -84,6 +95,8 @@ impl Default for UpdateConfig {
             changelog_path: None,
             custom_minor_increment_regex: None,
             custom_major_increment_regex: None,
+            version_constraint: None,
+            version_constraint_clamp: false,
         }
     }
 }
@@ -117,6 +130,57 @@ impl UpdateConfig {
         Self { publish, ..self }
     }
 
+    pub fn with_version_constraint(self, version_constraint: VersionReq) -> Self {
+        Self {
+            version_constraint: Some(version_constraint),
+            ..self
+        }
+    }
+
+    pub fn with_version_constraint_clamp(self, version_constraint_clamp: bool) -> Self {
+        Self {
+            version_constraint_clamp,
+            ..self
+        }
+    }
+
+    /// Make sure that `next` satisfies the [`UpdateConfig::version_constraint`].
+    /// If it doesn't and [`UpdateConfig::version_constraint_clamp`] is enabled, fall back
+    /// to a smaller increment of the `current` version (minor, then patch) that satisfies it.
+    /// Fails if no increment satisfies the constraint.
+    pub fn constrained_version(
+        &self,
+        package: &str,
+        current: &Version,
+        next: Version,
+    ) -> anyhow::Result<Version> {
+        let Some(constraint) = &self.version_constraint else {
+            return Ok(next);
+        };
+        if satisfies(constraint, &next) {
+            return Ok(next);
+        }
+        anyhow::ensure!(
+            self.version_constraint_clamp,
+            "{package}: next version {next} doesn't satisfy the version constraint `{constraint}`. \
+            Set `version_constraint_clamp = true` to use a smaller increment instead"
+        );
+        let fallback = [current.increment_minor(), current.increment_patch()]
+            .into_iter()
+            .find(|v| v < &next && v > current && satisfies(constraint, v));
+        match fallback {
+            Some(fallback) => {
+                warn!(
+                    "{package}: next version {next} doesn't satisfy the version constraint `{constraint}`. Using {fallback} instead"
+                );
+                Ok(fallback)
+            }
+            None => anyhow::bail!(
+                "{package}: next version {next} doesn't satisfy the version constraint `{constraint}`"
+            ),
+        }
+    }
+
     pub fn version_updater(&self) -> Result<VersionUpdater, regex::Error> {
         let mut updater = VersionUpdater::default()
             .with_features_always_increment_minor(self.features_always_increment_minor);
@@ -130,10 +194,17 @@ impl UpdateConfig {
     }
 }
 
+/// Like [`VersionReq::matches`], but pre-releases match the requirements
+/// of their final version.
+/// E.g. `1.2.0-rc.1` satisfies `<2.0.0`.
+fn satisfies(constraint: &VersionReq, version: &Version) -> bool {
+    let release = Version::new(version.major, version.minor, version.patch);
+    constraint.matches(&release)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use cargo_metadata::semver::Version;
 
     #[test]
     fn version_updater_with_custom_minor_regex() {
@@ -182,4 +253,62 @@ mod tests {
         let new_version = updater.increment(&version, commits);
         assert_eq!(new_version, Version::new(2, 0, 0));
     }
+
+    #[test]
+    fn version_inside_constraint_is_kept() {
+        let config = UpdateConfig::default().with_version_constraint("<2.0.0".parse().unwrap());
+        let next = config
+            .constrained_version("a", &Version::new(1, 2, 3), Version::new(1, 3, 0))
+            .unwrap();
+        assert_eq!(next, Version::new(1, 3, 0));
+    }
+
+    #[test]
+    fn breaking_change_outside_constraint_is_an_error() {
+        let config = UpdateConfig::default().with_version_constraint("<2.0.0".parse().unwrap());
+        let error = config
+            .constrained_version("a", &Version::new(1, 2, 3), Version::new(2, 0, 0))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "a: next version 2.0.0 doesn't satisfy the version constraint `<2.0.0`. \
+            Set `version_constraint_clamp = true` to use a smaller increment instead"
+        );
+    }
+
+    #[test]
+    fn breaking_change_is_clamped_to_constraint() {
+        let config = UpdateConfig::default()
+            .with_version_constraint("<2.0.0".parse().unwrap())
+            .with_version_constraint_clamp(true);
+        let next = config
+            .constrained_version("a", &Version::new(1, 2, 3), Version::new(2, 0, 0))
+            .unwrap();
+        assert_eq!(next, Version::new(1, 3, 0));
+    }
+
+    #[test]
+    fn version_is_clamped_to_patch() {
+        let config = UpdateConfig::default()
+            .with_version_constraint("~0.4".parse().unwrap())
+            .with_version_constraint_clamp(true);
+        let next = config
+            .constrained_version("a", &Version::new(0, 4, 3), Version::new(0, 5, 0))
+            .unwrap();
+        assert_eq!(next, Version::new(0, 4, 4));
+    }
+
+    #[test]
+    fn version_outside_constraint_is_an_error() {
+        let config = UpdateConfig::default()
+            .with_version_constraint(">=1.0.0, <1.3.0".parse().unwrap())
+            .with_version_constraint_clamp(true);
+        let error = config
+            .constrained_version("a", &Version::new(1, 3, 0), Version::new(1, 3, 1))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "a: next version 1.3.1 doesn't satisfy the version constraint `>=1.0.0, <1.3.0`"
+        );
+    }
 }
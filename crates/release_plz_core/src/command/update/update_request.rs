@@ -48,6 +48,8 @@ pub struct UpdateRequest {
     release_commits: Option<Regex>,
     git: Option<GitForge>,
     max_analyze_commits: Option<u32>,
+    /// If true, compute the template context of the new changelog entries.
+    changelog_context: bool,
 }
 
 impl UpdateRequest {
@@ -68,6 +70,7 @@ impl UpdateRequest {
             release_commits: None,
             git: None,
             max_analyze_commits: None,
+            changelog_context: false,
         })
     }
 
@@ -115,6 +118,19 @@ impl UpdateRequest {
         }
     }
 
+    pub fn changelog_context(&self) -> bool {
+        self.changelog_context
+    }
+
+    /// Compute the template context of the new changelog entries,
+    /// e.g. to show it in a changelog preview.
+    pub fn with_changelog_context(self, changelog_context: bool) -> Self {
+        Self {
+            changelog_context,
+            ..self
+        }
+    }
+
     pub fn with_max_analyze_commits(self, max_commits: Option<u32>) -> Self {
         Self {
             max_analyze_commits: max_commits,
@@ -167,9 +167,8 @@ impl Updater<'_> {
 
         for (pkg, diff) in packages_diffs {
             let pkg_config = self.req.get_package_config(&pkg.name);
-            let version_updater = pkg_config.generic.version_updater()?;
             if let Some(version_group) = pkg_config.version_group {
-                let next_pkg_ver = pkg.version.next_from_diff(diff, version_updater);
+                let next_pkg_ver = self.next_version_from_diff(pkg, diff)?;
                 match version_groups.entry(version_group.clone()) {
                     std::collections::hash_map::Entry::Occupied(v) => {
                         // maximum version of the group until now
@@ -202,9 +201,7 @@ impl Updater<'_> {
         for workspace_package in workspace_version_pkgs {
             for (p, diff) in packages_diffs {
                 if *workspace_package == *p.name {
-                    let pkg_config = self.req.get_package_config(&p.name);
-                    let version_updater = pkg_config.generic.version_updater()?;
-                    let next = p.version.next_from_diff(diff, version_updater);
+                    let next = self.next_version_from_diff(p, diff)?;
                     if let Some(workspace_version) = &workspace_version
                         && &next >= workspace_version
                     {
@@ -420,6 +417,11 @@ impl Updater<'_> {
         } else {
             p.version.increment_patch()
         };
+        let next_version = self
+            .req
+            .get_package_config(&p.name)
+            .generic
+            .constrained_version(&p.name, &p.version, next_version)?;
         info!(
             "{}: dependencies changed. Next version is {next_version}",
             p.name
@@ -489,20 +491,7 @@ impl Updater<'_> {
             let changelog_req = cfg
                 .should_update_changelog()
                 .then_some(self.req.changelog_req().clone());
-            let commits: Vec<Commit> = commits
-                .into_iter()
-                // If not conventional commit, only consider the first line of the commit message.
-                .filter_map(|c| {
-                    if c.is_conventional() {
-                        Some(c)
-                    } else {
-                        c.message.lines().next().map(|line| Commit {
-                            message: line.to_string(),
-                            ..c
-                        })
-                    }
-                })
-                .collect();
+            let commits = first_line_of_unconventional_commits(commits);
             changelog_req
                 .map(|r| {
                     get_changelog(
@@ -513,14 +502,19 @@ impl Updater<'_> {
                         repo_url,
                         release_link.as_deref(),
                         package,
+                        self.req.changelog_context(),
                     )
                 })
                 .transpose()
         }?;
 
-        let (changelog, new_changelog_entry) = match changelog_outcome {
-            Some((changelog, new_changelog_entry)) => (Some(changelog), Some(new_changelog_entry)),
-            None => (None, None),
+        let (changelog, new_changelog_entry, new_changelog_context) = match changelog_outcome {
+            Some((changelog, new_changelog_entry, new_changelog_context)) => (
+                Some(changelog),
+                Some(new_changelog_entry),
+                new_changelog_context,
+            ),
+            None => (None, None, None),
         };
 
         Ok(UpdateResult {
@@ -528,6 +522,7 @@ impl Updater<'_> {
             changelog,
             semver_check,
             new_changelog_entry,
+            new_changelog_context,
             registry_version,
         })
     }
@@ -553,6 +548,7 @@ impl Updater<'_> {
         repository
             .checkout_head()
             .context("can't checkout head to calculate diff")?;
+        let head = repository.current_commit_hash()?;
         let registry_package = registry_packages.get_registry_package(&package.name);
         let mut diff = Diff::new(registry_package.is_some());
         let pathbufs_to_check = pathbufs_to_check(&package_path, package)?;
@@ -569,6 +565,18 @@ impl Updater<'_> {
                     err.context("Failed to retrieve the last commit of local repository.")
                 }
             })?;
+        if repository.is_shallow()? {
+            self.checkout_in_fetched_history(repository, &head, || {
+                repository.checkout_head()?;
+                repository.checkout_last_commit_at_paths(&paths_to_check)
+            })
+            .with_context(|| {
+                format!(
+                    "can't find the last commit of package `{}` in the shallow clone",
+                    package.name
+                )
+            })?;
+        }
 
         let git_tag = self
             .project
@@ -628,6 +636,8 @@ impl Updater<'_> {
             u32::MAX
         };
 
+        let is_shallow = repository.is_shallow()?;
+        let first_commit = repository.current_commit_hash()?;
         for _ in 0..max_analyze_commits {
             let current_commit_message = repository.current_commit_message()?;
             let current_commit_hash = repository.current_commit_hash()?;
@@ -710,7 +720,36 @@ impl Updater<'_> {
             // Go back to the previous commit.
             // Keep in mind that the info contained in `package` might be outdated,
             // because commits could contain changes to Cargo.toml.
-            if let Err(_err) = repository.checkout_previous_commit_at_paths(&paths_to_check) {
+            let is_previous_commit_found = if is_shallow {
+                let current_commit = repository.current_commit_hash()?;
+                let checkout_previous_commit = || {
+                    repository.checkout(&current_commit)?;
+                    repository.checkout_previous_commit_at_paths(&paths_to_check)
+                };
+                match self.checkout_in_fetched_history(
+                    repository,
+                    &first_commit,
+                    checkout_previous_commit,
+                ) {
+                    Ok(is_found) => is_found,
+                    // Unpublished packages are analyzed up to `max_analyze_commits` anyway.
+                    Err(e) if registry_package.is_none() => {
+                        warn!("{}: {e:#}", package.name);
+                        false
+                    }
+                    Err(e) => {
+                        return Err(e.context(format!(
+                            "can't find the commit where package `{}` was published",
+                            package.name
+                        )));
+                    }
+                }
+            } else {
+                repository
+                    .checkout_previous_commit_at_paths(&paths_to_check)
+                    .is_ok()
+            };
+            if !is_previous_commit_found {
                 debug!("there are no other commits");
                 break;
             }
@@ -718,6 +757,27 @@ impl Updater<'_> {
         Ok(())
     }
 
+    /// In a shallow clone, the commit at the boundary of the history looks like it adds all
+    /// the files of the repository. So fetch more history until `checkout` checks out a
+    /// commit that isn't at the boundary, up to `max_analyze_commits` commits of the
+    /// history of `tip`, the commit where the analysis started.
+    ///
+    /// Returns `false` if `checkout` fails after fetching the whole history.
+    fn checkout_in_fetched_history(
+        &self,
+        repository: &Repo,
+        tip: &str,
+        checkout: impl Fn() -> anyhow::Result<()>,
+    ) -> anyhow::Result<bool> {
+        let max_commits = match self.req.max_analyze_commits() {
+            0 => u32::MAX,
+            n => n,
+        };
+        repository.deepen_until(tip, max_commits, || {
+            Ok(checkout().is_ok() && !repository.is_shallow_boundary("HEAD")?)
+        })
+    }
+
     fn check_package_equality(
         &self,
         repository: &Repo,
@@ -819,14 +879,24 @@ impl Updater<'_> {
                         })?
                         .clone()
                 } else {
-                    let version_updater = pkg_config.generic.version_updater()?;
-                    p.version.next_from_diff(diff, version_updater)
+                    self.next_version_from_diff(p, diff)?
                 }
             }
         };
         Ok(next_version)
     }
 
+    /// Next version of the package according to its commits,
+    /// within the allowed version range.
+    fn next_version_from_diff(&self, p: &Package, diff: &Diff) -> anyhow::Result<Version> {
+        let pkg_config = self.req.get_package_config(&p.name);
+        let version_updater = pkg_config.generic.version_updater()?;
+        let next_version = p.version.next_from_diff(diff, version_updater);
+        pkg_config
+            .generic
+            .constrained_version(&p.name, &p.version, next_version)
+    }
+
     /// `hash` is only used for logging purposes.
     fn are_changed_files_in_package(
         &self,
@@ -947,7 +1017,24 @@ fn is_commit_too_old(
     false
 }
 
-fn pathbufs_to_check(
+/// If not conventional commit, only consider the first line of the commit message.
+pub(crate) fn first_line_of_unconventional_commits(commits: Vec<Commit>) -> Vec<Commit> {
+    commits
+        .into_iter()
+        .filter_map(|c| {
+            if c.is_conventional() {
+                Some(c)
+            } else {
+                c.message.lines().next().map(|line| Commit {
+                    message: line.to_string(),
+                    ..c
+                })
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn pathbufs_to_check(
     package_path: &Utf8Path,
     package: &Package,
 ) -> anyhow::Result<Vec<Utf8PathBuf>> {
@@ -961,7 +1048,9 @@ fn pathbufs_to_check(
 /// Return the following tuple:
 /// - the entire changelog (with the new entries);
 /// - the new changelog entry alone
-///   (i.e. changelog body update without header and footer).
+///   (i.e. changelog body update without header and footer);
+/// - the template context of the new changelog entry, if `with_context` is true.
+#[allow(clippy::too_many_arguments)]
 fn get_changelog(
     commits: &[Commit],
     next_version: &Version,
@@ -970,7 +1059,8 @@ fn get_changelog(
     repo_url: Option<&RepoUrl>,
     release_link: Option<&str>,
     package: &Package,
-) -> anyhow::Result<(String, String)> {
+    with_context: bool,
+) -> anyhow::Result<(String, String, Option<serde_json::Value>)> {
     let commits: Vec<git_cliff_core::commit::Commit> =
         commits.iter().map(|c| c.to_cliff_commit()).collect();
     let mut changelog_builder = ChangelogBuilder::new(
@@ -985,6 +1075,8 @@ fn get_changelog(
         if let Some(config) = changelog_req.changelog_config {
             changelog_builder = changelog_builder.with_config(config);
         }
+        changelog_builder =
+            changelog_builder.with_unreleased_handling(changelog_req.unreleased_handling);
         if let Some(link) = release_link {
             changelog_builder = changelog_builder.with_release_link(link);
         }
@@ -1019,20 +1111,26 @@ fn get_changelog(
             // This can happen when no version of the package was published,
             // but the changelog already contains the changes of the initial version
             // of the package (e.g. because a release PR was merged).
-            return Ok((old_changelog.to_string(), String::new()));
+            return Ok((old_changelog.to_string(), String::new(), None));
         }
     }
     let new_changelog = changelog_builder.build();
+    let context = with_context
+        .then(|| new_changelog.context())
+        .transpose()
+        .context("can't determine changelog context")?;
     let changelog = match old_changelog {
         Some(old_changelog) => new_changelog.prepend(old_changelog)?,
         None => new_changelog.generate()?, // Old changelog doesn't exist.
     };
     let body_only =
         new_changelog_entry(changelog_builder).context("can't determine changelog body")?;
-    Ok((changelog, body_only.unwrap_or_default()))
+    Ok((changelog, body_only.unwrap_or_default(), context))
 }
 
-fn new_changelog_entry(changelog_builder: ChangelogBuilder) -> anyhow::Result<Option<String>> {
+pub(crate) fn new_changelog_entry(
+    changelog_builder: ChangelogBuilder,
+) -> anyhow::Result<Option<String>> {
     changelog_builder
         .config()
         .cloned()
@@ -1053,7 +1151,9 @@ fn new_changelog_entry(changelog_builder: ChangelogBuilder) -> anyhow::Result<Op
         .transpose()
 }
 
-fn get_contributors(commits: &[git_cliff_core::commit::Commit]) -> Vec<RemoteContributor> {
+pub(crate) fn get_contributors(
+    commits: &[git_cliff_core::commit::Commit],
+) -> Vec<RemoteContributor> {
     let mut unique_contributors = HashSet::new();
     commits
         .iter()
@@ -1115,6 +1215,7 @@ mod tests {
             None,
             None,
             &fake_package::FakePackage::new("my_package").into(),
+            false,
         )
         .unwrap();
         assert_eq!(old, new.0);
@@ -0,0 +1,573 @@
+use anyhow::Context as _;
+use base64::prelude::*;
+use git_cmd::Repo;
+use serde_json::{Value, json};
+use tracing::{debug, trace};
+
+use crate::git::forge::{ForgeType, GitClient};
+use crate::response_ext::ResponseExt;
+
+/// Commit all the changes (except typechanges) that are present in the repository
+/// using the REST API of GitLab ([docs](https://docs.gitlab.com/api/commits/#create-a-commit-with-multiple-files-and-actions))
+/// or Gitea ([docs](https://gitea.com/api/swagger#/repository/repoChangeFiles)).
+/// This way, release-plz doesn't need git credentials to push the commit and
+/// the commit is created by the owner of the token.
+///
+/// The changes are computed against `parent`, which becomes the parent of the new commit.
+/// - In GitLab, `force` overwrites `branch` with the new commit.
+///   If `branch` doesn't exist, it's created.
+/// - In Gitea, `branch` must exist and point to `parent`, because Gitea can't force-push.
+///   If there are no changes, no commit is created and `parent` is returned.
+///
+/// Returns the sha of the new commit.
+pub async fn commit_changes(
+    client: &GitClient,
+    repo: &Repo,
+    message: &str,
+    branch: &str,
+    parent: &str,
+    force: bool,
+) -> anyhow::Result<String> {
+    let commit = NewCommit {
+        message,
+        author: None,
+        branch,
+        base: parent,
+        parent,
+        force,
+    };
+    create_commit(client, repo, &commit).await
+}
+
+/// Recreate the local commit `commit` with the API, on top of the remote commit `parent`.
+/// `parent` must have the same content as the parent of `commit`, e.g. because it's the
+/// result of a previous call to this function.
+/// - In GitLab, `branch` is overwritten with the new commit. If it doesn't exist, it's created.
+/// - In Gitea, `branch` must exist and point to `parent`.
+///
+/// The working tree is checked out at `commit`, so the changes must be saved before calling this.
+/// If `preserve_author` is true, the author of the new commit is the one of `commit`.
+///
+/// Returns the sha of the new commit.
+pub async fn replay_commit(
+    client: &GitClient,
+    repo: &Repo,
+    commit: &str,
+    preserve_author: bool,
+    branch: &str,
+    parent: &str,
+) -> anyhow::Result<String> {
+    repo.git(&["checkout", "--detach", commit])?;
+    let message = repo.get_commit_message(commit)?;
+    let author = if preserve_author {
+        Some(CommitAuthor {
+            name: repo.get_author_name(commit)?,
+            email: repo.get_author_email(commit)?,
+        })
+    } else {
+        None
+    };
+    let base = format!("{commit}^");
+    let new_commit = NewCommit {
+        message: &message,
+        author: author.as_ref(),
+        branch,
+        base: &base,
+        parent,
+        force: client.forge == ForgeType::Gitlab,
+    };
+    create_commit(client, repo, &new_commit)
+        .await
+        .with_context(|| format!("failed to recreate commit {commit} via API"))
+}
+
+struct CommitAuthor {
+    name: String,
+    email: String,
+}
+
+struct NewCommit<'a> {
+    message: &'a str,
+    /// If `None`, the author is the owner of the token.
+    author: Option<&'a CommitAuthor>,
+    branch: &'a str,
+    /// Local commit with the same content as `parent`.
+    /// The new commit contains the changes of the working tree compared to it.
+    base: &'a str,
+    /// Remote commit that becomes the parent of the new commit.
+    parent: &'a str,
+    force: bool,
+}
+
+async fn create_commit(
+    client: &GitClient,
+    repo: &Repo,
+    commit: &NewCommit<'_>,
+) -> anyhow::Result<String> {
+    let changes = file_changes(client, repo, commit.base)?;
+    match client.forge {
+        ForgeType::Gitlab => {
+            let actions = gitlab_actions(repo, &changes).await?;
+            let mut body = json!({
+                "branch": commit.branch,
+                "commit_message": commit.message,
+                "start_sha": commit.parent,
+                "force": commit.force,
+                "actions": actions,
+            });
+            if let Some(author) = commit.author {
+                body["author_name"] = json!(author.name);
+                body["author_email"] = json!(author.email);
+            }
+            let commit = post_commit(client, "repository/commits", &body).await?;
+            json_str(&commit, "/id")
+        }
+        ForgeType::Gitea => {
+            anyhow::ensure!(!commit.force, "Gitea doesn't support force-pushing via API");
+            if changes.is_empty() {
+                debug!("no changes to commit on top of {}", commit.parent);
+                return Ok(commit.parent.to_string());
+            }
+            let files = gitea_files(repo, commit.base, &changes).await?;
+            let mut body = json!({
+                "branch": commit.branch,
+                "message": commit.message,
+                "files": files,
+            });
+            if let Some(author) = commit.author {
+                body["author"] = json!({ "name": author.name, "email": author.email });
+            }
+            let commit = post_commit(client, "contents", &body).await?;
+            json_str(&commit, "/commit/sha")
+        }
+        ForgeType::Github => {
+            anyhow::bail!("use the GraphQL API to create commits in GitHub")
+        }
+    }
+}
+
+async fn post_commit(client: &GitClient, endpoint: &str, body: &Value) -> anyhow::Result<Value> {
+    let url = format!("{}/{endpoint}", client.repo_url());
+    debug!("creating commit with {url}");
+    trace!("{body}");
+    let commit = client
+        .client
+        .post(url)
+        .json(body)
+        .send()
+        .await?
+        .successful_status()
+        .await
+        .context("failed to create commit")?
+        .json()
+        .await?;
+    Ok(commit)
+}
+
+fn json_str(value: &Value, pointer: &str) -> anyhow::Result<String> {
+    value
+        .pointer(pointer)
+        .and_then(Value::as_str)
+        .map(ToOwned::to_owned)
+        .with_context(|| format!("commit response doesn't contain `{pointer}`: {value}"))
+}
+
+/// Change that the API of the forge can't reproduce, so the commit must be pushed with git.
+#[derive(Debug)]
+pub struct UnsupportedChange {
+    forge: ForgeType,
+    path: String,
+    reason: &'static str,
+}
+
+impl std::fmt::Display for UnsupportedChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let forge = match self.forge {
+            ForgeType::Github => "GitHub",
+            ForgeType::Gitea => "Gitea",
+            ForgeType::Gitlab => "GitLab",
+        };
+        write!(
+            f,
+            "the {forge} API can't commit `{}`: {}",
+            self.path, self.reason
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedChange {}
+
+/// The change that prevented the API from creating the commit, if any.
+/// In this case, the commit can be pushed with git instead.
+pub fn unsupported_change(error: &anyhow::Error) -> Option<&UnsupportedChange> {
+    error.downcast_ref::<UnsupportedChange>()
+}
+
+/// Returns an [`UnsupportedChange`] error if the API can't recreate one of the `commits`.
+/// Use it before recreating several commits, so that none of them is created if
+/// they have to be pushed with git.
+pub fn check_commits(client: &GitClient, repo: &Repo, commits: &[&str]) -> anyhow::Result<()> {
+    for commit in commits {
+        let diff = repo.git(&[
+            "diff",
+            "--raw",
+            "-z",
+            "--find-renames",
+            &format!("{commit}^"),
+            commit,
+        ])?;
+        for change in parse_raw_diff(&diff)? {
+            change.check_supported(client.forge)?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum FileMode {
+    Regular,
+    Executable,
+    Symlink,
+    /// Commit of a git submodule.
+    Gitlink,
+}
+
+impl FileMode {
+    /// Returns `None` if the file doesn't exist.
+    fn parse(mode: &str) -> anyhow::Result<Option<Self>> {
+        Ok(match mode {
+            "000000" => None,
+            "100644" => Some(Self::Regular),
+            "100755" => Some(Self::Executable),
+            "120000" => Some(Self::Symlink),
+            "160000" => Some(Self::Gitlink),
+            _ => anyhow::bail!("unknown file mode `{mode}`"),
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum FileStatus {
+    Added,
+    Modified,
+    Deleted,
+    Renamed {
+        from: String,
+    },
+    /// E.g. a file replaced by a symlink.
+    TypeChanged,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct FileChange {
+    status: FileStatus,
+    path: String,
+    /// `None` if the file is added.
+    old_mode: Option<FileMode>,
+    /// `None` if the file is deleted.
+    new_mode: Option<FileMode>,
+}
+
+impl FileChange {
+    /// Whether the executable bit of the file is set or unset by the change.
+    fn executable_changed(&self) -> bool {
+        let is_executable = |mode| mode == Some(FileMode::Executable);
+        self.new_mode.is_some() && is_executable(self.old_mode) != is_executable(self.new_mode)
+    }
+
+    /// Returns an error if the API of the forge can't reproduce the change.
+    fn check_supported(&self, forge: ForgeType) -> Result<(), UnsupportedChange> {
+        let unsupported = |reason| {
+            Err(UnsupportedChange {
+                forge,
+                path: self.path.clone(),
+                reason,
+            })
+        };
+        if self.status == FileStatus::TypeChanged {
+            return unsupported("its file type changed");
+        }
+        if self.new_mode == Some(FileMode::Symlink) {
+            return unsupported("symlinks can't be created");
+        }
+        if self.new_mode == Some(FileMode::Gitlink) {
+            return unsupported("submodules can't be updated");
+        }
+        // Gitea keeps the mode of the files it updates or renames, but it can't change it.
+        if forge == ForgeType::Gitea && self.executable_changed() {
+            return unsupported("the executable bit can't be changed");
+        }
+        Ok(())
+    }
+}
+
+/// Files of the working tree that differ from `base`, including untracked files.
+/// Like in a local commit, the type changes of the working tree are skipped.
+fn file_changes(client: &GitClient, repo: &Repo, base: &str) -> anyhow::Result<Vec<FileChange>> {
+    // Stage the changes to list untracked files, too.
+    let working_tree_changes = repo.changes_except_typechanges()?;
+    if !working_tree_changes.is_empty() {
+        repo.add(&working_tree_changes)?;
+    }
+    let diff = repo.git(&["diff", "--cached", "--raw", "-z", "--find-renames", base]);
+    repo.git(&["reset", "--quiet"])?;
+    let changes = parse_raw_diff(&diff?)?;
+    for change in &changes {
+        change.check_supported(client.forge)?;
+    }
+    Ok(changes)
+}
+
+/// Parse the output of `git diff --raw -z`.
+fn parse_raw_diff(output: &str) -> anyhow::Result<Vec<FileChange>> {
+    let mut fields = output.split('\0').filter(|field| !field.is_empty());
+    let mut changes = vec![];
+    while let Some(info) = fields.next() {
+        // E.g. `:100644 100755 <old sha> <new sha> M`
+        let info: Vec<&str> = info.trim_start_matches(':').split(' ').collect();
+        let [old_mode, new_mode, _, _, status] = info[..] else {
+            anyhow::bail!("unexpected git diff output: {info:?}");
+        };
+        let mut next_path = || {
+            fields
+                .next()
+                .map(ToOwned::to_owned)
+                .context("missing path in git diff output")
+        };
+        let status = match &status[..1] {
+            "A" => FileStatus::Added,
+            "M" => FileStatus::Modified,
+            "D" => FileStatus::Deleted,
+            "T" => FileStatus::TypeChanged,
+            "R" => FileStatus::Renamed { from: next_path()? },
+            _ => anyhow::bail!("unexpected status `{status}` in git diff output"),
+        };
+        changes.push(FileChange {
+            status,
+            path: next_path()?,
+            old_mode: FileMode::parse(old_mode)?,
+            new_mode: FileMode::parse(new_mode)?,
+        });
+    }
+    Ok(changes)
+}
+
+/// Base64 encoded content of the file.
+/// Returns `None` for directories, e.g. git submodules.
+async fn encoded_content(repo: &Repo, path: &str) -> anyhow::Result<Option<String>> {
+    let realpath = repo.directory().join(path);
+    if realpath.is_dir() {
+        debug!("skipping directory `{realpath}` in git additions");
+        return Ok(None);
+    }
+    let content = fs_err::tokio::read(realpath).await?;
+    Ok(Some(BASE64_STANDARD.encode(content)))
+}
+
+async fn gitlab_actions(repo: &Repo, changes: &[FileChange]) -> anyhow::Result<Vec<Value>> {
+    let mut actions = vec![];
+    for change in changes {
+        let path = &change.path;
+        let mut action = match &change.status {
+            FileStatus::Added => json!({"action": "create", "file_path": path}),
+            FileStatus::Modified => json!({"action": "update", "file_path": path}),
+            FileStatus::Renamed { from } => {
+                json!({"action": "move", "file_path": path, "previous_path": from})
+            }
+            FileStatus::Deleted => {
+                actions.push(json!({"action": "delete", "file_path": path}));
+                continue;
+            }
+            FileStatus::TypeChanged => unreachable!("type changes are unsupported"),
+        };
+        let Some(content) = encoded_content(repo, path).await? else {
+            continue;
+        };
+        action["content"] = json!(content);
+        action["encoding"] = json!("base64");
+        actions.push(action);
+        if change.executable_changed() {
+            actions.push(json!({
+                "action": "chmod",
+                "file_path": path,
+                "execute_filemode": change.new_mode == Some(FileMode::Executable),
+            }));
+        }
+    }
+    Ok(actions)
+}
+
+async fn gitea_files(
+    repo: &Repo,
+    base: &str,
+    changes: &[FileChange],
+) -> anyhow::Result<Vec<Value>> {
+    // Gitea needs the sha of the blob to update or delete a file.
+    let blob_sha = |path: &str| repo.git(&["rev-parse", &format!("{base}:{path}")]);
+    let mut files = vec![];
+    for change in changes {
+        let path = &change.path;
+        let mut file = match &change.status {
+            FileStatus::Added => json!({"operation": "create", "path": path}),
+            FileStatus::Modified => {
+                json!({"operation": "update", "path": path, "sha": blob_sha(path)?})
+            }
+            FileStatus::Renamed { from } => json!({
+                "operation": "update",
+                "path": path,
+                "from_path": from,
+                "sha": blob_sha(from)?,
+            }),
+            FileStatus::Deleted => {
+                files.push(json!({"operation": "delete", "path": path, "sha": blob_sha(path)?}));
+                continue;
+            }
+            FileStatus::TypeChanged => unreachable!("type changes are unsupported"),
+        };
+        if let Some(content) = encoded_content(repo, path).await? {
+            file["content"] = json!(content);
+            files.push(file);
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(forge: ForgeType) -> GitClient {
+        let url = crate::RepoUrl::new("https://localhost/owner/repo").unwrap();
+        let forge = match forge {
+            ForgeType::Gitlab => {
+                crate::GitForge::Gitlab(crate::GitLab::new(url, "t".into()).unwrap())
+            }
+            ForgeType::Gitea => crate::GitForge::Gitea(crate::Gitea::new(url, "t".into()).unwrap()),
+            ForgeType::Github => unimplemented!("GitHub commits are created with GraphQL"),
+        };
+        GitClient::new(forge).unwrap()
+    }
+
+    fn change(status: FileStatus, old_mode: &str, new_mode: &str) -> FileChange {
+        FileChange {
+            status,
+            path: "script.sh".to_string(),
+            old_mode: FileMode::parse(old_mode).unwrap(),
+            new_mode: FileMode::parse(new_mode).unwrap(),
+        }
+    }
+
+    #[test]
+    fn raw_diff_is_parsed() {
+        let output = [
+            ":000000 100644 0000000 1111111 A",
+            "CHANGELOG.md",
+            ":100644 100755 1111111 1111111 M",
+            "script.sh",
+            ":100644 000000 1111111 0000000 D",
+            "old.txt",
+            ":100644 100644 1111111 2222222 R087",
+            "src/a.rs",
+            "src/b.rs",
+            ":100644 120000 1111111 2222222 T",
+            "link",
+            "",
+        ]
+        .join("\0");
+        assert_eq!(
+            parse_raw_diff(&output).unwrap(),
+            vec![
+                FileChange {
+                    status: FileStatus::Added,
+                    path: "CHANGELOG.md".to_string(),
+                    old_mode: None,
+                    new_mode: Some(FileMode::Regular),
+                },
+                change(FileStatus::Modified, "100644", "100755"),
+                FileChange {
+                    status: FileStatus::Deleted,
+                    path: "old.txt".to_string(),
+                    old_mode: Some(FileMode::Regular),
+                    new_mode: None,
+                },
+                FileChange {
+                    status: FileStatus::Renamed {
+                        from: "src/a.rs".to_string()
+                    },
+                    path: "src/b.rs".to_string(),
+                    old_mode: Some(FileMode::Regular),
+                    new_mode: Some(FileMode::Regular),
+                },
+                FileChange {
+                    status: FileStatus::TypeChanged,
+                    path: "link".to_string(),
+                    old_mode: Some(FileMode::Regular),
+                    new_mode: Some(FileMode::Symlink),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn executable_bit_can_be_changed_only_on_gitlab() {
+        let chmod = change(FileStatus::Modified, "100644", "100755");
+        assert!(chmod.check_supported(ForgeType::Gitlab).is_ok());
+        let error = chmod.check_supported(ForgeType::Gitea).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the Gitea API can't commit `script.sh`: the executable bit can't be changed"
+        );
+        // Gitea keeps the mode of the updated files.
+        let edit = change(FileStatus::Modified, "100755", "100755");
+        assert!(edit.check_supported(ForgeType::Gitea).is_ok());
+    }
+
+    #[test]
+    fn type_changes_and_symlinks_are_unsupported() {
+        for forge in [ForgeType::Gitlab, ForgeType::Gitea] {
+            let type_change = change(FileStatus::TypeChanged, "100644", "120000");
+            assert!(type_change.check_supported(forge).is_err());
+            let symlink = change(FileStatus::Added, "000000", "120000");
+            assert!(symlink.check_supported(forge).is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn renames_and_modes_are_committed_with_gitlab_api() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = Repo::init(tmp.path());
+        fs_err::write(tmp.path().join("run.sh"), "echo hi").unwrap();
+        repo.add_all_and_commit("add run.sh").unwrap();
+        let base = repo.current_commit_hash().unwrap();
+        repo.git(&["mv", "run.sh", "start.sh"]).unwrap();
+        repo.git(&["update-index", "--chmod=+x", "start.sh"])
+            .unwrap();
+        repo.git(&["commit", "-m", "rename run.sh"]).unwrap();
+        let commit = repo.current_commit_hash().unwrap();
+        // Check out the file again, so that its mode is the committed one.
+        fs_err::remove_file(tmp.path().join("start.sh")).unwrap();
+        repo.git(&["checkout", "--", "start.sh"]).unwrap();
+
+        let changes = file_changes(&client(ForgeType::Gitlab), &repo, &base).unwrap();
+        let actions = gitlab_actions(&repo, &changes).await.unwrap();
+        assert_eq!(
+            actions,
+            vec![
+                json!({
+                    "action": "move",
+                    "file_path": "start.sh",
+                    "previous_path": "run.sh",
+                    "content": BASE64_STANDARD.encode("echo hi"),
+                    "encoding": "base64",
+                }),
+                json!({
+                    "action": "chmod",
+                    "file_path": "start.sh",
+                    "execute_filemode": true,
+                }),
+            ]
+        );
+        let error = check_commits(&client(ForgeType::Gitea), &repo, &[&commit]).unwrap_err();
+        assert!(unsupported_change(&error).is_some(), "{error:?}");
+    }
+}
@@ -1,8 +1,14 @@
-use crate::git::{gitea_client::Gitea, gitlab_client::GitLab};
+use crate::git::{
+    gitea_client::{Gitea, GiteaServer},
+    gitlab_client::GitLab,
+};
 use crate::{GitHub, GitReleaseInfo};
 use std::collections::{HashMap, HashSet};
 
+use crate::git::github_app::InstallationAuth;
+use crate::git::github_graphql;
 use crate::pr::Pr;
+use crate::pr_parser::closed_issues_from_text;
 use crate::response_ext::ResponseExt;
 use anyhow::Context;
 use http::StatusCode;
@@ -14,7 +20,8 @@ use reqwest_retry::{RetryTransientMiddleware, policies::ExponentialBackoff};
 use secrecy::SecretString;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use tracing::{debug, info, instrument};
+use tokio::sync::OnceCell;
+use tracing::{debug, info, instrument, warn};
 
 #[derive(Debug, Clone)]
 pub enum GitForge {
@@ -45,6 +52,8 @@ pub struct GitClient {
     pub forge: ForgeType,
     pub remote: Remote,
     pub client: reqwest_middleware::ClientWithMiddleware,
+    /// Gitea or Forgejo server, detected on first use.
+    gitea_server: OnceCell<GiteaServer>,
 }
 
 #[derive(Debug, Clone)]
@@ -61,10 +70,24 @@ impl Remote {
     }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Clone, Debug)]
 pub struct PrCommit {
     pub author: Option<Author>,
     pub sha: String,
+    #[serde(default)]
+    pub parents: Vec<CommitParent>,
+}
+
+impl PrCommit {
+    /// Merge commits are created when updating the PR branch with the base branch.
+    pub fn is_merge(&self) -> bool {
+        self.parents.len() > 1
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct CommitParent {
+    pub sha: String,
 }
 
 #[derive(Deserialize, Clone, Debug)]
@@ -77,6 +100,8 @@ pub struct Author {
 #[derive(Deserialize, Clone, Debug)]
 pub struct GitLabMrCommit {
     pub id: String,
+    #[serde(default)]
+    pub parent_ids: Vec<String>,
 }
 
 impl From<GitLabMrCommit> for PrCommit {
@@ -84,6 +109,11 @@ impl From<GitLabMrCommit> for PrCommit {
         Self {
             author: None,
             sha: value.id,
+            parents: value
+                .parent_ids
+                .into_iter()
+                .map(|sha| CommitParent { sha })
+                .collect(),
         }
     }
 }
@@ -202,6 +232,56 @@ impl From<GitPr> for GitLabMr {
     }
 }
 
+/// How to merge a PR.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeMethod {
+    /// Create a merge commit.
+    Merge,
+    /// Squash the commits of the PR into a single commit.
+    Squash,
+    /// Rebase the commits of the PR onto the base branch.
+    Rebase,
+}
+
+/// PR or issue that can be commented.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommentTarget {
+    Pr(u64),
+    Issue(u64),
+}
+
+#[derive(Deserialize, Debug)]
+struct Comment {
+    body: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Milestone {
+    /// Global ID of the milestone. Used by Gitea and GitLab.
+    id: u64,
+    /// Number of the milestone in the repository. Used by GitHub.
+    number: Option<u64>,
+    title: String,
+}
+
+impl Milestone {
+    /// Identifier of the milestone used to assign it to a PR.
+    fn key(&self) -> u64 {
+        self.number.unwrap_or(self.id)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct GitLabUser {
+    id: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct GitLabIssue {
+    /// Number of the issue in the project.
+    iid: u64,
+}
+
 #[derive(Deserialize, Clone, Debug)]
 pub struct Commit {
     #[serde(rename = "ref")]
@@ -281,10 +361,19 @@ impl GitClient {
                 .context("can't build Git client")?;
 
             let retry_policy = ExponentialBackoff::builder().build_with_max_retries(3);
-            ClientBuilder::new(reqwest_client)
+            let client_builder = ClientBuilder::new(reqwest_client)
                 // Retry failed requests.
-                .with(RetryTransientMiddleware::new_with_policy(retry_policy))
-                .build()
+                .with(RetryTransientMiddleware::new_with_policy(retry_policy));
+            match &forge {
+                GitForge::Github(GitHub {
+                    remote,
+                    app: Some(app),
+                }) => client_builder
+                    // Added after the retry middleware, so that retried requests get a valid token.
+                    .with(InstallationAuth::new(app.clone(), remote.clone())?)
+                    .build(),
+                _ => client_builder.build(),
+            }
         };
 
         let (forge, remote) = match forge {
@@ -296,9 +385,69 @@ impl GitClient {
             forge,
             remote,
             client,
+            gitea_server: OnceCell::new(),
         })
     }
 
+    /// Server implementing the Gitea API. Only meaningful for [`ForgeType::Gitea`].
+    /// If the version of the server can't be detected, we assume it supports all the features.
+    pub async fn gitea_server(&self) -> &GiteaServer {
+        self.gitea_server
+            .get_or_init(async || match self.fetch_gitea_server().await {
+                Ok(Some(server)) => {
+                    debug!(
+                        "detected {} server implementing Gitea API {}",
+                        server.name(),
+                        server.api_version
+                    );
+                    server
+                }
+                Ok(None) => {
+                    debug!("can't parse the version of the Gitea server");
+                    GiteaServer::latest()
+                }
+                Err(e) => {
+                    warn!("can't detect the version of the Gitea server: {e:?}");
+                    GiteaServer::latest()
+                }
+            })
+            .await
+    }
+
+    async fn fetch_gitea_server(&self) -> anyhow::Result<Option<GiteaServer>> {
+        #[derive(Deserialize)]
+        struct ServerVersion {
+            version: String,
+        }
+        let server_version: ServerVersion = self
+            .client
+            .get(format!("{}version", self.remote.base_url))
+            .send()
+            .await?
+            .successful_status()
+            .await?
+            .json()
+            .await?;
+        Ok(GiteaServer::from_version(&server_version.version))
+    }
+
+    /// Whether the commits of the release PR can be created with the API
+    /// instead of pushing them with git.
+    pub async fn supports_api_commits(&self) -> bool {
+        match self.forge {
+            ForgeType::Github | ForgeType::Gitlab => true,
+            ForgeType::Gitea => self.gitea_server().await.supports_change_files(),
+        }
+    }
+
+    /// Whether annotated tags can be created with the API instead of pushing them with git.
+    pub async fn supports_tags_api(&self) -> bool {
+        match self.forge {
+            ForgeType::Github | ForgeType::Gitlab => true,
+            ForgeType::Gitea => self.gitea_server().await.supports_tags_api(),
+        }
+    }
+
     pub fn per_page(&self) -> &str {
         match self.forge {
             ForgeType::Github | ForgeType::Gitlab => "per_page",
@@ -318,7 +467,10 @@ impl GitClient {
     /// Same as Gitea.
     pub async fn create_github_release(&self, release_info: &GitReleaseInfo) -> anyhow::Result<()> {
         if release_info.latest.is_some() && self.forge == ForgeType::Gitea {
-            anyhow::bail!("Gitea does not support the `git_release_latest` option");
+            anyhow::bail!(
+                "{} does not support the `git_release_latest` option",
+                self.gitea_server().await.name()
+            );
         }
         let create_release_options = CreateReleaseOption {
             tag_name: &release_info.git_tag,
@@ -400,7 +552,7 @@ impl GitClient {
         }
     }
 
-    fn repo_url(&self) -> String {
+    pub(crate) fn repo_url(&self) -> String {
         match self.forge {
             ForgeType::Github | ForgeType::Gitea => {
                 format!(
@@ -573,6 +725,9 @@ impl GitClient {
         self.add_labels(&pr.labels, git_pr.number)
             .await
             .context("Failed to add labels")?;
+        self.assign_pr(pr, git_pr.number)
+            .await
+            .context("Failed to add reviewers, assignees and milestone")?;
         Ok(git_pr)
     }
 
@@ -589,6 +744,286 @@ impl GitClient {
         }
     }
 
+    /// Request the reviewers, add the assignees and set the milestone of the PR.
+    /// The milestone is created if it doesn't exist.
+    #[instrument(skip(self, pr))]
+    pub async fn assign_pr(&self, pr: &Pr, pr_number: u64) -> anyhow::Result<()> {
+        if !pr.has_assignments() {
+            return Ok(());
+        }
+        let milestone = match &pr.milestone {
+            Some(title) => Some(
+                self.get_or_create_milestone(title)
+                    .await
+                    .with_context(|| format!("failed to get milestone `{title}`"))?,
+            ),
+            None => None,
+        };
+        match self.forge {
+            ForgeType::Github | ForgeType::Gitea => {
+                if !pr.reviewers.is_empty() || !pr.team_reviewers.is_empty() {
+                    self.client
+                        .post(format!(
+                            "{}/{pr_number}/requested_reviewers",
+                            self.pulls_url()
+                        ))
+                        .json(&json!({
+                            "reviewers": pr.reviewers,
+                            "team_reviewers": pr.team_reviewers,
+                        }))
+                        .send()
+                        .await?
+                        .successful_status()
+                        .await
+                        .context("failed to request reviewers")?;
+                }
+                let mut issue_edit = serde_json::Map::new();
+                if !pr.assignees.is_empty() {
+                    issue_edit.insert("assignees".to_string(), json!(pr.assignees));
+                }
+                if let Some(milestone) = &milestone {
+                    issue_edit.insert("milestone".to_string(), json!(milestone.key()));
+                }
+                if !issue_edit.is_empty() {
+                    self.client
+                        .patch(format!("{}/{pr_number}", self.issues_url()))
+                        .json(&issue_edit)
+                        .send()
+                        .await?
+                        .successful_status()
+                        .await
+                        .context("failed to set assignees and milestone")?;
+                }
+            }
+            ForgeType::Gitlab => {
+                if !pr.team_reviewers.is_empty() {
+                    warn!("GitLab doesn't support team reviewers. Ignoring `pr_team_reviewers`");
+                }
+                // Docs: https://docs.gitlab.com/api/merge_requests/#update-mr
+                let mut mr_edit = serde_json::Map::new();
+                if !pr.reviewers.is_empty() {
+                    let reviewer_ids = self.gitlab_user_ids(&pr.reviewers).await?;
+                    mr_edit.insert("reviewer_ids".to_string(), json!(reviewer_ids));
+                }
+                if !pr.assignees.is_empty() {
+                    let assignee_ids = self.gitlab_user_ids(&pr.assignees).await?;
+                    mr_edit.insert("assignee_ids".to_string(), json!(assignee_ids));
+                }
+                if let Some(milestone) = &milestone {
+                    mr_edit.insert("milestone_id".to_string(), json!(milestone.key()));
+                }
+                if !mr_edit.is_empty() {
+                    self.client
+                        .put(format!("{}/{pr_number}", self.pulls_url()))
+                        .json(&mr_edit)
+                        .send()
+                        .await?
+                        .successful_status()
+                        .await
+                        .context("failed to set reviewers, assignees and milestone")?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_or_create_milestone(&self, title: &str) -> anyhow::Result<Milestone> {
+        let milestones_url = format!("{}/milestones", self.repo_url());
+        let mut page = 1;
+        let page_size = 50;
+        loop {
+            let mut url = Url::parse(&milestones_url).context("invalid milestones URL")?;
+            {
+                let mut qp = url.query_pairs_mut();
+                match self.forge {
+                    ForgeType::Github | ForgeType::Gitea => qp.append_pair("state", "all"),
+                    ForgeType::Gitlab => qp.append_pair("title", title),
+                };
+                qp.append_pair("page", &page.to_string());
+                qp.append_pair(self.per_page(), &page_size.to_string());
+            }
+            let milestones: Vec<Milestone> = self
+                .client
+                .get(url)
+                .send()
+                .await?
+                .successful_status()
+                .await?
+                .json()
+                .await
+                .context("failed to parse milestones")?;
+            let milestones_len = milestones.len();
+            if let Some(milestone) = milestones.into_iter().find(|m| m.title == title) {
+                return Ok(milestone);
+            }
+            if milestones_len < page_size {
+                break;
+            }
+            page += 1;
+        }
+
+        debug!("creating milestone `{title}`");
+        let milestone = self
+            .client
+            .post(milestones_url)
+            .json(&json!({ "title": title }))
+            .send()
+            .await?
+            .successful_status()
+            .await?
+            .json()
+            .await
+            .context("failed to parse created milestone")?;
+        info!("created milestone `{title}`");
+        Ok(milestone)
+    }
+
+    /// GitLab API requires user IDs instead of usernames.
+    async fn gitlab_user_ids(&self, usernames: &[String]) -> anyhow::Result<Vec<u64>> {
+        // The base URL ends with `/projects/<project>`, while the users API is at the root.
+        let base_url = self.remote.base_url.as_str();
+        let api_root = base_url
+            .rfind("/projects/")
+            .map_or(base_url, |index| &base_url[..index]);
+        let mut ids = vec![];
+        for username in usernames {
+            let mut url = Url::parse(&format!("{api_root}/users")).context("invalid users URL")?;
+            url.query_pairs_mut().append_pair("username", username);
+            let users: Vec<GitLabUser> = self
+                .client
+                .get(url)
+                .send()
+                .await?
+                .successful_status()
+                .await?
+                .json()
+                .await
+                .context("failed to parse GitLab users")?;
+            let user = users
+                .first()
+                .with_context(|| format!("GitLab user `{username}` not found"))?;
+            ids.push(user.id);
+        }
+        Ok(ids)
+    }
+
+    /// Fail if the forge can't merge PRs automatically with the given merge method.
+    pub fn check_auto_merge_method(&self, merge_method: MergeMethod) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            !(self.forge == ForgeType::Gitlab && merge_method == MergeMethod::Rebase),
+            "GitLab can't rebase merge requests automatically, because it uses the merge method of the project settings. Set `pr_auto_merge` to `merge` or `squash`, and choose the merge method in the GitLab project settings"
+        );
+        Ok(())
+    }
+
+    /// Whether the base branch requires the checks of the PRs to pass before merging.
+    /// Without required checks, GitLab and Gitea merge the PR right away instead of
+    /// waiting for the checks.
+    async fn has_required_checks(&self, base_branch: &str) -> anyhow::Result<bool> {
+        match self.forge {
+            // GitHub refuses to enable auto-merge if there are no required checks.
+            ForgeType::Github => Ok(true),
+            // Docs: https://docs.gitlab.com/api/projects/#get-a-single-project
+            ForgeType::Gitlab => {
+                #[derive(Deserialize)]
+                struct Project {
+                    #[serde(default)]
+                    only_allow_merge_if_pipeline_succeeds: bool,
+                }
+                let project: Project = self
+                    .client
+                    .get(self.repo_url())
+                    .send()
+                    .await?
+                    .successful_status()
+                    .await?
+                    .json()
+                    .await
+                    .context("failed to parse GitLab project")?;
+                Ok(project.only_allow_merge_if_pipeline_succeeds)
+            }
+            // Docs: https://gitea.com/api/swagger#/repository/repoGetBranch
+            ForgeType::Gitea => {
+                #[derive(Deserialize)]
+                struct Branch {
+                    #[serde(default)]
+                    enable_status_check: bool,
+                }
+                let branch: Branch = self
+                    .client
+                    .get(format!(
+                        "{}/branches/{}",
+                        self.repo_url(),
+                        urlencoding::encode(base_branch)
+                    ))
+                    .send()
+                    .await?
+                    .successful_status()
+                    .await?
+                    .json()
+                    .await
+                    .context("failed to parse Gitea branch")?;
+                Ok(branch.enable_status_check)
+            }
+        }
+    }
+
+    /// Merge the PR automatically when its checks pass.
+    /// Fails if `base_branch` doesn't require the checks to pass, because the forge
+    /// would merge the PR right away.
+    #[instrument(skip(self))]
+    pub async fn enable_auto_merge(
+        &self,
+        pr_number: u64,
+        base_branch: &str,
+        merge_method: MergeMethod,
+    ) -> anyhow::Result<()> {
+        self.check_auto_merge_method(merge_method)?;
+        anyhow::ensure!(
+            self.has_required_checks(base_branch).await?,
+            "the `{base_branch}` branch doesn't require the checks to pass, so the PR would be merged right away. Require the checks to pass in the branch protection (Gitea) or the \"Pipelines must succeed\" project setting (GitLab)"
+        );
+        let req = match self.forge {
+            ForgeType::Github => {
+                return github_graphql::enable_auto_merge(self, pr_number, merge_method).await;
+            }
+            // Docs: https://docs.gitlab.com/api/merge_requests/#merge-a-merge-request
+            // GitLab uses the merge method configured in the project, but it allows squashing.
+            // Rebasing is rejected by `check_auto_merge_method`.
+            ForgeType::Gitlab => self
+                .client
+                .put(format!("{}/{pr_number}/merge", self.pulls_url()))
+                .json(&json!({
+                    "merge_when_pipeline_succeeds": true,
+                    "auto_merge": true,
+                    "squash": merge_method == MergeMethod::Squash,
+                })),
+            // Docs: https://gitea.com/api/swagger#/repository/repoMergePullRequest
+            ForgeType::Gitea => {
+                let server = self.gitea_server().await;
+                anyhow::ensure!(
+                    server.supports_auto_merge(),
+                    "{} {} doesn't support merging PRs when checks succeed. Please update it to a version compatible with Gitea 1.17 or later",
+                    server.name(),
+                    server.api_version
+                );
+                let merge_style = match merge_method {
+                    MergeMethod::Merge => "merge",
+                    MergeMethod::Squash => "squash",
+                    MergeMethod::Rebase => "rebase",
+                };
+                self.client
+                    .post(format!("{}/{pr_number}/merge", self.pulls_url()))
+                    .json(&json!({
+                        "Do": merge_style,
+                        "merge_when_checks_succeed": true,
+                    }))
+            }
+        };
+        req.send().await?.successful_status().await?;
+        Ok(())
+    }
+
     fn pr_labels_url(&self, pr_number: u64) -> String {
         format!("{}/{}/labels", self.issues_url(), pr_number)
     }
@@ -871,6 +1306,94 @@ impl GitClient {
         Ok(prs)
     }
 
+    /// Numbers of the issues closed by the given PR.
+    pub async fn closed_issues(&self, pr_number: u64) -> anyhow::Result<Vec<u64>> {
+        match self.forge {
+            ForgeType::Github => github_graphql::closing_issues(self, pr_number).await,
+            ForgeType::Gitlab => {
+                // Docs: https://docs.gitlab.com/api/merge_requests/#list-issues-that-close-on-merge
+                let issues: Vec<GitLabIssue> = self
+                    .client
+                    .get(format!("{}/{pr_number}/closes_issues", self.pulls_url()))
+                    .send()
+                    .await?
+                    .successful_status()
+                    .await?
+                    .json()
+                    .await
+                    .context("failed to parse closed issues")?;
+                Ok(issues.into_iter().map(|issue| issue.iid).collect())
+            }
+            // Gitea doesn't expose the issues closed by a PR, so we parse the PR description.
+            ForgeType::Gitea => {
+                let pr = self.get_pr_info(pr_number).await?;
+                let body = pr.body.unwrap_or_default();
+                Ok(closed_issues_from_text(&body))
+            }
+        }
+    }
+
+    fn comments_url(&self, target: CommentTarget) -> String {
+        match (self.forge, target) {
+            // In GitHub and Gitea, PRs are issues.
+            (
+                ForgeType::Github | ForgeType::Gitea,
+                CommentTarget::Pr(number) | CommentTarget::Issue(number),
+            ) => {
+                format!("{}/{number}/comments", self.issues_url())
+            }
+            (ForgeType::Gitlab, CommentTarget::Pr(number)) => {
+                format!("{}/{number}/notes", self.pulls_url())
+            }
+            (ForgeType::Gitlab, CommentTarget::Issue(number)) => {
+                format!("{}/{number}/notes", self.issues_url())
+            }
+        }
+    }
+
+    /// Bodies of the comments of a PR or issue.
+    pub async fn comments(&self, target: CommentTarget) -> anyhow::Result<Vec<String>> {
+        let mut page = 1;
+        let page_size = 50;
+        let mut comments = vec![];
+        loop {
+            let mut url = Url::parse(&self.comments_url(target)).context("invalid comments URL")?;
+            url.query_pairs_mut()
+                .append_pair("page", &page.to_string())
+                .append_pair(self.per_page(), &page_size.to_string());
+            let page_comments: Vec<Comment> = self
+                .client
+                .get(url)
+                .send()
+                .await?
+                .successful_status()
+                .await?
+                .json()
+                .await
+                .context("failed to parse comments")?;
+            let page_len = page_comments.len();
+            comments.extend(page_comments.into_iter().map(|comment| comment.body));
+            if page_len < page_size {
+                break;
+            }
+            page += 1;
+        }
+        Ok(comments)
+    }
+
+    #[instrument(skip(self, body))]
+    pub async fn create_comment(&self, target: CommentTarget, body: &str) -> anyhow::Result<()> {
+        self.client
+            .post(self.comments_url(target))
+            .json(&json!({ "body": body }))
+            .send()
+            .await?
+            .successful_status()
+            .await
+            .context("failed to create comment")?;
+        Ok(())
+    }
+
     pub async fn get_remote_commit(&self, commit: &str) -> Result<RemoteCommit, anyhow::Error> {
         let api_path = self.commits_api_path(commit);
         let response = self.client.get(api_path).send().await?;
@@ -1167,6 +1690,8 @@ pub fn contributors_from_commits(commits: &[PrCommit], forge: ForgeType) -> Vec<
     let mut contributors = commits
         .iter()
         .skip(1) // skip pr author
+        // Merge commits are created when updating the PR branch, so they don't contain any work.
+        .filter(|commit| !commit.is_merge())
         .flat_map(|commit| &commit.author)
         .filter(|author| {
             let is_gitea_actions_account = forge == ForgeType::Gitea && author.id == -2;
@@ -1181,7 +1706,221 @@ pub fn contributors_from_commits(commits: &[PrCommit], forge: ForgeType) -> Vec<
 
 #[cfg(test)]
 mod tests {
+    use wiremock::matchers::{body_json, body_partial_json, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
     use super::*;
+    use crate::RepoUrl;
+
+    const GITLAB_MR_PATH: &str = "/api/v4/projects/owner%2Frepo/merge_requests/1";
+
+    fn gitlab_client(server: &MockServer) -> GitClient {
+        let url = RepoUrl::new(&format!("{}/owner/repo", server.uri())).unwrap();
+        let gitlab = GitLab::new(url, SecretString::from("token")).unwrap();
+        GitClient::new(GitForge::Gitlab(gitlab)).unwrap()
+    }
+
+    fn pr(reviewers: &[&str], team_reviewers: &[&str]) -> Pr {
+        let to_strings = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        Pr {
+            base_branch: "main".to_string(),
+            branch: "release-plz".to_string(),
+            title: "chore: release".to_string(),
+            body: String::new(),
+            draft: false,
+            labels: vec![],
+            reviewers: to_strings(reviewers),
+            team_reviewers: to_strings(team_reviewers),
+            assignees: vec![],
+            milestone: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn gitlab_mr_is_not_edited_with_team_reviewers_only() {
+        let server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .and(path(GITLAB_MR_PATH))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let client = gitlab_client(&server);
+        client.assign_pr(&pr(&[], &["team"]), 1).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn gitlab_reviewers_are_requested_by_id() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v4/users"))
+            .and(query_param("username", "alice"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([{ "id": 7 }])))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path(GITLAB_MR_PATH))
+            .and(body_json(json!({ "reviewer_ids": [7] })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = gitlab_client(&server);
+        client
+            .assign_pr(&pr(&["alice"], &["team"]), 1)
+            .await
+            .unwrap();
+    }
+
+    const GITLAB_PROJECT_PATH: &str = "/api/v4/projects/owner%2Frepo";
+
+    async fn mock_gitlab_project(server: &MockServer, pipeline_must_succeed: bool) {
+        Mock::given(method("GET"))
+            .and(path(GITLAB_PROJECT_PATH))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "only_allow_merge_if_pipeline_succeeds": pipeline_must_succeed,
+            })))
+            .mount(server)
+            .await;
+    }
+
+    async fn mock_gitlab_merge(server: &MockServer, expected_calls: u64) {
+        Mock::given(method("PUT"))
+            .and(path(format!("{GITLAB_MR_PATH}/merge")))
+            .and(body_partial_json(json!({ "squash": true })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(expected_calls)
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn gitlab_auto_merge_with_rebase_is_rejected() {
+        let server = MockServer::start().await;
+        mock_gitlab_project(&server, true).await;
+        let client = gitlab_client(&server);
+        let error = client
+            .enable_auto_merge(1, "main", MergeMethod::Rebase)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("can't rebase"));
+        assert!(server.received_requests().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn gitlab_auto_merge_is_enabled_when_pipeline_must_succeed() {
+        let server = MockServer::start().await;
+        mock_gitlab_project(&server, true).await;
+        mock_gitlab_merge(&server, 1).await;
+        let client = gitlab_client(&server);
+        client
+            .enable_auto_merge(1, "main", MergeMethod::Squash)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn gitlab_auto_merge_is_not_enabled_without_required_pipeline() {
+        let server = MockServer::start().await;
+        mock_gitlab_project(&server, false).await;
+        mock_gitlab_merge(&server, 0).await;
+        let client = gitlab_client(&server);
+        let error = client
+            .enable_auto_merge(1, "main", MergeMethod::Squash)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("would be merged right away"));
+    }
+
+    async fn gitea_client_with_status_check(server: &MockServer, status_check: bool) -> GitClient {
+        Mock::given(method("GET"))
+            .and(path("/api/v1/version"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "version": "1.21.0" })))
+            .mount(server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/repos/owner/repo/branches/main"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "name": "main",
+                "enable_status_check": status_check,
+            })))
+            .mount(server)
+            .await;
+        let url = RepoUrl::new(&format!("{}/owner/repo", server.uri())).unwrap();
+        let gitea = Gitea::new(url, SecretString::from("token")).unwrap();
+        GitClient::new(GitForge::Gitea(gitea)).unwrap()
+    }
+
+    async fn mock_gitea_merge(server: &MockServer, expected_calls: u64) {
+        Mock::given(method("POST"))
+            .and(path("/api/v1/repos/owner/repo/pulls/1/merge"))
+            .and(body_json(json!({
+                "Do": "rebase",
+                "merge_when_checks_succeed": true,
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(expected_calls)
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn gitea_auto_merge_is_enabled_when_status_checks_are_required() {
+        let server = MockServer::start().await;
+        mock_gitea_merge(&server, 1).await;
+        let client = gitea_client_with_status_check(&server, true).await;
+        client
+            .enable_auto_merge(1, "main", MergeMethod::Rebase)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn gitea_auto_merge_is_not_enabled_without_required_status_checks() {
+        let server = MockServer::start().await;
+        mock_gitea_merge(&server, 0).await;
+        let client = gitea_client_with_status_check(&server, false).await;
+        let error = client
+            .enable_auto_merge(1, "main", MergeMethod::Rebase)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("would be merged right away"));
+    }
+
+    #[tokio::test]
+    async fn github_auto_merge_is_enabled_with_graphql() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .and(body_partial_json(
+                json!({ "variables": { "owner": "owner", "number": 1 } }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "repository": { "pullRequest": { "id": "PR_1" } } }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .and(body_partial_json(json!({
+                "variables": { "pullRequestId": "PR_1", "mergeMethod": "REBASE" }
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "enablePullRequestAutoMerge": { "clientMutationId": null } }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let github = GitHub::new("owner".to_string(), "repo".to_string(), "token".into())
+            .with_base_url(format!("{}/", server.uri()).parse().unwrap());
+        let client = GitClient::new(GitForge::Github(github)).unwrap();
+        client
+            .enable_auto_merge(1, "main", MergeMethod::Rebase)
+            .await
+            .unwrap();
+    }
 
     #[test]
     fn contributors_are_extracted_from_commits() {
@@ -1192,6 +1931,7 @@ mod tests {
                     login: "bob".to_string(),
                 }),
                 sha: "abc".to_string(),
+                parents: vec![],
             },
             PrCommit {
                 author: Some(Author {
@@ -1199,6 +1939,7 @@ mod tests {
                     login: "marco".to_string(),
                 }),
                 sha: "abc".to_string(),
+                parents: vec![],
             },
             PrCommit {
                 author: Some(Author {
@@ -1206,6 +1947,7 @@ mod tests {
                     login: "release[bot]".to_string(),
                 }),
                 sha: "abc".to_string(),
+                parents: vec![],
             },
             PrCommit {
                 author: Some(Author {
@@ -1213,10 +1955,27 @@ mod tests {
                     login: "gitea-actions".to_string(),
                 }),
                 sha: "abc".to_string(),
+                parents: vec![],
             },
             PrCommit {
                 author: None,
                 sha: "abc".to_string(),
+                parents: vec![],
+            },
+            PrCommit {
+                author: Some(Author {
+                    id: 4,
+                    login: "alice".to_string(),
+                }),
+                sha: "def".to_string(),
+                parents: vec![
+                    CommitParent {
+                        sha: "abc".to_string(),
+                    },
+                    CommitParent {
+                        sha: "123".to_string(),
+                    },
+                ],
             },
         ];
         let contributors = contributors_from_commits(&commits, ForgeType::Gitea);
@@ -1,6 +1,7 @@
 use crate::RepoUrl;
 use crate::git::forge::Remote;
 use anyhow::{Context, bail};
+use cargo_metadata::semver::Version;
 use reqwest::header::HeaderMap;
 use reqwest::header::HeaderValue;
 use secrecy::{ExposeSecret, SecretString};
@@ -43,3 +44,142 @@ impl Gitea {
         Ok(headers)
     }
 }
+
+/// Server implementing the Gitea API, i.e. Gitea or Forgejo (e.g. Codeberg).
+/// It's detected from the `/version` endpoint of the API, so that release-plz can adapt
+/// to the features supported by the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GiteaServer {
+    pub flavor: GiteaFlavor,
+    /// Version of the Gitea API implemented by the server.
+    /// For Forgejo, it's the Gitea version Forgejo is compatible with.
+    pub api_version: Version,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GiteaFlavor {
+    Gitea,
+    Forgejo,
+}
+
+/// First major version of Forgejo that doesn't follow Gitea versioning.
+const FORGEJO_FIRST_MAJOR: u64 = 7;
+
+/// Gitea API implemented by Forgejo 7.0.
+/// Forgejo hard forked Gitea 1.21, so all the following versions support at least this API.
+const FORGEJO_7_API_VERSION: Version = Version::new(1, 21, 0);
+
+impl GiteaServer {
+    /// Parse the version returned by the server.
+    /// Forgejo returns versions like `7.0.0+gitea-1.21.0`, where the build metadata
+    /// contains the Gitea version Forgejo is compatible with.
+    /// Forgejo versions without it are mapped to the Gitea API of Forgejo 7.0, because
+    /// Gitea versions start with `1.`.
+    /// Forgejo versions before 7.0 follow Gitea versioning, so they are treated as Gitea.
+    /// Returns `None` if the version can't be parsed, e.g. for development builds.
+    pub fn from_version(version: &str) -> Option<Self> {
+        if let Some((_, gitea_version)) = version.split_once("+gitea-") {
+            return Some(Self {
+                flavor: GiteaFlavor::Forgejo,
+                api_version: Version::parse(gitea_version).ok()?,
+            });
+        }
+        let version = Version::parse(version).ok()?;
+        if version.major >= FORGEJO_FIRST_MAJOR {
+            return Some(Self {
+                flavor: GiteaFlavor::Forgejo,
+                api_version: FORGEJO_7_API_VERSION,
+            });
+        }
+        Some(Self {
+            flavor: GiteaFlavor::Gitea,
+            api_version: version,
+        })
+    }
+
+    /// Server used when the version can't be detected. We assume it supports all the features.
+    pub fn latest() -> Self {
+        Self {
+            flavor: GiteaFlavor::Gitea,
+            api_version: Version::new(u64::MAX, 0, 0),
+        }
+    }
+
+    fn supports(&self, major: u64, minor: u64) -> bool {
+        // Ignore pre-releases, e.g. `1.21.11-2` of Forgejo, because they support the same API.
+        let version = Version::new(
+            self.api_version.major,
+            self.api_version.minor,
+            self.api_version.patch,
+        );
+        version >= Version::new(major, minor, 0)
+    }
+
+    /// Whether the server can commit multiple files with the `/contents` endpoint.
+    /// Added in Gitea 1.20.
+    pub fn supports_change_files(&self) -> bool {
+        self.supports(1, 20)
+    }
+
+    /// Whether the server can merge a PR automatically when checks succeed.
+    /// Added in Gitea 1.17.
+    pub fn supports_auto_merge(&self) -> bool {
+        self.supports(1, 17)
+    }
+
+    /// Whether the server can create annotated tags with the `/tags` endpoint.
+    /// Added in Gitea 1.15.
+    pub fn supports_tags_api(&self) -> bool {
+        self.supports(1, 15)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self.flavor {
+            GiteaFlavor::Gitea => "Gitea",
+            GiteaFlavor::Forgejo => "Forgejo",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gitea_version_is_parsed() {
+        let server = GiteaServer::from_version("1.25.4").unwrap();
+        assert_eq!(server.flavor, GiteaFlavor::Gitea);
+        assert!(server.supports_change_files());
+
+        let old_server = GiteaServer::from_version("1.19.0").unwrap();
+        assert!(!old_server.supports_change_files());
+        assert!(old_server.supports_auto_merge());
+    }
+
+    #[test]
+    fn forgejo_version_is_parsed() {
+        let server = GiteaServer::from_version("11.0.1+gitea-1.22.0").unwrap();
+        assert_eq!(server.flavor, GiteaFlavor::Forgejo);
+        assert_eq!(server.api_version, Version::new(1, 22, 0));
+        assert!(server.supports_change_files());
+
+        // Without the Gitea version, Forgejo is mapped to the API of Forgejo 7.0.
+        let server = GiteaServer::from_version("12.0.0").unwrap();
+        assert_eq!(server.flavor, GiteaFlavor::Forgejo);
+        assert_eq!(server.api_version, FORGEJO_7_API_VERSION);
+        assert!(server.supports_change_files());
+        let server = GiteaServer::from_version("7.0.0-dev-123-abc").unwrap();
+        assert_eq!(server.flavor, GiteaFlavor::Forgejo);
+        assert!(server.supports_auto_merge());
+
+        // Forgejo versions before 7.0 follow Gitea versioning.
+        let old_server = GiteaServer::from_version("1.21.11-2").unwrap();
+        assert_eq!(old_server.flavor, GiteaFlavor::Gitea);
+        assert!(old_server.supports_change_files());
+    }
+
+    #[test]
+    fn development_version_is_not_parsed() {
+        assert_eq!(GiteaServer::from_version("development"), None);
+    }
+}
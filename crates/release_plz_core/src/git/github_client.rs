@@ -4,10 +4,14 @@ use secrecy::{ExposeSecret, SecretString};
 use url::Url;
 
 use crate::git::forge::Remote;
+use crate::git::github_app::GitHubApp;
 
 #[derive(Debug, Clone)]
 pub struct GitHub {
     pub remote: Remote,
+    /// If set, requests are authenticated with an installation token of the app
+    /// instead of `remote.token`.
+    pub app: Option<GitHubApp>,
 }
 
 impl GitHub {
@@ -19,6 +23,16 @@ impl GitHub {
                 token,
                 base_url: "https://api.github.com".parse().unwrap(),
             },
+            app: None,
+        }
+    }
+
+    /// Authenticate as the installation of the GitHub App in the repository.
+    /// The installation token is created on the first request and refreshed when it expires.
+    pub fn from_app(owner: String, repo: String, app: GitHubApp) -> Self {
+        Self {
+            app: Some(app),
+            ..Self::new(owner, repo, SecretString::default())
         }
     }
 
@@ -28,6 +42,7 @@ impl GitHub {
                 base_url,
                 ..self.remote
             },
+            ..self
         }
     }
 
@@ -37,11 +52,15 @@ impl GitHub {
             reqwest::header::ACCEPT,
             HeaderValue::from_static("application/vnd.github+json"),
         );
-        let mut auth_header: HeaderValue = format!("Bearer {}", self.remote.token.expose_secret())
-            .parse()
-            .context("invalid GitHub token")?;
-        auth_header.set_sensitive(true);
-        headers.insert(reqwest::header::AUTHORIZATION, auth_header);
+        // The GitHub App authenticates each request with its installation token.
+        if self.app.is_none() {
+            let mut auth_header: HeaderValue =
+                format!("Bearer {}", self.remote.token.expose_secret())
+                    .parse()
+                    .context("invalid GitHub token")?;
+            auth_header.set_sensitive(true);
+            headers.insert(reqwest::header::AUTHORIZATION, auth_header);
+        }
         Ok(headers)
     }
 }
@@ -7,7 +7,7 @@ use tracing::{debug, trace};
 use url::Url;
 
 use crate::GitClient;
-use crate::git::forge::Remote;
+use crate::git::forge::{MergeMethod, Remote};
 
 /// Commit all the changes (except typestates) that are present in the repository
 /// using GitHub's [GraphQL api](https://docs.github.com/en/graphql/reference/mutations#createcommitonbranch).
@@ -53,9 +53,115 @@ pub async fn commit_changes(
     Ok(commit_sha)
 }
 
+/// Numbers of the issues that the PR closes when merged.
+/// See GitHub's [docs](https://docs.github.com/en/graphql/reference/objects#pullrequest).
+pub async fn closing_issues(client: &GitClient, pr_number: u64) -> Result<Vec<u64>> {
+    let query = json!({
+        "query": "query($owner: String!, $repo: String!, $number: Int!) {
+            repository(owner: $owner, name: $repo) {
+                pullRequest(number: $number) {
+                    closingIssuesReferences(first: 100) { nodes { number } }
+                }
+            }
+        }",
+        "variables": {
+            "owner": client.remote.owner,
+            "repo": client.remote.repo,
+            "number": pr_number,
+        }
+    });
+    let res = send_query(client, &query, "closingIssuesReferences").await?;
+
+    let issues = res
+        .pointer("/data/repository/pullRequest/closingIssuesReferences/nodes")
+        .and_then(Value::as_array)
+        .with_context(|| format!("closingIssuesReferences did not return issues: {res}"))?
+        .iter()
+        .filter_map(|issue| issue.get("number").and_then(Value::as_u64))
+        .collect();
+    Ok(issues)
+}
+
+/// Merge the PR automatically when the required checks pass, using the
+/// [enablePullRequestAutoMerge](https://docs.github.com/en/graphql/reference/mutations#enablepullrequestautomerge)
+/// mutation.
+pub async fn enable_auto_merge(
+    client: &GitClient,
+    pr_number: u64,
+    merge_method: MergeMethod,
+) -> Result<()> {
+    let query = json!({
+        "query": "query($owner: String!, $repo: String!, $number: Int!) {
+            repository(owner: $owner, name: $repo) {
+                pullRequest(number: $number) { id }
+            }
+        }",
+        "variables": {
+            "owner": client.remote.owner,
+            "repo": client.remote.repo,
+            "number": pr_number,
+        }
+    });
+    let res = send_query(client, &query, "pullRequest").await?;
+    let pr_id = res
+        .pointer("/data/repository/pullRequest/id")
+        .and_then(Value::as_str)
+        .with_context(|| format!("pullRequest did not return the PR id: {res}"))?;
+
+    let merge_method = match merge_method {
+        MergeMethod::Merge => "MERGE",
+        MergeMethod::Squash => "SQUASH",
+        MergeMethod::Rebase => "REBASE",
+    };
+    let mutation = json!({
+        "query": "mutation($pullRequestId: ID!, $mergeMethod: PullRequestMergeMethod!) {
+            enablePullRequestAutoMerge(input: {pullRequestId: $pullRequestId, mergeMethod: $mergeMethod}) {
+                clientMutationId
+            }
+        }",
+        "variables": {
+            "pullRequestId": pr_id,
+            "mergeMethod": merge_method,
+        }
+    });
+    send_query(client, &mutation, "enablePullRequestAutoMerge").await?;
+    Ok(())
+}
+
+/// Send a GraphQL query and fail if the response contains errors.
+async fn send_query(client: &GitClient, query: &Value, name: &str) -> Result<Value> {
+    let graphql_endpoint = get_graphql_endpoint(&client.remote);
+    debug!("Sending {name} query to {}", graphql_endpoint);
+    trace!("{}", query);
+
+    let res: Value = client
+        .client
+        .post(graphql_endpoint)
+        .json(query)
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    if let Some(errors) = res.get("errors").and_then(Value::as_array) {
+        anyhow::bail!(
+            "{name} returned errors: {:?}",
+            serde_json::to_string(errors)?
+        );
+    }
+    Ok(res)
+}
+
+/// GraphQL endpoint of the GitHub instance.
+/// GitHub Enterprise Server serves the REST API under `/api/v3` and GraphQL under `/api/graphql`.
 fn get_graphql_endpoint(remote: &Remote) -> Url {
     let mut base_url = remote.base_url.clone();
-    base_url.set_path("graphql");
+    let rest_path = base_url.path().trim_end_matches('/');
+    let graphql_path = match rest_path.strip_suffix("/v3") {
+        Some(api_path) => format!("{api_path}/graphql"),
+        None => format!("{rest_path}/graphql"),
+    };
+    base_url.set_path(&graphql_path);
 
     base_url
 }
@@ -259,4 +365,25 @@ mod tests {
         expect_test::expect![[r#""mutation($input:CreateCommitOnBranchInput!){createCommitOnBranch(input:$input){commit{oid}}}""#]]
         .assert_eq(&query["query"].to_string());
     }
+
+    #[test]
+    fn graphql_endpoint_is_derived_from_rest_api() {
+        let endpoint = |base_url: &str| {
+            let remote = Remote {
+                owner: "owner".to_string(),
+                repo: "repo".to_string(),
+                token: "token".into(),
+                base_url: base_url.parse().unwrap(),
+            };
+            get_graphql_endpoint(&remote).to_string()
+        };
+        assert_eq!(
+            endpoint("https://api.github.com/"),
+            "https://api.github.com/graphql"
+        );
+        assert_eq!(
+            endpoint("https://github.example.com/api/v3/"),
+            "https://github.example.com/api/graphql"
+        );
+    }
 }
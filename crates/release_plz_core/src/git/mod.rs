@@ -1,5 +1,9 @@
+pub mod api_commit;
 pub mod forge;
 pub mod gitea_client;
+pub mod github_app;
 pub mod github_client;
 pub mod github_graphql;
 pub mod gitlab_client;
+pub mod release_checks;
+pub mod token_permissions;
@@ -0,0 +1,424 @@
+//! Reviews and CI status of a release PR, used to gate the release.
+
+use std::collections::BTreeMap;
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use url::Url;
+
+use crate::git::forge::{ForgeType, GitClient};
+use crate::response_ext::ResponseExt;
+
+/// State of a CI check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckState {
+    Success,
+    Pending,
+    Failure,
+}
+
+/// CI check (status, check run or pipeline) of a commit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub name: String,
+    pub state: CheckState,
+}
+
+#[derive(Deserialize, Debug)]
+struct ReviewUser {
+    login: String,
+}
+
+/// Review of a GitHub or Gitea PR.
+#[derive(Deserialize, Debug)]
+struct Review {
+    user: Option<ReviewUser>,
+    state: String,
+    /// Only present in Gitea.
+    #[serde(default)]
+    dismissed: bool,
+}
+
+/// Users who approved the PR and didn't revoke their approval.
+pub async fn pr_approvers(client: &GitClient, pr_number: u64) -> anyhow::Result<Vec<String>> {
+    let url = format!("{}/{pr_number}", client.pulls_url());
+    match client.forge {
+        ForgeType::Github | ForgeType::Gitea => {
+            let reviews = reviews(client, &format!("{url}/reviews")).await?;
+            Ok(approvers(&reviews))
+        }
+        ForgeType::Gitlab => {
+            // Docs: https://docs.gitlab.com/api/merge_request_approvals/#retrieve-approval-state-for-a-merge-request
+            #[derive(Deserialize)]
+            struct Approvals {
+                approved_by: Vec<ApprovedBy>,
+            }
+            #[derive(Deserialize)]
+            struct ApprovedBy {
+                user: GitLabUser,
+            }
+            #[derive(Deserialize)]
+            struct GitLabUser {
+                username: String,
+            }
+            let approvals: Approvals = get_json(client, &format!("{url}/approvals")).await?;
+            Ok(approvals
+                .approved_by
+                .into_iter()
+                .map(|a| a.user.username)
+                .collect())
+        }
+    }
+}
+
+async fn reviews(client: &GitClient, url: &str) -> anyhow::Result<Vec<Review>> {
+    paginated(client, url, |reviews: Vec<Review>| reviews).await
+}
+
+/// Fetches all the pages of a list, extracted from each response with `items`.
+async fn paginated<P, T>(
+    client: &GitClient,
+    url: &str,
+    items: impl Fn(P) -> Vec<T>,
+) -> anyhow::Result<Vec<T>>
+where
+    P: serde::de::DeserializeOwned,
+{
+    let mut page = 1;
+    let page_size = 50;
+    let mut all_items = vec![];
+    loop {
+        let mut page_url = Url::parse(url).with_context(|| format!("invalid URL {url}"))?;
+        page_url
+            .query_pairs_mut()
+            .append_pair("page", &page.to_string())
+            .append_pair(client.per_page(), &page_size.to_string());
+        let page_items = items(get_json(client, page_url.as_str()).await?);
+        let page_len = page_items.len();
+        all_items.extend(page_items);
+        if page_len < page_size {
+            break;
+        }
+        page += 1;
+    }
+    Ok(all_items)
+}
+
+/// Users whose latest review approves the PR.
+/// Reviews are in chronological order.
+/// Comments don't change the verdict of a previous review.
+fn approvers(reviews: &[Review]) -> Vec<String> {
+    let mut verdicts: BTreeMap<&str, bool> = BTreeMap::new();
+    for review in reviews {
+        let Some(user) = &review.user else {
+            continue;
+        };
+        let approved = match review.state.as_str() {
+            "APPROVED" => !review.dismissed,
+            "CHANGES_REQUESTED" | "REQUEST_CHANGES" | "DISMISSED" => false,
+            _ => continue,
+        };
+        verdicts.insert(&user.login, approved);
+    }
+    verdicts
+        .into_iter()
+        .filter(|(_, approved)| *approved)
+        .map(|(user, _)| user.to_string())
+        .collect()
+}
+
+/// CI checks that ran on `sha`, the last commit of the PR.
+pub async fn pr_checks(
+    client: &GitClient,
+    pr_number: u64,
+    sha: &str,
+) -> anyhow::Result<Vec<Check>> {
+    let commit_url = format!("{}/commits/{sha}", client.repo_url());
+    match client.forge {
+        ForgeType::Github => {
+            // Docs: https://docs.github.com/en/rest/commits/statuses#get-the-combined-status-for-a-specific-reference
+            let combined: CombinedStatus =
+                get_json(client, &format!("{commit_url}/status")).await?;
+            let mut checks = status_checks(combined);
+            // Docs: https://docs.github.com/en/rest/checks/runs#list-check-runs-for-a-git-reference
+            let check_runs = paginated(
+                client,
+                &format!("{commit_url}/check-runs"),
+                |runs: CheckRuns| runs.check_runs,
+            )
+            .await?;
+            checks.extend(check_runs.into_iter().map(Check::from));
+            Ok(checks)
+        }
+        ForgeType::Gitea => {
+            // Docs: https://gitea.com/api/swagger#/repository/repoGetCombinedStatusByRef
+            let combined: CombinedStatus =
+                get_json(client, &format!("{commit_url}/status")).await?;
+            Ok(status_checks(combined))
+        }
+        ForgeType::Gitlab => {
+            // The newest pipeline of the commit can be the one running `release-plz release`,
+            // so use the pipeline of the MR instead.
+            // Docs: https://docs.gitlab.com/api/merge_requests/#get-single-mr
+            #[derive(Deserialize)]
+            struct MergeRequest {
+                head_pipeline: Option<Pipeline>,
+            }
+            let mr: MergeRequest =
+                get_json(client, &format!("{}/{pr_number}", client.pulls_url())).await?;
+            Ok(mr
+                .head_pipeline
+                .filter(|pipeline| pipeline.sha == sha)
+                .map(Check::from)
+                .into_iter()
+                .collect())
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct CombinedStatus {
+    statuses: Vec<CommitStatus>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CommitStatus {
+    context: String,
+    /// GitHub calls this field `state`, Gitea calls it `status`.
+    #[serde(alias = "status")]
+    state: String,
+}
+
+fn status_checks(combined: CombinedStatus) -> Vec<Check> {
+    combined
+        .statuses
+        .into_iter()
+        .map(|status| Check {
+            state: match status.state.as_str() {
+                "success" => CheckState::Success,
+                "pending" => CheckState::Pending,
+                _ => CheckState::Failure,
+            },
+            name: status.context,
+        })
+        .collect()
+}
+
+#[derive(Deserialize, Debug)]
+struct CheckRuns {
+    check_runs: Vec<CheckRun>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CheckRun {
+    name: String,
+    status: String,
+    conclusion: Option<String>,
+}
+
+impl From<CheckRun> for Check {
+    fn from(run: CheckRun) -> Self {
+        let state = match (run.status.as_str(), run.conclusion.as_deref()) {
+            ("completed", Some("success" | "neutral" | "skipped")) => CheckState::Success,
+            ("completed", _) => CheckState::Failure,
+            _ => CheckState::Pending,
+        };
+        Self {
+            name: run.name,
+            state,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct Pipeline {
+    id: u64,
+    sha: String,
+    status: String,
+}
+
+impl From<Pipeline> for Check {
+    fn from(pipeline: Pipeline) -> Self {
+        let state = match pipeline.status.as_str() {
+            "success" => CheckState::Success,
+            "failed" | "canceled" | "skipped" => CheckState::Failure,
+            _ => CheckState::Pending,
+        };
+        Self {
+            name: format!("pipeline #{}", pipeline.id),
+            state,
+        }
+    }
+}
+
+async fn get_json<T: serde::de::DeserializeOwned>(
+    client: &GitClient,
+    url: &str,
+) -> anyhow::Result<T> {
+    client
+        .client
+        .get(url)
+        .send()
+        .await?
+        .successful_status()
+        .await?
+        .json()
+        .await
+        .with_context(|| format!("failed to parse response of {url}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::SecretString;
+    use serde_json::json;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::git::forge::GitForge;
+    use crate::{GitHub, GitLab, RepoUrl};
+
+    fn review(user: &str, state: &str) -> Review {
+        Review {
+            user: Some(ReviewUser {
+                login: user.to_string(),
+            }),
+            state: state.to_string(),
+            dismissed: false,
+        }
+    }
+
+    #[test]
+    fn latest_review_of_each_user_counts() {
+        let reviews = [
+            review("alice", "APPROVED"),
+            review("bob", "APPROVED"),
+            review("bob", "CHANGES_REQUESTED"),
+            review("carol", "CHANGES_REQUESTED"),
+            review("carol", "APPROVED"),
+            review("alice", "COMMENTED"),
+        ];
+        assert_eq!(approvers(&reviews), ["alice", "carol"]);
+    }
+
+    #[test]
+    fn dismissed_gitea_review_is_not_an_approval() {
+        let mut dismissed = review("alice", "APPROVED");
+        dismissed.dismissed = true;
+        assert!(approvers(&[dismissed]).is_empty());
+    }
+
+    #[test]
+    fn check_runs_are_converted() {
+        let checks: CheckRuns = serde_json::from_str(
+            r#"{"total_count": 3, "check_runs": [
+                {"name": "test", "status": "completed", "conclusion": "success"},
+                {"name": "lint", "status": "completed", "conclusion": "failure"},
+                {"name": "docs", "status": "in_progress", "conclusion": null}
+            ]}"#,
+        )
+        .unwrap();
+        let checks: Vec<Check> = checks.check_runs.into_iter().map(Check::from).collect();
+        let states: Vec<CheckState> = checks.iter().map(|c| c.state).collect();
+        assert_eq!(
+            states,
+            [
+                CheckState::Success,
+                CheckState::Failure,
+                CheckState::Pending
+            ]
+        );
+    }
+
+    #[test]
+    fn gitea_statuses_are_converted() {
+        let combined: CombinedStatus = serde_json::from_str(
+            r#"{"state": "failure", "statuses": [
+                {"context": "ci/test", "status": "success"},
+                {"context": "ci/lint", "status": "error"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            status_checks(combined),
+            [
+                Check {
+                    name: "ci/test".to_string(),
+                    state: CheckState::Success
+                },
+                Check {
+                    name: "ci/lint".to_string(),
+                    state: CheckState::Failure
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn all_pages_of_github_check_runs_are_fetched() {
+        let server = MockServer::start().await;
+        let commit_path = "/repos/owner/repo/commits/abc";
+        Mock::given(method("GET"))
+            .and(path(format!("{commit_path}/status")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "statuses": [] })))
+            .mount(&server)
+            .await;
+        let check_run = |status: &str, conclusion: Option<&str>| json!({ "name": "test", "status": status, "conclusion": conclusion });
+        let first_page = vec![check_run("completed", Some("success")); 50];
+        for (page, runs) in [("1", first_page), ("2", vec![check_run("queued", None)])] {
+            Mock::given(method("GET"))
+                .and(path(format!("{commit_path}/check-runs")))
+                .and(query_param("page", page))
+                .respond_with(
+                    ResponseTemplate::new(200).set_body_json(json!({ "check_runs": runs })),
+                )
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        let github = GitHub::new("owner".to_string(), "repo".to_string(), "token".into())
+            .with_base_url(server.uri().parse().unwrap());
+        let client = GitClient::new(GitForge::Github(github)).unwrap();
+
+        let checks = pr_checks(&client, 1, "abc").await.unwrap();
+
+        assert_eq!(checks.len(), 51);
+        assert_eq!(checks.last().unwrap().state, CheckState::Pending);
+    }
+
+    #[tokio::test]
+    async fn gitlab_checks_come_from_the_pipeline_of_the_mr() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v4/projects/owner%2Frepo/merge_requests/1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "iid": 1,
+                "head_pipeline": { "id": 10, "sha": "abc", "status": "success" },
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+        // The newest pipeline of the commit is the release pipeline, which is still running.
+        Mock::given(method("GET"))
+            .and(path("/api/v4/projects/owner%2Frepo/pipelines"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                { "id": 11, "sha": "abc", "status": "running" },
+            ])))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let url = RepoUrl::new(&format!("{}/owner/repo", server.uri())).unwrap();
+        let gitlab = GitLab::new(url, SecretString::from("token")).unwrap();
+        let client = GitClient::new(GitForge::Gitlab(gitlab)).unwrap();
+
+        assert_eq!(
+            pr_checks(&client, 1, "abc").await.unwrap(),
+            [Check {
+                name: "pipeline #10".to_string(),
+                state: CheckState::Success
+            }]
+        );
+        // The MR pipeline ran on an older commit.
+        assert!(pr_checks(&client, 1, "def").await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,359 @@
+//! Permissions of the git token on the repository, used to diagnose the setup.
+
+use anyhow::Context as _;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use url::Url;
+
+use crate::git::forge::{ForgeType, GitClient};
+
+/// Minimum GitLab access level needed to push branches and tags and open merge requests.
+/// Docs: <https://docs.gitlab.com/api/members/#roles>
+const GITLAB_MAINTAINER: u32 = 40;
+
+/// Result of the check of the token.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct TokenCheck {
+    /// Problems of the token that prevent release-plz from opening PRs and creating releases.
+    /// An empty list means that the token looks fine, although the forge might still
+    /// reject some requests, e.g. because of branch protection rules.
+    pub problems: Vec<String>,
+    /// Whether release-plz could verify that the token can open pull requests.
+    /// Only the scopes of classic GitHub tokens and the GitLab roles tell it:
+    /// for the other tokens, release-plz can only verify that they can read pull requests.
+    pub pr_permission_verified: bool,
+}
+
+/// Check the permissions of the token on the repository.
+pub async fn check_token(client: &GitClient) -> anyhow::Result<TokenCheck> {
+    let url = client.repo_url();
+    let response = client
+        .client
+        .get(&url)
+        .send()
+        .await
+        .with_context(|| format!("failed to send request to {url}"))?;
+    match response.status() {
+        StatusCode::UNAUTHORIZED => {
+            return Ok(TokenCheck::problem(
+                "the token is invalid or expired".to_string(),
+            ));
+        }
+        StatusCode::NOT_FOUND | StatusCode::FORBIDDEN => {
+            return Ok(TokenCheck::problem(format!(
+                "the token can't access the repository `{}`",
+                client.remote.owner_slash_repo()
+            )));
+        }
+        _ => {}
+    }
+    // Only present for classic GitHub Personal Access Tokens.
+    let scopes = response
+        .headers()
+        .get("x-oauth-scopes")
+        .and_then(|scopes| scopes.to_str().ok())
+        .map(str::to_string);
+    let body = response
+        .error_for_status()?
+        .text()
+        .await
+        .context("can't read response body")?;
+    let check = match client.forge {
+        ForgeType::Github | ForgeType::Gitea => {
+            let repo: Repo = serde_json::from_str(&body)
+                .with_context(|| format!("failed to parse response of {url}"))?;
+            let mut problems = repo_problems(&repo, scopes.as_deref());
+            // The scopes of classic GitHub tokens tell whether the token can open PRs.
+            // For the other tokens, check that it can at least read them.
+            let pr_permission_verified = scopes.is_some();
+            if !pr_permission_verified {
+                problems.extend(pull_requests_problem(client).await?);
+            }
+            TokenCheck {
+                problems,
+                pr_permission_verified,
+            }
+        }
+        ForgeType::Gitlab => {
+            let project: Project = serde_json::from_str(&body)
+                .with_context(|| format!("failed to parse response of {url}"))?;
+            TokenCheck {
+                problems: project_problems(&project),
+                pr_permission_verified: true,
+            }
+        }
+    };
+    Ok(check)
+}
+
+impl TokenCheck {
+    fn problem(problem: String) -> Self {
+        Self {
+            problems: vec![problem],
+            pr_permission_verified: false,
+        }
+    }
+}
+
+/// Check that the token can read the pull requests of the repository.
+/// This is the case for fine-grained GitHub tokens, `GITHUB_TOKEN`, GitHub App tokens
+/// and Gitea tokens, whose permissions aren't returned by the API.
+async fn pull_requests_problem(client: &GitClient) -> anyhow::Result<Option<String>> {
+    let mut url = Url::parse(&client.pulls_url()).context("invalid pulls URL")?;
+    url.query_pairs_mut().append_pair(client.per_page(), "1");
+    let response = client
+        .client
+        .get(url.clone())
+        .send()
+        .await
+        .with_context(|| format!("failed to send request to {url}"))?;
+    if !matches!(
+        response.status(),
+        StatusCode::NOT_FOUND | StatusCode::FORBIDDEN
+    ) {
+        response.error_for_status()?;
+        return Ok(None);
+    }
+    // GitHub returns the permissions that the request needs.
+    let accepted_permissions = response
+        .headers()
+        .get("x-accepted-github-permissions")
+        .and_then(|permissions| permissions.to_str().ok())
+        .map(str::to_string);
+    Ok(Some(pull_requests_message(accepted_permissions.as_deref())))
+}
+
+fn pull_requests_message(accepted_permissions: Option<&str>) -> String {
+    let message = "the token can't read the pull requests of the repository, \
+        so release-plz can't open the release PR";
+    match accepted_permissions {
+        Some(permissions) => format!(
+            "{message}: the request needs the `{permissions}` permission, \
+            and release-plz needs `pull_requests=write`"
+        ),
+        None => message.to_string(),
+    }
+}
+
+/// GitHub or Gitea repository.
+#[derive(Deserialize, Debug)]
+struct Repo {
+    /// Missing for tokens that don't belong to a user, e.g. GitHub App installation tokens.
+    permissions: Option<RepoPermissions>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RepoPermissions {
+    push: bool,
+}
+
+fn repo_problems(repo: &Repo, scopes: Option<&str>) -> Vec<String> {
+    let mut problems = vec![];
+    if repo.permissions.as_ref().is_some_and(|p| !p.push) {
+        problems.push(
+            "the token doesn't have write access to the repository, \
+            so release-plz can't push the release PR branch and the tags"
+                .to_string(),
+        );
+    }
+    if let Some(scopes) = scopes {
+        let has_repo_scope = scopes
+            .split(',')
+            .map(str::trim)
+            .any(|scope| scope == "repo" || scope == "public_repo");
+        if !has_repo_scope {
+            problems.push(format!(
+                "the token has the scopes `{scopes}`, \
+                but release-plz needs the `repo` scope to open pull requests"
+            ));
+        }
+    }
+    problems
+}
+
+#[derive(Deserialize, Debug)]
+struct Project {
+    permissions: ProjectPermissions,
+}
+
+#[derive(Deserialize, Debug)]
+struct ProjectPermissions {
+    project_access: Option<Access>,
+    group_access: Option<Access>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Access {
+    access_level: u32,
+}
+
+fn project_problems(project: &Project) -> Vec<String> {
+    let access_level = [
+        &project.permissions.project_access,
+        &project.permissions.group_access,
+    ]
+    .into_iter()
+    .flatten()
+    .map(|access| access.access_level)
+    .max()
+    .unwrap_or_default();
+    if access_level < GITLAB_MAINTAINER {
+        vec![format!(
+            "the token has access level {access_level}, but release-plz needs the Maintainer role \
+            (access level {GITLAB_MAINTAINER}) to push the release branch and the tags"
+        )]
+    } else {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::SecretString;
+    use serde_json::json;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::{GitForge, GitHub, Gitea, RepoUrl};
+
+    #[test]
+    fn read_only_repo_token_is_reported() {
+        let repo: Repo = serde_json::from_str(
+            r#"{"permissions": {"admin": false, "push": false, "pull": true}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            repo_problems(&repo, Some("read:org, workflow")),
+            [
+                "the token doesn't have write access to the repository, so release-plz can't push the release PR branch and the tags",
+                "the token has the scopes `read:org, workflow`, but release-plz needs the `repo` scope to open pull requests"
+            ]
+        );
+        let repo: Repo = serde_json::from_str(r#"{"permissions": {"push": true}}"#).unwrap();
+        assert!(repo_problems(&repo, Some("repo, workflow")).is_empty());
+        assert!(repo_problems(&repo, None).is_empty());
+    }
+
+    fn github_client(server: &MockServer) -> GitClient {
+        let github = GitHub::new("owner".to_string(), "repo".to_string(), "token".into())
+            .with_base_url(format!("{}/", server.uri()).parse().unwrap());
+        GitClient::new(GitForge::Github(github)).unwrap()
+    }
+
+    async fn mock_repo(server: &MockServer, repo_path: &str, response: ResponseTemplate) {
+        Mock::given(method("GET"))
+            .and(path(repo_path))
+            .respond_with(response)
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn fine_grained_github_token_without_pull_requests_permission_is_reported() {
+        let server = MockServer::start().await;
+        let repo = json!({ "permissions": { "push": true } });
+        mock_repo(
+            &server,
+            "/repos/owner/repo",
+            ResponseTemplate::new(200).set_body_json(repo),
+        )
+        .await;
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/pulls"))
+            .and(query_param("per_page", "1"))
+            .respond_with(
+                ResponseTemplate::new(403)
+                    .insert_header("x-accepted-github-permissions", "pull_requests=read"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let check = check_token(&github_client(&server)).await.unwrap();
+        assert_eq!(
+            check,
+            TokenCheck {
+                problems: vec![
+                    "the token can't read the pull requests of the repository, so release-plz can't open the release PR: the request needs the `pull_requests=read` permission, and release-plz needs `pull_requests=write`".to_string()
+                ],
+                pr_permission_verified: false,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn classic_github_token_scopes_verify_pull_requests_permission() {
+        let server = MockServer::start().await;
+        let repo = json!({ "permissions": { "push": true } });
+        mock_repo(
+            &server,
+            "/repos/owner/repo",
+            ResponseTemplate::new(200)
+                .set_body_json(repo)
+                .insert_header("x-oauth-scopes", "repo, workflow"),
+        )
+        .await;
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/pulls"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let check = check_token(&github_client(&server)).await.unwrap();
+        assert_eq!(
+            check,
+            TokenCheck {
+                problems: vec![],
+                pr_permission_verified: true,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn gitea_token_that_reads_pull_requests_is_not_verified() {
+        let server = MockServer::start().await;
+        let repo_path = "/api/v1/repos/owner/repo";
+        let repo = json!({ "permissions": { "push": true } });
+        mock_repo(
+            &server,
+            repo_path,
+            ResponseTemplate::new(200).set_body_json(repo),
+        )
+        .await;
+        Mock::given(method("GET"))
+            .and(path(format!("{repo_path}/pulls")))
+            .and(query_param("limit", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let url = RepoUrl::new(&format!("{}/owner/repo", server.uri())).unwrap();
+        let gitea = Gitea::new(url, SecretString::from("token")).unwrap();
+        let client = GitClient::new(GitForge::Gitea(gitea)).unwrap();
+
+        let check = check_token(&client).await.unwrap();
+        assert_eq!(check, TokenCheck::default());
+    }
+
+    #[test]
+    fn gitlab_access_level_is_checked() {
+        let project: Project = serde_json::from_str(
+            r#"{"permissions": {"project_access": {"access_level": 30}, "group_access": null}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            project_problems(&project),
+            [
+                "the token has access level 30, but release-plz needs the Maintainer role (access level 40) to push the release branch and the tags"
+            ]
+        );
+        let project: Project = serde_json::from_str(
+            r#"{"permissions": {"project_access": {"access_level": 30}, "group_access": {"access_level": 50}}}"#,
+        )
+        .unwrap();
+        assert!(project_problems(&project).is_empty());
+    }
+}
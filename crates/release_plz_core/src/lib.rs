@@ -32,13 +32,16 @@ mod version;
 pub use changelog::*;
 pub use command::*;
 pub use download::{PackageDownloader, read_package};
-pub use git::forge::{GitClient, GitForge, GitPr};
+pub use git::forge::{ForgeType, GitClient, GitForge, GitPr, MergeMethod};
 pub use git::gitea_client::Gitea;
+pub use git::github_app::GitHubApp;
 pub use git::github_client::GitHub;
 pub use git::gitlab_client::GitLab;
+pub use git::token_permissions::{TokenCheck, check_token};
 pub use next_ver::*;
 pub use package_compare::*;
 pub use package_path::*;
 pub use pr::{DEFAULT_BRANCH_PREFIX, Pr};
 pub use project::*;
 pub use repo_url::*;
+pub use tera::ConfigTemplate;
@@ -7,7 +7,7 @@ use crate::tmp_repo::TempRepo;
 use crate::update_request::UpdateRequest;
 use crate::updater::Updater;
 use crate::{
-    PackagesUpdate, Project,
+    PackagesUpdate, Project, UnreleasedHandling,
     changelog_parser::{self, ChangelogRelease},
     copy_dir::copy_dir,
     fs_utils::{Utf8TempDir, strip_prefix, to_utf8_path},
@@ -24,6 +24,8 @@ use cargo_metadata::{
 };
 use cargo_utils::get_manifest_metadata;
 use chrono::NaiveDate;
+use git_cmd::Repo;
+use regex::Regex;
 use std::collections::BTreeMap;
 use std::path::PathBuf;
 use toml_edit::TableLike;
@@ -51,6 +53,8 @@ pub struct ChangelogRequest {
     /// When the new release is published. If unspecified, current date is used.
     pub release_date: Option<NaiveDate>,
     pub changelog_config: Option<git_cliff_core::config::Config>,
+    /// What to do with the entries of the `Unreleased` section of the changelog.
+    pub unreleased_handling: UnreleasedHandling,
 }
 
 impl ReleaseMetadataBuilder for UpdateRequest {
@@ -103,13 +107,7 @@ fn process_git_only_package(
     input: &UpdateRequest,
     is_multi_package: bool,
 ) -> anyhow::Result<Option<(RegistryPackage, GitWorkTree)>> {
-    // Get the release tag template, falling back to default based on project structure
-    let template = input
-        .get_package_tag_name(&package.name)
-        .unwrap_or_else(|| default_tag_name_template(is_multi_package));
-
-    let release_regex =
-        release_regex::get_release_regex(&template, &package.name).context("get release regex")?;
+    let release_regex = package_release_regex(package, input, is_multi_package)?;
     debug!(
         "looking for tags matching pattern: {}",
         release_regex.to_string()
@@ -161,6 +159,73 @@ fn process_git_only_package(
     Ok(Some((registry_package, worktree)))
 }
 
+/// Regex matching the release tags of the package.
+fn package_release_regex(
+    package: &Package,
+    input: &UpdateRequest,
+    is_multi_package: bool,
+) -> anyhow::Result<Regex> {
+    // Get the release tag template, falling back to default based on project structure
+    let template = input
+        .get_package_tag_name(&package.name)
+        .unwrap_or_else(|| default_tag_name_template(is_multi_package));
+    release_regex::get_release_regex(&template, &package.name).context("get release regex")
+}
+
+/// Shallow clones might miss the release tags of the `git_only` packages, or the history
+/// between the tags and `HEAD`.
+/// Fetch more history until the latest release tag of each package and `HEAD` have a common
+/// ancestor, up to `max_analyze_commits` commits.
+/// The tag doesn't need to be an ancestor of `HEAD`, e.g. it can be on a maintenance branch.
+fn fetch_release_tags(
+    git_only_packages: &[&Package],
+    input: &UpdateRequest,
+    is_multi_package: bool,
+) -> anyhow::Result<()> {
+    let repo_dir = input
+        .local_manifest_dir()
+        .context("get local manifest dir")?;
+    let repo = Repo::new(repo_dir)?;
+    if !repo.is_shallow()? {
+        return Ok(());
+    }
+    let release_regexes = git_only_packages
+        .iter()
+        .map(|package| {
+            package_release_regex(package, input, is_multi_package)
+                .map(|regex| (package.name.as_str(), regex))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    // If `are_tags_fetched` is false, a package without release tags might miss them
+    // because they weren't fetched yet.
+    let are_release_tags_found = |are_tags_fetched: bool| {
+        // Open the repository again to read the fetched tags.
+        let git_repo = GitRepo::open(repo_dir)?;
+        for (package, release_regex) in &release_regexes {
+            match git_repo.get_release_tag(release_regex, package)? {
+                Some((release_tag, _)) if !repo.have_common_ancestor(&release_tag, "HEAD") => {
+                    return Ok(false);
+                }
+                None if !are_tags_fetched => return Ok(false),
+                _ => {}
+            }
+        }
+        Ok(true)
+    };
+    if are_release_tags_found(false)? {
+        return Ok(());
+    }
+    // The tags aren't fetched together with the shallow history.
+    repo.deepen(1)?;
+    let max_commits = match input.max_analyze_commits() {
+        0 => u32::MAX,
+        n => n,
+    };
+    repo.deepen_until("HEAD", max_commits, || are_release_tags_found(true))
+        .context("can't find the release tags of the `git_only` packages")?;
+    Ok(())
+}
+
 /// Run cargo package within a worktree
 fn run_cargo_package(worktree: &GitWorkTree) -> anyhow::Result<()> {
     let worktree_path = to_utf8_path(worktree.path())?;
@@ -310,6 +375,8 @@ fn collect_git_only_packages(
     // See the note on the custom worktree Drop impl for more details.
     let mut worktrees = Vec::new();
 
+    fetch_release_tags(&git_only_packages, input, is_multi_package)?;
+
     let mut unreleased_project_repo = GitRepo::open(
         input
             .local_manifest_dir()
@@ -419,6 +486,8 @@ pub struct UpdateResult {
     pub changelog: Option<String>,
     pub semver_check: SemverCheck,
     pub new_changelog_entry: Option<String>,
+    /// Template context used to render `new_changelog_entry`.
+    pub new_changelog_context: Option<serde_json::Value>,
     /// The last released/published version from the registry.
     /// This is set when the local version was already bumped (higher than registry version).
     /// Used to generate correct version transitions in PR body (e.g., "0.1.0 -> 0.2.0")
@@ -50,6 +50,14 @@ pub struct Pr {
     pub body: String,
     pub draft: bool,
     pub labels: Vec<String>,
+    /// Usernames of the users to request a review from.
+    pub reviewers: Vec<String>,
+    /// Slugs of the teams to request a review from.
+    pub team_reviewers: Vec<String>,
+    /// Usernames of the users to assign the PR to.
+    pub assignees: Vec<String>,
+    /// Title of the milestone of the PR.
+    pub milestone: Option<String>,
 }
 
 impl Pr {
@@ -72,6 +80,10 @@ impl Pr {
             body: pr_body(packages_to_update, body_template)?,
             draft: false,
             labels: vec![],
+            reviewers: vec![],
+            team_reviewers: vec![],
+            assignees: vec![],
+            milestone: None,
         };
         Ok(pr)
     }
@@ -85,6 +97,30 @@ impl Pr {
         self.labels = labels;
         self
     }
+
+    pub fn with_reviewers(mut self, reviewers: Vec<String>, team_reviewers: Vec<String>) -> Self {
+        self.reviewers = reviewers;
+        self.team_reviewers = team_reviewers;
+        self
+    }
+
+    pub fn with_assignees(mut self, assignees: Vec<String>) -> Self {
+        self.assignees = assignees;
+        self
+    }
+
+    pub fn with_milestone(mut self, milestone: Option<String>) -> Self {
+        self.milestone = milestone;
+        self
+    }
+
+    /// Returns `true` if the PR has reviewers, assignees or a milestone.
+    pub fn has_assignments(&self) -> bool {
+        !self.reviewers.is_empty()
+            || !self.team_reviewers.is_empty()
+            || !self.assignees.is_empty()
+            || self.milestone.is_some()
+    }
 }
 
 fn release_branch(prefix: &str) -> String {
@@ -111,18 +147,7 @@ fn pr_title(
     };
 
     let title = if let Some(title_template) = title_template {
-        let mut context = tera::Context::new();
-
-        if updates.len() == 1 {
-            let (package, _) = &updates[0];
-            context.insert(PACKAGE_VAR, &package.name);
-        }
-
-        if are_all_versions_equal() {
-            context.insert(VERSION_VAR, first_version.to_string().as_str());
-        }
-
-        render_template(&title_template, &context, "pr_name")?
+        render_template(&title_template, &pr_context(packages_to_update), "pr_name")?
     } else if updates.len() == 1 && project_contains_multiple_pub_packages {
         let (package, _) = &updates[0];
         // The project is a workspace with multiple public packages and we are only updating one of them.
@@ -141,6 +166,37 @@ fn pr_title(
     Ok(title)
 }
 
+/// Title of the milestone of the release PR.
+pub fn milestone_title(
+    packages_to_update: &PackagesUpdate,
+    milestone_template: &str,
+) -> anyhow::Result<String> {
+    render_template(
+        milestone_template,
+        &pr_context(packages_to_update),
+        "pr_milestone",
+    )
+}
+
+/// Template context of the PR name and milestone.
+/// - `package` is present if the PR updates a single package.
+/// - `version` is present if all the updated packages have the same version.
+fn pr_context(packages_to_update: &PackagesUpdate) -> tera::Context {
+    let updates = packages_to_update.updates();
+    let mut context = tera::Context::new();
+    if let [(package, _)] = updates {
+        context.insert(PACKAGE_VAR, &package.name);
+    }
+    if let Some((_, first_update)) = updates.first()
+        && updates
+            .iter()
+            .all(|(_, update)| update.version == first_update.version)
+    {
+        context.insert(VERSION_VAR, first_update.version.to_string().as_str());
+    }
+    context
+}
+
 /// The Github API allows a max of 65536 characters in the body field when trying to create a new PR
 const MAX_BODY_LEN: usize = 65536;
 
@@ -11,8 +11,9 @@ pub struct Pr {
 /// Parse PRs from text, e.g. a changelog entry.
 pub fn prs_from_text(text: &str) -> Vec<Pr> {
     // given a text, extract all the PRs
-    // each PR is a link ending with `/pull/<number>` or `/pulls/<number>`
-    let re = Regex::new(r"https?://[^\s]+/pulls?/(\d+)").unwrap();
+    // each PR is a link ending with `/pull/<number>`, `/pulls/<number>`
+    // or `/-/merge_requests/<number>` (GitLab)
+    let re = Regex::new(r"https?://[^\s]+/(?:pulls?|-/merge_requests)/(\d+)").unwrap();
 
     re.captures_iter(text)
         .filter_map(|capture| {
@@ -26,6 +27,43 @@ pub fn prs_from_text(text: &str) -> Vec<Pr> {
         .collect()
 }
 
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Issue {
+    html_url: Url,
+    pub number: u64,
+}
+
+/// Parse issues from text, e.g. a changelog entry.
+pub fn issues_from_text(text: &str) -> Vec<Issue> {
+    // each issue is a link ending with `/issues/<number>`
+    let re = Regex::new(r"https?://[^\s)]+/issues/(\d+)").unwrap();
+
+    re.captures_iter(text)
+        .filter_map(|capture| {
+            let number = capture.get(1)?.as_str().parse().ok()?;
+            let html_url = capture.get(0)?.as_str().to_owned();
+            Url::parse(&html_url).ok().map(|url| Issue {
+                number,
+                html_url: url,
+            })
+        })
+        .collect()
+}
+
+/// Parse the numbers of the issues closed by a PR description,
+/// e.g. `Closes #10` or `fixes: #12`.
+pub fn closed_issues_from_text(text: &str) -> Vec<u64> {
+    let re = Regex::new(r"(?i)\b(?:close[sd]?|fix(?:e[sd])?|resolve[sd]?):?\s+#(\d+)\b").unwrap();
+
+    let mut issues: Vec<u64> = re
+        .captures_iter(text)
+        .filter_map(|capture| capture.get(1)?.as_str().parse().ok())
+        .collect();
+    issues.sort_unstable();
+    issues.dedup();
+    issues
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -80,4 +118,37 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn parse_gitlab_merge_request_correctly() {
+        let changelog_entry =
+            "- fix crash ([#12](https://gitlab.com/owner/repo/-/merge_requests/12))";
+        let prs = prs_from_text(changelog_entry);
+        assert_eq!(
+            prs,
+            vec![Pr {
+                number: 12,
+                html_url: Url::parse("https://gitlab.com/owner/repo/-/merge_requests/12").unwrap()
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_issue_correctly() {
+        let changelog_entry = "- fix crash ([#12](https://github.com/a/b/pull/12)), closes [#10](https://github.com/a/b/issues/10)";
+        let issues = issues_from_text(changelog_entry);
+        assert_eq!(
+            issues,
+            vec![Issue {
+                number: 10,
+                html_url: Url::parse("https://github.com/a/b/issues/10").unwrap()
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_closed_issues_correctly() {
+        let pr_body = "Fixes #3\nThis closes: #5 and resolves #7.\nRelated to #9, see prefix#11";
+        assert_eq!(closed_issues_from_text(pr_body), vec![3, 5, 7]);
+    }
 }
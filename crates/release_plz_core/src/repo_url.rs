@@ -1,6 +1,11 @@
 use anyhow::Context;
 use git_cmd::Repo;
 use git_url_parse::{GitUrl, types::provider::GenericProvider};
+use url::Url;
+
+use crate::git::forge::ForgeType;
+
+const GITHUB_COM: &str = "github.com";
 
 #[derive(Debug, Clone)]
 pub struct RepoUrl {
@@ -10,6 +15,10 @@ pub struct RepoUrl {
     pub owner: String,
     pub name: String,
     pub path: String,
+    /// Forge hosting the repository. If unknown, it's guessed from the host.
+    forge: Option<ForgeType>,
+    /// Root of the REST API of the forge, if it can't be derived from the host.
+    api_url: Option<Url>,
 }
 
 impl RepoUrl {
@@ -29,32 +38,87 @@ impl RepoUrl {
         Self::new(&url)
     }
 
+    /// Set the forge hosting the repository, e.g. for GitHub Enterprise Server
+    /// instances whose host doesn't contain "github".
+    pub fn with_forge(mut self, forge: ForgeType) -> Self {
+        self.forge = Some(forge);
+        self
+    }
+
+    /// Override the root of the REST API of the forge, e.g. `https://example.com/api/v3`.
+    pub fn with_api_url(mut self, api_url: Url) -> Self {
+        self.api_url = Some(api_url);
+        self
+    }
+
     pub fn is_on_github(&self) -> bool {
-        self.host.contains("github")
+        match self.forge {
+            Some(forge) => forge == ForgeType::Github,
+            None => self.host.contains("github"),
+        }
+    }
+
+    fn is_on_gitlab(&self) -> bool {
+        self.forge == Some(ForgeType::Gitlab)
     }
 
     pub fn full_host(&self) -> String {
         format!("https://{}/{}/{}", self.host, self.owner, self.name)
     }
 
-    /// Get GitHub/Gitea release link
+    /// Get GitHub/Gitea/GitLab release link
     pub fn git_release_link(&self, prev_tag: &str, new_tag: &str) -> String {
         let host = self.full_host();
+        let prefix = if self.is_on_gitlab() { "/-" } else { "" };
 
         if prev_tag == new_tag {
-            format!("{host}/releases/tag/{new_tag}")
+            let release_path = if self.is_on_gitlab() {
+                "releases"
+            } else {
+                "releases/tag"
+            };
+            format!("{host}{prefix}/{release_path}/{new_tag}")
         } else {
-            format!("{host}/compare/{prev_tag}...{new_tag}")
+            format!("{host}{prefix}/compare/{prev_tag}...{new_tag}")
         }
     }
 
+    /// Comparison link generated by release-plz versions that didn't use the `/-/`
+    /// prefix of GitLab paths. GitLab redirects it to [`Self::git_release_link`].
+    pub fn legacy_git_release_link(&self, prev_tag: &str, new_tag: &str) -> Option<String> {
+        (self.is_on_gitlab() && prev_tag != new_tag)
+            .then(|| format!("{}/compare/{prev_tag}...{new_tag}", self.full_host()))
+    }
+
     pub fn git_pr_link(&self) -> String {
         let host = self.full_host();
-        let pull_path = if self.is_on_github() { "pull" } else { "pulls" };
+        let pull_path = if self.is_on_github() {
+            "pull"
+        } else if self.is_on_gitlab() {
+            "-/merge_requests"
+        } else {
+            "pulls"
+        };
         format!("{host}/{pull_path}")
     }
 
+    /// Root of the GitHub REST API.
+    /// GitHub Enterprise Server serves it under `/api/v3` of the instance host.
+    pub fn github_api_url(&self) -> String {
+        if let Some(api_url) = &self.api_url {
+            return with_trailing_slash(api_url);
+        }
+        if self.host == GITHUB_COM {
+            "https://api.github.com/".to_string()
+        } else {
+            format!("{}/api/v3/", self.web_host())
+        }
+    }
+
     pub fn gitea_api_url(&self) -> String {
+        if let Some(api_url) = &self.api_url {
+            return with_trailing_slash(api_url);
+        }
         let v1 = "api/v1/";
         if let Some(port) = self.port {
             format!("{}://{}:{}/{v1}", self.scheme, self.host, port)
@@ -64,21 +128,39 @@ impl RepoUrl {
     }
 
     pub fn gitlab_api_url(&self) -> String {
-        let v4 = "api/v4/projects";
         let prj_path = urlencoding::encode(self.path.strip_prefix('/').unwrap_or(&self.path));
+        let api_url = match &self.api_url {
+            Some(api_url) => api_url.as_str().trim_end_matches('/').to_string(),
+            None => format!("{}/api/v4", self.web_host()),
+        };
+        format!("{api_url}/projects/{prj_path}")
+    }
+
+    /// Scheme, host and port of the forge web server.
+    /// Ssh urls are served over https.
+    fn web_host(&self) -> String {
         let scheme = if self.scheme == "ssh" {
             "https"
         } else {
             self.scheme.as_str()
         };
-        if let Some(port) = self.port {
-            format!("{scheme}://{}:{port}/{v4}/{prj_path}", self.host)
-        } else {
-            format!("{scheme}://{}/{v4}/{prj_path}", self.host)
+        match self.port {
+            // The port of ssh urls isn't the port of the web server.
+            Some(port) if self.scheme != "ssh" => format!("{scheme}://{}:{port}", self.host),
+            _ => format!("{scheme}://{}", self.host),
         }
     }
 }
 
+fn with_trailing_slash(url: &Url) -> String {
+    let url = url.as_str();
+    if url.ends_with('/') {
+        url.to_string()
+    } else {
+        format!("{url}/")
+    }
+}
+
 fn new_url(git_host_url: &str) -> anyhow::Result<RepoUrl> {
     let git_url = GitUrl::parse(git_host_url)?;
     let provider: GenericProvider = git_url
@@ -101,12 +183,15 @@ fn new_url(git_host_url: &str) -> anyhow::Result<RepoUrl> {
         port: git_url.port(),
         scheme,
         path,
+        forge: None,
+        api_url: None,
     })
 }
 
 #[cfg(test)]
 mod tests {
     use super::RepoUrl;
+    use crate::ForgeType;
 
     const GITHUB_REPO_URL: &str = "https://github.com/release-plz/release-plz";
 
@@ -147,4 +232,61 @@ mod tests {
             http_repo.gitlab_api_url()
         );
     }
+
+    #[test]
+    fn github_api_url() {
+        let repo = RepoUrl::new(GITHUB_REPO_URL).unwrap();
+        assert_eq!("https://api.github.com/", repo.github_api_url());
+
+        let enterprise_repo = RepoUrl::new("git@github.example.com:owner/repo.git").unwrap();
+        assert!(enterprise_repo.is_on_github());
+        assert_eq!(
+            "https://github.example.com/api/v3/",
+            enterprise_repo.github_api_url()
+        );
+    }
+
+    #[test]
+    fn api_url_can_be_overridden() {
+        let api_url: url::Url = "https://git.example.com/api/v4".parse().unwrap();
+        let repo = RepoUrl::new("https://host.example.com/ab/myproj")
+            .unwrap()
+            .with_api_url(api_url);
+        assert_eq!(
+            "https://git.example.com/api/v4/projects/ab%2Fmyproj",
+            repo.gitlab_api_url()
+        );
+        assert_eq!("https://git.example.com/api/v4/", repo.github_api_url());
+    }
+
+    #[test]
+    fn links_depend_on_forge() {
+        let repo = RepoUrl::new("https://git.example.com/owner/repo").unwrap();
+        assert!(!repo.is_on_github());
+        assert_eq!(
+            "https://git.example.com/owner/repo/pulls",
+            repo.git_pr_link()
+        );
+
+        let github_repo = repo.clone().with_forge(ForgeType::Github);
+        assert!(github_repo.is_on_github());
+        assert_eq!(
+            "https://git.example.com/owner/repo/pull",
+            github_repo.git_pr_link()
+        );
+
+        let gitlab_repo = repo.with_forge(ForgeType::Gitlab);
+        assert_eq!(
+            "https://git.example.com/owner/repo/-/merge_requests",
+            gitlab_repo.git_pr_link()
+        );
+        assert_eq!(
+            "https://git.example.com/owner/repo/-/releases/v1.0.0",
+            gitlab_repo.git_release_link("v1.0.0", "v1.0.0")
+        );
+        assert_eq!(
+            "https://git.example.com/owner/repo/-/compare/v1.0.0...v1.1.0",
+            gitlab_repo.git_release_link("v1.0.0", "v1.1.0")
+        );
+    }
 }
@@ -1,12 +1,13 @@
 use anyhow::Context as _;
 
-use crate::Remote;
+use crate::{PrMode, ReleaseInfo, Remote};
 
 pub const PACKAGE_VAR: &str = "package";
 pub const VERSION_VAR: &str = "version";
 pub const CHANGELOG_VAR: &str = "changelog";
 pub const REMOTE_VAR: &str = "remote";
 pub const RELEASES_VAR: &str = "releases";
+pub const RELEASE_LINK_VAR: &str = "release_link";
 
 pub fn tera_var(var_name: &str) -> String {
     format!("{{{{ {var_name} }}}}")
@@ -34,14 +35,120 @@ pub fn release_body_from_template(
     remote: &Remote,
     body_template: Option<&str>,
 ) -> anyhow::Result<String> {
+    let context = release_body_context(package_name, version, changelog, remote);
+    let default_body_template = tera_var(CHANGELOG_VAR);
+    let body_template = body_template.unwrap_or(&default_body_template);
+
+    render_template(body_template, &context, "release_body")
+}
+
+fn release_body_context(
+    package_name: &str,
+    version: &str,
+    changelog: &str,
+    remote: &Remote,
+) -> tera::Context {
     let mut context = tera_context(package_name, version);
     context.insert(CHANGELOG_VAR, changelog);
     context.insert(REMOTE_VAR, remote);
+    context
+}
 
-    let default_body_template = tera_var(CHANGELOG_VAR);
-    let body_template = body_template.unwrap_or(&default_body_template);
+/// Templates of the release-plz configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigTemplate {
+    PrName,
+    PrBody,
+    PrMilestone,
+    GitTagName,
+    GitReleaseName,
+    GitReleaseBody,
+    ReleaseComment,
+}
 
-    render_template(body_template, &context, "release_body")
+impl ConfigTemplate {
+    /// Render the template with sample values of the variables that release-plz provides,
+    /// to find errors before release-plz uses the template.
+    /// In multi-package workspaces, the templates of the release PR are also rendered
+    /// for PRs that update more than one package, unless `pr_mode` opens one PR per package.
+    pub fn render_sample(
+        self,
+        template: &str,
+        is_multi_package: bool,
+        pr_mode: PrMode,
+    ) -> anyhow::Result<String> {
+        let (package, version) = ("my_package", "1.2.3");
+        let (template_name, context) = match self {
+            Self::PrName | Self::PrMilestone => {
+                let template_name = if self == Self::PrName {
+                    "pr_name"
+                } else {
+                    "pr_milestone"
+                };
+                let context = tera_context(package, version);
+                let render = render_template(template, &context, template_name)?;
+                if is_multi_package {
+                    check_multi_package_pr_samples(template, template_name, version, pr_mode)?;
+                }
+                return Ok(render);
+            }
+            Self::GitTagName => ("tag_name", tera_context(package, version)),
+            Self::GitReleaseName => ("release_name", tera_context(package, version)),
+            Self::PrBody => {
+                let mut context = tera::Context::new();
+                context.insert(RELEASES_VAR, &[ReleaseInfo::sample(package, version)]);
+                ("pr_body", context)
+            }
+            Self::GitReleaseBody => {
+                let remote = Remote {
+                    owner: "owner".to_string(),
+                    repo: "repo".to_string(),
+                    link: "https://github.com/owner/repo".to_string(),
+                    contributors: vec![],
+                };
+                let changelog = "### Added\n\n- new feature";
+                (
+                    "release_body",
+                    release_body_context(package, version, changelog, &remote),
+                )
+            }
+            Self::ReleaseComment => {
+                let mut context = tera_context(package, version);
+                let release_link = "https://github.com/owner/repo/releases/tag/v1.2.3";
+                context.insert(RELEASE_LINK_VAR, release_link);
+                ("release_comment", context)
+            }
+        };
+        render_template(template, &context, template_name)
+    }
+}
+
+/// Render the template of the PR name or milestone for release PRs that update
+/// more than one package: they don't have the `package` variable, and they have the
+/// `version` variable only if all the packages have the same version.
+/// With [`PrMode::PerGroup`], the packages of a release PR are in the same
+/// `version_group`, so they have the same version.
+fn check_multi_package_pr_samples(
+    template: &str,
+    template_name: &str,
+    version: &str,
+    pr_mode: PrMode,
+) -> anyhow::Result<()> {
+    let mut same_version = tera::Context::new();
+    same_version.insert(VERSION_VAR, version);
+    let samples = match pr_mode {
+        PrMode::Single => vec![
+            ("packages with the same version", same_version),
+            ("packages with different versions", tera::Context::new()),
+        ],
+        PrMode::PerGroup => vec![("packages with the same version", same_version)],
+        PrMode::PerPackage => vec![],
+    };
+    for (pr_content, context) in samples {
+        render_template(template, &context, template_name)
+            .with_context(|| format!("release PR of {pr_content}"))?;
+    }
+    Ok(())
 }
 
 pub fn render_template(
@@ -52,7 +159,7 @@ pub fn render_template(
     let mut tera = tera::Tera::default();
 
     tera.add_raw_template(template_name, template)
-        .context("failed to add release_body raw template")?;
+        .with_context(|| format!("failed to parse {template_name}"))?;
 
     tera.render(template_name, context)
         .with_context(|| format!("failed to render {template_name}"))
@@ -82,6 +189,87 @@ mod tests {
         assert_eq!(body, "my changes");
     }
 
+    #[test]
+    fn config_templates_are_rendered_with_sample_values() {
+        let body = "{% for r in releases %}{{ r.package }} {{ r.next_version }}{% endfor %}";
+        assert_eq!(
+            ConfigTemplate::PrBody
+                .render_sample(body, false, PrMode::Single)
+                .unwrap(),
+            "my_package 1.2.3"
+        );
+        assert_eq!(
+            ConfigTemplate::ReleaseComment
+                .render_sample(
+                    "Released in [{{ version }}]({{ release_link }})",
+                    false,
+                    PrMode::Single,
+                )
+                .unwrap(),
+            "Released in [1.2.3](https://github.com/owner/repo/releases/tag/v1.2.3)"
+        );
+        let error = ConfigTemplate::GitTagName
+            .render_sample("{{ package }}-{{ tag }}", false, PrMode::Single)
+            .unwrap_err();
+        assert!(format!("{error:#}").contains("tag"));
+    }
+
+    #[test]
+    fn pr_templates_are_rendered_for_multiple_packages() {
+        let template = "chore: release{% if package %} {{ package }}{% endif %}";
+        assert_eq!(
+            ConfigTemplate::PrName
+                .render_sample(template, true, PrMode::Single)
+                .unwrap(),
+            "chore: release my_package"
+        );
+        let template = "chore: release {{ package }}";
+        assert_eq!(
+            ConfigTemplate::PrName
+                .render_sample(template, false, PrMode::Single)
+                .unwrap(),
+            "chore: release my_package"
+        );
+        let error = ConfigTemplate::PrName
+            .render_sample(template, true, PrMode::Single)
+            .unwrap_err();
+        assert!(
+            format!("{error:#}").contains("release PR of packages with the same version"),
+            "{error:#}"
+        );
+        let error = ConfigTemplate::PrMilestone
+            .render_sample("v{{ version }}", true, PrMode::Single)
+            .unwrap_err();
+        assert!(
+            format!("{error:#}").contains("release PR of packages with different versions"),
+            "{error:#}"
+        );
+    }
+
+    #[test]
+    fn pr_templates_are_rendered_for_the_packages_of_the_pr_mode() {
+        let template = "chore: release {{ package }}";
+        assert_eq!(
+            ConfigTemplate::PrName
+                .render_sample(template, true, PrMode::PerPackage)
+                .unwrap(),
+            "chore: release my_package"
+        );
+        let error = ConfigTemplate::PrName
+            .render_sample(template, true, PrMode::PerGroup)
+            .unwrap_err();
+        assert!(
+            format!("{error:#}").contains("release PR of packages with the same version"),
+            "{error:#}"
+        );
+        assert_eq!(
+            ConfigTemplate::PrMilestone
+                .render_sample("v{{ version }}", true, PrMode::PerGroup)
+                .unwrap(),
+            "v1.2.3"
+        );
+    }
+
     #[test]
     fn default_tag_template_single_package() {
         let template = default_tag_name_template(false);
@@ -27,6 +27,9 @@ const sidebars = {
         "usage/release",
         "usage/init",
         "usage/set-version",
+        "usage/changelog",
+        "usage/config",
+        "usage/doctor",
         "usage/shell-completion",
         "usage/generate-schema",
       ],